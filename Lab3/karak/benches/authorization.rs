@@ -0,0 +1,80 @@
+//! Coût des décisions d'accès pour lister un dossier de 200 rapports:
+//! Casbin sans cache, Casbin avec le cache rempli, et les règles natives.
+
+use std::collections::BTreeSet;
+use std::hint::black_box;
+
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, Criterion};
+use karak::{
+    authorization::Enforcer,
+    models::{
+        BloodType, MedicalFolder, MedicalReport, PersonalData, ReportID, Role, UserData, UserID,
+    },
+    utils::{
+        input_validation::{AVSNumber, Username},
+        password_utils::hash,
+    },
+};
+
+const REPORTS: usize = 200;
+
+fn user(name: &str, role: Role, medical_folder: Option<MedicalFolder>) -> UserData {
+    UserData {
+        id: UserID::new(),
+        role,
+        username: Username::try_from(name).unwrap(),
+        password: hash("password"),
+        medical_folder,
+    }
+}
+
+fn read_all(enforcer: &Enforcer, doctor: &UserData, patient: &UserData, reports: &[MedicalReport]) {
+    let context = enforcer.with_subject(doctor);
+    for report in reports {
+        black_box(context.read_report(report, patient).is_ok());
+    }
+}
+
+fn authorization(c: &mut Criterion) {
+    let doctor = user("doctor", Role::Doctor, None);
+    let folder = MedicalFolder {
+        personal_data: PersonalData {
+            avs_number: AVSNumber::try_from("756.1234.5678.97").unwrap(),
+            blood_type: BloodType::O,
+        },
+        doctors: BTreeSet::from([doctor.id]),
+        report_readers: BTreeSet::new(),
+    };
+    let patient = user("patient", Role::Patient, Some(folder));
+    let now = Utc::now();
+    let reports: Vec<MedicalReport> = (0..REPORTS)
+        .map(|i| MedicalReport {
+            id: ReportID::new(),
+            title: format!("Rapport {i}"),
+            author: doctor.id,
+            patient: patient.id,
+            content: String::new(),
+            created_at: now,
+            updated_at: now,
+        })
+        .collect();
+
+    let uncached = Enforcer::load().unwrap().without_cache();
+    let cached = Enforcer::load().unwrap();
+    let native = Enforcer::native().without_cache();
+    read_all(&cached, &doctor, &patient, &reports);
+
+    c.bench_function("read_reports_casbin", |b| {
+        b.iter(|| read_all(&uncached, &doctor, &patient, &reports))
+    });
+    c.bench_function("read_reports_casbin_cached", |b| {
+        b.iter(|| read_all(&cached, &doctor, &patient, &reports))
+    });
+    c.bench_function("read_reports_native", |b| {
+        b.iter(|| read_all(&native, &doctor, &patient, &reports))
+    });
+}
+
+criterion_group!(benches, authorization);
+criterion_main!(benches);
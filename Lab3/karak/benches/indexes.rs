@@ -0,0 +1,89 @@
+//! Performances des recherches indexées sur une grande base:
+//! 100 000 utilisateurs, dont 1 000 médecins, et 1 000 000 de rapports.
+
+use std::hint::black_box;
+
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, Criterion};
+use karak::{
+    db::Database,
+    models::{
+        BloodType, MedicalFolder, MedicalReport, PersonalData, ReportID, Role, UserData, UserID,
+    },
+    utils::{
+        input_validation::{AVSNumber, Username},
+        password_utils::hash,
+    },
+};
+
+const USERS: usize = 100_000;
+const DOCTORS: usize = 1_000;
+const REPORTS: usize = 1_000_000;
+
+fn username(i: usize) -> Username {
+    Username::try_from(format!("user{i}")).unwrap()
+}
+
+fn populate() -> (Database, Vec<UserID>) {
+    let mut db = Database::default();
+    // Un seul hachage, Argon2 étant volontairement lent
+    let password = hash("password");
+
+    let doctors: Vec<UserID> = (0..DOCTORS).map(|_| UserID::new()).collect();
+    let mut patients = Vec::with_capacity(USERS - DOCTORS);
+    for i in 0..USERS {
+        let (id, role, medical_folder) = match doctors.get(i) {
+            Some(&doctor) => (doctor, Role::Doctor, None),
+            None => {
+                let mut folder = MedicalFolder::new(PersonalData {
+                    avs_number: AVSNumber::try_from("756.1234.5678.97").unwrap(),
+                    blood_type: BloodType::O,
+                });
+                folder.doctors.insert(doctors[i % DOCTORS]);
+                let id = UserID::new();
+                patients.push(id);
+                (id, Role::Patient, Some(folder))
+            }
+        };
+        db.store_user(UserData {
+            id,
+            role,
+            username: username(i),
+            password: password.clone(),
+            medical_folder,
+        });
+    }
+
+    let now = Utc::now();
+    for i in 0..REPORTS {
+        db.store_report(MedicalReport {
+            id: ReportID::new(),
+            title: format!("Rapport {i}"),
+            author: doctors[i % DOCTORS],
+            patient: patients[i % patients.len()],
+            content: String::new(),
+            created_at: now,
+            updated_at: now,
+        });
+    }
+
+    (db, doctors)
+}
+
+fn indexes(c: &mut Criterion) {
+    let (db, doctors) = populate();
+    let patient = db.get_patients(doctors[0]).next().unwrap();
+
+    c.bench_function("lookup_username", |b| {
+        b.iter(|| db.lookup_username(black_box(&username(USERS / 2))))
+    });
+    c.bench_function("get_patients", |b| {
+        b.iter(|| db.get_patients(black_box(doctors[DOCTORS / 2])).count())
+    });
+    c.bench_function("list_patient_reports", |b| {
+        b.iter(|| db.list_patient_reports(black_box(patient)).count())
+    });
+}
+
+criterion_group!(benches, indexes);
+criterion_main!(benches);
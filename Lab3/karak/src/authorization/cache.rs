@@ -0,0 +1,115 @@
+//! Cache des décisions d'accès
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::{Request, Subject};
+
+/// Au-delà, le cache est vidé plutôt que de croître sans limite
+const CAPACITY: usize = 10_000;
+
+/// Les décisions déjà prises, indexées par l'empreinte de ce dont les
+/// règles dépendent: les identifiants, rôles et accès du sujet et de
+/// l'objet, l'état des demandes, l'action et la version des règles. Une
+/// modification de l'un d'eux change l'empreinte; le cache est tout de même
+/// vidé à chaque modification des rôles, des accès ou des règles, pour ne
+/// pas garder de décisions qui ne serviront plus.
+#[derive(Default)]
+pub(super) struct DecisionCache {
+    decisions: Mutex<HashMap<[u8; 32], bool>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+/// Les mesures du cache de décisions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub invalidations: u64,
+    pub entries: usize,
+}
+
+impl CacheStats {
+    /// La part des décisions servies par le cache, entre 0 et 1
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+impl DecisionCache {
+    /// L'empreinte d'une demande, sans sérialiser le sujet ni l'objet
+    pub fn key(&self, subject: &Subject, request: &Request) -> [u8; 32] {
+        let mut hasher = KeyHasher::default();
+        // Chaque invalidation est une nouvelle version des règles ou des rôles
+        self.invalidations.load(Ordering::Relaxed).hash(&mut hasher);
+        (subject.user.id, subject.user.role).hash(&mut hasher);
+        (&subject.proxy_read, &subject.proxy_manage).hash(&mut hasher);
+        request.action().hash(&mut hasher);
+        request.hash_seen(&mut hasher);
+        hasher.0.finalize().into()
+    }
+
+    /// La décision en cache, ou celle que prend `decide`
+    pub fn get_or_decide(&self, key: [u8; 32], decide: impl FnOnce() -> bool) -> (bool, bool) {
+        if let Some(&granted) = self.lock().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return (granted, true);
+        }
+
+        // Le verrou n'est pas gardé pendant la décision
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let granted = decide();
+        let mut decisions = self.lock();
+        if decisions.len() >= CAPACITY {
+            decisions.clear();
+        }
+        decisions.insert(key, granted);
+        (granted, false)
+    }
+
+    pub fn invalidate(&self) {
+        self.lock().clear();
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            entries: self.lock().len(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<[u8; 32], bool>> {
+        self.decisions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Un `Hasher` qui accumule dans SHA-256, pour que deux demandes
+/// différentes n'aient pas la même clé
+#[derive(Default)]
+struct KeyHasher(Sha256);
+
+impl Hasher for KeyHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().expect("Digests are 32 bytes"))
+    }
+}
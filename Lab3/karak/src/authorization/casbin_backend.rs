@@ -0,0 +1,352 @@
+//! Le moteur de règles Casbin, lu depuis des fichiers signés ou intégré à
+//! l'exécutable, et dont les règles peuvent être modifiées à l'exécution
+
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use casbin::{CoreApi, DefaultModel, MemoryAdapter, MgmtApi};
+use futures::executor::block_on;
+use log::{error, warn};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use thiserror::Error;
+
+use super::signing::{self, SignatureError};
+use super::{Backend, Request, Subject};
+use crate::i18n::Msg;
+use crate::models::{PolicyChange, PolicyRule};
+use crate::t;
+
+/// Les règles de `access_control/`, compilées dans l'exécutable
+const BUILTIN_MODEL: &str = include_str!("../../access_control/model.conf");
+const BUILTIN_POLICY: &str = include_str!("../../access_control/policy.csv");
+
+/// Une colonne d'une ligne de règles, découpée comme le fait Casbin
+static COLUMN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(\s*"[^"]*"?|\s*[^,]*)"#).expect("Valid regex"));
+
+/// Où lire le modèle et les règles Casbin; à défaut, les règles intégrées
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolicySource {
+    pub model: Option<PathBuf>,
+    pub policy: Option<PathBuf>,
+    /// La clé publique des responsables des règles, en hexadécimal
+    pub key: Option<String>,
+    /// Démarre en mode verrouillé plutôt que de refuser de démarrer si la
+    /// signature des règles est absente ou fausse
+    pub lockdown: bool,
+}
+
+impl PolicySource {
+    /// Vrai si le modèle ou les règles intégrés sont utilisés
+    pub fn uses_builtin(&self) -> bool {
+        self.model.is_none() || self.policy.is_none()
+    }
+
+    /// Le fichier de la signature, à côté des règles (ou du modèle si seules
+    /// les règles sont intégrées); aucun si tout est intégré
+    pub fn signature(&self) -> Option<PathBuf> {
+        self.policy
+            .as_ref()
+            .or(self.model.as_ref())
+            .map(|path| signing::signature_path(path))
+    }
+
+    /// Le texte du modèle et des règles, tel qu'il est signé et chargé
+    pub fn read(&self) -> Result<(Cow<'static, str>, Cow<'static, str>), PolicyError> {
+        let read = |path: &Option<PathBuf>, builtin: &'static str| match path {
+            Some(path) => fs::read_to_string(path)
+                .map(Cow::Owned)
+                .map_err(|e| PolicyError::Read(path.clone(), e)),
+            None => Ok(Cow::Borrowed(builtin)),
+        };
+        Ok((
+            read(&self.model, BUILTIN_MODEL)?,
+            read(&self.policy, BUILTIN_POLICY)?,
+        ))
+    }
+
+    /// Vérifie la signature des fichiers lus; les règles intégrées n'en ont
+    /// pas besoin
+    pub fn verify(&self, model: &str, policy: &str) -> Result<(), PolicyError> {
+        let Some(path) = self.signature() else {
+            return Ok(());
+        };
+        let key = self.key.as_deref().ok_or(PolicyError::NoKey)?;
+        let signature = match fs::read_to_string(&path) {
+            Ok(signature) => signature,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(PolicyError::Unsigned(path));
+            }
+            Err(e) => return Err(PolicyError::Read(path, e)),
+        };
+        Ok(signing::verify(key, model, policy, &signature)?)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PolicyError {
+    #[error(transparent)]
+    Casbin(#[from] casbin::Error),
+    #[error("{}", t!(ErrPolicyRead, .0.display(), .1))]
+    Read(PathBuf, io::Error),
+    #[error("{}", Msg::ErrPolicyNoKey)]
+    NoKey,
+    #[error("{}", t!(ErrPolicyUnsigned, .0.display()))]
+    Unsigned(PathBuf),
+    #[error(transparent)]
+    Signature(#[from] SignatureError),
+    #[error("{}", Msg::ErrPolicyNotEditable)]
+    NotEditable,
+    #[error("{}", Msg::ErrInvalidPolicyRule)]
+    InvalidRule,
+    #[error("{}", Msg::ErrPolicyUnchanged)]
+    Unchanged,
+    /// Les règles de la base dérivent d'autres règles signées
+    #[error("{}", Msg::ErrPolicyOtherBase)]
+    OtherBase,
+}
+
+impl PolicyError {
+    /// Vrai si les règles ont pu être lues, mais pas authentifiées
+    pub fn is_signature(&self) -> bool {
+        matches!(
+            self,
+            PolicyError::NoKey | PolicyError::Unsigned(_) | PolicyError::Signature(_)
+        )
+    }
+}
+
+/// Les règles Casbin, et d'où les relire
+pub struct CasbinBackend {
+    enforcer: casbin::Enforcer,
+    /// Le texte du modèle, pour créer des copies aux règles modifiées
+    model: String,
+    source: PolicySource,
+}
+
+impl CasbinBackend {
+    /// Les règles intégrées à l'exécutable
+    pub fn load() -> Result<Self, PolicyError> {
+        Self::from_source(&PolicySource::default())
+    }
+
+    /// Les règles de `source`, chargées depuis le texte même dont la
+    /// signature a été vérifiée
+    pub fn from_source(source: &PolicySource) -> Result<Self, PolicyError> {
+        if source.uses_builtin() {
+            warn!(model:? = source.model, policy:? = source.policy; "Built-in access policy in use");
+        }
+        let (model, policy) = source.read()?;
+        source.verify(&model, &policy)?;
+
+        Ok(CasbinBackend {
+            enforcer: build(&model, parse_rules(&policy))?,
+            model: model.into_owned(),
+            source: source.clone(),
+        })
+    }
+
+    #[cfg(test)]
+    fn raw_rules(&self) -> Vec<Vec<String>> {
+        self.enforcer.get_policy()
+    }
+}
+
+/// Un enforcer Casbin sur le modèle et les lignes de règles donnés
+fn build(model: &str, rules: Vec<Vec<String>>) -> Result<casbin::Enforcer, casbin::Error> {
+    block_on(async {
+        let model = DefaultModel::from_str(model).await?;
+        let mut enforcer = casbin::Enforcer::new(model, MemoryAdapter::default()).await?;
+        enforcer.add_policies(rules).await?;
+        Ok(enforcer)
+    })
+}
+
+/// Les colonnes d'une ligne de règles, sans le type de ligne
+fn columns(rule: &PolicyRule) -> Vec<String> {
+    vec![rule.action.clone(), rule.rule.clone()]
+}
+
+/// Les lignes `p, <action>, <règle>`, découpées comme le fait l'adaptateur
+/// de fichiers de Casbin; le modèle ne définit pas d'autre type de ligne
+fn parse_rules(policy: &str) -> Vec<Vec<String>> {
+    policy
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut columns = COLUMN.find_iter(line).map(|column| {
+                let column = column.as_str().trim();
+                match column.strip_prefix('"').and_then(|c| c.strip_suffix('"')) {
+                    Some(unquoted) => unquoted.to_owned(),
+                    None => column.to_owned(),
+                }
+            });
+            (columns.next()? == "p").then(|| columns.collect())
+        })
+        .collect()
+}
+
+impl Backend for CasbinBackend {
+    fn decide(&self, subject: &Subject, request: &Request) -> bool {
+        let action = request.action();
+        match self.enforcer.enforce((subject, request.object(), action)) {
+            Err(e) => {
+                error!(action; "Casbin error: {e:?}");
+                false
+            }
+            Ok(granted) => granted,
+        }
+    }
+
+    /// Relit et vérifie les fichiers; en cas d'erreur, les règles en place
+    /// sont gardées
+    fn reload(&mut self) -> Result<(), PolicyError> {
+        *self = Self::from_source(&self.source)?;
+        Ok(())
+    }
+
+    fn rules(&self) -> Option<Vec<PolicyRule>> {
+        Some(
+            self.enforcer
+                .get_policy()
+                .into_iter()
+                .filter_map(|columns| match <[String; 2]>::try_from(columns) {
+                    Ok([action, rule]) => Some(PolicyRule { action, rule }),
+                    Err(_) => None,
+                })
+                .collect(),
+        )
+    }
+
+    fn with_rules(&self, rules: &[PolicyRule]) -> Result<Box<dyn Backend>, PolicyError> {
+        Ok(Box::new(CasbinBackend {
+            enforcer: build(&self.model, rules.iter().map(columns).collect())?,
+            model: self.model.clone(),
+            source: self.source.clone(),
+        }))
+    }
+
+    /// Passe par l'API de gestion de Casbin, qui met à jour le modèle sans
+    /// le recharger
+    fn apply(&mut self, change: &PolicyChange) -> Result<bool, PolicyError> {
+        let changed = block_on(async {
+            match change {
+                PolicyChange::Add(rule) => self.enforcer.add_policy(columns(rule)).await,
+                PolicyChange::Remove(rule) => self.enforcer.remove_policy(columns(rule)).await,
+            }
+        })?;
+        Ok(changed)
+    }
+
+    fn cacheable(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::authorization::signing::PolicyKey;
+
+    /// A copy of the built-in policy in a fresh directory, signed with a new key
+    fn signed_copy() -> (PolicySource, PolicyKey) {
+        let dir = std::env::temp_dir().join(format!("karak-policy-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let key = PolicyKey::generate();
+        let source = PolicySource {
+            model: Some(dir.join("model.conf")),
+            policy: Some(dir.join("policy.csv")),
+            key: Some(key.public_key()),
+            lockdown: false,
+        };
+        fs::write(dir.join("model.conf"), BUILTIN_MODEL).unwrap();
+        fs::write(dir.join("policy.csv"), BUILTIN_POLICY).unwrap();
+        let signature = key.sign(BUILTIN_MODEL, BUILTIN_POLICY);
+        fs::write(source.signature().unwrap(), signature).unwrap();
+        (source, key)
+    }
+
+    #[test]
+    fn test_builtin_policy_matches_the_files() {
+        let builtin = CasbinBackend::load().unwrap();
+        let (source, _) = signed_copy();
+        let files = CasbinBackend::from_source(&source).unwrap();
+
+        let rules = builtin.raw_rules();
+        assert!(rules.len() > 50);
+        assert_eq!(rules, files.raw_rules());
+        assert!(rules.iter().all(|rule| rule.len() == 2));
+
+        // Each rule names an action of the application, and each action has rules
+        let actions: BTreeSet<&str> = rules.iter().map(|rule| rule[0].as_str()).collect();
+        assert_eq!(actions, Request::ACTIONS.iter().copied().collect());
+    }
+
+    #[test]
+    fn test_policy_files_must_be_signed() {
+        let (source, key) = signed_copy();
+        let policy = source.policy.clone().unwrap();
+
+        // One line is enough to make everyone an admin
+        let escalated = format!("{BUILTIN_POLICY}\np, update-role, true\n");
+        fs::write(&policy, &escalated).unwrap();
+        let Err(e) = CasbinBackend::from_source(&source) else {
+            panic!("A tampered policy should be refused");
+        };
+        assert!(matches!(
+            e,
+            PolicyError::Signature(SignatureError::BadSignature)
+        ));
+
+        let unkeyed = PolicySource {
+            key: None,
+            ..source.clone()
+        };
+        assert!(matches!(
+            CasbinBackend::from_source(&unkeyed),
+            Err(PolicyError::NoKey)
+        ));
+
+        fs::remove_file(source.signature().unwrap()).unwrap();
+        assert!(matches!(
+            CasbinBackend::from_source(&source),
+            Err(PolicyError::Unsigned(_))
+        ));
+
+        // Once signed by the maintainers, the new rule is accepted
+        fs::write(
+            source.signature().unwrap(),
+            key.sign(BUILTIN_MODEL, &escalated),
+        )
+        .unwrap();
+        let mut backend = CasbinBackend::from_source(&source).unwrap();
+        assert!(backend
+            .raw_rules()
+            .contains(&vec!["update-role".to_owned(), "true".to_owned()]));
+
+        // A reload that fails keeps the rules in place
+        fs::write(&policy, BUILTIN_POLICY).unwrap();
+        assert!(backend.reload().unwrap_err().is_signature());
+        assert_eq!(backend.raw_rules().len(), parse_rules(&escalated).len());
+    }
+
+    #[test]
+    fn test_rules_are_split_like_casbin() {
+        let rules = parse_rules(
+            "// comment\n# comment\n\np, read-data, r.sub.id == r.obj.id\n  p, \"a, b\", c\n",
+        );
+        assert_eq!(
+            rules,
+            [
+                vec!["read-data".to_owned(), "r.sub.id == r.obj.id".to_owned()],
+                vec!["a, b".to_owned(), "c".to_owned()],
+            ]
+        );
+    }
+}
@@ -0,0 +1,402 @@
+//! Differential test of the authorization backends: both decide on every
+//! request over randomly generated users, folders and records, and any
+//! mismatch is reported with the seed that reproduces it.
+
+use chrono::{Days, NaiveDate, Utc};
+
+use super::*;
+use crate::models::*;
+use crate::utils::input_validation::{AVSNumber, Username};
+use crate::utils::password_utils::{hash, PWHash};
+
+const SEED: u64 = 0x6b61_7261_6b00_0045;
+const SCENARIOS: u64 = 150;
+const USERS: usize = 4;
+
+/// A small deterministic generator (SplitMix64), so that a failing seed can
+/// be replayed
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self) -> bool {
+        self.next() & 1 == 0
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+}
+
+/// Everything the requests of one scenario refer to
+struct World {
+    users: Vec<UserData>,
+    proxies: Vec<ProxyRelationship>,
+    reports: Vec<MedicalReport>,
+    lab_results: Vec<LabResult>,
+    requests: Vec<AccessRequest>,
+    log: Vec<AccessLogEntry>,
+    prescriptions: Vec<Prescription>,
+    role_changes: Vec<RoleChangeRequest>,
+}
+
+fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 3, 15).unwrap()
+}
+
+fn random_role(rng: &mut Rng) -> Role {
+    rng.pick(&[Role::Doctor, Role::Patient, Role::Admin, Role::Pharmacist])
+}
+
+fn random_day(rng: &mut Rng) -> NaiveDate {
+    let offset = Days::new(rng.below(10) as u64);
+    if rng.chance() {
+        today() - offset
+    } else {
+        today() + offset
+    }
+}
+
+/// Generates users with random roles, and folders whose doctors and readers
+/// are taken among them
+fn generate(rng: &mut Rng, password: &PWHash) -> World {
+    let mut users: Vec<UserData> = (0..USERS)
+        .map(|i| UserData {
+            id: UserID::new(),
+            role: random_role(rng),
+            username: Username::try_from(format!("user{i}")).unwrap(),
+            password: password.clone(),
+            medical_folder: None,
+        })
+        .collect();
+    let ids: Vec<UserID> = users.iter().map(|user| user.id).collect();
+    for user in &mut users {
+        if rng.below(4) == 0 {
+            continue;
+        }
+        let mut folder = MedicalFolder::new(PersonalData {
+            avs_number: AVSNumber::try_from("756.1234.5678.97".to_string()).unwrap(),
+            blood_type: BloodType::O,
+        });
+        for &id in &ids {
+            match rng.below(3) {
+                0 => folder.grant(id, AccessScope::Folder),
+                1 => folder.grant(id, AccessScope::Reports),
+                _ => {}
+            }
+        }
+        user.medical_folder = Some(folder);
+    }
+
+    let proxies = (0..3)
+        .map(|_| ProxyRelationship {
+            id: ProxyID::new(),
+            proxy: rng.pick(&ids),
+            dependant: rng.pick(&ids),
+            kind: rng.pick(&[ProxyKind::Guardian, ProxyKind::Carer]),
+            scope: rng.pick(&[ProxyScope::Read, ProxyScope::Manage]),
+            start: random_day(rng),
+            end: rng.chance().then(|| random_day(rng)),
+        })
+        .collect();
+    let reports = (0..3)
+        .map(|_| MedicalReport {
+            id: ReportID::new(),
+            title: "Report".to_string(),
+            author: rng.pick(&ids),
+            patient: rng.pick(&ids),
+            content: "Content".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        })
+        .collect();
+    let lab_results = (0..2)
+        .map(|_| LabResult {
+            id: LabResultID::new(),
+            patient: rng.pick(&ids),
+            author: rng.pick(&ids),
+            analyte: "HBA1C".to_string(),
+            value: 5.4,
+            unit: "%".to_string(),
+            range: ReferenceRange {
+                low: Some(4.0),
+                high: Some(6.0),
+            },
+            taken_at: Utc::now(),
+        })
+        .collect();
+    let requests = (0..3)
+        .map(|_| AccessRequest {
+            id: AccessRequestID::new(),
+            doctor: rng.pick(&ids),
+            patient: rng.pick(&ids),
+            reason: "Second opinion".to_string(),
+            scope: rng.pick(&[AccessScope::Folder, AccessScope::Reports]),
+            status: rng.pick(&[
+                RequestStatus::Pending,
+                RequestStatus::Approved,
+                RequestStatus::Denied,
+            ]),
+        })
+        .collect();
+    let log = (0..3)
+        .map(|_| AccessLogEntry {
+            id: AccessLogID::new(),
+            patient: rng.pick(&ids),
+            accessor: rng.pick(&ids),
+            accessor_role: random_role(rng),
+            action: AccessAction::ReadData,
+            at: Utc::now(),
+            dispute: rng.pick(&[
+                DisputeStatus::Undisputed,
+                DisputeStatus::Disputed,
+                DisputeStatus::Reviewed,
+            ]),
+            as_proxy: None,
+        })
+        .collect();
+    let prescriptions = (0..3)
+        .map(|_| {
+            let repeats = rng.below(2) as u32;
+            let pharmacist = rng.pick(&ids);
+            Prescription {
+                id: PrescriptionID::new(),
+                patient: rng.pick(&ids),
+                prescriber: rng.pick(&ids),
+                drug: "Amoxicillin".to_string(),
+                dose: "500 mg".to_string(),
+                duration_days: 7,
+                repeats,
+                issued_at: Utc::now(),
+                dispensings: (0..rng.below(repeats as usize + 2))
+                    .map(|_| Dispensing {
+                        pharmacist,
+                        at: Utc::now(),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    let role_changes = (0..3)
+        .map(|_| RoleChangeRequest {
+            id: RoleChangeID::new(),
+            target: rng.pick(&ids),
+            role: random_role(rng),
+            previous: Some(random_role(rng)),
+            requester: rng.pick(&ids),
+            requested_at: Utc::now(),
+            expires_at: Utc::now(),
+            status: rng.pick(&[
+                RoleChangeStatus::Pending,
+                RoleChangeStatus::Approved,
+                RoleChangeStatus::Denied,
+                RoleChangeStatus::Expired,
+                RoleChangeStatus::Cancelled,
+            ]),
+            reviewer: None,
+        })
+        .collect();
+
+    World {
+        users,
+        proxies,
+        reports,
+        lab_results,
+        requests,
+        log,
+        prescriptions,
+        role_changes,
+    }
+}
+
+/// Every request that can be made on the world, each record being paired
+/// with each user so that mismatched patients are covered too
+fn requests<'a>(world: &'a World, rng: &mut Rng) -> Vec<Request<'a>> {
+    let mut requests = vec![
+        Request::ImportUsers,
+        Request::ResearchExport,
+        Request::Fsck,
+        Request::ReadMetrics,
+        Request::ManagePolicy,
+        Request::SearchAvs,
+    ];
+    for user in &world.users {
+        requests.extend([
+            Request::ReadData(user),
+            Request::UpdateData(user),
+            Request::DeleteData(user),
+            Request::CloseAccount(user),
+            Request::RevealAvs(user),
+            Request::UpdateRole {
+                target: user,
+                role: random_role(rng),
+            },
+        ]);
+        for other in &world.users {
+            requests.extend([
+                Request::AddDoctor {
+                    patient: user,
+                    doctor: other,
+                },
+                Request::RemoveDoctor {
+                    patient: user,
+                    doctor: other,
+                },
+            ]);
+        }
+        for report in &world.reports {
+            requests.extend([
+                Request::AddReport {
+                    patient: user,
+                    report,
+                },
+                Request::ReadReport {
+                    report,
+                    patient: user,
+                },
+            ]);
+        }
+        for result in &world.lab_results {
+            requests.extend([
+                Request::AddLabResult {
+                    patient: user,
+                    result,
+                },
+                Request::ReadLabResult {
+                    result,
+                    patient: user,
+                },
+            ]);
+        }
+        for request in &world.requests {
+            requests.push(Request::RequestAccess {
+                patient: user,
+                request,
+            });
+        }
+        for prescription in &world.prescriptions {
+            requests.extend([
+                Request::Prescribe {
+                    patient: user,
+                    prescription,
+                },
+                Request::ReadPrescription {
+                    prescription,
+                    patient: user,
+                },
+            ]);
+        }
+    }
+    requests.extend(world.reports.iter().map(Request::UpdateReport));
+    requests.extend(world.requests.iter().map(Request::ReviewAccess));
+    requests.extend(world.role_changes.iter().map(Request::ReviewRoleChange));
+    requests.extend(world.role_changes.iter().map(Request::CancelRoleChange));
+    for entry in &world.log {
+        requests.extend([
+            Request::ReadAccessLog(entry),
+            Request::DisputeAccess(entry),
+            Request::ReviewDispute(entry),
+        ]);
+    }
+    requests.extend(world.proxies.iter().map(Request::ManageProxy));
+    for prescription in &world.prescriptions {
+        requests.extend([
+            Request::IssuePrescriptionCode(prescription),
+            Request::LookupPrescription(prescription),
+            Request::Dispense(prescription),
+        ]);
+    }
+    requests
+}
+
+#[test]
+fn test_native_backend_agrees_with_casbin() {
+    let casbin = CasbinBackend::load().expect("Error in loading Enforcer");
+    let enforcer = Enforcer::native();
+    let password = hash("password123");
+    let mut mismatches = Vec::new();
+    let mut granted = 0;
+    let mut decided = 0;
+
+    for scenario in 0..SCENARIOS {
+        let seed = SEED + scenario;
+        let mut rng = Rng(seed);
+        let world = generate(&mut rng, &password);
+        let user = &world.users[rng.below(USERS)];
+        let context = enforcer
+            .with_subject(user)
+            .with_proxies(&world.proxies, today());
+
+        for request in requests(&world, &mut rng) {
+            let expected = casbin.decide(&context.subject, &request);
+            let actual = NativeBackend.decide(&context.subject, &request);
+            decided += 1;
+            granted += expected as usize;
+            if expected != actual {
+                mismatches.push(format!(
+                    "seed {seed:#x}: {} by a {:?} (Casbin: {expected}, native: {actual})",
+                    request.action(),
+                    user.role,
+                ));
+            }
+        }
+    }
+
+    // The comparison proves nothing if the scenarios deny everything
+    assert!(
+        granted > decided / 10,
+        "Only {granted} of {decided} requests were granted"
+    );
+    assert!(
+        mismatches.is_empty(),
+        "{} mismatches between the backends:\n{}",
+        mismatches.len(),
+        mismatches.join("\n")
+    );
+}
+
+#[test]
+fn test_backend_kind_is_parsed_from_its_name() {
+    assert_eq!(
+        "casbin".parse::<BackendKind>().ok(),
+        Some(BackendKind::Casbin)
+    );
+    assert_eq!(
+        "Native".parse::<BackendKind>().ok(),
+        Some(BackendKind::Native)
+    );
+    assert!("rego".parse::<BackendKind>().is_err());
+    assert_eq!(BackendKind::default().to_string(), "casbin");
+}
+
+#[test]
+fn test_both_backends_enforce_through_a_context() {
+    let password = hash("password123");
+    let mut rng = Rng(SEED);
+    let world = generate(&mut rng, &password);
+    let admin = UserData {
+        role: Role::Admin,
+        username: Username::try_from("admin").unwrap(),
+        password,
+        id: UserID::new(),
+        medical_folder: None,
+    };
+    for enforcer in [Enforcer::load().unwrap(), Enforcer::native()] {
+        let context = enforcer.with_subject(&admin);
+        assert!(context.fsck().is_ok());
+        assert!(context.read_data(&world.users[0]).is_ok());
+        assert!(context.dispute_access(&world.log[0]).is_err());
+    }
+}
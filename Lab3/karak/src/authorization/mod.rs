@@ -0,0 +1,1474 @@
+//! Contrôle d'accès: chaque opération du service est décrite par une
+//! `Request`, et décidée par un moteur de règles (`Backend`).
+//!
+//! Deux moteurs existent: les règles Casbin de `access_control/`, évaluées à
+//! l'exécution, et les mêmes règles écrites en Rust (`native`), vérifiées à
+//! la compilation. Un test différentiel s'assure qu'ils décident pareil.
+//!
+//! Les règles Casbin peuvent aussi être modifiées depuis l'application: une
+//! modification n'est appliquée que si les règles obtenues passent les
+//! scénarios de `scenarios`. Elles sont alors gardées dans la base, qui n'est
+//! pas signée, avec l'empreinte des règles signées dont elles dérivent: au
+//! démarrage, elles ne remplacent les règles signées que si celles-ci n'ont
+//! pas changé et qu'elles passent encore les mêmes vérifications.
+
+use std::collections::BTreeSet;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use derive_more::Display;
+use log::{error, info, log_enabled, Level};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::i18n::Msg;
+use crate::models::{
+    AccessLogEntry, AccessRequest, LabResult, MedicalReport, PolicyChange, PolicyRule,
+    Prescription, ProxyRelationship, ProxyScope, Role, RoleChangeRequest, UserData, UserID,
+};
+use crate::utils::input_validation::InvalidInput;
+use crate::utils::keyed_hash::to_hex;
+
+mod cache;
+mod casbin_backend;
+#[cfg(test)]
+mod differential;
+mod native;
+mod scenarios;
+mod signing;
+
+pub use cache::CacheStats;
+use cache::DecisionCache;
+pub use casbin_backend::{CasbinBackend, PolicyError, PolicySource};
+pub use native::NativeBackend;
+pub use scenarios::failed_scenarios;
+pub use signing::{signature_path, verify, PolicyKey, SignatureError};
+
+/// Un enforcer, qui délègue les décisions à un moteur de règles et garde
+/// en cache celles déjà prises par Casbin
+pub struct Enforcer {
+    backend: Box<dyn Backend>,
+    cache: Option<DecisionCache>,
+    locked: bool,
+    /// L'empreinte des règles chargées, avant toute modification
+    base: Option<String>,
+    /// Vrai si les règles en vigueur ne sont plus celles chargées. Elles
+    /// peuvent alors lire des attributs absents de la clé du cache, qui
+    /// n'est pas utilisé.
+    edited: bool,
+}
+
+type CasbinResult = Result<(), AccessDenied>;
+
+/// Une erreur sans détails en cas d'accès refusé
+#[derive(Debug, Error)]
+#[error("{}", Msg::ErrAccessDenied)]
+pub struct AccessDenied;
+
+/// Un moteur de règles d'accès
+pub trait Backend: Send + Sync {
+    /// Vrai si le sujet peut faire ce qui est demandé. Une erreur interne
+    /// du moteur est journalisée et vaut un refus.
+    fn decide(&self, subject: &Subject, request: &Request) -> bool;
+
+    /// Relit les règles, pour les moteurs qui les chargent
+    fn reload(&mut self) -> Result<(), PolicyError> {
+        Ok(())
+    }
+
+    /// Les règles en vigueur, pour les moteurs dont les règles peuvent être
+    /// modifiées à l'exécution
+    fn rules(&self) -> Option<Vec<PolicyRule>> {
+        None
+    }
+
+    /// Un nouveau moteur du même type, avec les règles données
+    fn with_rules(&self, _rules: &[PolicyRule]) -> Result<Box<dyn Backend>, PolicyError> {
+        Err(PolicyError::NotEditable)
+    }
+
+    /// Applique une modification des règles; faux si elle ne change rien
+    fn apply(&mut self, _change: &PolicyChange) -> Result<bool, PolicyError> {
+        Err(PolicyError::NotEditable)
+    }
+
+    /// Vrai si les décisions valent la peine d'être gardées en cache: les
+    /// règles natives sont plus rapides à évaluer que la clé à calculer
+    fn cacheable(&self) -> bool {
+        false
+    }
+}
+
+/// Le moteur du mode verrouillé, qui refuse tout
+struct Locked;
+
+impl Backend for Locked {
+    fn decide(&self, _: &Subject, _: &Request) -> bool {
+        false
+    }
+}
+
+/// Le choix du moteur de règles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display)]
+pub enum BackendKind {
+    #[default]
+    #[display("casbin")]
+    Casbin,
+    #[display("native")]
+    Native,
+}
+
+impl FromStr for BackendKind {
+    type Err = InvalidInput;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "casbin" => Ok(BackendKind::Casbin),
+            "native" => Ok(BackendKind::Native),
+            _ => Err(InvalidInput),
+        }
+    }
+}
+
+/// Un contexte contenant une référence à un enforcer et à un sujet.
+pub struct Context<'ctx> {
+    enforcer: &'ctx Enforcer,
+    subject: Subject<'ctx>,
+}
+
+/// Le sujet tel que vu par les règles: l'utilisateur, et les personnes qu'il
+/// représente ce jour (`r.sub.proxy_read`, `r.sub.proxy_manage`)
+#[derive(Debug, Serialize, Hash)]
+pub struct Subject<'ctx> {
+    #[serde(flatten)]
+    pub user: &'ctx UserData,
+    pub proxy_read: BTreeSet<UserID>,
+    pub proxy_manage: BTreeSet<UserID>,
+}
+
+impl<'ctx> Subject<'ctx> {
+    /// Un sujet qui ne représente personne
+    pub fn new(user: &'ctx UserData) -> Self {
+        Subject {
+            user,
+            proxy_read: BTreeSet::new(),
+            proxy_manage: BTreeSet::new(),
+        }
+    }
+}
+
+/// Une demande d'autorisation: une action et l'objet sur lequel elle porte
+#[derive(Debug, Clone, Copy)]
+pub enum Request<'a> {
+    ReadData(&'a UserData),
+    UpdateData(&'a UserData),
+    DeleteData(&'a UserData),
+    AddReport {
+        patient: &'a UserData,
+        report: &'a MedicalReport,
+    },
+    ReadReport {
+        report: &'a MedicalReport,
+        patient: &'a UserData,
+    },
+    UpdateReport(&'a MedicalReport),
+    AddLabResult {
+        patient: &'a UserData,
+        result: &'a LabResult,
+    },
+    ReadLabResult {
+        result: &'a LabResult,
+        patient: &'a UserData,
+    },
+    UpdateRole {
+        target: &'a UserData,
+        role: Role,
+    },
+    ReviewRoleChange(&'a RoleChangeRequest),
+    CancelRoleChange(&'a RoleChangeRequest),
+    AddDoctor {
+        patient: &'a UserData,
+        doctor: &'a UserData,
+    },
+    RemoveDoctor {
+        patient: &'a UserData,
+        doctor: &'a UserData,
+    },
+    RequestAccess {
+        patient: &'a UserData,
+        request: &'a AccessRequest,
+    },
+    ReviewAccess(&'a AccessRequest),
+    ReadAccessLog(&'a AccessLogEntry),
+    DisputeAccess(&'a AccessLogEntry),
+    ReviewDispute(&'a AccessLogEntry),
+    ImportUsers,
+    ResearchExport,
+    ManageProxy(&'a ProxyRelationship),
+    CloseAccount(&'a UserData),
+    Fsck,
+    ReadMetrics,
+    ManagePolicy,
+    RevealAvs(&'a UserData),
+    SearchAvs,
+    Prescribe {
+        patient: &'a UserData,
+        prescription: &'a Prescription,
+    },
+    ReadPrescription {
+        prescription: &'a Prescription,
+        patient: &'a UserData,
+    },
+    IssuePrescriptionCode(&'a Prescription),
+    LookupPrescription(&'a Prescription),
+    Dispense(&'a Prescription),
+}
+
+impl Request<'_> {
+    /// Les noms de toutes les actions
+    pub const ACTIONS: &'static [&'static str] = &[
+        "read-data",
+        "update-data",
+        "delete-data",
+        "add-report",
+        "read-report",
+        "update-report",
+        "add-lab-result",
+        "read-lab-result",
+        "update-role",
+        "review-role-change",
+        "cancel-role-change",
+        "add-doctor",
+        "remove-doctor",
+        "request-access",
+        "review-access",
+        "read-access-log",
+        "dispute-access",
+        "review-dispute",
+        "import-users",
+        "research-export",
+        "manage-proxy",
+        "close-account",
+        "fsck",
+        "read-metrics",
+        "manage-policy",
+        "reveal-avs",
+        "search-avs",
+        "prescribe",
+        "read-prescription",
+        "issue-prescription-code",
+        "lookup-prescription",
+        "dispense",
+    ];
+
+    /// Le nom de l'action dans les règles (`p.act`)
+    pub fn action(&self) -> &'static str {
+        match self {
+            Request::ReadData(_) => "read-data",
+            Request::UpdateData(_) => "update-data",
+            Request::DeleteData(_) => "delete-data",
+            Request::AddReport { .. } => "add-report",
+            Request::ReadReport { .. } => "read-report",
+            Request::UpdateReport(_) => "update-report",
+            Request::AddLabResult { .. } => "add-lab-result",
+            Request::ReadLabResult { .. } => "read-lab-result",
+            Request::UpdateRole { .. } => "update-role",
+            Request::ReviewRoleChange(_) => "review-role-change",
+            Request::CancelRoleChange(_) => "cancel-role-change",
+            Request::AddDoctor { .. } => "add-doctor",
+            Request::RemoveDoctor { .. } => "remove-doctor",
+            Request::RequestAccess { .. } => "request-access",
+            Request::ReviewAccess(_) => "review-access",
+            Request::ReadAccessLog(_) => "read-access-log",
+            Request::DisputeAccess(_) => "dispute-access",
+            Request::ReviewDispute(_) => "review-dispute",
+            Request::ImportUsers => "import-users",
+            Request::ResearchExport => "research-export",
+            Request::ManageProxy(_) => "manage-proxy",
+            Request::CloseAccount(_) => "close-account",
+            Request::Fsck => "fsck",
+            Request::ReadMetrics => "read-metrics",
+            Request::ManagePolicy => "manage-policy",
+            Request::RevealAvs(_) => "reveal-avs",
+            Request::SearchAvs => "search-avs",
+            Request::Prescribe { .. } => "prescribe",
+            Request::ReadPrescription { .. } => "read-prescription",
+            Request::IssuePrescriptionCode(_) => "issue-prescription-code",
+            Request::LookupPrescription(_) => "lookup-prescription",
+            Request::Dispense(_) => "dispense",
+        }
+    }
+
+    /// L'objet tel que vu par les règles Casbin (`r.obj`)
+    pub fn object(&self) -> Value {
+        match *self {
+            Request::ReadData(user)
+            | Request::UpdateData(user)
+            | Request::DeleteData(user)
+            | Request::CloseAccount(user)
+            | Request::RevealAvs(user) => json!(user),
+            Request::AddReport { patient, report } | Request::ReadReport { report, patient } => {
+                json!({ "patient": patient, "report": report })
+            }
+            Request::UpdateReport(report) => json!(report),
+            Request::AddLabResult { patient, result }
+            | Request::ReadLabResult { result, patient } => {
+                json!({ "patient": patient, "result": lab_result_refs(result) })
+            }
+            Request::UpdateRole { target, role } => json!({ "target": target, "role": role }),
+            Request::ReviewRoleChange(change) | Request::CancelRoleChange(change) => json!(change),
+            Request::AddDoctor { patient, doctor } | Request::RemoveDoctor { patient, doctor } => {
+                json!({ "patient": patient, "doctor": doctor })
+            }
+            Request::RequestAccess { patient, request } => {
+                json!({ "patient": patient, "request": request })
+            }
+            Request::ReviewAccess(request) => json!(request),
+            Request::ReadAccessLog(entry)
+            | Request::DisputeAccess(entry)
+            | Request::ReviewDispute(entry) => json!(entry),
+            Request::ImportUsers
+            | Request::ResearchExport
+            | Request::Fsck
+            | Request::ReadMetrics
+            | Request::ManagePolicy
+            | Request::SearchAvs => json!({}),
+            Request::ManageProxy(relationship) => json!(relationship),
+            Request::Prescribe {
+                patient,
+                prescription,
+            }
+            | Request::ReadPrescription {
+                prescription,
+                patient,
+            } => json!({ "patient": patient, "prescription": prescription }),
+            Request::IssuePrescriptionCode(prescription)
+            | Request::LookupPrescription(prescription) => json!(prescription),
+            Request::Dispense(prescription) => {
+                json!({ "prescription": prescription, "remaining": prescription.remaining() })
+            }
+        }
+    }
+
+    /// Ce dont les règles dépendent dans l'objet, pour la clé du cache: les
+    /// identifiants, les rôles, les accès accordés et l'état des demandes.
+    /// Le contenu des documents et les données personnelles n'en font pas
+    /// partie: les règles ne doivent pas les lire.
+    fn hash_seen<H: Hasher>(&self, state: &mut H) {
+        fn user<H: Hasher>(user: &UserData, state: &mut H) {
+            (user.id, user.role).hash(state);
+            user.medical_folder
+                .as_ref()
+                .map(|folder| (&folder.doctors, &folder.report_readers))
+                .hash(state);
+        }
+        fn prescription<H: Hasher>(prescription: &Prescription, state: &mut H) {
+            (
+                prescription.id,
+                prescription.patient,
+                prescription.prescriber,
+                prescription.remaining(),
+            )
+                .hash(state);
+        }
+        fn access_request<H: Hasher>(request: &AccessRequest, state: &mut H) {
+            (request.id, request.doctor, request.patient).hash(state);
+            (request.scope, request.status).hash(state);
+        }
+
+        match *self {
+            Request::ReadData(target)
+            | Request::UpdateData(target)
+            | Request::DeleteData(target)
+            | Request::CloseAccount(target)
+            | Request::RevealAvs(target) => user(target, state),
+            Request::AddReport { patient, report } | Request::ReadReport { report, patient } => {
+                user(patient, state);
+                (report.id, report.author, report.patient).hash(state);
+            }
+            Request::UpdateReport(report) => (report.id, report.author, report.patient).hash(state),
+            Request::AddLabResult { patient, result }
+            | Request::ReadLabResult { result, patient } => {
+                user(patient, state);
+                (result.id, result.patient, result.author).hash(state);
+            }
+            Request::UpdateRole { target, role } => {
+                user(target, state);
+                role.hash(state);
+            }
+            Request::ReviewRoleChange(change) | Request::CancelRoleChange(change) => (
+                change.id,
+                change.target,
+                change.role,
+                change.previous,
+                change.requester,
+                change.status,
+                change.reviewer,
+            )
+                .hash(state),
+            Request::AddDoctor { patient, doctor } | Request::RemoveDoctor { patient, doctor } => {
+                user(patient, state);
+                user(doctor, state);
+            }
+            Request::RequestAccess { patient, request } => {
+                user(patient, state);
+                access_request(request, state);
+            }
+            Request::ReviewAccess(request) => access_request(request, state),
+            Request::ReadAccessLog(entry)
+            | Request::DisputeAccess(entry)
+            | Request::ReviewDispute(entry) => (
+                entry.id,
+                entry.patient,
+                entry.accessor,
+                entry.accessor_role,
+                entry.dispute,
+            )
+                .hash(state),
+            Request::ImportUsers
+            | Request::ResearchExport
+            | Request::Fsck
+            | Request::ReadMetrics
+            | Request::ManagePolicy
+            | Request::SearchAvs => {}
+            Request::ManageProxy(relationship) => relationship.hash(state),
+            Request::Prescribe {
+                patient,
+                prescription: seen,
+            }
+            | Request::ReadPrescription {
+                prescription: seen,
+                patient,
+            } => {
+                user(patient, state);
+                prescription(seen, state);
+            }
+            Request::IssuePrescriptionCode(seen)
+            | Request::LookupPrescription(seen)
+            | Request::Dispense(seen) => prescription(seen, state),
+        }
+    }
+}
+
+impl Enforcer {
+    /// Un enforcer utilisant les règles Casbin intégrées à l'exécutable
+    pub fn load() -> Result<Self, PolicyError> {
+        Ok(Self::new(Box::new(CasbinBackend::load()?)))
+    }
+
+    /// Un enforcer utilisant les règles natives
+    pub fn native() -> Self {
+        Self::new(Box::new(NativeBackend))
+    }
+
+    pub fn new(backend: Box<dyn Backend>) -> Self {
+        Enforcer {
+            cache: backend.cacheable().then(DecisionCache::default),
+            base: backend.rules().map(|rules| rules_digest(&rules)),
+            backend,
+            locked: false,
+            edited: false,
+        }
+    }
+
+    /// Un enforcer qui refuse tout, quand les règles ne sont pas authentiques
+    pub fn locked() -> Self {
+        let mut enforcer = Self::new(Box::new(Locked));
+        enforcer.locked = true;
+        enforcer
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Désactive le cache: chaque demande est soumise au moteur de règles
+    pub fn without_cache(mut self) -> Self {
+        self.cache = None;
+        self
+    }
+
+    /// Oublie les décisions prises, après une modification pouvant les changer
+    pub fn invalidate(&self) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate();
+        }
+    }
+
+    /// Relit les règles et oublie les décisions prises avec les anciennes
+    pub fn reload(&mut self) -> Result<(), PolicyError> {
+        self.backend.reload()?;
+        self.base = self.backend.rules().map(|rules| rules_digest(&rules));
+        self.edited = false;
+        self.invalidate();
+        Ok(())
+    }
+
+    /// Le cache des décisions, sauf si les règles ont été modifiées
+    fn active_cache(&self) -> Option<&DecisionCache> {
+        self.cache.as_ref().filter(|_| !self.edited)
+    }
+
+    /// Remplace le moteur par une copie aux règles modifiées
+    fn replace_backend(&mut self, backend: Box<dyn Backend>) {
+        let digest = backend.rules().map(|rules| rules_digest(&rules));
+        self.edited = digest != self.base;
+        self.backend = backend;
+        self.invalidate();
+    }
+
+    /// L'empreinte des règles signées chargées, dont dérivent les
+    /// modifications faites depuis l'application
+    pub fn base_digest(&self) -> Option<&str> {
+        self.base.as_deref()
+    }
+
+    /// Les règles en vigueur, si le moteur permet de les modifier
+    pub fn rules(&self) -> Option<Vec<PolicyRule>> {
+        self.backend.rules()
+    }
+
+    /// Remplace les règles en vigueur, par exemple par celles gardées dans
+    /// la base, si `check` accepte le moteur obtenu
+    pub fn set_rules<E: From<PolicyError>>(
+        &mut self,
+        rules: &[PolicyRule],
+        check: impl FnOnce(&dyn Backend) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let candidate = self.backend.with_rules(rules)?;
+        check(candidate.as_ref())?;
+        self.replace_backend(candidate);
+        Ok(())
+    }
+
+    /// Essaie une modification des règles sur une copie du moteur, et
+    /// retourne la copie si `check` l'accepte, sans l'appliquer
+    pub fn check_change<E: From<PolicyError>>(
+        &self,
+        change: &PolicyChange,
+        check: impl FnOnce(&dyn Backend) -> Result<(), E>,
+    ) -> Result<Box<dyn Backend>, E> {
+        if let PolicyChange::Add(rule) = change {
+            if !Request::ACTIONS.contains(&rule.action.as_str()) || rule.rule.trim().is_empty() {
+                return Err(PolicyError::InvalidRule.into());
+            }
+        }
+        let rules = self.rules().ok_or(PolicyError::NotEditable)?;
+        let mut candidate = self.backend.with_rules(&rules)?;
+        if !candidate.apply(change)? {
+            return Err(PolicyError::Unchanged.into());
+        }
+        check(candidate.as_ref())?;
+        Ok(candidate)
+    }
+
+    /// Essaie une modification des règles sur une copie du moteur, et ne
+    /// l'applique que si `check` accepte la copie. Retourne les règles
+    /// obtenues.
+    pub fn change_rules<E: From<PolicyError>>(
+        &mut self,
+        change: &PolicyChange,
+        check: impl FnOnce(&dyn Backend) -> Result<(), E>,
+    ) -> Result<Vec<PolicyRule>, E> {
+        let candidate = self.check_change(change, check)?;
+        let rules = candidate.rules().unwrap_or_default();
+        self.replace_backend(candidate);
+        Ok(rules)
+    }
+
+    /// Les mesures du cache, nulles s'il est désactivé
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache
+            .as_ref()
+            .map(DecisionCache::stats)
+            .unwrap_or_default()
+    }
+
+    /// Un enforcer utilisant le moteur donné; les règles Casbin sont lues
+    /// depuis `source`, et leur signature vérifiée
+    pub fn with_backend(kind: BackendKind, source: &PolicySource) -> Result<Self, PolicyError> {
+        match kind {
+            BackendKind::Casbin => match CasbinBackend::from_source(source) {
+                Ok(backend) => Ok(Self::new(Box::new(backend))),
+                Err(e) if e.is_signature() && source.lockdown => {
+                    error!("Access policy not authentic, locked down: {e}");
+                    Ok(Self::locked())
+                }
+                Err(e) => Err(e),
+            },
+            BackendKind::Native => Ok(Self::native()),
+        }
+    }
+
+    pub fn with_subject<'ctx>(&'ctx self, subject: &'ctx UserData) -> Context<'ctx> {
+        Context {
+            enforcer: self,
+            subject: Subject::new(subject),
+        }
+    }
+}
+
+impl Drop for Enforcer {
+    /// Journalise l'efficacité du cache en fin de session
+    fn drop(&mut self) {
+        if self.cache.is_some() {
+            let stats = self.cache_stats();
+            info!(
+                hits = stats.hits,
+                misses = stats.misses,
+                invalidations = stats.invalidations,
+                hit_rate = stats.hit_rate();
+                "Decision cache"
+            );
+        }
+    }
+}
+
+impl Context<'_> {
+    /// Permet au sujet d'agir au nom des personnes qu'il représente, selon
+    /// les relations en vigueur ce jour
+    pub fn with_proxies<'a>(
+        mut self,
+        relationships: impl IntoIterator<Item = &'a ProxyRelationship>,
+        today: NaiveDate,
+    ) -> Self {
+        for relationship in relationships {
+            if relationship.proxy != self.subject.user.id || !relationship.is_active(today) {
+                continue;
+            }
+            self.subject.proxy_read.insert(relationship.dependant);
+            if relationship.scope == ProxyScope::Manage {
+                self.subject.proxy_manage.insert(relationship.dependant);
+            }
+        }
+        self
+    }
+
+    fn enforce(&self, request: Request) -> CasbinResult {
+        let subject = &self.subject;
+        let backend = &self.enforcer.backend;
+        let (granted, cached) = match self.enforcer.active_cache() {
+            Some(cache) => cache.get_or_decide(cache.key(subject, &request), || {
+                backend.decide(subject, &request)
+            }),
+            None => (backend.decide(subject, &request), false),
+        };
+
+        // Seuls les identifiants sont journalisés, jamais les données.
+        // L'objet n'est construit que si la décision est journalisée.
+        if log_enabled!(Level::Info) {
+            info!(
+                subject:% = subject.user.id,
+                role:? = subject.user.role,
+                action = request.action(),
+                object:% = identifiers(&request.object()),
+                granted,
+                cached;
+                "Access decision"
+            );
+        }
+
+        if granted {
+            Ok(())
+        } else {
+            Err(AccessDenied)
+        }
+    }
+
+    pub fn read_data(&self, patient: &UserData) -> CasbinResult {
+        self.enforce(Request::ReadData(patient))
+    }
+
+    pub fn update_data(&self, target: &UserData) -> CasbinResult {
+        self.enforce(Request::UpdateData(target))
+    }
+
+    pub fn delete_data(&self, target: &UserData) -> CasbinResult {
+        self.enforce(Request::DeleteData(target))
+    }
+
+    pub fn add_report(&self, patient: &UserData, report: &MedicalReport) -> CasbinResult {
+        self.enforce(Request::AddReport { patient, report })
+    }
+
+    pub fn read_report(&self, report: &MedicalReport, patient: &UserData) -> CasbinResult {
+        self.enforce(Request::ReadReport { report, patient })
+    }
+
+    pub fn add_lab_result(&self, patient: &UserData, result: &LabResult) -> CasbinResult {
+        self.enforce(Request::AddLabResult { patient, result })
+    }
+
+    pub fn read_lab_result(&self, result: &LabResult, patient: &UserData) -> CasbinResult {
+        self.enforce(Request::ReadLabResult { result, patient })
+    }
+
+    pub fn update_report(&self, report: &MedicalReport) -> CasbinResult {
+        self.enforce(Request::UpdateReport(report))
+    }
+
+    pub fn update_role(&self, target: &UserData, role: Role) -> CasbinResult {
+        self.enforce(Request::UpdateRole { target, role })
+    }
+
+    pub fn review_role_change(&self, change: &RoleChangeRequest) -> CasbinResult {
+        self.enforce(Request::ReviewRoleChange(change))
+    }
+
+    pub fn cancel_role_change(&self, change: &RoleChangeRequest) -> CasbinResult {
+        self.enforce(Request::CancelRoleChange(change))
+    }
+
+    pub fn add_doctor(&self, target: &UserData, doctor: &UserData) -> CasbinResult {
+        self.enforce(Request::AddDoctor {
+            patient: target,
+            doctor,
+        })
+    }
+
+    pub fn remove_doctor(&self, target: &UserData, doctor: &UserData) -> CasbinResult {
+        self.enforce(Request::RemoveDoctor {
+            patient: target,
+            doctor,
+        })
+    }
+
+    pub fn request_access(&self, patient: &UserData, request: &AccessRequest) -> CasbinResult {
+        self.enforce(Request::RequestAccess { patient, request })
+    }
+
+    pub fn review_access(&self, request: &AccessRequest) -> CasbinResult {
+        self.enforce(Request::ReviewAccess(request))
+    }
+
+    pub fn read_access_log(&self, entry: &AccessLogEntry) -> CasbinResult {
+        self.enforce(Request::ReadAccessLog(entry))
+    }
+
+    pub fn dispute_access(&self, entry: &AccessLogEntry) -> CasbinResult {
+        self.enforce(Request::DisputeAccess(entry))
+    }
+
+    pub fn review_dispute(&self, entry: &AccessLogEntry) -> CasbinResult {
+        self.enforce(Request::ReviewDispute(entry))
+    }
+
+    pub fn import_users(&self) -> CasbinResult {
+        self.enforce(Request::ImportUsers)
+    }
+
+    pub fn research_export(&self) -> CasbinResult {
+        self.enforce(Request::ResearchExport)
+    }
+
+    pub fn manage_proxy(&self, relationship: &ProxyRelationship) -> CasbinResult {
+        self.enforce(Request::ManageProxy(relationship))
+    }
+
+    pub fn close_account(&self, target: &UserData) -> CasbinResult {
+        self.enforce(Request::CloseAccount(target))
+    }
+
+    pub fn fsck(&self) -> CasbinResult {
+        self.enforce(Request::Fsck)
+    }
+
+    pub fn read_metrics(&self) -> CasbinResult {
+        self.enforce(Request::ReadMetrics)
+    }
+
+    pub fn manage_policy(&self) -> CasbinResult {
+        self.enforce(Request::ManagePolicy)
+    }
+
+    pub fn prescribe(&self, patient: &UserData, prescription: &Prescription) -> CasbinResult {
+        self.enforce(Request::Prescribe {
+            patient,
+            prescription,
+        })
+    }
+
+    pub fn read_prescription(
+        &self,
+        prescription: &Prescription,
+        patient: &UserData,
+    ) -> CasbinResult {
+        self.enforce(Request::ReadPrescription {
+            prescription,
+            patient,
+        })
+    }
+
+    pub fn issue_prescription_code(&self, prescription: &Prescription) -> CasbinResult {
+        self.enforce(Request::IssuePrescriptionCode(prescription))
+    }
+
+    pub fn lookup_prescription(&self, prescription: &Prescription) -> CasbinResult {
+        self.enforce(Request::LookupPrescription(prescription))
+    }
+
+    pub fn dispense(&self, prescription: &Prescription) -> CasbinResult {
+        self.enforce(Request::Dispense(prescription))
+    }
+
+    pub fn reveal_avs(&self, patient: &UserData) -> CasbinResult {
+        self.enforce(Request::RevealAvs(patient))
+    }
+
+    pub fn search_avs(&self) -> CasbinResult {
+        self.enforce(Request::SearchAvs)
+    }
+}
+
+/// Les références d'un résultat de laboratoire vues par les règles. Les
+/// valeurs numériques ne sont pas transmises: le moteur de règles ne
+/// connaît pas les nombres à virgule.
+fn lab_result_refs(result: &LabResult) -> Value {
+    json!({ "id": result.id, "patient": result.patient, "author": result.author })
+}
+
+/// L'empreinte d'un ensemble de règles, quel que soit leur ordre
+pub fn rules_digest(rules: &[PolicyRule]) -> String {
+    let mut sorted: Vec<&PolicyRule> = rules.iter().collect();
+    sorted.sort();
+    let mut hasher = Sha256::new();
+    for rule in sorted {
+        for column in [&rule.action, &rule.rule] {
+            hasher.update((column.len() as u64).to_le_bytes());
+            hasher.update(column);
+        }
+    }
+    to_hex(&hasher.finalize())
+}
+
+/// Ne garde d'un objet que les identifiants, pour le journal
+fn identifiers(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => match fields.get("id") {
+            Some(id) => id.clone(),
+            None => Value::Object(
+                fields
+                    .iter()
+                    .map(|(key, field)| (key.clone(), identifiers(field)))
+                    .filter(|(_, field)| !field.is_null())
+                    .collect(),
+            ),
+        },
+        Value::Array(items) => Value::Array(items.iter().map(identifiers).collect()),
+        _ => Value::Null,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+    use crate::models::*;
+    use crate::utils::input_validation::{AVSNumber, Username};
+    use crate::utils::password_utils::{hash};
+    use super::*;
+
+    /// Creates an enforcer to use within the test
+    fn set_enforcer() -> Enforcer {
+        Enforcer::load().expect("Error in loading Enforcer")
+    }
+
+    /// Creates a test user with the given role and username
+    fn create_test_user(role: Role, username: &str) -> UserData {
+        UserData {
+            id: UserID::new(),
+            role,
+            username: Username::try_from(username.to_string()).unwrap(),
+            password: hash("password123"),
+            medical_folder: None,
+        }
+    }
+
+    /// Creates a test user with a medical folder
+    fn create_test_patient(username: &str, doctor: UserID) -> UserData {
+        let mut user = create_test_user(Role::Patient, username);
+        user.medical_folder = Some(MedicalFolder {
+            personal_data: PersonalData {
+                avs_number: AVSNumber::try_from("756.1234.5678.97".to_string()).unwrap(),
+                blood_type: BloodType::A,
+            },
+            doctors: BTreeSet::from_iter([doctor]),
+            report_readers: BTreeSet::new(),
+        });
+        user
+    }
+
+    /// Creates a test doctor
+    fn create_test_doctor(username: &str) -> UserData {
+        create_test_user(Role::Doctor, username)
+    }
+
+    /// Creates a test admin
+    fn create_test_admin(username: &str) -> UserData {
+        create_test_user(Role::Admin, username)
+    }
+
+    /// Creates a pending access request from a doctor
+    fn create_test_request(doctor: UserID, patient: UserID, scope: AccessScope) -> AccessRequest {
+        AccessRequest {
+            id: AccessRequestID::new(),
+            doctor,
+            patient,
+            reason: "Second opinion".to_string(),
+            scope,
+            status: RequestStatus::Pending,
+        }
+    }
+
+    /// Creates an access log entry of a doctor reading a patient folder
+    fn create_test_access(accessor: &UserData, patient: UserID) -> AccessLogEntry {
+        AccessLogEntry {
+            id: AccessLogID::new(),
+            patient,
+            accessor: accessor.id,
+            accessor_role: accessor.role,
+            action: AccessAction::ReadData,
+            at: chrono::Utc::now(),
+            dispute: DisputeStatus::Undisputed,
+            as_proxy: None,
+        }
+    }
+
+    /// Creates a test medical report
+    fn create_test_report(author: UserID, patient: UserID) -> MedicalReport {
+        MedicalReport {
+            id: ReportID::new(),
+            title: "Test Report".to_string(),
+            author,
+            patient,
+            content: "Test content".to_string(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_admin_permissions() {
+        let enforcer = set_enforcer();
+        let admin = create_test_admin("admin");
+        let doctor = create_test_doctor("doctor");
+        let patient = create_test_patient("patient", doctor.id);
+        let report = create_test_report(doctor.id, patient.id);
+        let context = enforcer.with_subject(&admin);
+
+        // Test 1: Admin read data permission
+        let read_data_result = context.read_data(&patient);
+        assert!(
+            read_data_result.is_ok(),
+            "Admin should be able to read any patient's data (Casbin rule: p, read-data, r.sub.role == \"Admin\"), but got error: {:?}",
+            read_data_result.err()
+        );
+
+        // Test 2: Admin update data permission
+        let update_data_result = context.update_data(&patient);
+        assert!(
+            update_data_result.is_ok(),
+            "Admin should be able to update any patient's data (Casbin rule: p, update-data, r.sub.role == \"Admin\"), but got error: {:?}",
+            update_data_result.err()
+        );
+
+        // Test 3: Admin delete data permission
+        let delete_data_result = context.delete_data(&patient);
+        assert!(
+            delete_data_result.is_ok(),
+            "Admin should be able to delete any patient's data (Casbin rule: p, delete-data, r.sub.role == \"Admin\"), but got error: {:?}",
+            delete_data_result.err()
+        );
+
+        // Test 4: Admin doctor management permissions
+        let new_doctor = create_test_doctor("new_doctor");
+
+        let add_doctor_result = context.add_doctor(&patient, &new_doctor);
+        assert!(
+            add_doctor_result.is_ok(),
+            "Admin should be able to add doctors to any patient (Casbin rule: p, add-doctor, r.sub.role == \"Admin\"), but got error: {:?}",
+            add_doctor_result.err()
+        );
+
+        let remove_doctor_result = context.remove_doctor(&patient, &new_doctor);
+        assert!(
+            remove_doctor_result.is_ok(),
+            "Admin should be able to remove doctors from any patient (Casbin rule: p, remove-doctor, r.sub.role == \"Admin\"), but got error: {:?}",
+            remove_doctor_result.err()
+        );
+
+        // Test 5: Admin report management permissions
+        let add_report_result = context.add_report(&patient, &report);
+        assert!(
+            add_report_result.is_ok(),
+            "Admin should be able to add reports for any patient (Casbin rule: p, add-report, r.sub.role == \"Admin\"), but got error: {:?}",
+            add_report_result.err()
+        );
+
+        let update_report_result = context.update_report(&report);
+        assert!(
+            update_report_result.is_ok(),
+            "Admin should be able to update any report (Casbin rule: p, update-report, r.sub.role == \"Admin\"), but got error: {:?}",
+            update_report_result.err()
+        );
+
+        let read_report_result = context.read_report(&report, &patient);
+        assert!(
+            read_report_result.is_ok(),
+            "Admin should be able to read any report (Casbin rule: p, read-report, r.sub.role == \
+            \"Admin\"), but got error: {:?}",
+            read_report_result.err()
+        );
+
+        // Test 6: Admin role management permission
+        let role_update_result = context.update_role(&patient, Role::Doctor);
+        assert!(
+            role_update_result.is_ok(),
+            "Admin should be able to update any user's role (Casbin rule: p, update-role, r.sub.role == \"Admin\"), but got error: {:?}",
+            role_update_result.err()
+        );
+    }
+
+    #[test]
+    fn test_user_self_management() {
+        let enforcer = set_enforcer();
+        let doctor = create_test_doctor("doctor");
+        let patient = create_test_patient("patient", doctor.id);
+        let report = create_test_report(doctor.id, patient.id);
+
+        // Patient or doctor
+        let context = enforcer.with_subject(&patient);
+
+        // User can read their own data
+        let read_data_result = context.read_data(&patient);
+        assert!(
+            read_data_result.is_ok(),
+            "User should be able to read their own data (Casbin rule: p, read-data, r.sub.id \
+            == r.obj.id), but got error: {:?}",
+            read_data_result.err()
+        );
+
+        // User can delete their own data
+        let delete_data_result = context.delete_data(&patient);
+        assert!(
+            delete_data_result.is_ok(),
+            "User should be able to delete their own data (Casbin rule: p, delete-data, r.sub.id \
+            == r.obj.id), but got error: {:?}",
+            delete_data_result.err()
+        );
+
+        // User can update their own data
+        let update_data_result = context.update_data(&patient);
+        assert!(
+            update_data_result.is_ok(),
+            "User should be able to update their own data (Casbin rule: p, update-data, r.sub.id \
+            == r.obj.id), but got error: {:?}",
+            update_data_result.err()
+        );
+
+        // User can choose their doctor
+        let add_doctor_result = context.add_doctor(&patient, &doctor);
+        assert!(
+            add_doctor_result.is_ok(),
+            "User can add their doctor to their medical folder (Casbin rule: p, add-doctor, r.sub\
+            .id == r.obj.patient.id && (r.obj.doctor.role == \"Doctor\" || r.obj.doctor.role == \
+            \"Admin\"), but got: {:?}",
+            add_doctor_result.err()
+        );
+
+        // User can remove their doctor
+        let remove_doctor_result = context.remove_doctor(&patient, &doctor);
+        assert!(
+            remove_doctor_result.is_ok(),
+            "User can remove their doctor to their medical folder (Casbin rule: p, remove-doctor,\
+             r.sub.id == r.obj.patient.id && (r.obj.doctor.role == \"Doctor\" || r.obj.doctor\
+             .role == \"Admin\"), but got: {:?}",
+            remove_doctor_result.err()
+        )
+    }
+
+    #[test]
+    fn test_patient_without_permission() {
+        let enforcer = set_enforcer();
+        let doctor = create_test_doctor("doctor");
+        let patient = create_test_patient("patient", doctor.id);
+        let report = create_test_report(doctor.id, patient.id);
+        let context = enforcer.with_subject(&patient);
+
+        // Patient should not be able to manage the reports
+        let add_report_result = context.add_report(&patient, &report);
+        assert!(
+            add_report_result.is_err(),
+            "User should not be able to add reports for any patient, but got: {:?}",
+            add_report_result.ok()
+        );
+
+        let update_report_result = context.update_report(&report);
+        assert!(
+            update_report_result.is_err(),
+            "User should not be able to update any report but got: {:?}",
+            update_report_result.ok()
+        );
+
+        let read_report_result = context.read_report(&report, &patient);
+        assert!(
+            read_report_result.is_err(),
+            "User should not be able to read any report, but got: {:?}",
+            read_report_result.ok()
+        );
+
+        // Patient should not be able to update the role of a person
+        let role_update_result = context.update_role(&patient, Role::Doctor);
+        assert!(
+            role_update_result.is_err(),
+            "User should not be able to update any user's role, but got: {:?}",
+            role_update_result.ok()
+        );
+    }
+
+    #[test]
+    fn test_doctor_permissions() {
+        let enforcer = set_enforcer();
+        let admin = create_test_admin("admin");
+        let doctor = create_test_doctor("doctor");
+        let patient = create_test_patient("patient", doctor.id);
+        let context = enforcer.with_subject(&doctor);
+
+        // Doctor can see the data from their patient
+        let read_data_patient_result = context.read_data(&patient);
+        assert!(
+            read_data_patient_result.is_ok(),
+            "Doctor should be able to see the data from their patient (Casbin rule: p, read-data,\
+             r.sub.role == \"Doctor\" && r.obj.patient.medical_folder != () && r.obj.patient\
+             .medical_folder.doctors.contains(r.sub.id), but got: {:?}",
+            read_data_patient_result.err()
+        );
+
+        // Doctor can only see the data from their patient
+        let new_doctor = create_test_doctor("newDoc");
+        let context_new = enforcer.with_subject(&new_doctor);
+
+        let read_data_not_authorized = context_new.read_data(&patient);
+        assert!(
+            read_data_not_authorized.is_err(),
+            "Doctor should not be able to read data from patient that are not theirs, but got: \
+            {:?}",
+            read_data_not_authorized.ok()
+        );
+
+        // Doctor can add a report for any patient
+        let report = create_test_report(new_doctor.id, patient.id);
+        let add_report_patient = context_new.add_report(&patient, &report);
+
+        assert!(
+            add_report_patient.is_ok(),
+            "Doctor should be able to add a report for a patient (Casbin rule: p, add-report, r\
+            .sub.role == \"Doctor\" && r.sub.id == r.obj.report.author && r.obj.patient.id == r\
+            .obj.report.patient && r.obj.patient.medical_folder != null, but got: {:?}",
+            add_report_patient.err()
+        );
+
+        // Doctor can modify their report
+        let update_report = context_new.update_report(&report);
+
+        assert!(
+            update_report.is_ok(),
+            "Author of a report should be able to modify it (Casbin rule: p, read-report, r.obj\
+            .author == r.sub.id), but got: {:?}",
+            update_report.err()
+        );
+
+        // Doctor can read their report
+        let read_report = context_new.read_report(&report, &patient);
+
+        assert!(
+            read_report.is_ok(),
+            "Author of a report should be able to read it (Casbin rule: p, read-report, r.obj\
+            .author == r.sub.id), but got: {:?}",
+            read_report.err()
+        );
+
+        // Doctor can read all the reports of their patient
+        let read_report_patient = context.read_report(&report, &patient);
+
+        assert!(
+            read_report_patient.is_ok(),
+            "Doctor should be able to read report of their patient (Casbin rule: p, read-report, \
+            r.sub.role == \"Doctor\" && r.obj.medical_folder != () && r.obj.medical_folder\
+            .doctors.contains(r.sub.id), but got: {:?}",
+            read_report_patient.err()
+        );
+    }
+
+    #[test]
+    fn test_access_requests() {
+        let enforcer = set_enforcer();
+        let doctor = create_test_doctor("doctor");
+        let specialist = create_test_doctor("specialist");
+        let patient = create_test_patient("patient", doctor.id);
+        let other_patient = create_test_patient("other", doctor.id);
+        let request = create_test_request(specialist.id, patient.id, AccessScope::Folder);
+
+        // A doctor can file a request in their own name
+        let specialist_context = enforcer.with_subject(&specialist);
+        let request_result = specialist_context.request_access(&patient, &request);
+        assert!(
+            request_result.is_ok(),
+            "Doctor should be able to request access to a patient folder (Casbin rule: p, \
+            request-access, r.sub.role == \"Doctor\" && r.sub.id == r.obj.request.doctor ...), \
+            but got: {:?}",
+            request_result.err()
+        );
+
+        // A doctor cannot file a request in the name of another doctor
+        let doctor_context = enforcer.with_subject(&doctor);
+        let impersonation = doctor_context.request_access(&patient, &request);
+        assert!(
+            impersonation.is_err(),
+            "Doctor should not be able to file a request for someone else, but got: {:?}",
+            impersonation.ok()
+        );
+
+        // A patient cannot file a request
+        let patient_context = enforcer.with_subject(&patient);
+        let own_request = create_test_request(patient.id, patient.id, AccessScope::Folder);
+        let patient_request = patient_context.request_access(&patient, &own_request);
+        assert!(
+            patient_request.is_err(),
+            "Patient should not be able to file an access request, but got: {:?}",
+            patient_request.ok()
+        );
+
+        // Only the patient concerned can review the request
+        let review_result = patient_context.review_access(&request);
+        assert!(
+            review_result.is_ok(),
+            "Patient should be able to review requests to their folder (Casbin rule: p, \
+            review-access, r.sub.id == r.obj.patient && r.obj.status == \"Pending\"), but got: \
+            {:?}",
+            review_result.err()
+        );
+
+        let other_context = enforcer.with_subject(&other_patient);
+        let other_review = other_context.review_access(&request);
+        assert!(
+            other_review.is_err(),
+            "Patient should not be able to review requests to another folder, but got: {:?}",
+            other_review.ok()
+        );
+
+        let self_review = specialist_context.review_access(&request);
+        assert!(
+            self_review.is_err(),
+            "Doctor should not be able to approve their own request, but got: {:?}",
+            self_review.ok()
+        );
+
+        // A request can only be reviewed once
+        let mut decided = create_test_request(specialist.id, patient.id, AccessScope::Folder);
+        decided.status = RequestStatus::Denied;
+        let decided_review = patient_context.review_access(&decided);
+        assert!(
+            decided_review.is_err(),
+            "A request that was already decided should not be reviewable, but got: {:?}",
+            decided_review.ok()
+        );
+    }
+
+    #[test]
+    fn test_report_only_access() {
+        let enforcer = set_enforcer();
+        let doctor = create_test_doctor("doctor");
+        let specialist = create_test_doctor("specialist");
+        let mut patient = create_test_patient("patient", doctor.id);
+        patient
+            .medical_folder
+            .as_mut()
+            .unwrap()
+            .grant(specialist.id, AccessScope::Reports);
+        let report = create_test_report(doctor.id, patient.id);
+        let context = enforcer.with_subject(&specialist);
+
+        let read_report = context.read_report(&report, &patient);
+        assert!(
+            read_report.is_ok(),
+            "Doctor with a reports-only access should be able to read reports (Casbin rule: p, \
+            read-report, ... r.obj.patient.medical_folder.report_readers.contains(r.sub.id)), \
+            but got: {:?}",
+            read_report.err()
+        );
+
+        let read_data = context.read_data(&patient);
+        assert!(
+            read_data.is_err(),
+            "Doctor with a reports-only access should not see the personal data, but got: {:?}",
+            read_data.ok()
+        );
+    }
+
+    #[test]
+    fn test_access_log_disputes() {
+        let enforcer = set_enforcer();
+        let admin = create_test_admin("admin");
+        let doctor = create_test_doctor("doctor");
+        let patient = create_test_patient("patient", doctor.id);
+        let mut entry = create_test_access(&doctor, patient.id);
+
+        let patient_context = enforcer.with_subject(&patient);
+        let doctor_context = enforcer.with_subject(&doctor);
+        let admin_context = enforcer.with_subject(&admin);
+
+        assert!(
+            patient_context.read_access_log(&entry).is_ok(),
+            "Patient should see who accessed their folder (Casbin rule: p, read-access-log, \
+            r.sub.id == r.obj.patient)"
+        );
+        assert!(
+            doctor_context.read_access_log(&entry).is_err(),
+            "Doctor should not see the access history of their patient"
+        );
+        assert!(
+            admin_context.review_dispute(&entry).is_err(),
+            "Admin should only review disputed accesses"
+        );
+
+        let dispute = patient_context.dispute_access(&entry);
+        assert!(
+            dispute.is_ok(),
+            "Patient should be able to dispute an access to their folder (Casbin rule: p, \
+            dispute-access, r.sub.id == r.obj.patient && r.obj.dispute == \"Undisputed\"), but \
+            got: {:?}",
+            dispute.err()
+        );
+        assert!(
+            doctor_context.dispute_access(&entry).is_err(),
+            "Doctor should not be able to dispute accesses to another folder"
+        );
+
+        entry.dispute = DisputeStatus::Disputed;
+        assert!(
+            patient_context.dispute_access(&entry).is_err(),
+            "An access should only be disputed once"
+        );
+        assert!(
+            patient_context.review_dispute(&entry).is_err(),
+            "Patient should not be able to close their own dispute"
+        );
+        let review = admin_context.review_dispute(&entry);
+        assert!(
+            review.is_ok(),
+            "Admin should be able to review disputed accesses (Casbin rule: p, review-dispute, \
+            r.sub.role == \"Admin\" && r.obj.dispute == \"Disputed\"), but got: {:?}",
+            review.err()
+        );
+    }
+
+    #[test]
+    fn test_reveal_avs_and_log_redaction() {
+        let enforcer = set_enforcer();
+        let doctor = create_test_doctor("doctor");
+        let other_doctor = create_test_doctor("other");
+        let patient = create_test_patient("patient", doctor.id);
+
+        for (user, allowed) in [(&patient, true), (&doctor, true), (&other_doctor, false)] {
+            assert_eq!(
+                enforcer.with_subject(user).reveal_avs(&patient).is_ok(),
+                allowed,
+                "Unexpected reveal-avs decision for {}",
+                user.username
+            );
+        }
+
+        let logged = identifiers(&json!({ "target": &patient, "role": "Doctor" }));
+        assert_eq!(logged, json!({ "target": patient.id }));
+        assert_eq!(identifiers(&json!([&doctor])), json!([doctor.id]));
+    }
+
+    #[test]
+    fn test_decision_cache() {
+        let enforcer = set_enforcer();
+        let doctor = create_test_doctor("doctor");
+        let mut patient = create_test_patient("patient", doctor.id);
+        let context = enforcer.with_subject(&doctor);
+
+        assert!(context.read_data(&patient).is_ok());
+        assert!(context.read_data(&patient).is_ok());
+        let stats = enforcer.cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+        assert_eq!(stats.hit_rate(), 0.5);
+
+        // Any change to a grant is a new decision, even without invalidation
+        patient.medical_folder.as_mut().unwrap().revoke(doctor.id);
+        assert!(enforcer.with_subject(&doctor).read_data(&patient).is_err());
+        assert_eq!(enforcer.cache_stats().misses, 2);
+
+        enforcer.invalidate();
+        let stats = enforcer.cache_stats();
+        assert_eq!((stats.entries, stats.invalidations), (0, 1));
+
+        let uncached = set_enforcer().without_cache();
+        assert!(uncached.with_subject(&doctor).read_data(&patient).is_err());
+        assert_eq!(uncached.cache_stats(), CacheStats::default());
+
+        // Native rules are cheaper than the key, they are never cached
+        let native = Enforcer::native();
+        assert!(native.with_subject(&doctor).read_data(&patient).is_err());
+        assert_eq!(native.cache_stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn test_edited_rules_bypass_the_cache() {
+        let mut enforcer = set_enforcer();
+        let mut doctor = create_test_doctor("doctor");
+        let patient = create_test_patient("patient", UserID::new());
+
+        // The username is not part of the cache key
+        let by_name = PolicyChange::Add(PolicyRule {
+            action: "read-data".to_owned(),
+            rule: "r.sub.username == \"house\"".to_owned(),
+        });
+        enforcer
+            .change_rules(&by_name, |_| Ok::<_, PolicyError>(()))
+            .unwrap();
+        assert!(enforcer.with_subject(&doctor).read_data(&patient).is_err());
+        doctor.username = Username::try_from("house".to_string()).unwrap();
+        assert!(enforcer.with_subject(&doctor).read_data(&patient).is_ok());
+        let stats = enforcer.cache_stats();
+        assert_eq!((stats.hits, stats.misses), (0, 0));
+
+        // The signed rules use the cache again
+        enforcer.reload().unwrap();
+        assert!(enforcer.with_subject(&doctor).read_data(&patient).is_err());
+        assert_eq!(enforcer.cache_stats().misses, 1);
+    }
+
+    #[test]
+    fn test_decision_cache_key_ignores_contents() {
+        let enforcer = set_enforcer();
+        let doctor = create_test_doctor("doctor");
+        let patient = create_test_patient("patient", doctor.id);
+        let mut report = create_test_report(doctor.id, patient.id);
+        let key = |report: &MedicalReport| {
+            let subject = Subject::new(&doctor);
+            let request = Request::ReadReport {
+                report,
+                patient: &patient,
+            };
+            enforcer.cache.as_ref().unwrap().key(&subject, &request)
+        };
+
+        let before = key(&report);
+        report.content = "Changed".into();
+        assert_eq!(key(&report), before);
+        report.author = UserID::new();
+        assert_ne!(key(&report), before);
+
+        // A new version of the rules never reuses older decisions
+        report.author = doctor.id;
+        enforcer.invalidate();
+        assert_ne!(key(&report), before);
+    }
+}
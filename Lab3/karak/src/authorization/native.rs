@@ -0,0 +1,148 @@
+//! Les règles de `access_control/policy.csv`, écrites en Rust: chaque
+//! action est décidée par une seule expression, vérifiée à la compilation
+
+use crate::models::{
+    DisputeStatus, ProxyKind, RequestStatus, Role, RoleChangeStatus, UserData, UserID,
+};
+
+use super::{Backend, Request, Subject};
+
+/// Le moteur de règles natif
+pub struct NativeBackend;
+
+impl Backend for NativeBackend {
+    fn decide(&self, subject: &Subject, request: &Request) -> bool {
+        let me = subject.user;
+        let admin = me.role == Role::Admin;
+        let doctor = me.role == Role::Doctor;
+        let own = |id: UserID| me.id == id;
+        let reads_for = |id: UserID| subject.proxy_read.contains(&id);
+        let manages_for = |id: UserID| subject.proxy_manage.contains(&id);
+        // Un médecin traitant du patient
+        let treats = |patient: &UserData| {
+            doctor
+                && patient
+                    .medical_folder
+                    .as_ref()
+                    .is_some_and(|folder| folder.doctors.contains(&me.id))
+        };
+        // Un médecin ayant accès aux seuls rapports du patient
+        let reads_reports = |patient: &UserData| {
+            doctor
+                && patient
+                    .medical_folder
+                    .as_ref()
+                    .is_some_and(|folder| folder.report_readers.contains(&me.id))
+        };
+        let has_folder = |user: &UserData| user.medical_folder.is_some();
+        let is_doctor_or_admin = |user: &UserData| matches!(user.role, Role::Doctor | Role::Admin);
+
+        match *request {
+            Request::ReadData(patient) => {
+                admin || own(patient.id) || reads_for(patient.id) || treats(patient)
+            }
+            Request::UpdateData(target) => admin || own(target.id) || manages_for(target.id),
+            Request::DeleteData(target) => admin || own(target.id),
+            Request::AddReport { patient, report } => {
+                admin
+                    || (doctor
+                        && own(report.author)
+                        && patient.id == report.patient
+                        && has_folder(patient))
+            }
+            Request::ReadReport { report, patient } => {
+                admin || own(report.author) || treats(patient) || reads_reports(patient)
+            }
+            Request::UpdateReport(report) => admin || own(report.author),
+            Request::AddLabResult { patient, result } => {
+                admin
+                    || (doctor
+                        && own(result.author)
+                        && patient.id == result.patient
+                        && has_folder(patient))
+            }
+            Request::ReadLabResult { result, patient } => {
+                admin || own(result.author) || treats(patient) || reads_reports(patient)
+            }
+            Request::UpdateRole { .. } => admin,
+            Request::ReviewRoleChange(change) => {
+                admin
+                    && !own(change.requester)
+                    && !own(change.target)
+                    && change.status == RoleChangeStatus::Pending
+            }
+            Request::CancelRoleChange(change) => {
+                own(change.requester) && change.status == RoleChangeStatus::Pending
+            }
+            Request::AddDoctor { patient, doctor } => {
+                admin
+                    || (manages_for(patient.id) && doctor.role == Role::Doctor)
+                    || (own(patient.id) && is_doctor_or_admin(doctor))
+            }
+            Request::RemoveDoctor { patient, doctor } => {
+                admin || manages_for(patient.id) || (own(patient.id) && is_doctor_or_admin(doctor))
+            }
+            Request::RequestAccess { patient, request } => {
+                doctor
+                    && own(request.doctor)
+                    && request.patient == patient.id
+                    && has_folder(patient)
+            }
+            Request::ReviewAccess(request) => {
+                (own(request.patient) || manages_for(request.patient))
+                    && request.status == RequestStatus::Pending
+            }
+            Request::ReadAccessLog(entry) => {
+                admin || own(entry.patient) || reads_for(entry.patient)
+            }
+            Request::DisputeAccess(entry) => {
+                own(entry.patient) && entry.dispute == DisputeStatus::Undisputed
+            }
+            Request::ReviewDispute(entry) => admin && entry.dispute == DisputeStatus::Disputed,
+            Request::ImportUsers
+            | Request::ResearchExport
+            | Request::Fsck
+            | Request::ReadMetrics
+            | Request::ManagePolicy
+            | Request::SearchAvs => admin,
+            Request::ManageProxy(relationship) => {
+                admin || (own(relationship.dependant) && relationship.kind == ProxyKind::Carer)
+            }
+            Request::CloseAccount(target) => {
+                target.role != Role::Admin && (admin || own(target.id))
+            }
+            Request::RevealAvs(patient) => {
+                admin
+                    || (has_folder(patient) && (own(patient.id) || reads_for(patient.id)))
+                    || treats(patient)
+            }
+            Request::Prescribe {
+                patient,
+                prescription,
+            } => {
+                own(prescription.prescriber)
+                    && prescription.patient == patient.id
+                    && treats(patient)
+            }
+            Request::ReadPrescription {
+                prescription,
+                patient,
+            } => {
+                admin
+                    || own(prescription.patient)
+                    || own(prescription.prescriber)
+                    || reads_for(prescription.patient)
+                    || treats(patient)
+            }
+            Request::IssuePrescriptionCode(prescription) => {
+                own(prescription.patient)
+                    || own(prescription.prescriber)
+                    || manages_for(prescription.patient)
+            }
+            Request::LookupPrescription(_) => me.role == Role::Pharmacist,
+            Request::Dispense(prescription) => {
+                me.role == Role::Pharmacist && prescription.remaining() > 0
+            }
+        }
+    }
+}
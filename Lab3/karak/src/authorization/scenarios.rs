@@ -0,0 +1,504 @@
+//! Les scénarios d'accès que toute version des règles doit respecter: qui
+//! peut, et surtout qui ne peut pas, faire quoi. Une modification des règles
+//! faite depuis l'application n'est appliquée que s'ils passent tous.
+
+use std::sync::LazyLock;
+
+use chrono::{TimeDelta, Utc};
+
+use super::{Backend, Request, Subject};
+use crate::models::{
+    AccessAction, AccessLogEntry, AccessLogID, AccessRequest, AccessRequestID, AccessScope,
+    BloodType, DisputeStatus, MedicalFolder, MedicalReport, PersonalData, Prescription,
+    PrescriptionID, ReportID, RequestStatus, Role, RoleChangeID, RoleChangeRequest,
+    RoleChangeStatus, UserData, UserID,
+};
+use crate::utils::input_validation::{AVSNumber, Username};
+use crate::utils::password_utils::{hash, PWHash};
+
+/// Le mot de passe des utilisateurs fictifs, haché une seule fois
+static PASSWORD: LazyLock<PWHash> = LazyLock::new(|| hash(""));
+
+/// Les utilisateurs et les données fictifs des scénarios
+struct World {
+    /// Le représentant légal du patient
+    guardian: UserData,
+    admin: UserData,
+    other_admin: UserData,
+    doctor: UserData,
+    other_doctor: UserData,
+    pharmacist: UserData,
+    patient: UserData,
+    other_patient: UserData,
+    /// Un rapport du médecin traitant sur le patient
+    report: MedicalReport,
+    /// Une demande d'accès de l'autre médecin au dossier du patient
+    request: AccessRequest,
+    /// Une lecture du dossier du patient par son médecin
+    access: AccessLogEntry,
+    /// Une ordonnance du médecin traitant, pas encore délivrée
+    prescription: Prescription,
+    /// Une promotion de l'autre médecin au rôle d'administrateur, demandée
+    /// par l'administrateur
+    role_change: RoleChangeRequest,
+    /// Une rétrogradation de l'autre administrateur, demandée par
+    /// l'administrateur
+    demotion: RoleChangeRequest,
+}
+
+fn new_user(role: Role, name: &str) -> UserData {
+    UserData {
+        id: UserID::new(),
+        role,
+        username: Username::try_from(name).expect("Valid username"),
+        password: PASSWORD.clone(),
+        medical_folder: None,
+    }
+}
+
+fn new_patient(name: &str, doctor: UserID) -> UserData {
+    let mut folder = MedicalFolder::new(PersonalData {
+        avs_number: AVSNumber::try_from("756.1234.5678.97".to_string()).expect("Valid AVS"),
+        blood_type: BloodType::O,
+    });
+    folder.grant(doctor, AccessScope::Folder);
+    UserData {
+        medical_folder: Some(folder),
+        ..new_user(Role::Patient, name)
+    }
+}
+
+impl World {
+    fn new() -> Self {
+        let doctor = new_user(Role::Doctor, "doctor");
+        let other_doctor = new_user(Role::Doctor, "other_doctor");
+        let patient = new_patient("patient", doctor.id);
+        let other_patient = new_patient("other_patient", other_doctor.id);
+        let admin = new_user(Role::Admin, "admin");
+        let other_admin = new_user(Role::Admin, "other_admin");
+        let role_change = |target: &UserData, role| RoleChangeRequest {
+            id: RoleChangeID::new(),
+            target: target.id,
+            role,
+            previous: Some(target.role),
+            requester: admin.id,
+            requested_at: Utc::now(),
+            expires_at: Utc::now() + TimeDelta::hours(1),
+            status: RoleChangeStatus::Pending,
+            reviewer: None,
+        };
+
+        World {
+            report: MedicalReport {
+                id: ReportID::new(),
+                title: "Report".to_string(),
+                author: doctor.id,
+                patient: patient.id,
+                content: "Content".to_string(),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            },
+            request: AccessRequest {
+                id: AccessRequestID::new(),
+                doctor: other_doctor.id,
+                patient: patient.id,
+                reason: "Second opinion".to_string(),
+                scope: AccessScope::Folder,
+                status: RequestStatus::Pending,
+            },
+            access: AccessLogEntry {
+                id: AccessLogID::new(),
+                patient: patient.id,
+                accessor: doctor.id,
+                accessor_role: Role::Doctor,
+                action: AccessAction::ReadData,
+                at: Utc::now(),
+                dispute: DisputeStatus::Undisputed,
+                as_proxy: None,
+            },
+            prescription: Prescription {
+                id: PrescriptionID::new(),
+                patient: patient.id,
+                prescriber: doctor.id,
+                drug: "Amoxicillin".to_string(),
+                dose: "500 mg".to_string(),
+                duration_days: 7,
+                repeats: 0,
+                issued_at: Utc::now(),
+                dispensings: Vec::new(),
+            },
+            role_change: role_change(&other_doctor, Role::Admin),
+            demotion: role_change(&other_admin, Role::Doctor),
+            admin,
+            guardian: new_user(Role::Patient, "guardian"),
+            other_admin,
+            pharmacist: new_user(Role::Pharmacist, "pharmacist"),
+            doctor,
+            other_doctor,
+            patient,
+            other_patient,
+        }
+    }
+}
+
+/// Un scénario: un sujet, sa demande et la décision attendue
+struct Scenario<'a> {
+    name: &'static str,
+    subject: Subject<'a>,
+    request: Request<'a>,
+    granted: bool,
+}
+
+fn scenarios(world: &World) -> Vec<Scenario<'_>> {
+    let World {
+        guardian,
+        admin,
+        other_admin,
+        doctor,
+        other_doctor,
+        pharmacist,
+        patient,
+        other_patient,
+        report,
+        request,
+        access,
+        prescription,
+        role_change,
+        demotion,
+    } = world;
+    let scenario = |name, subject, request, granted| Scenario {
+        name,
+        subject: Subject::new(subject),
+        request,
+        granted,
+    };
+    // Le représentant du patient, en lecture seulement
+    let as_guardian = |name, request, granted| Scenario {
+        name,
+        subject: Subject {
+            proxy_read: [patient.id].into(),
+            ..Subject::new(guardian)
+        },
+        request,
+        granted,
+    };
+    // Le proche aidant d'un médecin, en lecture
+    let as_doctor_carer = |name, request, granted| Scenario {
+        name,
+        subject: Subject {
+            proxy_read: [doctor.id].into(),
+            ..Subject::new(guardian)
+        },
+        request,
+        granted,
+    };
+    let add_report = Request::AddReport { patient, report };
+    let read_report = Request::ReadReport { report, patient };
+    let prescribe = Request::Prescribe {
+        patient,
+        prescription,
+    };
+
+    vec![
+        scenario("admin-manages-policy", admin, Request::ManagePolicy, true),
+        scenario(
+            "doctor-cannot-manage-policy",
+            doctor,
+            Request::ManagePolicy,
+            false,
+        ),
+        scenario(
+            "patient-cannot-manage-policy",
+            patient,
+            Request::ManagePolicy,
+            false,
+        ),
+        scenario(
+            "admin-updates-roles",
+            admin,
+            Request::UpdateRole {
+                target: doctor,
+                role: Role::Patient,
+            },
+            true,
+        ),
+        scenario(
+            "doctor-cannot-update-roles",
+            doctor,
+            Request::UpdateRole {
+                target: doctor,
+                role: Role::Admin,
+            },
+            false,
+        ),
+        scenario(
+            "patient-cannot-update-roles",
+            patient,
+            Request::UpdateRole {
+                target: patient,
+                role: Role::Admin,
+            },
+            false,
+        ),
+        scenario(
+            "other-admin-approves-role-change",
+            other_admin,
+            Request::ReviewRoleChange(role_change),
+            true,
+        ),
+        scenario(
+            "admin-cannot-approve-own-role-change",
+            admin,
+            Request::ReviewRoleChange(role_change),
+            false,
+        ),
+        scenario(
+            "admin-cannot-approve-own-demotion",
+            other_admin,
+            Request::ReviewRoleChange(demotion),
+            false,
+        ),
+        scenario(
+            "doctor-cannot-approve-role-change",
+            doctor,
+            Request::ReviewRoleChange(role_change),
+            false,
+        ),
+        scenario(
+            "admin-cancels-own-role-change",
+            admin,
+            Request::CancelRoleChange(role_change),
+            true,
+        ),
+        scenario(
+            "other-admin-cannot-cancel-role-change",
+            other_admin,
+            Request::CancelRoleChange(role_change),
+            false,
+        ),
+        scenario(
+            "admin-closes-patient-account",
+            admin,
+            Request::CloseAccount(patient),
+            true,
+        ),
+        scenario(
+            "admin-cannot-close-admin-account",
+            admin,
+            Request::CloseAccount(other_admin),
+            false,
+        ),
+        scenario(
+            "admin-cannot-close-own-account",
+            admin,
+            Request::CloseAccount(admin),
+            false,
+        ),
+        as_guardian(
+            "guardian-reads-dependant-data",
+            Request::ReadData(patient),
+            true,
+        ),
+        as_guardian(
+            "guardian-cannot-update-with-read-scope",
+            Request::UpdateData(patient),
+            false,
+        ),
+        as_guardian(
+            "guardian-reads-dependant-prescriptions",
+            Request::ReadPrescription {
+                prescription,
+                patient,
+            },
+            true,
+        ),
+        as_guardian(
+            "guardian-cannot-read-reports-hidden-from-dependant",
+            read_report,
+            false,
+        ),
+        as_doctor_carer(
+            "doctor-carer-cannot-read-doctor-reports",
+            read_report,
+            false,
+        ),
+        as_doctor_carer(
+            "doctor-carer-cannot-read-doctor-prescriptions",
+            Request::ReadPrescription {
+                prescription,
+                patient,
+            },
+            false,
+        ),
+        scenario(
+            "patient-reads-own-data",
+            patient,
+            Request::ReadData(patient),
+            true,
+        ),
+        scenario(
+            "patient-cannot-read-other-data",
+            patient,
+            Request::ReadData(other_patient),
+            false,
+        ),
+        scenario(
+            "doctor-reads-patient-data",
+            doctor,
+            Request::ReadData(patient),
+            true,
+        ),
+        scenario(
+            "other-doctor-cannot-read-data",
+            other_doctor,
+            Request::ReadData(patient),
+            false,
+        ),
+        scenario(
+            "pharmacist-cannot-read-data",
+            pharmacist,
+            Request::ReadData(patient),
+            false,
+        ),
+        scenario(
+            "other-doctor-cannot-reveal-avs",
+            other_doctor,
+            Request::RevealAvs(patient),
+            false,
+        ),
+        scenario("doctor-adds-report", doctor, add_report, true),
+        scenario("patient-cannot-add-report", patient, add_report, false),
+        scenario("doctor-reads-patient-report", doctor, read_report, true),
+        scenario(
+            "other-doctor-cannot-read-report",
+            other_doctor,
+            read_report,
+            false,
+        ),
+        scenario(
+            "other-doctor-cannot-update-report",
+            other_doctor,
+            Request::UpdateReport(report),
+            false,
+        ),
+        scenario(
+            "doctor-requests-access",
+            other_doctor,
+            Request::RequestAccess { patient, request },
+            true,
+        ),
+        scenario(
+            "patient-reviews-request",
+            patient,
+            Request::ReviewAccess(request),
+            true,
+        ),
+        scenario(
+            "doctor-cannot-review-own-request",
+            other_doctor,
+            Request::ReviewAccess(request),
+            false,
+        ),
+        scenario(
+            "patient-reads-access-log",
+            patient,
+            Request::ReadAccessLog(access),
+            true,
+        ),
+        scenario(
+            "doctor-cannot-read-access-log",
+            doctor,
+            Request::ReadAccessLog(access),
+            false,
+        ),
+        scenario(
+            "patient-disputes-access",
+            patient,
+            Request::DisputeAccess(access),
+            true,
+        ),
+        scenario("doctor-prescribes", doctor, prescribe, true),
+        scenario(
+            "other-doctor-cannot-prescribe",
+            other_doctor,
+            prescribe,
+            false,
+        ),
+        scenario(
+            "pharmacist-dispenses",
+            pharmacist,
+            Request::Dispense(prescription),
+            true,
+        ),
+        scenario(
+            "patient-cannot-dispense",
+            patient,
+            Request::Dispense(prescription),
+            false,
+        ),
+        scenario("admin-checks-database", admin, Request::Fsck, true),
+        scenario(
+            "patient-cannot-check-database",
+            patient,
+            Request::Fsck,
+            false,
+        ),
+        scenario(
+            "doctor-cannot-import-users",
+            doctor,
+            Request::ImportUsers,
+            false,
+        ),
+        scenario(
+            "patient-cannot-export-research",
+            patient,
+            Request::ResearchExport,
+            false,
+        ),
+    ]
+}
+
+/// Les noms des scénarios dont la décision n'est pas celle attendue
+pub fn failed_scenarios(backend: &dyn Backend) -> Vec<&'static str> {
+    let world = World::new();
+    scenarios(&world)
+        .into_iter()
+        .filter(|scenario| backend.decide(&scenario.subject, &scenario.request) != scenario.granted)
+        .map(|scenario| scenario.name)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::authorization::{CasbinBackend, NativeBackend};
+    use crate::models::{PolicyChange, PolicyRule};
+
+    #[test]
+    fn test_both_backends_pass_the_scenarios() {
+        let casbin = CasbinBackend::load().unwrap();
+        assert_eq!(failed_scenarios(&casbin), Vec::<&str>::new());
+        assert_eq!(failed_scenarios(&NativeBackend), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_scenarios_catch_escalations_and_lockouts() {
+        let mut casbin = CasbinBackend::load().unwrap();
+        casbin
+            .apply(&PolicyChange::Add(PolicyRule {
+                action: "read-data".to_owned(),
+                rule: "r.sub.role == \"Doctor\"".to_owned(),
+            }))
+            .unwrap();
+        casbin
+            .apply(&PolicyChange::Remove(PolicyRule {
+                action: "manage-policy".to_owned(),
+                rule: "r.sub.role == \"Admin\"".to_owned(),
+            }))
+            .unwrap();
+        assert_eq!(
+            failed_scenarios(&casbin),
+            ["admin-manages-policy", "other-doctor-cannot-read-data"]
+        );
+    }
+}
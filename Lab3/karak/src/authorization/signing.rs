@@ -0,0 +1,140 @@
+//! Signature Ed25519 des règles d'accès.
+//!
+//! Le modèle et les règles sont signés ensemble, avec une clé gardée hors
+//! ligne par les responsables des règles. La signature est écrite à côté des
+//! règles (`policy.csv.sig`), et seule la clé publique est configurée.
+
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand_core::OsRng;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::i18n::Msg;
+use crate::utils::keyed_hash::{from_hex, to_hex};
+
+/// Sépare ces signatures de toute autre signature faite avec la même clé
+const CONTEXT: &[u8] = b"karak policy v1\0";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SignatureError {
+    #[error("{}", Msg::ErrPolicyKey)]
+    InvalidKey,
+    #[error("{}", Msg::ErrPolicySignature)]
+    BadSignature,
+}
+
+/// Le message signé: les empreintes du modèle et des règles, qui ne
+/// peuvent être confondues l'une avec l'autre
+fn message(model: &str, policy: &str) -> Vec<u8> {
+    [CONTEXT, &Sha256::digest(model), &Sha256::digest(policy)].concat()
+}
+
+/// Le fichier de la signature de règles
+pub fn signature_path(policy: &Path) -> PathBuf {
+    let mut name = policy.as_os_str().to_owned();
+    name.push(".sig");
+    name.into()
+}
+
+/// Une clé secrète de signature des règles
+pub struct PolicyKey(SigningKey);
+
+impl PolicyKey {
+    pub fn generate() -> Self {
+        Self(SigningKey::generate(&mut OsRng))
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, SignatureError> {
+        let secret = from_hex(hex).map_err(|_| SignatureError::InvalidKey)?;
+        Ok(Self(SigningKey::from_bytes(&secret)))
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(self.0.as_bytes())
+    }
+
+    /// La clé publique à configurer, en hexadécimal
+    pub fn public_key(&self) -> String {
+        to_hex(self.0.verifying_key().as_bytes())
+    }
+
+    /// La signature du modèle et des règles, en hexadécimal
+    pub fn sign(&self, model: &str, policy: &str) -> String {
+        to_hex(&self.0.sign(&message(model, policy)).to_bytes())
+    }
+}
+
+/// Vérifie la signature du modèle et des règles avec la clé publique donnée
+pub fn verify(
+    public_key: &str,
+    model: &str,
+    policy: &str,
+    signature: &str,
+) -> Result<(), SignatureError> {
+    let key = from_hex(public_key)
+        .ok()
+        .and_then(|key| VerifyingKey::from_bytes(&key).ok())
+        .ok_or(SignatureError::InvalidKey)?;
+    let signature = from_hex(signature)
+        .map(|signature| Signature::from_bytes(&signature))
+        .map_err(|_| SignatureError::BadSignature)?;
+    key.verify_strict(&message(model, policy), &signature)
+        .map_err(|_| SignatureError::BadSignature)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MODEL: &str = "[matchers]\nm = r.act == p.act && eval(p.rule)\n";
+    const POLICY: &str = "p, fsck, r.sub.role == \"Admin\"\n";
+
+    #[test]
+    fn test_signatures_cover_the_model_and_the_policy() {
+        let key = PolicyKey::generate();
+        let signature = key.sign(MODEL, POLICY);
+        assert_eq!(verify(&key.public_key(), MODEL, POLICY, &signature), Ok(()));
+
+        let escalated = format!("{POLICY}p, update-role, true\n");
+        assert_eq!(
+            verify(&key.public_key(), MODEL, &escalated, &signature),
+            Err(SignatureError::BadSignature)
+        );
+        assert_eq!(
+            verify(&key.public_key(), "", POLICY, &signature),
+            Err(SignatureError::BadSignature)
+        );
+        // Moving text between the two files changes the signed message
+        let (head, tail) = POLICY.split_at(3);
+        let moved = format!("{MODEL}{head}");
+        assert!(verify(&key.public_key(), &moved, tail, &signature).is_err());
+    }
+
+    #[test]
+    fn test_only_the_matching_public_key_verifies() {
+        let key = PolicyKey::generate();
+        let signature = key.sign(MODEL, POLICY);
+        let other = PolicyKey::generate();
+        assert_eq!(
+            verify(&other.public_key(), MODEL, POLICY, &signature),
+            Err(SignatureError::BadSignature)
+        );
+        assert_eq!(
+            verify("not a key", MODEL, POLICY, &signature),
+            Err(SignatureError::InvalidKey)
+        );
+        assert_eq!(
+            verify(&key.public_key(), MODEL, POLICY, "00"),
+            Err(SignatureError::BadSignature)
+        );
+
+        let restored = PolicyKey::from_hex(&key.to_hex()).unwrap();
+        assert_eq!(restored.public_key(), key.public_key());
+        assert_eq!(
+            signature_path(Path::new("/etc/karak/policy.csv")),
+            PathBuf::from("/etc/karak/policy.csv.sig")
+        );
+    }
+}
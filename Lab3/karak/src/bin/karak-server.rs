@@ -0,0 +1,61 @@
+//! Serveur de l'API HTTP de karak (voir le module `server`)
+
+use std::net::SocketAddr;
+
+use clap::Parser;
+use karak::authorization::{BackendKind, Enforcer};
+use karak::config::{Config, ConfigArgs};
+use karak::db::{Database, InvalidRecords};
+use karak::i18n::{self, Msg};
+use karak::logging;
+use karak::server;
+use karak::services::Service;
+use karak::utils::keyed_hash::HashKey;
+
+#[derive(Parser)]
+#[command(version, about = "API HTTP de karak")]
+struct Cli {
+    /// Adresse d'écoute
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// Met de côté les enregistrements invalides de la base au lieu de
+    /// refuser de l'ouvrir
+    #[arg(long)]
+    quarantine: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let config = Config::load(cli.config)?;
+    i18n::set_default_lang(config.lang);
+    config.create_dirs()?;
+    logging::init(config.log.clone())?;
+    config.warn_on_start();
+
+    let invalid_records = if cli.quarantine {
+        InvalidRecords::Quarantine
+    } else {
+        InvalidRecords::Reject
+    };
+    let avs_key = HashKey::load_or_create(&config.avs_key)?;
+    let (db, _) = Database::open_with(config.database, &avs_key, invalid_records)?;
+    if config.authorization == BackendKind::Casbin && config.policy.uses_builtin() {
+        eprintln!("{}", Msg::WarnBuiltinPolicy);
+    }
+    let enforcer = Enforcer::with_backend(config.authorization, &config.policy)?;
+    if enforcer.is_locked() {
+        eprintln!("{}", Msg::WarnPolicyLocked);
+    }
+    let service = Service::new(db, enforcer);
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(cli.listen).await?;
+        server::serve(listener, service).await
+    })?;
+    Ok(())
+}
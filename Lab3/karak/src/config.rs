@@ -0,0 +1,517 @@
+//! Configuration des exécutables.
+//!
+//! Chaque réglage vient, par ordre de priorité, de la ligne de commande, du
+//! fichier de configuration, puis d'un emplacement par défaut selon XDG:
+//!
+//! | Réglage         | Défaut                                            |
+//! |-----------------|---------------------------------------------------|
+//! | `config`        | `$XDG_CONFIG_HOME/karak/config.json`, facultatif  |
+//! | `database`      | `$XDG_DATA_HOME/karak/database.json`              |
+//! | `avs_key`       | `$XDG_CONFIG_HOME/karak/avs.key`, créée au besoin |
+//! | `model`         | `$XDG_CONFIG_HOME/karak/model.conf` s'il existe   |
+//! | `policy`        | `$XDG_CONFIG_HOME/karak/policy.csv` s'il existe   |
+//! | `log`           | `$XDG_STATE_HOME/karak/karak.log`                 |
+//!
+//! Si la base par défaut n'existe pas encore mais qu'un `database.json` des
+//! versions précédentes se trouve dans le dossier courant, celui-ci est
+//! gardé, avec un avertissement: il suffit de le déplacer pour suivre XDG.
+//!
+//! Sans modèle ni règles, ceux intégrés à l'exécutable sont utilisés. Des
+//! fichiers de règles doivent être signés (voir `karak policy`). Leur clé
+//! publique est intégrée à la compilation (`KARAK_POLICY_KEY`); elle ne peut
+//! être remplacée (`policy_key`) que par un fichier de configuration de
+//! root, que l'utilisateur ne peut pas modifier comme les règles elles-mêmes.
+//!
+//! La clé AVS chiffre les numéros AVS de la base: la perdre les rend
+//! illisibles. Les chemins relatifs du fichier de configuration partent de
+//! son dossier. Voir `config.json.example`.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use clap::Args;
+use log::{warn, LevelFilter};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::authorization::{BackendKind, PolicySource};
+use crate::i18n::{Lang, Msg};
+use crate::logging::{LogArgs, LogConfig, LogDestination, Rotation};
+use crate::t;
+
+/// Les options communes des exécutables, qui priment sur le fichier
+#[derive(Debug, Clone, Default, Args)]
+pub struct ConfigArgs {
+    /// Fichier de configuration [défaut: $XDG_CONFIG_HOME/karak/config.json]
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Fichier de la base de données [défaut: $XDG_DATA_HOME/karak/database.json]
+    #[arg(long, global = true)]
+    pub db: Option<PathBuf>,
+
+    /// Clé de chiffrement des numéros AVS [défaut: $XDG_CONFIG_HOME/karak/avs.key]
+    #[arg(long, global = true)]
+    pub avs_key: Option<PathBuf>,
+
+    /// Modèle Casbin [défaut: $XDG_CONFIG_HOME/karak/model.conf, sinon intégré]
+    #[arg(long, global = true)]
+    pub model: Option<PathBuf>,
+
+    /// Règles Casbin [défaut: $XDG_CONFIG_HOME/karak/policy.csv, sinon intégrées]
+    #[arg(long, global = true)]
+    pub policy: Option<PathBuf>,
+
+    /// Démarre en refusant tout accès si la signature des règles est absente
+    /// ou fausse, plutôt que de refuser de démarrer
+    #[arg(long, global = true)]
+    pub policy_lockdown: bool,
+
+    /// Moteur de règles d'accès (casbin ou native) [défaut: casbin]
+    #[arg(long, global = true)]
+    pub authorization: Option<BackendKind>,
+
+    /// Langue de l'interface (fr, de ou en), sinon selon `KARAK_LANG` ou `LANG`
+    #[arg(long, global = true)]
+    pub lang: Option<Lang>,
+
+    #[command(flatten)]
+    pub log: LogArgs,
+}
+
+/// Le contenu du fichier de configuration
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    database: Option<PathBuf>,
+    avs_key: Option<PathBuf>,
+    model: Option<PathBuf>,
+    policy: Option<PathBuf>,
+    policy_key: Option<String>,
+    policy_lockdown: bool,
+    authorization: Option<String>,
+    lang: Option<String>,
+    log: Option<String>,
+    log_rotation: Option<String>,
+    log_keep: Option<usize>,
+    log_level: Option<String>,
+}
+
+/// Les dossiers de base XDG, chacun suivi de `karak/`
+#[derive(Debug, Clone)]
+pub struct Dirs {
+    pub config: PathBuf,
+    pub data: PathBuf,
+    pub state: PathBuf,
+    /// Le dossier des données des versions précédentes: le dossier courant
+    pub legacy: PathBuf,
+}
+
+impl Dirs {
+    /// Les dossiers donnés par l'environnement, sinon ceux de la
+    /// spécification XDG sous `$HOME`, sinon le dossier courant
+    pub fn from_env() -> Self {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let base = |var: &str, fallback: &str| {
+            std::env::var_os(var)
+                .map(PathBuf::from)
+                .filter(|dir| dir.is_absolute())
+                .or_else(|| home.as_ref().map(|home| home.join(fallback)))
+                .map(|dir| dir.join("karak"))
+                .unwrap_or_default()
+        };
+        Self {
+            config: base("XDG_CONFIG_HOME", ".config"),
+            data: base("XDG_DATA_HOME", ".local/share"),
+            state: base("XDG_STATE_HOME", ".local/state"),
+            legacy: PathBuf::new(),
+        }
+    }
+}
+
+/// La clé publique des responsables des règles, fixée à la compilation
+pub const BUILTIN_POLICY_KEY: Option<&str> = option_env!("KARAK_POLICY_KEY");
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("{}", t!(ErrConfigRead, .0.display(), .1))]
+    Read(PathBuf, io::Error),
+    #[error("{}", t!(ErrConfigSyntax, .0.display(), .1))]
+    Syntax(PathBuf, serde_json::Error),
+    #[error("{}", t!(ErrConfigValue, .0, .1))]
+    Value(&'static str, String),
+    #[error("{}", t!(ErrUntrustedPolicyKey, .0.display()))]
+    UntrustedPolicyKey(PathBuf),
+}
+
+/// La configuration retenue
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub database: PathBuf,
+    /// Vrai si `database` est la base du dossier courant des versions
+    /// précédentes, faute de base à l'emplacement XDG
+    pub legacy_database: bool,
+    pub avs_key: PathBuf,
+    pub policy: PolicySource,
+    /// Vrai si la clé des règles intégrée est remplacée par une autre
+    pub policy_key_overridden: bool,
+    pub authorization: BackendKind,
+    pub lang: Lang,
+    pub log: LogConfig,
+}
+
+impl Config {
+    pub fn load(args: ConfigArgs) -> Result<Self, ConfigError> {
+        Self::resolve(args, &Dirs::from_env())
+    }
+
+    pub fn resolve(args: ConfigArgs, dirs: &Dirs) -> Result<Self, ConfigError> {
+        // Le fichier par défaut est facultatif, pas celui demandé
+        let (path, required) = match args.config {
+            Some(path) => (path, true),
+            None => (dirs.config.join("config.json"), false),
+        };
+        let file = match fs::read_to_string(&path) {
+            Ok(text) => {
+                serde_json::from_str(&text).map_err(|e| ConfigError::Syntax(path.clone(), e))?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !required => ConfigFile::default(),
+            Err(e) => return Err(ConfigError::Read(path, e)),
+        };
+        let ConfigFile {
+            database,
+            avs_key,
+            model,
+            policy,
+            policy_key,
+            policy_lockdown,
+            authorization,
+            lang,
+            log,
+            log_rotation,
+            log_keep,
+            log_level,
+        } = file;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        let from_file = |path: Option<PathBuf>| path.map(|path| base.join(path));
+        let existing = |path: PathBuf| path.is_file().then_some(path);
+        let defaults = LogConfig::default();
+        let (database, legacy_database) = match args.db.or_else(|| from_file(database)) {
+            Some(database) => (database, false),
+            None => default_database(dirs),
+        };
+        if policy_key.is_some() && !root_owned(&path) {
+            return Err(ConfigError::UntrustedPolicyKey(path));
+        }
+        let policy_key_overridden = matches!(
+            (&policy_key, BUILTIN_POLICY_KEY),
+            (Some(key), Some(builtin)) if key != builtin
+        );
+
+        Ok(Self {
+            database,
+            legacy_database,
+            avs_key: args
+                .avs_key
+                .or_else(|| from_file(avs_key))
+                .unwrap_or_else(|| dirs.config.join("avs.key")),
+            policy: PolicySource {
+                model: args
+                    .model
+                    .or_else(|| from_file(model))
+                    .or_else(|| existing(dirs.config.join("model.conf"))),
+                policy: args
+                    .policy
+                    .or_else(|| from_file(policy))
+                    .or_else(|| existing(dirs.config.join("policy.csv"))),
+                key: policy_key.or_else(|| BUILTIN_POLICY_KEY.map(String::from)),
+                lockdown: args.policy_lockdown || policy_lockdown,
+            },
+            policy_key_overridden,
+            authorization: args
+                .authorization
+                .or(parse("authorization", authorization)?)
+                .unwrap_or_default(),
+            lang: args
+                .lang
+                .or(parse("lang", lang)?)
+                .or_else(Lang::from_env)
+                .unwrap_or_default(),
+            log: LogConfig {
+                destination: match (args.log.destination, parse("log", log)?) {
+                    (Some(destination), _) => destination,
+                    (None, Some(LogDestination::File(file))) => {
+                        LogDestination::File(base.join(file))
+                    }
+                    (None, Some(destination)) => destination,
+                    (None, None) => LogDestination::File(dirs.state.join("karak.log")),
+                },
+                rotation: args
+                    .log
+                    .rotation
+                    .or(parse::<Rotation>("log_rotation", log_rotation)?)
+                    .unwrap_or(defaults.rotation),
+                keep: args.log.keep.or(log_keep).unwrap_or(defaults.keep),
+                level: parse::<LevelFilter>("log_level", log_level)?.unwrap_or(defaults.level),
+            },
+        })
+    }
+
+    /// Signale, une fois le journal ouvert, une base gardée dans le dossier
+    /// courant ou une clé des règles remplacée
+    pub fn warn_on_start(&self) {
+        if self.policy_key_overridden {
+            eprintln!("{}", Msg::WarnPolicyKeyOverridden);
+            warn!("Clé publique des règles intégrée remplacée par la configuration");
+        }
+        if self.legacy_database {
+            eprintln!("{}", t!(WarnLegacyDatabase, self.database.display()));
+            warn!(
+                "Base de données {} lue depuis le dossier courant, à déplacer selon XDG",
+                self.database.display()
+            );
+        }
+    }
+
+    /// Crée les dossiers de la base, de la clé AVS et du journal
+    pub fn create_dirs(&self) -> io::Result<()> {
+        let log = match &self.log.destination {
+            LogDestination::File(path) => Some(path),
+            LogDestination::Stderr => None,
+        };
+        for path in [Some(&self.database), Some(&self.avs_key), log]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(parent) = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// La base par défaut selon XDG, sauf si elle n'existe pas et que celle du
+/// dossier courant, l'ancien emplacement, existe
+fn default_database(dirs: &Dirs) -> (PathBuf, bool) {
+    let database = dirs.data.join("database.json");
+    let legacy = dirs.legacy.join("database.json");
+    if !database.exists() && legacy.is_file() {
+        (legacy, true)
+    } else {
+        (database, false)
+    }
+}
+
+/// Vrai si le fichier et son dossier appartiennent à root, sans que
+/// personne d'autre ne puisse les modifier
+#[cfg(unix)]
+fn root_owned(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    [path, dir].into_iter().all(|path| {
+        fs::metadata(path).is_ok_and(|meta| meta.uid() == 0 && meta.mode() & 0o022 == 0)
+    })
+}
+
+#[cfg(not(unix))]
+fn root_owned(_path: &Path) -> bool {
+    false
+}
+
+/// Lit une valeur textuelle du fichier de configuration
+fn parse<T: FromStr>(key: &'static str, value: Option<String>) -> Result<Option<T>, ConfigError> {
+    value
+        .map(|value| value.parse().map_err(|_| ConfigError::Value(key, value)))
+        .transpose()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Base directories inside a fresh temporary directory
+    fn temp_dirs() -> Dirs {
+        let root = std::env::temp_dir().join(format!("karak-config-{}", uuid::Uuid::new_v4()));
+        let dirs = Dirs {
+            config: root.join("config"),
+            data: root.join("data"),
+            state: root.join("state"),
+            legacy: root.join("legacy"),
+        };
+        fs::create_dir_all(&dirs.config).unwrap();
+        dirs
+    }
+
+    #[test]
+    fn test_defaults_follow_xdg_and_use_the_builtin_policy() {
+        let dirs = temp_dirs();
+        let config = Config::resolve(ConfigArgs::default(), &dirs).unwrap();
+
+        assert_eq!(config.database, dirs.data.join("database.json"));
+        assert!(!config.legacy_database);
+        assert_eq!(config.avs_key, dirs.config.join("avs.key"));
+        assert_eq!(config.policy, PolicySource::default());
+        assert!(config.policy.uses_builtin());
+        assert_eq!(
+            config.log.destination,
+            LogDestination::File(dirs.state.join("karak.log"))
+        );
+        assert_eq!(config.authorization, BackendKind::Casbin);
+
+        fs::write(dirs.config.join("policy.csv"), "").unwrap();
+        let config = Config::resolve(ConfigArgs::default(), &dirs).unwrap();
+        assert_eq!(config.policy.policy, Some(dirs.config.join("policy.csv")));
+        assert_eq!(config.policy.model, None);
+    }
+
+    #[test]
+    fn test_legacy_database_is_kept_until_moved() {
+        let dirs = temp_dirs();
+        let legacy = dirs.legacy.join("database.json");
+        fs::create_dir_all(&dirs.legacy).unwrap();
+        fs::write(&legacy, "{}").unwrap();
+
+        let config = Config::resolve(ConfigArgs::default(), &dirs).unwrap();
+        assert_eq!(config.database, legacy);
+        assert!(config.legacy_database);
+
+        // An explicit database is never replaced
+        let args = ConfigArgs {
+            db: Some("other.json".into()),
+            ..ConfigArgs::default()
+        };
+        let config = Config::resolve(args, &dirs).unwrap();
+        assert_eq!(config.database, PathBuf::from("other.json"));
+        assert!(!config.legacy_database);
+
+        fs::create_dir_all(&dirs.data).unwrap();
+        fs::rename(&legacy, dirs.data.join("database.json")).unwrap();
+        let config = Config::resolve(ConfigArgs::default(), &dirs).unwrap();
+        assert_eq!(config.database, dirs.data.join("database.json"));
+        assert!(!config.legacy_database);
+    }
+
+    #[test]
+    fn test_command_line_overrides_the_file() {
+        let dirs = temp_dirs();
+        let file = dirs.config.join("config.json");
+        fs::write(
+            &file,
+            r#"{
+                "database": "records/db.json",
+                "policy": "/etc/karak/policy.csv",
+                "authorization": "native",
+                "lang": "de",
+                "log": "stderr",
+                "log_rotation": "daily",
+                "log_level": "warn"
+            }"#,
+        )
+        .unwrap();
+
+        let config = Config::resolve(ConfigArgs::default(), &dirs).unwrap();
+        assert_eq!(config.database, dirs.config.join("records/db.json"));
+        assert_eq!(
+            config.policy.policy,
+            Some(PathBuf::from("/etc/karak/policy.csv"))
+        );
+        assert_eq!(config.authorization, BackendKind::Native);
+        assert_eq!(config.lang, Lang::De);
+        assert_eq!(config.log.destination, LogDestination::Stderr);
+        assert_eq!(config.log.rotation, Rotation::Daily);
+        assert_eq!(config.log.level, LevelFilter::Warn);
+
+        let args = ConfigArgs {
+            db: Some("other.json".into()),
+            lang: Some(Lang::En),
+            log: LogArgs {
+                destination: Some(LogDestination::File("karak.log".into())),
+                ..LogArgs::default()
+            },
+            ..ConfigArgs::default()
+        };
+        let config = Config::resolve(args, &dirs).unwrap();
+        assert_eq!(config.database, PathBuf::from("other.json"));
+        assert_eq!(config.lang, Lang::En);
+        assert_eq!(
+            config.log.destination,
+            LogDestination::File("karak.log".into())
+        );
+    }
+
+    #[test]
+    fn test_invalid_files_are_rejected() {
+        let dirs = temp_dirs();
+        let file = dirs.config.join("config.json");
+
+        fs::write(&file, r#"{ "authorization": "rego" }"#).unwrap();
+        assert!(matches!(
+            Config::resolve(ConfigArgs::default(), &dirs),
+            Err(ConfigError::Value("authorization", _))
+        ));
+
+        fs::write(&file, r#"{ "databse": "db.json" }"#).unwrap();
+        assert!(matches!(
+            Config::resolve(ConfigArgs::default(), &dirs),
+            Err(ConfigError::Syntax(..))
+        ));
+
+        let missing = ConfigArgs {
+            config: Some(dirs.config.join("missing.json")),
+            ..ConfigArgs::default()
+        };
+        assert!(matches!(
+            Config::resolve(missing, &dirs),
+            Err(ConfigError::Read(..))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_policy_key_needs_a_root_owned_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dirs = temp_dirs();
+        let file = dirs.config.join("config.json");
+        let key = "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c";
+        fs::write(&file, format!(r#"{{ "policy_key": "{key}" }}"#)).unwrap();
+
+        // Anyone may write this file, so it cannot hold the trust anchor
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o666)).unwrap();
+        assert!(matches!(
+            Config::resolve(ConfigArgs::default(), &dirs),
+            Err(ConfigError::UntrustedPolicyKey(_))
+        ));
+
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+        let resolved = Config::resolve(ConfigArgs::default(), &dirs);
+        if root_owned(&file) {
+            let config = resolved.unwrap();
+            assert_eq!(config.policy.key.as_deref(), Some(key));
+            assert_eq!(
+                config.policy_key_overridden,
+                BUILTIN_POLICY_KEY.is_some_and(|builtin| builtin != key)
+            );
+        } else {
+            assert!(matches!(resolved, Err(ConfigError::UntrustedPolicyKey(_))));
+        }
+    }
+
+    #[test]
+    fn test_example_file_is_valid() {
+        let file: ConfigFile =
+            serde_json::from_str(include_str!("../config.json.example")).unwrap();
+        assert!(file.database.is_some());
+    }
+}
@@ -1,67 +1,594 @@
-//! Stockage des données en mémoire, avec sauvegarde en JSON
+//! Stockage des données en mémoire, avec sauvegarde en JSON.
+//!
+//! Les index (noms d'utilisateur, numéros AVS, patients d'un médecin) sont
+//! tenus à jour à travers les gardes `UserMut` et `ReportMut`. Au chargement,
+//! les enregistrements illisibles sont mis en quarantaine plutôt que de
+//! bloquer la base; sur disque, les numéros AVS sont chiffrés.
 
 use crate::{
-    models::{MedicalReport, ReportID, UserData, UserID},
-    utils::input_validation::Username,
+    i18n::Lang,
+    models::{
+        AccessLogEntry, AccessLogID, AccessRequest, AccessRequestID, DisputeStatus, LabResult,
+        LabResultID, MedicalReport, Notification, NotificationKind, PolicyChangeID,
+        PolicyChangeRequest, PolicyRevision, Prescription, PrescriptionID, ProxyID,
+        ProxyRelationship, ReportID, Role, RoleChangeID, RoleChangeRequest, UserData, UserID,
+    },
+    t,
+    utils::{
+        files::create_private,
+        input_validation::{AVSNumber, Username},
+        keyed_hash::{hex_bytes, to_hex, HashKey},
+    },
 };
-use log::info;
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::{
-    collections::HashMap,
+    cmp::Reverse,
+    collections::{hash_map::Entry, BTreeSet, HashMap, HashSet},
     fs::File,
     io::{self, ErrorKind::NotFound},
+    ops::{Deref, DerefMut},
     path::PathBuf,
 };
 use thiserror::Error;
 
-// DO NOT MODIFY THIS FILE!!!
-
 #[derive(Serialize, Deserialize, Default)]
 pub struct Database {
     #[serde(skip)]
     path: Option<PathBuf>,
     users: HashMap<UserID, UserData>,
     reports: HashMap<ReportID, MedicalReport>,
+    /// Vrai une fois que l'administrateur initial a été créé
+    #[serde(default)]
+    bootstrapped: bool,
+    #[serde(default)]
+    access_requests: HashMap<AccessRequestID, AccessRequest>,
+    #[serde(default)]
+    notifications: Vec<Notification>,
+    /// Types de notifications désactivés par chaque utilisateur
+    #[serde(default)]
+    muted_notifications: HashMap<UserID, BTreeSet<NotificationKind>>,
+    #[serde(default)]
+    access_log: Vec<AccessLogEntry>,
+    /// Langue de l'interface choisie par chaque utilisateur
+    #[serde(default)]
+    languages: HashMap<UserID, Lang>,
+    /// Utilisateurs devant remplacer leur mot de passe initial
+    #[serde(default)]
+    password_resets: HashSet<UserID>,
+    #[serde(default)]
+    proxies: HashMap<ProxyID, ProxyRelationship>,
+    /// Date de naissance des personnes ayant eu un représentant légal
+    #[serde(default)]
+    birth_dates: HashMap<UserID, NaiveDate>,
+    #[serde(default)]
+    prescriptions: HashMap<PrescriptionID, Prescription>,
+    #[serde(default)]
+    lab_results: HashMap<LabResultID, LabResult>,
+    /// L'ordonnance de chaque code en cours, par empreinte du code
+    #[serde(default)]
+    prescription_codes: HashMap<String, PrescriptionID>,
+    /// Les versions successives des règles d'accès modifiées depuis
+    /// l'application, la dernière étant en vigueur
+    #[serde(default)]
+    policy_history: Vec<PolicyRevision>,
+    #[serde(default)]
+    role_changes: HashMap<RoleChangeID, RoleChangeRequest>,
+    #[serde(default)]
+    policy_changes: HashMap<PolicyChangeID, PolicyChangeRequest>,
+    /// Clé de chiffrement des numéros AVS dans le fichier
+    #[serde(skip)]
+    avs_seal: HashKey,
+    #[serde(skip)]
+    indexes: Indexes,
+}
+
+/// Index secondaires, reconstruits à l'ouverture de la base et tenus à jour
+/// par chaque modification des utilisateurs et des rapports
+#[derive(Default)]
+struct Indexes {
+    usernames: HashMap<Username, UserID>,
+    /// Patients dont chaque médecin peut lire les rapports
+    patients: HashMap<UserID, BTreeSet<UserID>>,
+    /// Rapports de chaque patient, par ordre de création
+    reports: HashMap<UserID, BTreeSet<(DateTime<Utc>, ReportID)>>,
+    /// Tous les rapports, par ordre de création
+    reports_by_date: BTreeSet<(DateTime<Utc>, ReportID)>,
+    /// Index aveugle des numéros AVS: le HMAC de chaque numéro, avec la
+    /// clé AVS de la base. L'index ne contient aucun numéro en clair; seuls
+    /// ces HMAC et les numéros chiffrés sont écrits sur le disque.
+    avs_numbers: HashMap<[u8; 32], BTreeSet<UserID>>,
+    avs_key: HashKey,
+}
+
+impl Indexes {
+    fn readers(user: &UserData) -> impl Iterator<Item = UserID> + '_ {
+        user.medical_folder
+            .iter()
+            .flat_map(|folder| folder.doctors.iter().chain(&folder.report_readers))
+            .copied()
+    }
+
+    fn avs_digest(&self, avs_number: &AVSNumber) -> [u8; 32] {
+        self.avs_key.digest(avs_number.reveal().as_bytes())
+    }
+
+    fn add_user(&mut self, user: &UserData) {
+        self.usernames.insert(user.username.clone(), user.id);
+        for doctor in Self::readers(user) {
+            self.patients.entry(doctor).or_default().insert(user.id);
+        }
+        if let Some(folder) = &user.medical_folder {
+            let digest = self.avs_digest(&folder.personal_data.avs_number);
+            self.avs_numbers.entry(digest).or_default().insert(user.id);
+        }
+    }
+
+    fn remove_user(&mut self, user: &UserData) {
+        // Un autre utilisateur du même nom a pu prendre sa place dans l'index
+        if self.usernames.get(&user.username) == Some(&user.id) {
+            self.usernames.remove(&user.username);
+        }
+        for doctor in Self::readers(user) {
+            remove_from(&mut self.patients, doctor, user.id);
+        }
+        if let Some(folder) = &user.medical_folder {
+            let digest = self.avs_digest(&folder.personal_data.avs_number);
+            remove_from(&mut self.avs_numbers, digest, user.id);
+        }
+    }
+
+    fn add_report(&mut self, report: &MedicalReport) {
+        let key = (report.created_at, report.id);
+        self.reports.entry(report.patient).or_default().insert(key);
+        self.reports_by_date.insert(key);
+    }
+
+    fn remove_report(&mut self, report: &MedicalReport) {
+        let key = (report.created_at, report.id);
+        remove_from(&mut self.reports, report.patient, key);
+        self.reports_by_date.remove(&key);
+    }
+}
+
+/// Retire d'une collection de la base brute les enregistrements dont la clé
+/// ou la valeur ne passe pas la validation de son type
+fn take_invalid<K: DeserializeOwned, T: DeserializeOwned>(
+    raw: &mut Value,
+    collection: &'static str,
+    invalid: &mut Vec<(InvalidRecord, Value)>,
+) {
+    let check = |key: Value, record: &Value| {
+        serde_json::from_value::<K>(key)
+            .and_then(|_| serde_json::from_value::<T>(record.clone()))
+            .err()
+            .map(|e| e.to_string())
+    };
+
+    let mut found = |key: String, error: String, record: Value| {
+        invalid.push((
+            InvalidRecord {
+                collection,
+                key,
+                error,
+            },
+            record,
+        ))
+    };
+
+    match raw.get_mut(collection) {
+        Some(Value::Object(records)) => {
+            let bad: Vec<(String, String)> = records
+                .iter()
+                .filter_map(|(key, record)| {
+                    check(json!(key), record).map(|error| (key.clone(), error))
+                })
+                .collect();
+            for (key, error) in bad {
+                let record = records.remove(&key).unwrap_or_default();
+                found(key, error, record);
+            }
+        }
+        Some(Value::Array(records)) => {
+            let (good, bad): (Vec<_>, Vec<_>) = records
+                .drain(..)
+                .enumerate()
+                .map(|(i, record)| (i, check(json!(i), &record), record))
+                .partition(|(_, error, _)| error.is_none());
+            *records = good.into_iter().map(|(_, _, record)| record).collect();
+            for (i, error, record) in bad {
+                found(i.to_string(), error.unwrap_or_default(), record);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Les données personnelles de chaque patient dans la base brute
+fn personal_data_mut(raw: &mut Value) -> impl Iterator<Item = (String, &mut Value)> {
+    raw.get_mut("users")
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|users| users.iter_mut())
+        .filter_map(|(id, user)| {
+            let data = user.pointer_mut("/medical_folder/personal_data")?;
+            Some((id.clone(), data))
+        })
+}
+
+/// Remplace dans la base brute chaque numéro AVS par son HMAC et sa
+/// version chiffrée, liée à l'identifiant du patient
+fn seal_avs_numbers(raw: &mut Value, index: &HashKey, seal: &HashKey) {
+    for (id, data) in personal_data_mut(raw) {
+        let Some(Value::String(avs_number)) = data
+            .as_object_mut()
+            .and_then(|data| data.remove("avs_number"))
+        else {
+            continue;
+        };
+        data["avs_digest"] = json!(to_hex(&index.digest(avs_number.as_bytes())));
+        data["avs_sealed"] = json!(to_hex(&seal.seal(avs_number.as_bytes(), id.as_bytes())));
+    }
+}
+
+/// Déchiffre dans la base brute les numéros AVS scellés par
+/// `seal_avs_numbers`, et compte ceux encore en clair. Un numéro illisible
+/// est laissé chiffré: son patient sera signalé comme invalide.
+fn unseal_avs_numbers(raw: &mut Value, index: &HashKey, seal: &HashKey) -> usize {
+    let mut plain = 0;
+    for (id, data) in personal_data_mut(raw) {
+        if data.get("avs_number").is_some() {
+            plain += 1;
+            continue;
+        }
+        let avs_number = data["avs_sealed"]
+            .as_str()
+            .and_then(|sealed| hex_bytes(sealed).ok())
+            .and_then(|sealed| seal.open(&sealed, id.as_bytes()))
+            .and_then(|avs_number| String::from_utf8(avs_number).ok())
+            .filter(|avs_number| {
+                data["avs_digest"].as_str() == Some(&to_hex(&index.digest(avs_number.as_bytes())))
+            });
+        match avs_number {
+            Some(avs_number) => {
+                if let Some(data) = data.as_object_mut() {
+                    data.remove("avs_digest");
+                    data.remove("avs_sealed");
+                    data.insert("avs_number".into(), json!(avs_number));
+                }
+            }
+            None => warn!("Numéro AVS illisible pour l'utilisateur {id}: mauvaise clé AVS?"),
+        }
+    }
+    plain
+}
+
+/// Écrit les enregistrements invalides dans un nouveau fichier à côté de la
+/// base, lisible seulement par son propriétaire
+fn quarantine(path: &std::path::Path, invalid: Vec<(InvalidRecord, Value)>) -> io::Result<PathBuf> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(
+        ".quarantine-{}.json",
+        Utc::now().format("%Y%m%dT%H%M%S")
+    ));
+    let quarantine = PathBuf::from(name);
+
+    let file = create_private(&quarantine)?;
+
+    let records: Vec<Value> = invalid
+        .into_iter()
+        .map(|(info, record)| {
+            json!({
+                "collection": info.collection,
+                "key": info.key,
+                "error": info.error,
+                "record": record,
+            })
+        })
+        .collect();
+    serde_json::to_writer_pretty(file, &records)?;
+
+    warn!(
+        "{} enregistrement(s) invalide(s) mis en quarantaine dans {}",
+        records.len(),
+        quarantine.display()
+    );
+    Ok(quarantine)
+}
+
+/// Retire une valeur d'un index, et la clé si elle n'a plus de valeurs
+fn remove_from<K: Eq + std::hash::Hash, V: Ord>(
+    index: &mut HashMap<K, BTreeSet<V>>,
+    key: K,
+    value: V,
+) {
+    if let Entry::Occupied(mut entry) = index.entry(key) {
+        entry.get_mut().remove(&value);
+        if entry.get().is_empty() {
+            entry.remove();
+        }
+    }
+}
+
+/// Accès en écriture à un utilisateur. L'utilisateur est retiré des index
+/// pendant la modification, et y est remis quand l'accès est relâché.
+pub struct UserMut<'db> {
+    db: &'db mut Database,
+    id: UserID,
+}
+
+impl Deref for UserMut<'_> {
+    type Target = UserData;
+
+    fn deref(&self) -> &UserData {
+        &self.db.users[&self.id]
+    }
+}
+
+impl DerefMut for UserMut<'_> {
+    fn deref_mut(&mut self) -> &mut UserData {
+        self.db
+            .users
+            .get_mut(&self.id)
+            .expect("User exists while borrowed")
+    }
+}
+
+impl Drop for UserMut<'_> {
+    fn drop(&mut self) {
+        let Database { users, indexes, .. } = &mut *self.db;
+        indexes.add_user(&users[&self.id]);
+    }
+}
+
+/// Accès en écriture à un rapport, sur le même principe que `UserMut`
+pub struct ReportMut<'db> {
+    db: &'db mut Database,
+    id: ReportID,
+}
+
+impl Deref for ReportMut<'_> {
+    type Target = MedicalReport;
+
+    fn deref(&self) -> &MedicalReport {
+        &self.db.reports[&self.id]
+    }
+}
+
+impl DerefMut for ReportMut<'_> {
+    fn deref_mut(&mut self) -> &mut MedicalReport {
+        self.db
+            .reports
+            .get_mut(&self.id)
+            .expect("Report exists while borrowed")
+    }
+}
+
+impl Drop for ReportMut<'_> {
+    fn drop(&mut self) {
+        let Database {
+            reports, indexes, ..
+        } = &mut *self.db;
+        indexes.add_report(&reports[&self.id]);
+    }
 }
 
 #[derive(Debug, Error)]
 pub enum DBError {
-    #[error("Invalid user ID: {0}")]
+    #[error("{}", t!(ErrInvalidUserID, .0))]
     InvalidUserID(UserID),
-    #[error("User already exists: {username}")]
+    #[error("{}", t!(ErrUserExists, .username))]
     UserAlreadyExists { username: Username },
 }
 
+/// Que faire des enregistrements invalides trouvés à l'ouverture de la base
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvalidRecords {
+    /// Refuser d'ouvrir la base
+    #[default]
+    Reject,
+    /// Les déplacer dans un fichier de quarantaine et ouvrir la base sans eux
+    Quarantine,
+}
+
+/// Un enregistrement de la base qui ne passe pas la validation de son type
+#[derive(Debug, Clone, Serialize)]
+pub struct InvalidRecord {
+    pub collection: &'static str,
+    /// La clé de l'enregistrement, ou sa position dans une liste
+    pub key: String,
+    pub error: String,
+}
+
+/// Le rapport de chargement de la base
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    pub invalid: Vec<InvalidRecord>,
+    /// Le fichier où les enregistrements invalides ont été déplacés
+    pub quarantine: Option<PathBuf>,
+}
+
+#[derive(Debug, Error)]
+pub enum OpenError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("{}", t!(ErrInvalidRecords, .0.invalid.len()))]
+    InvalidRecords(LoadReport),
+}
+
 impl Database {
-    pub fn open(path: PathBuf) -> Result<Self, io::Error> {
+    /// Ouvre la base, en refusant tout enregistrement invalide
+    pub fn open(path: PathBuf, avs_key: &HashKey) -> Result<Self, OpenError> {
+        Self::open_with(path, avs_key, InvalidRecords::Reject).map(|(db, _)| db)
+    }
+
+    /// Ouvre la base en validant chaque enregistrement séparément, pour
+    /// signaler tous ceux qui sont invalides plutôt que seulement le premier.
+    /// Les numéros AVS y sont chiffrés avec `avs_key`.
+    pub fn open_with(
+        path: PathBuf,
+        avs_key: &HashKey,
+        invalid_records: InvalidRecords,
+    ) -> Result<(Self, LoadReport), OpenError> {
+        let (index_key, seal_key) = Self::avs_keys(avs_key);
         match File::open(&path) {
             // File successfuly opened
             Ok(f) => {
-                let mut db: Self = serde_json::from_reader(f)?;
+                let modified = f.metadata().and_then(|m| m.modified()).ok();
+                let mut raw: Value = serde_json::from_reader(f).map_err(io::Error::from)?;
+                let plain = unseal_avs_numbers(&mut raw, &index_key, &seal_key);
+
+                let mut invalid = Vec::new();
+                take_invalid::<UserID, UserData>(&mut raw, "users", &mut invalid);
+                take_invalid::<ReportID, MedicalReport>(&mut raw, "reports", &mut invalid);
+                take_invalid::<AccessRequestID, AccessRequest>(
+                    &mut raw,
+                    "access_requests",
+                    &mut invalid,
+                );
+                take_invalid::<ProxyID, ProxyRelationship>(&mut raw, "proxies", &mut invalid);
+                take_invalid::<PrescriptionID, Prescription>(
+                    &mut raw,
+                    "prescriptions",
+                    &mut invalid,
+                );
+                take_invalid::<LabResultID, LabResult>(&mut raw, "lab_results", &mut invalid);
+                take_invalid::<RoleChangeID, RoleChangeRequest>(
+                    &mut raw,
+                    "role_changes",
+                    &mut invalid,
+                );
+                take_invalid::<PolicyChangeID, PolicyChangeRequest>(
+                    &mut raw,
+                    "policy_changes",
+                    &mut invalid,
+                );
+                take_invalid::<usize, Notification>(&mut raw, "notifications", &mut invalid);
+                take_invalid::<usize, AccessLogEntry>(&mut raw, "access_log", &mut invalid);
+                take_invalid::<usize, PolicyRevision>(&mut raw, "policy_history", &mut invalid);
+
+                let mut report = LoadReport {
+                    invalid: invalid.iter().map(|(record, _)| record.clone()).collect(),
+                    quarantine: None,
+                };
+                for record in &report.invalid {
+                    warn!(
+                        "Enregistrement invalide {}/{}: {}",
+                        record.collection, record.key, record.error
+                    );
+                }
+
+                if !invalid.is_empty() {
+                    match invalid_records {
+                        InvalidRecords::Reject => return Err(OpenError::InvalidRecords(report)),
+                        InvalidRecords::Quarantine => {
+                            report.quarantine = Some(quarantine(&path, invalid)?);
+                        }
+                    }
+                }
+
+                let mut db: Self = serde_json::from_value(raw).map_err(io::Error::from)?;
                 db.path = Some(path);
-                Ok(db)
+                db.avs_seal = seal_key;
+                db.indexes.avs_key = index_key;
+
+                let fallback = modified.map(DateTime::from).unwrap_or_else(Utc::now);
+                db.migrate_report_timestamps(fallback);
+                db.rebuild_indexes();
+
+                // Les enregistrements sont en sécurité dans la quarantaine:
+                // on les retire de la base pour ne pas les signaler à nouveau.
+                // Les numéros AVS encore en clair sont chiffrés tout de suite.
+                if plain > 0 {
+                    info!("{plain} numéro(s) AVS en clair chiffré(s) dans la base");
+                }
+                if report.quarantine.is_some() || plain > 0 {
+                    db.save()?;
+                }
+                Ok((db, report))
             }
 
             // Fichier non existant, on le crée
             Err(not_found) if not_found.kind() == NotFound => {
                 info!("DB file not found, creating new empty DB");
-                let mut new_db = Database::default();
-                new_db.path = Some(path);
+                let mut new_db = Database {
+                    path: Some(path),
+                    avs_seal: seal_key,
+                    ..Default::default()
+                };
+                new_db.indexes.avs_key = index_key;
 
                 // On vérifie la sauvegarde immédiatement pour diminuer le risque de perte de données
                 new_db.save()?;
-                Ok(new_db)
+                Ok((new_db, LoadReport::default()))
             }
 
             // Autre erreur d'IO, on s'arrête
-            Err(other) => Err(other),
+            Err(other) => Err(other.into()),
         }
     }
 
+    /// Date les rapports enregistrés avant l'ajout de l'horodatage. Faute de
+    /// mieux, on utilise la date de dernière modification de la base, qui est
+    /// une borne supérieure de leur date de création.
+    fn migrate_report_timestamps(&mut self, fallback: DateTime<Utc>) {
+        let mut migrated = 0;
+        for report in self.reports.values_mut() {
+            if report.created_at == DateTime::<Utc>::UNIX_EPOCH {
+                report.created_at = fallback;
+                migrated += 1;
+            }
+            if report.updated_at < report.created_at {
+                report.updated_at = report.created_at;
+            }
+        }
+
+        if migrated > 0 {
+            info!("{migrated} rapport(s) sans date migré(s) au {fallback}");
+        }
+    }
+
+    /// Les sous-clés de l'index aveugle et du chiffrement des numéros AVS
+    fn avs_keys(avs_key: &HashKey) -> (HashKey, HashKey) {
+        (avs_key.derive("avs-index"), avs_key.derive("avs-seal"))
+    }
+
+    pub(crate) fn rebuild_indexes(&mut self) {
+        let mut indexes = Indexes {
+            avs_key: std::mem::take(&mut self.indexes.avs_key),
+            ..Indexes::default()
+        };
+        self.users.values().for_each(|user| indexes.add_user(user));
+        self.reports
+            .values()
+            .for_each(|report| indexes.add_report(report));
+        self.indexes = indexes;
+    }
+
+    /// Copie le fichier de la base à côté de lui, avant une réparation
+    pub fn backup(&self) -> Result<PathBuf, io::Error> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| io::Error::other("No database file"))?;
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".bak-{}", Utc::now().format("%Y%m%dT%H%M%S")));
+        let backup = PathBuf::from(name);
+
+        io::copy(&mut File::open(path)?, &mut create_private(&backup)?)?;
+        info!("Copie de sauvegarde de la base: {}", backup.display());
+        Ok(backup)
+    }
+
+    /// Enregistre la base, sans aucun numéro AVS en clair
     pub fn save(&self) -> Result<(), io::Error> {
         if let Some(path) = &self.path {
+            let mut raw = serde_json::to_value(self)?;
+            seal_avs_numbers(&mut raw, &self.indexes.avs_key, &self.avs_seal);
             let file = File::create(path)?;
-            serde_json::to_writer_pretty(file, self)?;
+            serde_json::to_writer_pretty(file, &raw)?;
         }
         Ok(())
     }
@@ -70,44 +597,615 @@ impl Database {
         self.users.get(&user).ok_or(DBError::InvalidUserID(user))
     }
 
-    pub fn get_user_mut(&mut self, user: UserID) -> Result<&mut UserData, DBError> {
-        self.users
-            .get_mut(&user)
-            .ok_or(DBError::InvalidUserID(user))
+    pub fn get_user_mut(&mut self, user: UserID) -> Result<UserMut<'_>, DBError> {
+        let data = self.users.get(&user).ok_or(DBError::InvalidUserID(user))?;
+        self.indexes.remove_user(data);
+        Ok(UserMut { db: self, id: user })
     }
 
     pub fn lookup_username(&self, name: &Username) -> Option<&UserData> {
-        self.users.values().find(|user| &user.username == name)
+        self.users.get(self.indexes.usernames.get(name)?)
+    }
+
+    /// Les patients dont le dossier porte ce numéro AVS
+    pub fn find_by_avs(&self, avs_number: &AVSNumber) -> impl Iterator<Item = UserID> + '_ {
+        self.indexes
+            .avs_numbers
+            .get(&self.indexes.avs_digest(avs_number))
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    pub fn has_admin(&self) -> bool {
+        self.users.values().any(|user| user.role == Role::Admin)
+    }
+
+    /// Indique si l'amorçage a eu lieu (ou n'est plus nécessaire car
+    /// un administrateur existe déjà)
+    pub fn is_bootstrapped(&self) -> bool {
+        self.bootstrapped || self.has_admin()
+    }
+
+    pub fn mark_bootstrapped(&mut self) {
+        self.bootstrapped = true;
     }
 
     pub fn store_user(&mut self, data: UserData) {
-        self.users.insert(data.id, data);
+        self.indexes.add_user(&data);
+        if let Some(previous) = self.users.insert(data.id, data) {
+            self.indexes.remove_user(&previous);
+            self.indexes.add_user(&self.users[&previous.id]);
+        }
+    }
+
+    /// Supprime un utilisateur, avec tout ce qu'efface `forget_user`
+    pub fn remove_user(&mut self, user: UserID) -> Option<UserData> {
+        let data = self.users.remove(&user)?;
+        self.indexes.remove_user(&data);
+        self.forget_user(user);
+        Some(data)
+    }
+
+    /// Efface les notifications et les préférences d'un utilisateur, et les
+    /// demandes d'accès et relations de représentation qui le concernent.
+    /// L'historique des accès est conservé.
+    pub fn forget_user(&mut self, user: UserID) {
+        self.notifications
+            .retain(|notification| notification.recipient != user);
+        self.muted_notifications.remove(&user);
+        self.languages.remove(&user);
+        self.birth_dates.remove(&user);
+        self.password_resets.remove(&user);
+        self.access_requests
+            .retain(|_, request| request.doctor != user && request.patient != user);
+        self.proxies
+            .retain(|_, proxy| proxy.proxy != user && proxy.dependant != user);
     }
 
     pub fn get_report(&self, report: ReportID) -> Option<&MedicalReport> {
         self.reports.get(&report)
     }
 
-    pub fn get_report_data_mut(&mut self, report: ReportID) -> Option<&mut String> {
-        Some(&mut self.reports.get_mut(&report)?.content)
+    pub fn get_report_mut(&mut self, report: ReportID) -> Option<ReportMut<'_>> {
+        self.indexes.remove_report(self.reports.get(&report)?);
+        Some(ReportMut {
+            db: self,
+            id: report,
+        })
     }
 
     pub fn store_report(&mut self, report: MedicalReport) {
-        self.reports.insert(report.id, report);
+        self.indexes.add_report(&report);
+        if let Some(previous) = self.reports.insert(report.id, report) {
+            self.indexes.remove_report(&previous);
+            self.indexes.add_report(&self.reports[&previous.id]);
+        }
     }
 
+    pub fn list_users(&self) -> impl Iterator<Item = &UserData> + '_ {
+        self.users.values()
+    }
+
+    /// Liste les rapports par ordre de création
     pub fn list_reports(&self) -> impl Iterator<Item = &MedicalReport> + '_ {
-        self.reports.values()
+        self.indexes
+            .reports_by_date
+            .iter()
+            .filter_map(|(_, id)| self.reports.get(id))
+    }
+
+    /// Liste les rapports d'un patient par ordre de création
+    pub fn list_patient_reports(
+        &self,
+        patient: UserID,
+    ) -> impl Iterator<Item = &MedicalReport> + '_ {
+        self.indexes
+            .reports
+            .get(&patient)
+            .into_iter()
+            .flatten()
+            .filter_map(|(_, id)| self.reports.get(id))
     }
 
     pub fn remove_reports(&mut self, patient: UserID) {
-        self.reports.retain(|_id, report| report.patient != patient);
+        for key in self.indexes.reports.remove(&patient).unwrap_or_default() {
+            self.indexes.reports_by_date.remove(&key);
+            self.reports.remove(&key.1);
+        }
     }
 
     pub fn get_patients(&self, doctor: UserID) -> impl Iterator<Item = UserID> + '_ {
-        self.users
+        self.indexes
+            .patients
+            .get(&doctor)
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    pub fn get_access_request(&self, request: AccessRequestID) -> Option<&AccessRequest> {
+        self.access_requests.get(&request)
+    }
+
+    pub fn get_access_request_mut(
+        &mut self,
+        request: AccessRequestID,
+    ) -> Option<&mut AccessRequest> {
+        self.access_requests.get_mut(&request)
+    }
+
+    pub fn store_access_request(&mut self, request: AccessRequest) {
+        self.access_requests.insert(request.id, request);
+    }
+
+    pub fn list_access_requests(&self) -> impl Iterator<Item = &AccessRequest> + '_ {
+        self.access_requests.values()
+    }
+
+    pub fn get_proxy(&self, proxy: ProxyID) -> Option<&ProxyRelationship> {
+        self.proxies.get(&proxy)
+    }
+
+    pub fn get_proxy_mut(&mut self, proxy: ProxyID) -> Option<&mut ProxyRelationship> {
+        self.proxies.get_mut(&proxy)
+    }
+
+    pub fn store_proxy(&mut self, proxy: ProxyRelationship) {
+        self.proxies.insert(proxy.id, proxy);
+    }
+
+    /// Les relations de représentation d'un utilisateur, qu'il soit
+    /// représentant ou représenté, en vigueur ou non
+    pub fn list_proxies(&self, user: UserID) -> impl Iterator<Item = &ProxyRelationship> + '_ {
+        self.proxies
+            .values()
+            .filter(move |proxy| proxy.proxy == user || proxy.dependant == user)
+    }
+
+    pub fn get_prescription(&self, prescription: PrescriptionID) -> Option<&Prescription> {
+        self.prescriptions.get(&prescription)
+    }
+
+    pub fn get_prescription_mut(
+        &mut self,
+        prescription: PrescriptionID,
+    ) -> Option<&mut Prescription> {
+        self.prescriptions.get_mut(&prescription)
+    }
+
+    pub fn store_prescription(&mut self, prescription: Prescription) {
+        self.prescriptions.insert(prescription.id, prescription);
+    }
+
+    /// Les ordonnances d'un patient, de la plus récente à la plus ancienne
+    pub fn list_prescriptions(&self, patient: UserID) -> impl Iterator<Item = &Prescription> + '_ {
+        let mut prescriptions: Vec<&Prescription> = self
+            .prescriptions
+            .values()
+            .filter(|prescription| prescription.patient == patient)
+            .collect();
+        prescriptions
+            .sort_by_key(|prescription| Reverse((prescription.issued_at, prescription.id)));
+        prescriptions.into_iter()
+    }
+
+    pub fn list_all_prescriptions(&self) -> impl Iterator<Item = &Prescription> + '_ {
+        self.prescriptions.values()
+    }
+
+    /// Remplace le code d'une ordonnance, dont on ne garde que l'empreinte
+    pub fn set_prescription_code(&mut self, prescription: PrescriptionID, digest: String) {
+        self.prescription_codes.retain(|_, id| *id != prescription);
+        self.prescription_codes.insert(digest, prescription);
+    }
+
+    /// L'ordonnance d'un code en cours
+    pub fn find_prescription_by_code(&self, digest: &str) -> Option<&Prescription> {
+        self.prescriptions.get(self.prescription_codes.get(digest)?)
+    }
+
+    /// Invalide un code: il ne peut servir qu'une fois
+    pub fn take_prescription_code(&mut self, digest: &str) -> Option<PrescriptionID> {
+        self.prescription_codes.remove(digest)
+    }
+
+    /// Supprime les ordonnances d'un patient, et leurs codes
+    pub fn remove_prescriptions(&mut self, patient: UserID) {
+        self.prescriptions
+            .retain(|_, prescription| prescription.patient != patient);
+        let prescriptions = &self.prescriptions;
+        self.prescription_codes
+            .retain(|_, id| prescriptions.contains_key(id));
+    }
+
+    pub fn get_lab_result_mut(&mut self, result: LabResultID) -> Option<&mut LabResult> {
+        self.lab_results.get_mut(&result)
+    }
+
+    pub fn list_all_lab_results(&self) -> impl Iterator<Item = &LabResult> + '_ {
+        self.lab_results.values()
+    }
+
+    pub fn store_lab_result(&mut self, result: LabResult) {
+        self.lab_results.insert(result.id, result);
+    }
+
+    /// Les résultats de laboratoire d'un patient, par ordre chronologique
+    pub fn list_lab_results(&self, patient: UserID) -> impl Iterator<Item = &LabResult> + '_ {
+        let mut results: Vec<&LabResult> = self
+            .lab_results
             .values()
-            .filter(move |u| u.has_doctor(doctor))
-            .map(|u| u.id)
+            .filter(|result| result.patient == patient)
+            .collect();
+        results.sort_by_key(|result| (result.taken_at, result.id));
+        results.into_iter()
+    }
+
+    pub fn remove_lab_results(&mut self, patient: UserID) {
+        self.lab_results
+            .retain(|_, result| result.patient != patient);
+    }
+
+    pub fn store_notification(&mut self, notification: Notification) {
+        self.notifications.push(notification);
+    }
+
+    /// Les notifications d'un utilisateur, de la plus récente à la plus ancienne
+    pub fn list_notifications(&self, user: UserID) -> impl Iterator<Item = &Notification> + '_ {
+        self.notifications
+            .iter()
+            .rev()
+            .filter(move |notification| notification.recipient == user)
+    }
+
+    pub fn mark_notifications_read(&mut self, user: UserID) {
+        self.notifications
+            .iter_mut()
+            .filter(|notification| notification.recipient == user)
+            .for_each(|notification| notification.read = true);
+    }
+
+    pub fn is_muted(&self, user: UserID, kind: NotificationKind) -> bool {
+        self.muted_notifications
+            .get(&user)
+            .is_some_and(|muted| muted.contains(&kind))
+    }
+
+    pub fn set_muted(&mut self, user: UserID, kind: NotificationKind, muted: bool) {
+        let kinds = self.muted_notifications.entry(user).or_default();
+        if muted {
+            kinds.insert(kind);
+        } else {
+            kinds.remove(&kind);
+        }
+    }
+
+    pub fn get_language(&self, user: UserID) -> Option<Lang> {
+        self.languages.get(&user).copied()
+    }
+
+    pub fn set_language(&mut self, user: UserID, lang: Lang) {
+        self.languages.insert(user, lang);
+    }
+
+    pub fn get_birth_date(&self, user: UserID) -> Option<NaiveDate> {
+        self.birth_dates.get(&user).copied()
+    }
+
+    pub fn set_birth_date(&mut self, user: UserID, birth_date: NaiveDate) {
+        self.birth_dates.insert(user, birth_date);
+    }
+
+    pub fn require_password_change(&mut self, user: UserID) {
+        self.password_resets.insert(user);
+    }
+
+    pub fn password_change_required(&self, user: UserID) -> bool {
+        self.password_resets.contains(&user)
+    }
+
+    pub fn clear_password_change(&mut self, user: UserID) {
+        self.password_resets.remove(&user);
+    }
+
+    pub fn store_access_log(&mut self, entry: AccessLogEntry) {
+        self.access_log.push(entry);
+    }
+
+    /// Les accès au dossier d'un patient, du plus récent au plus ancien
+    pub fn list_access_log(&self, patient: UserID) -> impl Iterator<Item = &AccessLogEntry> + '_ {
+        self.access_log
+            .iter()
+            .rev()
+            .filter(move |entry| entry.patient == patient)
+    }
+
+    pub fn list_disputed_accesses(&self) -> impl Iterator<Item = &AccessLogEntry> + '_ {
+        self.access_log
+            .iter()
+            .filter(|entry| entry.dispute == DisputeStatus::Disputed)
+    }
+
+    pub fn get_access_log(&self, entry: AccessLogID) -> Option<&AccessLogEntry> {
+        self.access_log.iter().find(|e| e.id == entry)
+    }
+
+    pub fn get_access_log_mut(&mut self, entry: AccessLogID) -> Option<&mut AccessLogEntry> {
+        self.access_log.iter_mut().find(|e| e.id == entry)
+    }
+
+    pub fn get_role_change(&self, change: RoleChangeID) -> Option<&RoleChangeRequest> {
+        self.role_changes.get(&change)
+    }
+
+    pub fn get_role_change_mut(&mut self, change: RoleChangeID) -> Option<&mut RoleChangeRequest> {
+        self.role_changes.get_mut(&change)
+    }
+
+    pub fn store_role_change(&mut self, change: RoleChangeRequest) {
+        self.role_changes.insert(change.id, change);
+    }
+
+    /// Les demandes de changement de rôle, de la plus ancienne à la plus
+    /// récente
+    pub fn list_role_changes(&self) -> impl Iterator<Item = &RoleChangeRequest> + '_ {
+        let mut changes: Vec<&RoleChangeRequest> = self.role_changes.values().collect();
+        changes.sort_by_key(|change| (change.requested_at, change.id));
+        changes.into_iter()
+    }
+
+    pub fn get_policy_change(&self, change: PolicyChangeID) -> Option<&PolicyChangeRequest> {
+        self.policy_changes.get(&change)
+    }
+
+    pub fn get_policy_change_mut(
+        &mut self,
+        change: PolicyChangeID,
+    ) -> Option<&mut PolicyChangeRequest> {
+        self.policy_changes.get_mut(&change)
+    }
+
+    pub fn store_policy_change(&mut self, change: PolicyChangeRequest) {
+        self.policy_changes.insert(change.id, change);
+    }
+
+    /// Les demandes de modification des règles, de la plus ancienne à la
+    /// plus récente
+    pub fn list_policy_changes(&self) -> impl Iterator<Item = &PolicyChangeRequest> + '_ {
+        let mut changes: Vec<&PolicyChangeRequest> = self.policy_changes.values().collect();
+        changes.sort_by_key(|change| (change.requested_at, change.id));
+        changes.into_iter()
+    }
+
+    pub fn store_policy_revision(&mut self, revision: PolicyRevision) {
+        self.policy_history.push(revision);
+    }
+
+    /// La version des règles d'accès en vigueur, si elles ont été modifiées
+    /// depuis l'application
+    pub fn current_policy(&self) -> Option<&PolicyRevision> {
+        self.policy_history.last()
+    }
+
+    /// Les versions des règles d'accès, de la plus récente à la plus ancienne
+    pub fn list_policy_revisions(&self) -> impl Iterator<Item = &PolicyRevision> + '_ {
+        self.policy_history.iter().rev()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::models::AccessScope;
+
+    #[test]
+    fn test_untimestamped_reports_are_migrated() {
+        let json = include_str!("../database.json.example");
+        let mut db: Database = serde_json::from_str(json).expect("Example DB should load");
+        let fallback = Utc::now();
+
+        db.migrate_report_timestamps(fallback);
+        db.rebuild_indexes();
+
+        assert!(db.list_reports().count() > 0);
+        for report in db.list_reports() {
+            assert_eq!(report.created_at, fallback);
+            assert_eq!(report.updated_at, fallback);
+        }
+    }
+
+    #[test]
+    fn test_invalid_records_are_reported_and_quarantined() {
+        let mut raw: Value =
+            serde_json::from_str(include_str!("../database.json.example")).unwrap();
+        for user in raw["users"].as_object_mut().unwrap().values_mut() {
+            match user["username"].as_str() {
+                Some("medecin2") => user["username"] = json!("1234"),
+                Some("patient") => {
+                    user["medical_folder"]["personal_data"]["avs_number"] =
+                        json!("756.1234.5678.98")
+                }
+                _ => {}
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("karak-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("database.json");
+        std::fs::write(&path, raw.to_string()).unwrap();
+
+        let key = HashKey::generate();
+        let Err(OpenError::InvalidRecords(report)) = Database::open(path.clone(), &key) else {
+            panic!("Invalid records should be rejected");
+        };
+        assert_eq!(report.invalid.len(), 2);
+        assert!(report
+            .invalid
+            .iter()
+            .all(|record| record.collection == "users"));
+
+        let (db, report) =
+            Database::open_with(path.clone(), &key, InvalidRecords::Quarantine).unwrap();
+        assert_eq!(db.list_users().count(), 2);
+        assert_eq!(db.list_reports().count(), 2);
+        let quarantined: Value =
+            serde_json::from_str(&std::fs::read_to_string(report.quarantine.unwrap()).unwrap())
+                .unwrap();
+        assert_eq!(quarantined.as_array().unwrap().len(), 2);
+
+        // Quarantined records are removed from the database file
+        assert!(Database::open(path, &key).is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_avs_numbers_are_sealed_on_disk() {
+        let dir = std::env::temp_dir().join(format!("karak-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("database.json");
+        std::fs::write(&path, include_str!("../database.json.example")).unwrap();
+        let avs_number = AVSNumber::try_from("756.1234.5678.97").unwrap();
+
+        // Plain numbers of older databases are sealed when they are opened
+        let key = HashKey::generate();
+        let db = Database::open(path.clone(), &key).unwrap();
+        assert_eq!(db.find_by_avs(&avs_number).count(), 1);
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("avs_number"));
+        assert!(!text.contains("5678"));
+        assert!(text.contains("avs_digest") && text.contains("avs_sealed"));
+
+        let db = Database::open(path.clone(), &key).unwrap();
+        let patient = db.find_by_avs(&avs_number).next().unwrap();
+        let folder = db.get_user(patient).unwrap().medical_folder.as_ref();
+        assert_eq!(folder.unwrap().personal_data.avs_number, avs_number);
+
+        // Another key cannot read them
+        let Err(OpenError::InvalidRecords(report)) =
+            Database::open(path.clone(), &HashKey::generate())
+        else {
+            panic!("Sealed numbers need their key");
+        };
+        assert_eq!(report.invalid.len(), 1);
+
+        // Nor can a sealed number be moved to another patient
+        let mut raw: Value = serde_json::from_str(&text).unwrap();
+        let users = raw["users"].as_object_mut().unwrap();
+        let moved = users.remove(&patient.to_string()).unwrap();
+        users.insert(UserID::new().to_string(), moved);
+        std::fs::write(&path, raw.to_string()).unwrap();
+        assert!(Database::open(path, &key).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// The example database, with its indexes
+    pub(crate) fn example_db() -> Database {
+        let json = include_str!("../database.json.example");
+        let mut db: Database = serde_json::from_str(json).expect("Example DB should load");
+        db.rebuild_indexes();
+        db
+    }
+
+    fn username(name: &str) -> Username {
+        Username::try_from(name).unwrap()
+    }
+
+    #[test]
+    fn test_indexes_are_rebuilt() {
+        let db = example_db();
+        let patient = db.lookup_username(&username("patient")).unwrap().id;
+        let doctor = db.lookup_username(&username("medecin1")).unwrap().id;
+
+        assert_eq!(db.get_patients(doctor).collect::<Vec<_>>(), [patient]);
+        assert_eq!(db.list_patient_reports(patient).count(), 2);
+        let avs_number = AVSNumber::try_from("7561234567897").unwrap();
+        assert_eq!(db.find_by_avs(&avs_number).collect::<Vec<_>>(), [patient]);
+        assert!(db.lookup_username(&username("nobody")).is_none());
+    }
+
+    #[test]
+    fn test_indexes_follow_user_changes() {
+        let mut db = example_db();
+        let patient = db.lookup_username(&username("patient")).unwrap().id;
+        let doctor1 = db.lookup_username(&username("medecin1")).unwrap().id;
+        let doctor2 = db.lookup_username(&username("medecin2")).unwrap().id;
+
+        {
+            let mut user = db.get_user_mut(patient).unwrap();
+            user.username = username("renamed");
+            let folder = user.medical_folder.as_mut().unwrap();
+            folder.revoke(doctor1);
+            folder.grant(doctor2, AccessScope::Reports);
+        }
+
+        assert!(db.lookup_username(&username("patient")).is_none());
+        assert_eq!(
+            db.lookup_username(&username("renamed")).unwrap().id,
+            patient
+        );
+        assert_eq!(db.get_patients(doctor1).count(), 0);
+        assert_eq!(db.get_patients(doctor2).collect::<Vec<_>>(), [patient]);
+
+        // Remplacer un utilisateur le retire des index
+        let password = db.get_user(patient).unwrap().password.clone();
+        db.store_user(UserData {
+            id: patient,
+            role: Role::Patient,
+            username: username("replaced"),
+            password,
+            medical_folder: None,
+        });
+
+        assert!(db.lookup_username(&username("renamed")).is_none());
+        let avs_number = AVSNumber::try_from("756.1234.5678.97").unwrap();
+        assert_eq!(db.find_by_avs(&avs_number).count(), 0);
+        assert_eq!(
+            db.lookup_username(&username("replaced")).unwrap().id,
+            patient
+        );
+        assert_eq!(db.get_patients(doctor2).count(), 0);
+    }
+
+    #[test]
+    fn test_indexes_follow_report_changes() {
+        let mut db = example_db();
+        let patient = db.lookup_username(&username("patient")).unwrap().id;
+        let other = db.lookup_username(&username("admin")).unwrap().id;
+
+        let moved = db.list_patient_reports(patient).next().unwrap().id;
+        db.get_report_mut(moved).unwrap().patient = other;
+        assert_eq!(db.list_patient_reports(patient).count(), 1);
+        assert_eq!(db.list_patient_reports(other).next().unwrap().id, moved);
+
+        db.remove_reports(patient);
+        assert_eq!(db.list_patient_reports(patient).count(), 0);
+        assert_eq!(db.list_reports().count(), 1);
+    }
+
+    #[test]
+    fn test_indexes_keep_duplicates_and_report_order() {
+        let mut db = example_db();
+        let patient = db.lookup_username(&username("patient")).unwrap().id;
+        let password = db.get_user(patient).unwrap().password.clone();
+        let twin = UserID::new();
+        db.store_user(UserData {
+            id: twin,
+            role: Role::Patient,
+            username: username("patient"),
+            password,
+            medical_folder: None,
+        });
+
+        // Renommer le premier ne retire pas l'autre du même nom
+        db.get_user_mut(patient).unwrap().username = username("renamed");
+        assert_eq!(db.lookup_username(&username("patient")).unwrap().id, twin);
+
+        let first = db.list_reports().next().unwrap().id;
+        db.get_report_mut(first).unwrap().created_at = Utc::now();
+        let ordered: Vec<DateTime<Utc>> = db.list_reports().map(|r| r.created_at).collect();
+        assert!(ordered.is_sorted());
+        assert_eq!(db.list_reports().last().unwrap().id, first);
+        assert_eq!(db.list_patient_reports(patient).last().unwrap().id, first);
     }
 }
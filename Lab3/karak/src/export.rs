@@ -0,0 +1,440 @@
+//! Export des rapports médicaux en Markdown, HTML et PDF.
+//!
+//! Les documents sont entièrement générés localement. Le contrôle d'accès
+//! est fait en amont par `Service::export_reports`: ce module ne reçoit que
+//! des rapports dont la lecture a été autorisée.
+
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use strum_macros::EnumIter;
+
+use crate::i18n;
+use crate::models::{ReportID, UserID};
+use crate::t;
+
+/// Un format d'export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+pub enum ExportFormat {
+    #[display("Markdown")]
+    Markdown,
+    #[display("HTML")]
+    Html,
+    #[display("PDF")]
+    Pdf,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+/// Un rapport prêt à être exporté
+#[derive(Debug)]
+pub struct ExportedReport {
+    pub id: ReportID,
+    pub title: String,
+    pub author: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub content: String,
+}
+
+/// Un ensemble de rapports d'un même patient, avec l'en-tête du document
+#[derive(Debug)]
+pub struct ExportDocument {
+    pub patient_id: UserID,
+    pub patient_name: String,
+    /// Numéro AVS masqué, seulement si l'exportateur peut lire les données du patient
+    pub avs_number: Option<String>,
+    pub generated_at: DateTime<Utc>,
+    pub reports: Vec<ExportedReport>,
+}
+
+const DATE_FORMAT: &str = "%d.%m.%Y %H:%M";
+
+impl ExportDocument {
+    /// Génère le document dans le format demandé
+    pub fn render(&self, format: ExportFormat) -> Vec<u8> {
+        match format {
+            ExportFormat::Markdown => self.to_markdown().into_bytes(),
+            ExportFormat::Html => self.to_html().into_bytes(),
+            ExportFormat::Pdf => self.to_pdf(),
+        }
+    }
+
+    fn header_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            t!(ExportPatient, self.patient_name, self.patient_id),
+            t!(ExportGeneratedAt, self.generated_at.format(DATE_FORMAT)),
+            t!(ExportReportCount, self.reports.len()),
+        ];
+        if let Some(avs) = &self.avs_number {
+            lines.insert(1, t!(ExportAvsNumber, avs));
+        }
+        lines
+    }
+
+    fn report_meta(report: &ExportedReport) -> [String; 3] {
+        [
+            t!(ExportAuthor, report.author),
+            t!(ExportCreatedAt, report.created_at.format(DATE_FORMAT)),
+            t!(ExportUpdatedAt, report.updated_at.format(DATE_FORMAT)),
+        ]
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", t!(ExportTitle, self.patient_name));
+        for line in self.header_lines() {
+            out += &format!("- {line}\n");
+        }
+
+        let total = self.reports.len();
+        for (i, report) in self.reports.iter().enumerate() {
+            out += &format!("\n---\n\n## {} ({}/{total})\n\n", report.title, i + 1);
+            for line in Self::report_meta(report) {
+                out += &format!("- {line}\n");
+            }
+            out += &format!(
+                "- {}\n\n{}\n",
+                t!(ExportReference, report.id),
+                report.content
+            );
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let title = escape_html(&t!(ExportTitle, self.patient_name));
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n",
+            i18n::lang().code()
+        );
+        out += &format!("<title>{title}</title>\n");
+        out += concat!(
+            "<style>\n",
+            "body { font-family: sans-serif; max-width: 50em; margin: auto; }\n",
+            "header { border-bottom: 2px solid #333; }\n",
+            "article { page-break-before: always; }\n",
+            "pre { white-space: pre-wrap; font-family: inherit; }\n",
+            ".meta { color: #555; }\n",
+            "@page { @bottom-right { content: \"Page \" counter(page) \" / \" counter(pages); } }\n",
+            "</style>\n</head>\n<body>\n<header>\n",
+        );
+        out += &format!("<h1>{title}</h1>\n<ul>\n");
+        for line in self.header_lines() {
+            out += &format!("<li>{}</li>\n", escape_html(&line));
+        }
+        out += "</ul>\n</header>\n";
+
+        for report in &self.reports {
+            out += &format!("<article>\n<h2>{}</h2>\n", escape_html(&report.title));
+            out += "<ul class=\"meta\">\n";
+            for line in Self::report_meta(report) {
+                out += &format!("<li>{}</li>\n", escape_html(&line));
+            }
+            out += &format!(
+                "</ul>\n<pre>{}</pre>\n</article>\n",
+                escape_html(&report.content)
+            );
+        }
+        out += "</body>\n</html>\n";
+        out
+    }
+
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let mut lines = vec![PdfLine::Title(t!(ExportTitle, self.patient_name))];
+        lines.extend(self.header_lines().into_iter().map(PdfLine::Text));
+
+        for report in &self.reports {
+            lines.push(PdfLine::PageBreak);
+            lines.push(PdfLine::Title(report.title.clone()));
+            lines.extend(Self::report_meta(report).into_iter().map(PdfLine::Text));
+            lines.push(PdfLine::Text(String::new()));
+            for paragraph in report.content.lines() {
+                lines.extend(
+                    wrap(paragraph, PDF_LINE_WIDTH)
+                        .into_iter()
+                        .map(PdfLine::Text),
+                );
+            }
+        }
+
+        let running_header = format!("KARAK - {} ({})", self.patient_name, self.patient_id);
+        render_pdf(&running_header, &lines)
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out += "&amp;",
+            '<' => out += "&lt;",
+            '>' => out += "&gt;",
+            '"' => out += "&quot;",
+            '\'' => out += "&#39;",
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Découpe un paragraphe en lignes d'au plus `width` caractères
+fn wrap(paragraph: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+
+    for word in paragraph.split_whitespace() {
+        let mut word = word.to_owned();
+        // Les mots trop longs sont coupés de force
+        while word.chars().count() > width {
+            if !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            let split = word.char_indices().nth(width).map(|(i, _)| i).unwrap();
+            lines.push(word[..split].to_owned());
+            word = word[split..].to_owned();
+        }
+
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current += &word;
+    }
+
+    lines.push(current);
+    lines
+}
+
+// ------------------------------------------------------------------
+// Écriture PDF minimale: pages A4, polices standard Helvetica en
+// WinAnsiEncoding, sans dépendance externe.
+// ------------------------------------------------------------------
+
+const PDF_PAGE_WIDTH: u32 = 595;
+const PDF_PAGE_HEIGHT: u32 = 842;
+const PDF_MARGIN: u32 = 56;
+const PDF_LEADING: u32 = 14;
+const PDF_LINE_WIDTH: usize = 90;
+/// Lignes de corps par page, en laissant la place de l'en-tête et du pied de page
+const PDF_LINES_PER_PAGE: usize =
+    ((PDF_PAGE_HEIGHT - 2 * PDF_MARGIN - 3 * PDF_LEADING) / PDF_LEADING) as usize;
+
+enum PdfLine {
+    Title(String),
+    Text(String),
+    PageBreak,
+}
+
+fn render_pdf(running_header: &str, lines: &[PdfLine]) -> Vec<u8> {
+    // Pagination
+    let mut pages: Vec<Vec<&PdfLine>> = vec![vec![]];
+    for line in lines {
+        let page = pages.last_mut().unwrap();
+        match line {
+            PdfLine::PageBreak if page.is_empty() => {}
+            PdfLine::PageBreak => pages.push(vec![]),
+            _ if page.len() == PDF_LINES_PER_PAGE => pages.push(vec![line]),
+            _ => page.push(line),
+        }
+    }
+
+    let total = pages.len();
+    let streams: Vec<Vec<u8>> = pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| page_stream(running_header, page, i + 1, total))
+        .collect();
+
+    // Objets: 1 catalogue, 2 arbre des pages, 3-4 polices, puis page et contenu
+    let first_page_obj = 5;
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {total} >>",
+            (0..total)
+                .map(|i| format!("{} 0 R", first_page_obj + 2 * i))
+                .collect::<Vec<_>>()
+                .join(" ")
+        )
+        .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    ];
+    for (i, stream) in streams.into_iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PDF_PAGE_WIDTH} {PDF_PAGE_HEIGHT}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                first_page_obj + 2 * i + 1
+            )
+            .into_bytes(),
+        );
+        let mut content = format!("<< /Length {} >>\nstream\n", stream.len()).into_bytes();
+        content.extend(stream);
+        content.extend(b"\nendstream");
+        objects.push(content);
+    }
+
+    let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+        out.extend(object);
+        out.extend(b"\nendobj\n");
+    }
+
+    let xref = out.len();
+    out.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        out.extend(format!("{offset:010} 00000 n \n").into_bytes());
+    }
+    out.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .into_bytes(),
+    );
+    out
+}
+
+fn page_stream(running_header: &str, lines: &[&PdfLine], page: usize, total: usize) -> Vec<u8> {
+    let top = PDF_PAGE_HEIGHT - PDF_MARGIN;
+    let mut stream = vec![];
+
+    let mut text_at = |font: &str, size: u32, x: u32, y: u32, text: &str| {
+        stream.extend(format!("BT /{font} {size} Tf {x} {y} Td (").into_bytes());
+        stream.extend(pdf_string(text));
+        stream.extend(b") Tj ET\n");
+    };
+
+    text_at("F1", 8, PDF_MARGIN, top, running_header);
+    text_at(
+        "F1",
+        8,
+        PDF_PAGE_WIDTH - PDF_MARGIN - 50,
+        PDF_MARGIN - PDF_LEADING,
+        &t!(ExportPage, page, total),
+    );
+
+    let mut y = top - 2 * PDF_LEADING;
+    for line in lines {
+        match line {
+            PdfLine::Title(text) => text_at("F2", 12, PDF_MARGIN, y, text),
+            PdfLine::Text(text) => text_at("F1", 10, PDF_MARGIN, y, text),
+            PdfLine::PageBreak => continue,
+        }
+        y -= PDF_LEADING;
+    }
+    stream
+}
+
+/// Encode un texte en chaîne littérale PDF (WinAnsiEncoding)
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut out = vec![];
+    for c in text.chars() {
+        let byte = match c {
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '–' => 0x96,
+            '—' => 0x97,
+            c if (c as u32) < 0x100 => c as u8,
+            _ => b'?',
+        };
+        match byte {
+            b'(' | b')' | b'\\' => out.extend([b'\\', byte]),
+            0x20..=0x7E => out.push(byte),
+            _ => out.extend(format!("\\{byte:03o}").into_bytes()),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn create_test_document(reports: usize, lines: usize) -> ExportDocument {
+        ExportDocument {
+            patient_id: UserID::new(),
+            patient_name: "patient".to_string(),
+            avs_number: Some("756.1234.5678.97".to_string()),
+            generated_at: Utc::now(),
+            reports: (0..reports)
+                .map(|i| ExportedReport {
+                    id: ReportID::new(),
+                    title: format!("Rapport <{i}> & co"),
+                    author: "medecin".to_string(),
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                    content: "Ligne de contenu (é)\n".repeat(lines),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_markdown_and_html_headers() {
+        let document = create_test_document(2, 1);
+
+        let markdown = document.to_markdown();
+        assert!(markdown.starts_with("# Dossier médical de patient"));
+        assert!(markdown.contains("Numéro AVS: 756.1234.5678.97"));
+        assert!(markdown.contains("(2/2)"));
+
+        let html = document.to_html();
+        assert!(
+            html.contains("Rapport &lt;0&gt; &amp; co"),
+            "HTML must be escaped"
+        );
+        assert!(!html.contains("<0>"));
+        assert!(html.contains("Auteur: medecin"));
+    }
+
+    #[test]
+    fn test_pdf_pages_and_offsets() {
+        let document = create_test_document(2, PDF_LINES_PER_PAGE);
+        let pdf = document.to_pdf();
+        let text = String::from_utf8_lossy(&pdf);
+
+        // Une page d'en-tête, puis deux pages par rapport
+        let pages = text.matches("/Type /Page ").count();
+        assert_eq!(pages, 5);
+        assert!(text.contains("(Page 5 / 5)"));
+        assert!(text.contains("Ligne de contenu \\(\\351\\)"));
+
+        // Les offsets de la table xref pointent bien sur les objets
+        let xref = pdf.windows(5).position(|w| w == b"xref\n").unwrap();
+        let table = String::from_utf8(pdf[xref..].to_vec()).unwrap();
+        for (i, entry) in table.lines().skip(3).take(pages).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_wrap_long_lines() {
+        let lines = wrap(&"mot ".repeat(40), 20);
+        assert!(lines.iter().all(|line| line.chars().count() <= 20));
+        assert_eq!(lines.join(" "), "mot ".repeat(40).trim());
+
+        let lines = wrap(&"x".repeat(45), 20);
+        assert_eq!(lines.len(), 3);
+    }
+}
@@ -0,0 +1,461 @@
+//! Vérification et réparation de la cohérence de la base.
+//!
+//! Les enregistrements sont validés un par un à l'ouverture de la base
+//! (voir `Database::open_with`), mais les références entre eux ne le sont
+//! pas: un fichier modifié à la main peut contenir des rapports, des
+//! ordonnances ou des résultats d'analyse de patients supprimés, des
+//! auteurs, prescripteurs ou pharmaciens inexistants, des accès accordés à
+//! des comptes qui ne sont pas médecins ou des noms d'utilisateur en double.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use derive_more::Display;
+
+use crate::db::Database;
+use crate::i18n::Msg;
+use crate::models::{LabResultID, PrescriptionID, ReportID, Role, UserData, UserID};
+use crate::t;
+use crate::utils::input_validation::Username;
+use crate::utils::password_utils::{generate_password, hash};
+
+/// Une incohérence de la base
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Display)]
+pub enum Issue {
+    /// Des rapports dont le patient n'existe pas
+    #[display("{}", t!(FsckOrphanReports, reports.len(), patient))]
+    OrphanReports {
+        patient: UserID,
+        reports: Vec<ReportID>,
+    },
+    /// Un rapport dont l'auteur n'existe pas
+    #[display("{}", t!(FsckUnknownAuthor, report, author))]
+    UnknownAuthor { report: ReportID, author: UserID },
+    /// Des ordonnances dont le patient n'existe pas
+    #[display("{}", t!(FsckOrphanPrescriptions, prescriptions.len(), patient))]
+    OrphanPrescriptions {
+        patient: UserID,
+        prescriptions: Vec<PrescriptionID>,
+    },
+    /// Une ordonnance dont le prescripteur n'existe pas
+    #[display("{}", t!(FsckUnknownPrescriber, prescription, prescriber))]
+    UnknownPrescriber {
+        prescription: PrescriptionID,
+        prescriber: UserID,
+    },
+    /// Une ordonnance délivrée par un pharmacien qui n'existe pas
+    #[display("{}", t!(FsckUnknownPharmacist, prescription, pharmacist))]
+    UnknownPharmacist {
+        prescription: PrescriptionID,
+        pharmacist: UserID,
+    },
+    /// Des résultats d'analyse dont le patient n'existe pas
+    #[display("{}", t!(FsckOrphanLabResults, results.len(), patient))]
+    OrphanLabResults {
+        patient: UserID,
+        results: Vec<LabResultID>,
+    },
+    /// Un résultat d'analyse dont l'auteur n'existe pas
+    #[display("{}", t!(FsckUnknownLabAuthor, result, author))]
+    UnknownLabAuthor { result: LabResultID, author: UserID },
+    /// Un accès au dossier accordé à un compte qui n'est pas médecin, ou
+    /// qui n'existe plus
+    #[display("{}", t!(FsckNotADoctor, patient, doctor))]
+    NotADoctor { patient: UserID, doctor: UserID },
+    /// Un nom d'utilisateur porté par plusieurs comptes
+    #[display("{}", t!(FsckDuplicateUsername, username, users.len()))]
+    DuplicateUsername {
+        username: Username,
+        users: Vec<UserID>,
+    },
+}
+
+impl Issue {
+    /// Ce que fait la réparation
+    pub fn repair_description(&self) -> Msg {
+        match self {
+            Issue::OrphanReports { .. } => Msg::FsckRepairDeleteReports,
+            Issue::OrphanPrescriptions { .. } => Msg::FsckRepairDeletePrescriptions,
+            Issue::OrphanLabResults { .. } => Msg::FsckRepairDeleteLabResults,
+            Issue::UnknownAuthor { .. }
+            | Issue::UnknownPrescriber { .. }
+            | Issue::UnknownPharmacist { .. }
+            | Issue::UnknownLabAuthor { .. } => Msg::FsckRepairDeletedAuthor,
+            Issue::NotADoctor { .. } => Msg::FsckRepairRevoke,
+            Issue::DuplicateUsername { .. } => Msg::FsckRepairRename,
+        }
+    }
+}
+
+/// Cherche les incohérences de la base, dans un ordre stable
+pub fn check(db: &Database) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let mut orphans: BTreeMap<UserID, Vec<ReportID>> = BTreeMap::new();
+    for report in db.list_reports() {
+        if db.get_user(report.patient).is_err() {
+            orphans.entry(report.patient).or_default().push(report.id);
+        }
+        if db.get_user(report.author).is_err() {
+            issues.push(Issue::UnknownAuthor {
+                report: report.id,
+                author: report.author,
+            });
+        }
+    }
+    issues.extend(orphans.into_iter().map(|(patient, mut reports)| {
+        reports.sort();
+        Issue::OrphanReports { patient, reports }
+    }));
+
+    let exists = |user: UserID| db.get_user(user).is_ok();
+    let mut orphans: BTreeMap<UserID, Vec<PrescriptionID>> = BTreeMap::new();
+    for prescription in db.list_all_prescriptions() {
+        if !exists(prescription.patient) {
+            orphans
+                .entry(prescription.patient)
+                .or_default()
+                .push(prescription.id);
+        }
+        if !exists(prescription.prescriber) {
+            issues.push(Issue::UnknownPrescriber {
+                prescription: prescription.id,
+                prescriber: prescription.prescriber,
+            });
+        }
+        let pharmacists: BTreeSet<UserID> = prescription
+            .dispensings
+            .iter()
+            .map(|dispensing| dispensing.pharmacist)
+            .filter(|&pharmacist| !exists(pharmacist))
+            .collect();
+        issues.extend(
+            pharmacists
+                .into_iter()
+                .map(|pharmacist| Issue::UnknownPharmacist {
+                    prescription: prescription.id,
+                    pharmacist,
+                }),
+        );
+    }
+    issues.extend(orphans.into_iter().map(|(patient, mut prescriptions)| {
+        prescriptions.sort();
+        Issue::OrphanPrescriptions {
+            patient,
+            prescriptions,
+        }
+    }));
+
+    let mut orphans: BTreeMap<UserID, Vec<LabResultID>> = BTreeMap::new();
+    for result in db.list_all_lab_results() {
+        if !exists(result.patient) {
+            orphans.entry(result.patient).or_default().push(result.id);
+        }
+        if !exists(result.author) {
+            issues.push(Issue::UnknownLabAuthor {
+                result: result.id,
+                author: result.author,
+            });
+        }
+    }
+    issues.extend(orphans.into_iter().map(|(patient, mut results)| {
+        results.sort();
+        Issue::OrphanLabResults { patient, results }
+    }));
+
+    let mut usernames: BTreeMap<&Username, Vec<UserID>> = BTreeMap::new();
+    for user in db.list_users() {
+        usernames.entry(&user.username).or_default().push(user.id);
+
+        let Some(folder) = &user.medical_folder else {
+            continue;
+        };
+        for &doctor in folder.doctors.iter().chain(&folder.report_readers) {
+            let is_doctor = db
+                .get_user(doctor)
+                .is_ok_and(|doctor| doctor.role == Role::Doctor);
+            if !is_doctor {
+                issues.push(Issue::NotADoctor {
+                    patient: user.id,
+                    doctor,
+                });
+            }
+        }
+    }
+    issues.extend(
+        usernames
+            .into_iter()
+            .filter(|(_, users)| users.len() > 1)
+            .map(|(username, mut users)| {
+                users.sort();
+                Issue::DuplicateUsername {
+                    username: username.clone(),
+                    users,
+                }
+            }),
+    );
+
+    issues.sort();
+    issues
+}
+
+/// Répare une incohérence trouvée par `check`. Un auteur, prescripteur ou
+/// pharmacien inexistant est remplacé par un compte verrouillé
+/// `deleted_...` du même identifiant: les documents gardent leur auteur
+/// d'origine, sans l'attribuer à personne d'autre. Pour les noms en
+/// double, le premier compte garde son nom et les autres sont renommés: les
+/// comptes renommés sont retournés avec leur nouveau nom.
+pub fn repair(db: &mut Database, issue: &Issue) -> Vec<(UserID, Username)> {
+    let mut renamed = Vec::new();
+    match issue {
+        Issue::OrphanReports { patient, .. } => db.remove_reports(*patient),
+        Issue::OrphanPrescriptions { patient, .. } => db.remove_prescriptions(*patient),
+        Issue::OrphanLabResults { patient, .. } => db.remove_lab_results(*patient),
+        Issue::UnknownAuthor { author: user, .. }
+        | Issue::UnknownPrescriber {
+            prescriber: user, ..
+        }
+        | Issue::UnknownPharmacist {
+            pharmacist: user, ..
+        }
+        | Issue::UnknownLabAuthor { author: user, .. } => store_placeholder(db, *user),
+        Issue::NotADoctor { patient, doctor } => {
+            if let Ok(mut user) = db.get_user_mut(*patient) {
+                if let Some(folder) = user.medical_folder.as_mut() {
+                    folder.revoke(*doctor);
+                }
+            }
+        }
+        Issue::DuplicateUsername { username, users } => {
+            for &user in users.iter().skip(1) {
+                let new_name = free_username(db, username);
+                if let Ok(mut user) = db.get_user_mut(user) {
+                    user.username = new_name.clone();
+                }
+                renamed.push((user, new_name));
+            }
+            // L'index des noms ne contenait qu'un seul des comptes en double
+            db.rebuild_indexes();
+        }
+    }
+    renamed
+}
+
+/// Crée un compte verrouillé `deleted_...` pour un utilisateur inexistant
+fn store_placeholder(db: &mut Database, user: UserID) {
+    if db.get_user(user).is_ok() {
+        return;
+    }
+    let base = Username::try_from(format!("deleted_{}", &user.to_string()[..8]))
+        .expect("Placeholder names are valid usernames");
+    let username = match db.lookup_username(&base) {
+        Some(_) => free_username(db, &base),
+        None => base,
+    };
+    // Personne ne connaît ce mot de passe: le compte est verrouillé
+    db.store_user(UserData {
+        id: user,
+        role: Role::Patient,
+        username,
+        password: hash(&generate_password()),
+        medical_folder: None,
+    });
+}
+
+/// Un nom libre dérivé de `base`: `base_2`, `base_3`, ... raccourci si
+/// besoin pour rester un nom valide
+fn free_username(db: &Database, base: &Username) -> Username {
+    (2..)
+        .find_map(|n| {
+            let suffix = format!("_{n}");
+            let stem: String = base.as_ref().chars().take(20 - suffix.len()).collect();
+            Username::try_from(format!("{stem}{suffix}"))
+                .ok()
+                .filter(|name| db.lookup_username(name).is_none())
+        })
+        .expect("Some suffix is always free")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::test::example_db;
+    use crate::models::{Dispensing, LabResult, MedicalReport, Prescription};
+
+    fn id_of(db: &Database, name: &str) -> UserID {
+        db.lookup_username(&Username::try_from(name).unwrap())
+            .unwrap()
+            .id
+    }
+
+    #[test]
+    fn test_example_db_is_consistent() {
+        assert_eq!(check(&example_db()), []);
+    }
+
+    #[test]
+    fn test_issues_are_found_and_repaired() {
+        let mut db = example_db();
+        let admin = id_of(&db, "admin");
+        let patient = id_of(&db, "patient");
+        let doctor = id_of(&db, "medecin1");
+        let ghost = UserID::new();
+
+        let report = |author, patient| MedicalReport {
+            id: ReportID::new(),
+            title: "Report".into(),
+            author,
+            patient,
+            content: String::new(),
+            created_at: Default::default(),
+            updated_at: Default::default(),
+        };
+        let orphan = report(doctor, ghost);
+        let orphan_id = orphan.id;
+        db.store_report(orphan);
+        let unknown_author = report(ghost, patient);
+        let unknown_author_id = unknown_author.id;
+        db.store_report(unknown_author);
+        db.store_report(report(ghost, patient));
+
+        db.get_user_mut(patient)
+            .unwrap()
+            .medical_folder
+            .as_mut()
+            .unwrap()
+            .doctors
+            .insert(admin);
+
+        let duplicate = UserID::new();
+        db.store_user(UserData {
+            id: duplicate,
+            role: Role::Doctor,
+            username: Username::try_from("medecin1").unwrap(),
+            password: hash("password"),
+            medical_folder: None,
+        });
+
+        let issues = check(&db);
+        assert_eq!(issues.len(), 5, "{issues:?}");
+        assert!(issues.contains(&Issue::OrphanReports {
+            patient: ghost,
+            reports: vec![orphan_id],
+        }));
+        assert!(issues.contains(&Issue::UnknownAuthor {
+            report: unknown_author_id,
+            author: ghost,
+        }));
+        assert!(issues.contains(&Issue::NotADoctor {
+            patient,
+            doctor: admin,
+        }));
+
+        let mut renamed = Vec::new();
+        for issue in &issues {
+            renamed.extend(repair(&mut db, issue));
+        }
+
+        assert_eq!(check(&db), []);
+        assert!(db.get_report(orphan_id).is_none());
+
+        // Reports keep their author, now a locked placeholder account
+        assert_eq!(db.get_report(unknown_author_id).unwrap().author, ghost);
+        let placeholder = db.get_user(ghost).unwrap();
+        assert!(placeholder.username.as_ref().starts_with("deleted_"));
+        assert_eq!(placeholder.role, Role::Patient);
+        assert!(placeholder.medical_folder.is_none());
+        assert!(!db.get_user(patient).unwrap().has_doctor(admin));
+
+        let [(renamed_user, new_name)] = renamed.as_slice() else {
+            panic!("Exactly one account should be renamed: {renamed:?}");
+        };
+        assert_eq!(new_name.as_ref(), "medecin1_2");
+        assert_eq!(db.lookup_username(new_name).unwrap().id, *renamed_user);
+        assert_ne!(id_of(&db, "medecin1"), *renamed_user);
+    }
+
+    #[test]
+    fn test_prescription_and_lab_result_issues_are_repaired() {
+        let mut db = example_db();
+        let patient = id_of(&db, "patient");
+        let doctor = id_of(&db, "medecin1");
+        let (ghost, pharmacist) = (UserID::new(), UserID::new());
+
+        let prescription = |patient, prescriber| Prescription {
+            id: PrescriptionID::new(),
+            patient,
+            prescriber,
+            drug: "Drug".into(),
+            dose: "Dose".into(),
+            duration_days: 1,
+            repeats: 1,
+            issued_at: Default::default(),
+            dispensings: vec![
+                Dispensing {
+                    pharmacist,
+                    at: Default::default(),
+                };
+                2
+            ],
+        };
+        let orphan = prescription(ghost, doctor);
+        let orphan_id = orphan.id;
+        db.store_prescription(orphan);
+        let unknown_prescriber = prescription(patient, ghost);
+        let unknown_prescriber_id = unknown_prescriber.id;
+        db.store_prescription(unknown_prescriber);
+
+        let result = |patient, author| LabResult {
+            id: LabResultID::new(),
+            patient,
+            author,
+            analyte: "hgb".into(),
+            value: 1.0,
+            unit: "g/L".into(),
+            range: Default::default(),
+            taken_at: Default::default(),
+        };
+        let orphan_result = result(ghost, doctor);
+        let orphan_result_id = orphan_result.id;
+        db.store_lab_result(orphan_result);
+        let unknown_author = result(patient, ghost);
+        let unknown_author_id = unknown_author.id;
+        db.store_lab_result(unknown_author);
+
+        let issues = check(&db);
+        assert_eq!(issues.len(), 6, "{issues:?}");
+        assert!(issues.contains(&Issue::OrphanPrescriptions {
+            patient: ghost,
+            prescriptions: vec![orphan_id],
+        }));
+        assert!(issues.contains(&Issue::UnknownPrescriber {
+            prescription: unknown_prescriber_id,
+            prescriber: ghost,
+        }));
+        assert!(issues.contains(&Issue::UnknownPharmacist {
+            prescription: unknown_prescriber_id,
+            pharmacist,
+        }));
+        assert!(issues.contains(&Issue::OrphanLabResults {
+            patient: ghost,
+            results: vec![orphan_result_id],
+        }));
+        assert!(issues.contains(&Issue::UnknownLabAuthor {
+            result: unknown_author_id,
+            author: ghost,
+        }));
+
+        for issue in &issues {
+            repair(&mut db, issue);
+        }
+        assert_eq!(check(&db), []);
+        assert!(db.get_prescription(orphan_id).is_none());
+        assert_eq!(db.list_lab_results(ghost).count(), 0);
+        assert_eq!(
+            db.get_prescription(unknown_prescriber_id)
+                .unwrap()
+                .prescriber,
+            ghost
+        );
+        assert!(db.get_user(pharmacist).is_ok());
+    }
+}
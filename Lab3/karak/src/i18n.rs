@@ -0,0 +1,2118 @@
+//! Catalogue des messages de l'interface, en français, allemand et anglais.
+//!
+//! La langue par défaut de l'installation est choisie au démarrage (option
+//! `--lang`, variable `KARAK_LANG` ou `LANG`), puis remplacée pendant une
+//! session par la préférence de l'utilisateur connecté.
+
+use crate::utils::input_validation::InvalidInput;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// Une langue de l'interface, affichée dans sa propre langue
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Hash, EnumIter, Display,
+)]
+pub enum Lang {
+    #[default]
+    #[display("Français")]
+    Fr,
+    #[display("Deutsch")]
+    De,
+    #[display("English")]
+    En,
+}
+
+impl Lang {
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::Fr => "fr",
+            Lang::De => "de",
+            Lang::En => "en",
+        }
+    }
+
+    /// Langue demandée par l'environnement: `KARAK_LANG`, puis la locale POSIX
+    pub fn from_env() -> Option<Self> {
+        ["KARAK_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| value.parse().ok())
+    }
+}
+
+impl FromStr for Lang {
+    type Err = InvalidInput;
+
+    /// Accepte un code de langue ou une locale (`de`, `de_CH.UTF-8`, `en-GB`)
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let code = value
+            .split(['_', '-', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        Lang::iter()
+            .find(|lang| lang.code() == code)
+            .ok_or(InvalidInput)
+    }
+}
+
+static DEFAULT_LANG: AtomicU8 = AtomicU8::new(Lang::Fr as u8);
+
+thread_local! {
+    static SESSION_LANG: Cell<Option<Lang>> = const { Cell::new(None) };
+}
+
+/// Définit la langue de l'installation
+pub fn set_default_lang(lang: Lang) {
+    DEFAULT_LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+pub fn default_lang() -> Lang {
+    match DEFAULT_LANG.load(Ordering::Relaxed) {
+        l if l == Lang::De as u8 => Lang::De,
+        l if l == Lang::En as u8 => Lang::En,
+        _ => Lang::Fr,
+    }
+}
+
+/// Définit la langue de la session courante, `None` pour revenir à la
+/// langue de l'installation.
+pub fn set_session_lang(lang: Option<Lang>) {
+    SESSION_LANG.set(lang);
+}
+
+/// La langue dans laquelle les messages sont actuellement affichés
+pub fn lang() -> Lang {
+    SESSION_LANG.get().unwrap_or_else(default_lang)
+}
+
+/// Exécute `f` avec une autre langue, par exemple pour rédiger une
+/// notification dans la langue de son destinataire.
+pub fn with_lang<T>(lang: Lang, f: impl FnOnce() -> T) -> T {
+    let previous = SESSION_LANG.replace(Some(lang));
+    let result = f();
+    SESSION_LANG.set(previous);
+    result
+}
+
+/// Donne le texte d'un message dans la langue courante, en remplaçant
+/// les paramètres `{0}`, `{1}`, ... par les arguments donnés.
+#[macro_export]
+macro_rules! t {
+    ($key:ident) => {
+        $crate::i18n::Msg::$key.text()
+    };
+    ($key:ident, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::Msg::$key.format(&[$(&$arg),+])
+    };
+}
+
+macro_rules! catalogue {
+    ($($key:ident { fr: $fr:literal, de: $de:literal, en: $en:literal, })*) => {
+        /// Un message de l'interface
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+        pub enum Msg {
+            $($key,)*
+        }
+
+        impl Msg {
+            pub fn text_in(self, lang: Lang) -> &'static str {
+                match (self, lang) {
+                    $(
+                        (Msg::$key, Lang::Fr) => $fr,
+                        (Msg::$key, Lang::De) => $de,
+                        (Msg::$key, Lang::En) => $en,
+                    )*
+                }
+            }
+        }
+    };
+}
+
+impl Msg {
+    pub fn text(self) -> &'static str {
+        self.text_in(lang())
+    }
+
+    /// Le texte du message avec ses paramètres remplacés
+    pub fn format(self, args: &[&dyn fmt::Display]) -> String {
+        let mut out = String::new();
+        let mut rest = self.text();
+        while let Some(start) = rest.find('{') {
+            out += &rest[..start];
+            rest = &rest[start..];
+            let arg = rest
+                .find('}')
+                .and_then(|end| Some((end, args.get(rest[1..end].parse::<usize>().ok()?)?)));
+            match arg {
+                Some((end, arg)) => {
+                    out += &arg.to_string();
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    out.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        out + rest
+    }
+}
+
+impl fmt::Display for Msg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.text())
+    }
+}
+
+catalogue! {
+    // Menu principal
+    Welcome {
+        fr: "Bienvenue sur KARAK, le dossier électronique du patient super sécurisé.",
+        de: "Willkommen bei KARAK, dem supersicheren elektronischen Patientendossier.",
+        en: "Welcome to KARAK, the super secure electronic patient record.",
+    }
+    NoAdminYet {
+        fr: "[!] Aucun administrateur n'existe encore. Lancez `karak init` pour en créer un.",
+        de: "[!] Es gibt noch keinen Administrator. Starten Sie `karak init`, um einen zu erstellen.",
+        en: "[!] No administrator exists yet. Run `karak init` to create one.",
+    }
+    ErrorPrefix {
+        fr: "Erreur: {0}",
+        de: "Fehler: {0}",
+        en: "Error: {0}",
+    }
+    WhatToDo {
+        fr: "Que voulez-vous faire ?",
+        de: "Was möchten Sie tun?",
+        en: "What do you want to do?",
+    }
+    MenuRegister {
+        fr: "Créer un compte",
+        de: "Konto erstellen",
+        en: "Create an account",
+    }
+    MenuLogin {
+        fr: "Se connecter",
+        de: "Anmelden",
+        en: "Log in",
+    }
+    MenuExit {
+        fr: "Quitter",
+        de: "Beenden",
+        en: "Quit",
+    }
+    PromptUsernameToRegister {
+        fr: "Username à enregistrer: ",
+        de: "Zu registrierender Benutzername: ",
+        en: "Username to register: ",
+    }
+    PromptUsername {
+        fr: "Username: ",
+        de: "Benutzername: ",
+        en: "Username: ",
+    }
+    PromptPassword {
+        fr: "Entrez votre mot de passe : ",
+        de: "Geben Sie Ihr Passwort ein: ",
+        en: "Enter your password: ",
+    }
+    WelcomeUser {
+        fr: "[*] Bienvenue, {0}.",
+        de: "[*] Willkommen, {0}.",
+        en: "[*] Welcome, {0}.",
+    }
+    UnreadNotifications {
+        fr: "[*] Vous avez {0} notification(s) non lue(s).",
+        de: "[*] Sie haben {0} ungelesene Benachrichtigung(en).",
+        en: "[*] You have {0} unread notification(s).",
+    }
+
+    // Menu utilisateur
+    MenuSetPersonalData {
+        fr: "Créer mon dossier médical",
+        de: "Mein Patientendossier erstellen",
+        en: "Create my medical folder",
+    }
+    MenuReadFolder {
+        fr: "Lire mon dossier médical",
+        de: "Mein Patientendossier lesen",
+        en: "Read my medical folder",
+    }
+    MenuAddDoctor {
+        fr: "Donner accès à mon dossier à un médecin",
+        de: "Einem Arzt Zugriff auf mein Dossier geben",
+        en: "Give a doctor access to my folder",
+    }
+    MenuReviewAccessRequests {
+        fr: "Demandes d'accès à mon dossier",
+        de: "Zugriffsanfragen auf mein Dossier",
+        en: "Access requests to my folder",
+    }
+    MenuCheckPatient {
+        fr: "Lire le dossier d'un patient",
+        de: "Dossier eines Patienten lesen",
+        en: "Read a patient's folder",
+    }
+    MenuRequestAccess {
+        fr: "Demander l'accès au dossier d'un patient",
+        de: "Zugriff auf das Dossier eines Patienten anfragen",
+        en: "Request access to a patient's folder",
+    }
+    MenuAddReport {
+        fr: "Écrire un rapport",
+        de: "Einen Bericht schreiben",
+        en: "Write a report",
+    }
+    MenuUpdateRole {
+        fr: "Administrer les Rôles",
+        de: "Rollen verwalten",
+        en: "Manage roles",
+    }
+    MenuReviewRoleChanges {
+        fr: "Changements de rôle à approuver",
+        de: "Zu genehmigende Rollenänderungen",
+        en: "Role changes to approve",
+    }
+    NoPendingRoleChanges {
+        fr: "[*] Aucun changement de rôle à approuver",
+        de: "[*] Keine zu genehmigenden Rollenänderungen",
+        en: "[*] No role changes to approve",
+    }
+    PromptChooseRoleChange {
+        fr: "Choisissez un changement de rôle:",
+        de: "Wählen Sie eine Rollenänderung:",
+        en: "Choose a role change:",
+    }
+    RoleChangeLine {
+        fr: "{0} demande le rôle {2} pour {1} (expire le {3})",
+        de: "{0} beantragt die Rolle {2} für {1} (läuft am {3} ab)",
+        en: "{0} requests the role {2} for {1} (expires on {3})",
+    }
+    RoleChangePending {
+        fr: "[*] Changement de rôle en attente de l'approbation d'un autre administrateur",
+        de: "[*] Rollenänderung wartet auf die Genehmigung eines anderen Administrators",
+        en: "[*] Role change awaiting approval by another administrator",
+    }
+    MenuPolicy {
+        fr: "Administrer les règles d'accès",
+        de: "Zugriffsregeln verwalten",
+        en: "Manage the access policy",
+    }
+    PolicyActionAdd {
+        fr: "Ajouter une règle",
+        de: "Eine Regel hinzufügen",
+        en: "Add a rule",
+    }
+    PolicyActionRemove {
+        fr: "Retirer une règle",
+        de: "Eine Regel entfernen",
+        en: "Remove a rule",
+    }
+    PolicyActionReview {
+        fr: "Modifications à approuver",
+        de: "Zu genehmigende Änderungen",
+        en: "Changes to approve",
+    }
+    NoPendingPolicyChanges {
+        fr: "[*] Aucune modification des règles à approuver",
+        de: "[*] Keine zu genehmigenden Regeländerungen",
+        en: "[*] No policy changes to approve",
+    }
+    PromptChoosePolicyChange {
+        fr: "Choisissez une modification des règles:",
+        de: "Wählen Sie eine Regeländerung:",
+        en: "Choose a policy change:",
+    }
+    PolicyChangeLine {
+        fr: "{0} demande {1} (expire le {2})",
+        de: "{0} beantragt {1} (läuft am {2} ab)",
+        en: "{0} requests {1} (expires on {2})",
+    }
+    PolicyChangePending {
+        fr: "[*] Modification des règles en attente de l'approbation d'un autre administrateur",
+        de: "[*] Regeländerung wartet auf die Genehmigung eines anderen Administrators",
+        en: "[*] Policy change awaiting approval by another administrator",
+    }
+    PolicyActionHistory {
+        fr: "Historique des modifications",
+        de: "Änderungsverlauf",
+        en: "History of changes",
+    }
+    PromptPolicyAction {
+        fr: "Action autorisée par la règle:",
+        de: "Von der Regel erlaubte Aktion:",
+        en: "Action allowed by the rule:",
+    }
+    PromptPolicyRule {
+        fr: "Condition (expression Casbin, p. ex. r.sub.role == \"Admin\"):",
+        de: "Bedingung (Casbin-Ausdruck, z. B. r.sub.role == \"Admin\"):",
+        en: "Condition (Casbin expression, e.g. r.sub.role == \"Admin\"):",
+    }
+    PromptChoosePolicyRule {
+        fr: "Règle à retirer:",
+        de: "Zu entfernende Regel:",
+        en: "Rule to remove:",
+    }
+    PolicyChanged {
+        fr: "[*] Règles d'accès modifiées (version {0})",
+        de: "[*] Zugriffsregeln geändert (Version {0})",
+        en: "[*] Access policy changed (version {0})",
+    }
+    NoPolicyHistory {
+        fr: "Les règles d'accès n'ont pas été modifiées depuis l'application",
+        de: "Die Zugriffsregeln wurden nicht in der Anwendung geändert",
+        en: "The access policy was not changed from the application",
+    }
+    PolicyRevisionLine {
+        fr: "Version {0}, le {1} par {2}: {3}",
+        de: "Version {0}, am {1} von {2}: {3}",
+        en: "Version {0}, on {1} by {2}: {3}",
+    }
+    MenuReviewDisputes {
+        fr: "Examiner les accès contestés",
+        de: "Beanstandete Zugriffe prüfen",
+        en: "Review disputed accesses",
+    }
+    MenuAccessHistory {
+        fr: "Historique des accès à mon dossier",
+        de: "Zugriffsverlauf meines Dossiers",
+        en: "Access history of my folder",
+    }
+    MenuNotifications {
+        fr: "Mes notifications",
+        de: "Meine Benachrichtigungen",
+        en: "My notifications",
+    }
+    MenuNotificationPreferences {
+        fr: "Préférences de notification",
+        de: "Benachrichtigungseinstellungen",
+        en: "Notification preferences",
+    }
+    MenuRevealAvs {
+        fr: "Afficher un numéro AVS complet",
+        de: "Vollständige AHV-Nummer anzeigen",
+        en: "Reveal a full AVS number",
+    }
+    MenuSearchAvs {
+        fr: "Rechercher un patient par numéro AVS",
+        de: "Patient nach AHV-Nummer suchen",
+        en: "Find a patient by AVS number",
+    }
+    PromptSearchAvsNumber {
+        fr: "Numéro AVS recherché:",
+        de: "Gesuchte AHV-Nummer:",
+        en: "AVS number to find:",
+    }
+    AvsRevealed {
+        fr: "Numéro AVS de {0}: {1}",
+        de: "AHV-Nummer von {0}: {1}",
+        en: "AVS number of {0}: {1}",
+    }
+    AvsNoMatch {
+        fr: "Aucun patient avec ce numéro AVS",
+        de: "Kein Patient mit dieser AHV-Nummer",
+        en: "No patient with this AVS number",
+    }
+    AvsMatch {
+        fr: "Patient trouvé: {0}",
+        de: "Patient gefunden: {0}",
+        en: "Patient found: {0}",
+    }
+    MenuLanguage {
+        fr: "Langue de l'interface",
+        de: "Sprache der Benutzeroberfläche",
+        en: "Interface language",
+    }
+    MenuWipeAccount {
+        fr: "Supprimer toutes mes données",
+        de: "Alle meine Daten löschen",
+        en: "Delete all my data",
+    }
+    MenuCloseAccount {
+        fr: "Fermer mon compte",
+        de: "Mein Konto schließen",
+        en: "Close my account",
+    }
+    MenuAddLabResult {
+        fr: "Ajouter un résultat de laboratoire",
+        de: "Ein Laborergebnis hinzufügen",
+        en: "Add a lab result",
+    }
+    MenuPrescribe {
+        fr: "Prescrire un traitement",
+        de: "Eine Behandlung verschreiben",
+        en: "Prescribe a treatment",
+    }
+    MenuPrescriptions {
+        fr: "Consulter mes ordonnances",
+        de: "Meine Rezepte ansehen",
+        en: "View my prescriptions",
+    }
+    MenuDispense {
+        fr: "Délivrer une ordonnance",
+        de: "Ein Rezept abgeben",
+        en: "Dispense a prescription",
+    }
+    MenuProxies {
+        fr: "Gérer les représentations",
+        de: "Vertretungen verwalten",
+        en: "Manage proxies",
+    }
+    MenuActForDependant {
+        fr: "Agir pour une personne que je représente",
+        de: "Für eine vertretene Person handeln",
+        en: "Act for someone I represent",
+    }
+    MenuLogout {
+        fr: "Se déconnecter",
+        de: "Abmelden",
+        en: "Log out",
+    }
+    PromptAvsNumber {
+        fr: "Entrez votre numéro AVS:",
+        de: "Geben Sie Ihre AHV-Nummer ein:",
+        en: "Enter your AVS number:",
+    }
+    PromptBloodType {
+        fr: "Entrez votre groupe sanguin:",
+        de: "Geben Sie Ihre Blutgruppe ein:",
+        en: "Enter your blood type:",
+    }
+    PromptDoctorUsername {
+        fr: "Username du médecin: ",
+        de: "Benutzername des Arztes: ",
+        en: "Doctor's username: ",
+    }
+    DoctorHasAccess {
+        fr: "Ce médecin a maintenant accès a votre dossier",
+        de: "Dieser Arzt hat nun Zugriff auf Ihr Dossier",
+        en: "This doctor now has access to your folder",
+    }
+    DecisionApprove {
+        fr: "Approuver",
+        de: "Genehmigen",
+        en: "Approve",
+    }
+    DecisionDeny {
+        fr: "Refuser",
+        de: "Ablehnen",
+        en: "Deny",
+    }
+    DecisionLater {
+        fr: "Décider plus tard",
+        de: "Später entscheiden",
+        en: "Decide later",
+    }
+    NoPendingRequests {
+        fr: "[*] Aucune demande d'accès en attente",
+        de: "[*] Keine offenen Zugriffsanfragen",
+        en: "[*] No pending access requests",
+    }
+    PromptChooseRequest {
+        fr: "Choisissez une demande:",
+        de: "Wählen Sie eine Anfrage:",
+        en: "Choose a request:",
+    }
+    PromptDecision {
+        fr: "Décision",
+        de: "Entscheidung",
+        en: "Decision",
+    }
+    PromptPatientUsername {
+        fr: "Username du patient:",
+        de: "Benutzername des Patienten:",
+        en: "Patient's username:",
+    }
+    UnknownPatient {
+        fr: "Patient inexistant",
+        de: "Unbekannter Patient",
+        en: "Unknown patient",
+    }
+    PromptRequestReason {
+        fr: "Motif de la demande:",
+        de: "Grund der Anfrage:",
+        en: "Reason for the request:",
+    }
+    PromptRequestScope {
+        fr: "Accès demandé:",
+        de: "Angefragter Zugriff:",
+        en: "Requested access:",
+    }
+    RequestSent {
+        fr: "[*] Demande envoyée, en attente de l'approbation du patient",
+        de: "[*] Anfrage gesendet, sie wartet auf die Zustimmung des Patienten",
+        en: "[*] Request sent, waiting for the patient's approval",
+    }
+    PromptChoosePatient {
+        fr: "Choisissez un patient:",
+        de: "Wählen Sie einen Patienten:",
+        en: "Choose a patient:",
+    }
+    PromptReportTitle {
+        fr: "Entrez le titre du rapport:",
+        de: "Geben Sie den Titel des Berichts ein:",
+        en: "Enter the report title:",
+    }
+    PromptReportContent {
+        fr: "Entrez le rapport:",
+        de: "Geben Sie den Bericht ein:",
+        en: "Enter the report:",
+    }
+    NoOtherAccess {
+        fr: "[*] Personne d'autre n'a accédé à votre dossier",
+        de: "[*] Niemand sonst hat auf Ihr Dossier zugegriffen",
+        en: "[*] Nobody else has accessed your folder",
+    }
+    PromptDisputeAccess {
+        fr: "Contester un accès ? (Échap pour revenir)",
+        de: "Einen Zugriff beanstanden? (Esc zum Zurückkehren)",
+        en: "Dispute an access? (Esc to go back)",
+    }
+    AccessDisputed {
+        fr: "[*] L'accès a été signalé aux administrateurs",
+        de: "[*] Der Zugriff wurde den Administratoren gemeldet",
+        en: "[*] The access was reported to the administrators",
+    }
+    NoDisputes {
+        fr: "[*] Aucun accès contesté",
+        de: "[*] Keine beanstandeten Zugriffe",
+        en: "[*] No disputed accesses",
+    }
+    PromptChooseDispute {
+        fr: "Choisissez un accès contesté:",
+        de: "Wählen Sie einen beanstandeten Zugriff:",
+        en: "Choose a disputed access:",
+    }
+    ConfirmResolveDispute {
+        fr: "Marquer cet accès comme examiné ?",
+        de: "Diesen Zugriff als geprüft markieren?",
+        en: "Mark this access as reviewed?",
+    }
+    NoNotifications {
+        fr: "[*] Aucune notification",
+        de: "[*] Keine Benachrichtigungen",
+        en: "[*] No notifications",
+    }
+    PromptNotificationKinds {
+        fr: "Notifications à recevoir:",
+        de: "Zu erhaltende Benachrichtigungen:",
+        en: "Notifications to receive:",
+    }
+    PromptLanguage {
+        fr: "Choisissez votre langue:",
+        de: "Wählen Sie Ihre Sprache:",
+        en: "Choose your language:",
+    }
+    ConfirmWipe {
+        fr: "VOULEZ-VOUS VRAIMENT EFFACER VOTRE COMPTE ?",
+        de: "WOLLEN SIE IHR KONTO WIRKLICH LÖSCHEN?",
+        en: "DO YOU REALLY WANT TO DELETE YOUR ACCOUNT?",
+    }
+    WipeHelp {
+        fr: "Si vous effacez votre compte, toutes vos données médicales seront effacées.",
+        de: "Wenn Sie Ihr Konto löschen, werden alle Ihre medizinischen Daten gelöscht.",
+        en: "If you delete your account, all your medical data will be deleted.",
+    }
+    ConfirmCloseAccount {
+        fr: "VOULEZ-VOUS VRAIMENT FERMER VOTRE COMPTE ?",
+        de: "WOLLEN SIE IHR KONTO WIRKLICH SCHLIESSEN?",
+        en: "DO YOU REALLY WANT TO CLOSE YOUR ACCOUNT?",
+    }
+    CloseAccountHelp {
+        fr: "Votre dossier et vos rapports seront effacés, tous les accès retirés et vous ne pourrez plus vous connecter.",
+        de: "Ihr Dossier und Ihre Berichte werden gelöscht, alle Zugriffe entzogen und Sie können sich nicht mehr anmelden.",
+        en: "Your folder and reports will be deleted, all accesses revoked, and you will no longer be able to log in.",
+    }
+    PromptAuthoredReports {
+        fr: "Que faire des rapports, ordonnances et analyses que vous avez écrits pour d'autres patients ?",
+        de: "Was soll mit den Berichten, Rezepten und Laborwerten geschehen, die Sie für andere Patienten geschrieben haben?",
+        en: "What should happen to the reports, prescriptions and lab results you wrote for other patients?",
+    }
+    AuthoredReportsPseudonymize {
+        fr: "Les garder sous un auteur pseudonyme",
+        de: "Unter einem pseudonymen Autor behalten",
+        en: "Keep them under a pseudonymous author",
+    }
+    AuthoredReportsReassign {
+        fr: "Les confier à un autre médecin de ces patients",
+        de: "Einem anderen Arzt dieser Patienten übertragen",
+        en: "Reassign them to another doctor of these patients",
+    }
+    AccountClosed {
+        fr: "[*] Votre compte a été fermé",
+        de: "[*] Ihr Konto wurde geschlossen",
+        en: "[*] Your account has been closed",
+    }
+    PromptAnalyte {
+        fr: "Code de l'analyse (ex. HGB): ",
+        de: "Analysecode (z.B. HGB): ",
+        en: "Analyte code (e.g. HGB): ",
+    }
+    PromptLabValue {
+        fr: "Valeur: ",
+        de: "Wert: ",
+        en: "Value: ",
+    }
+    PromptLabUnit {
+        fr: "Unité: ",
+        de: "Einheit: ",
+        en: "Unit: ",
+    }
+    PromptRangeLow {
+        fr: "Borne inférieure de référence (Échap pour aucune): ",
+        de: "Untere Referenzgrenze (Esc für keine): ",
+        en: "Lower reference bound (Esc for none): ",
+    }
+    PromptRangeHigh {
+        fr: "Borne supérieure de référence (Échap pour aucune): ",
+        de: "Obere Referenzgrenze (Esc für keine): ",
+        en: "Upper reference bound (Esc for none): ",
+    }
+    PromptTakenOn {
+        fr: "Date du prélèvement:",
+        de: "Datum der Probenahme:",
+        en: "Sample date:",
+    }
+    PromptTakenAt {
+        fr: "Heure du prélèvement (HH:MM, UTC): ",
+        de: "Uhrzeit der Probenahme (HH:MM, UTC): ",
+        en: "Sample time (HH:MM, UTC): ",
+    }
+    InvalidDecimal {
+        fr: "Veuillez entrer un nombre, avec un point décimal",
+        de: "Bitte geben Sie eine Zahl mit Dezimalpunkt ein",
+        en: "Please enter a number, with a decimal point",
+    }
+    InvalidTime {
+        fr: "Heure invalide, format attendu HH:MM",
+        de: "Ungültige Uhrzeit, erwartet wird HH:MM",
+        en: "Invalid time, expected HH:MM",
+    }
+    PromptDrug {
+        fr: "Médicament: ",
+        de: "Medikament: ",
+        en: "Drug: ",
+    }
+    PromptDose {
+        fr: "Posologie: ",
+        de: "Dosierung: ",
+        en: "Dose: ",
+    }
+    PromptDurationDays {
+        fr: "Durée du traitement (jours): ",
+        de: "Behandlungsdauer (Tage): ",
+        en: "Treatment duration (days): ",
+    }
+    PromptRepeats {
+        fr: "Nombre de renouvellements: ",
+        de: "Anzahl Wiederholungen: ",
+        en: "Number of repeats: ",
+    }
+    InvalidNumber {
+        fr: "Veuillez entrer un nombre entier positif",
+        de: "Bitte geben Sie eine positive ganze Zahl ein",
+        en: "Please enter a positive whole number",
+    }
+    PrescriptionCode {
+        fr: "[*] Code de l'ordonnance, à présenter en pharmacie: {0}",
+        de: "[*] Code des Rezepts, in der Apotheke vorzuweisen: {0}",
+        en: "[*] Prescription code, to show at the pharmacy: {0}",
+    }
+    PrescriptionLine {
+        fr: "{0} pendant {1} jours, {2} délivrance(s) restante(s)",
+        de: "{0} während {1} Tagen, {2} verbleibende Abgabe(n)",
+        en: "{0} for {1} days, {2} dispensing(s) left",
+    }
+    NoPrescriptions {
+        fr: "Aucune ordonnance",
+        de: "Keine Rezepte",
+        en: "No prescriptions",
+    }
+    PromptNewCode {
+        fr: "Ordonnance pour laquelle générer un nouveau code:",
+        de: "Rezept, für das ein neuer Code erzeugt werden soll:",
+        en: "Prescription to generate a new code for:",
+    }
+    PromptPrescriptionCode {
+        fr: "Code de l'ordonnance: ",
+        de: "Code des Rezepts: ",
+        en: "Prescription code: ",
+    }
+    ConfirmDispense {
+        fr: "Délivrer cette ordonnance ?",
+        de: "Dieses Rezept abgeben?",
+        en: "Dispense this prescription?",
+    }
+    ConfirmCancelChange {
+        fr: "Annuler votre demande ?",
+        de: "Ihren Antrag zurückziehen?",
+        en: "Withdraw your request?",
+    }
+    Dispensed {
+        fr: "[*] Délivrance enregistrée, le code n'est plus valable",
+        de: "[*] Abgabe gespeichert, der Code ist nicht mehr gültig",
+        en: "[*] Dispensing recorded, the code is no longer valid",
+    }
+    ProxyLine {
+        fr: "{0} représente {1} ({2}, {3}) du {4} {5}",
+        de: "{0} vertritt {1} ({2}, {3}) vom {4} {5}",
+        en: "{0} represents {1} ({2}, {3}) from {4} {5}",
+    }
+    ProxyUntil {
+        fr: "au {0}",
+        de: "bis {0}",
+        en: "until {0}",
+    }
+    NoProxies {
+        fr: "Aucune représentation",
+        de: "Keine Vertretungen",
+        en: "No proxy relationships",
+    }
+    ProxyActionAdd {
+        fr: "Désigner un représentant",
+        de: "Eine Vertretung bestimmen",
+        en: "Name a proxy",
+    }
+    ProxyActionEnd {
+        fr: "Mettre fin à une représentation",
+        de: "Eine Vertretung beenden",
+        en: "End a proxy relationship",
+    }
+    PromptProxyKind {
+        fr: "Type de représentation:",
+        de: "Art der Vertretung:",
+        en: "Kind of proxy:",
+    }
+    PromptProxyScope {
+        fr: "Ce que le représentant peut faire:",
+        de: "Was die Vertretung tun darf:",
+        en: "What the proxy may do:",
+    }
+    PromptDependantUsername {
+        fr: "Username de la personne représentée: ",
+        de: "Benutzername der vertretenen Person: ",
+        en: "Username of the person represented: ",
+    }
+    PromptProxyUsername {
+        fr: "Username du représentant: ",
+        de: "Benutzername der Vertretung: ",
+        en: "Username of the proxy: ",
+    }
+    PromptBirthDate {
+        fr: "Date de naissance de la personne représentée:",
+        de: "Geburtsdatum der vertretenen Person:",
+        en: "Birth date of the person represented:",
+    }
+    PromptProxyEnd {
+        fr: "Fin de la représentation (Échap pour aucune):",
+        de: "Ende der Vertretung (Esc für keines):",
+        en: "End of the relationship (Esc for none):",
+    }
+    PromptChooseProxy {
+        fr: "Représentation à terminer:",
+        de: "Zu beendende Vertretung:",
+        en: "Relationship to end:",
+    }
+    ProxyAdded {
+        fr: "[*] Représentation enregistrée",
+        de: "[*] Vertretung gespeichert",
+        en: "[*] Proxy relationship saved",
+    }
+    NoDependants {
+        fr: "Vous ne représentez personne actuellement",
+        de: "Sie vertreten zurzeit niemanden",
+        en: "You do not currently represent anyone",
+    }
+    PromptChooseDependant {
+        fr: "Personne représentée:",
+        de: "Vertretene Person:",
+        en: "Person represented:",
+    }
+    ActingFor {
+        fr: "[*] Vous agissez pour {0}, jusqu'à votre retour au menu",
+        de: "[*] Sie handeln für {0}, bis Sie zum Menü zurückkehren",
+        en: "[*] You are acting for {0} until you return to the menu",
+    }
+    AccessAsProxy {
+        fr: " [en tant que {0}]",
+        de: " [als {0}]",
+        en: " [as {0}]",
+    }
+    PromptUsernameToManage {
+        fr: "Username à administrer: ",
+        de: "Zu verwaltender Benutzername: ",
+        en: "Username to manage: ",
+    }
+    UnknownUser {
+        fr: "Utilisateur inconnu",
+        de: "Unbekannter Benutzer",
+        en: "Unknown user",
+    }
+    PromptNewRole {
+        fr: "Nouveau rôle",
+        de: "Neue Rolle",
+        en: "New role",
+    }
+
+    // Consultation des dossiers et rapports
+    Yes {
+        fr: "oui",
+        de: "ja",
+        en: "yes",
+    }
+    No {
+        fr: "non",
+        de: "nein",
+        en: "no",
+    }
+    FolderSummary {
+        fr: "User: {0}\nRole: {1}\nDossier électronique: {2}",
+        de: "Benutzer: {0}\nRolle: {1}\nElektronisches Dossier: {2}",
+        en: "User: {0}\nRole: {1}\nElectronic folder: {2}",
+    }
+    PersonalDataSummary {
+        fr: "Numéro AVS: {0}\nGroupe sanguin: {1}",
+        de: "AHV-Nummer: {0}\nBlutgruppe: {1}",
+        en: "AVS number: {0}\nBlood type: {1}",
+    }
+    FolderRestricted {
+        fr: "[!] L'accès à ce dossier est restreint",
+        de: "[!] Der Zugriff auf dieses Dossier ist eingeschränkt",
+        en: "[!] Access to this folder is restricted",
+    }
+    NoReports {
+        fr: "[*] Il n'y a pas de rapports dans ce dossier",
+        de: "[*] Dieses Dossier enthält keine Berichte",
+        en: "[*] There are no reports in this folder",
+    }
+    PromptChooseReport {
+        fr: "Choisissez un rapport ({0} rapport(s), page {1}/{2}):",
+        de: "Wählen Sie einen Bericht ({0} Bericht(e), Seite {1}/{2}):",
+        en: "Choose a report ({0} report(s), page {1}/{2}):",
+    }
+    ReportView {
+        fr: "\n[{0}]\nTitre: {1}\nAuteur: {2}\nCréé le: {3}\nModifié le: {4}\n\n{5}\n===============",
+        de: "\n[{0}]\nTitel: {1}\nAutor: {2}\nErstellt am: {3}\nGeändert am: {4}\n\n{5}\n===============",
+        en: "\n[{0}]\nTitle: {1}\nAuthor: {2}\nCreated: {3}\nModified: {4}\n\n{5}\n===============",
+    }
+    PreviousPage {
+        fr: "<< Page précédente",
+        de: "<< Vorherige Seite",
+        en: "<< Previous page",
+    }
+    NextPage {
+        fr: ">> Page suivante",
+        de: ">> Nächste Seite",
+        en: ">> Next page",
+    }
+    EditQuery {
+        fr: "[Trier / filtrer]",
+        de: "[Sortieren / filtern]",
+        en: "[Sort / filter]",
+    }
+    ResetQuery {
+        fr: "[Effacer les filtres]",
+        de: "[Filter zurücksetzen]",
+        en: "[Clear filters]",
+    }
+    Export {
+        fr: "[Exporter]",
+        de: "[Exportieren]",
+        en: "[Export]",
+    }
+    LabResults {
+        fr: "[Résultats de laboratoire]",
+        de: "[Laborergebnisse]",
+        en: "[Lab results]",
+    }
+    WholeFolder {
+        fr: "Tout le dossier",
+        de: "Das ganze Dossier",
+        en: "The whole folder",
+    }
+    PromptAnalyteTrend {
+        fr: "Analyse à afficher:",
+        de: "Anzuzeigende Analyse:",
+        en: "Analyte to show:",
+    }
+    LabTrendHeader {
+        fr: "Évolution de {0} ({1} résultat(s)), * valeur, [---] intervalle de référence:",
+        de: "Verlauf von {0} ({1} Ergebnis(se)), * Wert, [---] Referenzbereich:",
+        en: "Trend of {0} ({1} result(s)), * value, [---] reference range:",
+    }
+    PromptSortBy {
+        fr: "Trier par:",
+        de: "Sortieren nach:",
+        en: "Sort by:",
+    }
+    PromptAuthorFilter {
+        fr: "Auteur (vide pour tous):",
+        de: "Autor (leer für alle):",
+        en: "Author (empty for all):",
+    }
+    PromptFromDate {
+        fr: "Créés depuis le (Échap pour ignorer):",
+        de: "Erstellt ab (Esc zum Überspringen):",
+        en: "Created from (Esc to skip):",
+    }
+    PromptToDate {
+        fr: "Créés jusqu'au (Échap pour ignorer):",
+        de: "Erstellt bis (Esc zum Überspringen):",
+        en: "Created until (Esc to skip):",
+    }
+    PromptKeywords {
+        fr: "Mots-clés du titre (vide pour tous):",
+        de: "Stichwörter im Titel (leer für alle):",
+        en: "Title keywords (empty for all):",
+    }
+    PromptExportTarget {
+        fr: "Que voulez-vous exporter ?",
+        de: "Was möchten Sie exportieren?",
+        en: "What do you want to export?",
+    }
+    PromptExportFormat {
+        fr: "Format:",
+        de: "Format:",
+        en: "Format:",
+    }
+    PromptExportPath {
+        fr: "Fichier de destination:",
+        de: "Zieldatei:",
+        en: "Destination file:",
+    }
+    ExportDone {
+        fr: "[*] {0} rapport(s) exporté(s) dans {1}",
+        de: "[*] {0} Bericht(e) nach {1} exportiert",
+        en: "[*] {0} report(s) exported to {1}",
+    }
+
+    // Export des dossiers
+    ExportTitle {
+        fr: "Dossier médical de {0}",
+        de: "Patientendossier von {0}",
+        en: "Medical folder of {0}",
+    }
+    ExportPatient {
+        fr: "Patient: {0} ({1})",
+        de: "Patient: {0} ({1})",
+        en: "Patient: {0} ({1})",
+    }
+    ExportAvsNumber {
+        fr: "Numéro AVS: {0}",
+        de: "AHV-Nummer: {0}",
+        en: "AVS number: {0}",
+    }
+    ExportGeneratedAt {
+        fr: "Généré le: {0}",
+        de: "Erstellt am: {0}",
+        en: "Generated on: {0}",
+    }
+    ExportReportCount {
+        fr: "Nombre de rapports: {0}",
+        de: "Anzahl Berichte: {0}",
+        en: "Number of reports: {0}",
+    }
+    ExportAuthor {
+        fr: "Auteur: {0}",
+        de: "Autor: {0}",
+        en: "Author: {0}",
+    }
+    ExportCreatedAt {
+        fr: "Créé le: {0}",
+        de: "Erstellt am: {0}",
+        en: "Created: {0}",
+    }
+    ExportUpdatedAt {
+        fr: "Modifié le: {0}",
+        de: "Geändert am: {0}",
+        en: "Modified: {0}",
+    }
+    ExportReference {
+        fr: "Référence: {0}",
+        de: "Referenz: {0}",
+        en: "Reference: {0}",
+    }
+    ExportPage {
+        fr: "Page {0} / {1}",
+        de: "Seite {0} / {1}",
+        en: "Page {0} / {1}",
+    }
+
+    // Initialisation
+    InitAdminExists {
+        fr: "Un administrateur existe déjà, l'initialisation est refusée",
+        de: "Es existiert bereits ein Administrator, die Initialisierung wird verweigert",
+        en: "An administrator already exists, initialization refused",
+    }
+    InitTitle {
+        fr: "Création des deux administrateurs initiaux de KARAK, qui approuveront les changements de rôle l'un de l'autre.",
+        de: "Erstellung der zwei ersten KARAK-Administratoren, die gegenseitig ihre Rollenänderungen genehmigen.",
+        en: "Creating the two initial KARAK administrators, who approve each other's role changes.",
+    }
+    PromptAdminUsername {
+        fr: "Username de l'administrateur: ",
+        de: "Benutzername des Administrators: ",
+        en: "Administrator username: ",
+    }
+    InitDone {
+        fr: "[*] Administrateurs créés. Vous pouvez maintenant lancer `karak`.",
+        de: "[*] Administratoren erstellt. Sie können jetzt `karak` starten.",
+        en: "[*] Administrators created. You can now run `karak`.",
+    }
+
+    // Choix du mot de passe
+    PasswordRuleLength {
+        fr: "Votre mot de passe doit contenir entre 8 et 64 caractères [8-64]",
+        de: "Ihr Passwort muss zwischen 8 und 64 Zeichen lang sein [8-64]",
+        en: "Your password must be between 8 to 64 characters long [8-64]",
+    }
+    PasswordRuleUsername {
+        fr: "Votre mot de passe doit être différent de votre nom d'utilisateur",
+        de: "Ihr Passwort muss sich von Ihrem Benutzernamen unterscheiden",
+        en: "Your password must be different from your username",
+    }
+    PasswordRuleGuessable {
+        fr: "Votre mot de passe ne doit pas pouvoir être deviné",
+        de: "Ihr Passwort darf nicht erratbar sein",
+        en: "Your password must be unguessable",
+    }
+    PasswordWarning {
+        fr: "\nAttention: {0}",
+        de: "\nWarnung: {0}",
+        en: "\nWarning: {0}",
+    }
+    PasswordSuggestions {
+        fr: "Suggestions: ",
+        de: "Vorschläge: ",
+        en: "Suggestions: ",
+    }
+    WarnStraightRows {
+        fr: "Les rangées de touches du clavier sont faciles à deviner.",
+        de: "Gerade Tastenreihen sind leicht zu erraten.",
+        en: "Straight rows of keys are easy to guess.",
+    }
+    WarnShortKeyboardPatterns {
+        fr: "Les motifs courts sur le clavier sont faciles à deviner.",
+        de: "Kurze Tastaturmuster sind leicht zu erraten.",
+        en: "Short keyboard patterns are easy to guess.",
+    }
+    WarnRepeats {
+        fr: "Les répétitions comme \"aaa\" sont faciles à deviner.",
+        de: "Wiederholungen wie \"aaa\" sind leicht zu erraten.",
+        en: "Repeats like \"aaa\" are easy to guess.",
+    }
+    WarnRepeatedPatterns {
+        fr: "Les répétitions comme \"abcabcabc\" sont à peine plus difficiles à deviner que \"abc\".",
+        de: "Wiederholungen wie \"abcabcabc\" sind nur wenig schwerer zu erraten als \"abc\".",
+        en: "Repeats like \"abcabcabc\" are only slightly harder to guess than \"abc\".",
+    }
+    WarnTop10 {
+        fr: "Ce mot de passe fait partie des 10 plus courants.",
+        de: "Dies ist eines der 10 häufigsten Passwörter.",
+        en: "This is a top-10 common password.",
+    }
+    WarnTop100 {
+        fr: "Ce mot de passe fait partie des 100 plus courants.",
+        de: "Dies ist eines der 100 häufigsten Passwörter.",
+        en: "This is a top-100 common password.",
+    }
+    WarnCommon {
+        fr: "Ce mot de passe est très courant.",
+        de: "Dies ist ein sehr häufiges Passwort.",
+        en: "This is a very common password.",
+    }
+    WarnSimilarToCommon {
+        fr: "Ce mot de passe ressemble à un mot de passe courant.",
+        de: "Dies ähnelt einem häufig verwendeten Passwort.",
+        en: "This is similar to a commonly used password.",
+    }
+    WarnSequences {
+        fr: "Les suites comme \"abc\" ou \"6543\" sont faciles à deviner.",
+        de: "Folgen wie \"abc\" oder \"6543\" sind leicht zu erraten.",
+        en: "Sequences like \"abc\" or \"6543\" are easy to guess.",
+    }
+    WarnRecentYears {
+        fr: "Les années récentes sont faciles à deviner.",
+        de: "Die letzten Jahre sind leicht zu erraten.",
+        en: "Recent years are easy to guess.",
+    }
+    WarnSingleWord {
+        fr: "Un mot seul est facile à deviner.",
+        de: "Ein einzelnes Wort ist leicht zu erraten.",
+        en: "A word by itself is easy to guess.",
+    }
+    WarnDates {
+        fr: "Les dates sont souvent faciles à deviner.",
+        de: "Daten sind oft leicht zu erraten.",
+        en: "Dates are often easy to guess.",
+    }
+    WarnNames {
+        fr: "Les noms et prénoms seuls sont faciles à deviner.",
+        de: "Vor- und Nachnamen allein sind leicht zu erraten.",
+        en: "Names and surnames by themselves are easy to guess.",
+    }
+    WarnCommonNames {
+        fr: "Les noms et prénoms courants sont faciles à deviner.",
+        de: "Häufige Vor- und Nachnamen sind leicht zu erraten.",
+        en: "Common names and surnames are easy to guess.",
+    }
+    SuggestFewWords {
+        fr: "Utilisez quelques mots, en évitant les expressions courantes.",
+        de: "Verwenden Sie einige Wörter und vermeiden Sie gängige Redewendungen.",
+        en: "Use a few words, avoid common phrases.",
+    }
+    SuggestNoSymbolsNeeded {
+        fr: "Les symboles, chiffres ou majuscules ne sont pas nécessaires.",
+        de: "Symbole, Ziffern oder Grossbuchstaben sind nicht nötig.",
+        en: "No need for symbols, digits, or uppercase letters.",
+    }
+    SuggestAnotherWord {
+        fr: "Ajoutez un ou deux mots. Les mots peu courants sont préférables.",
+        de: "Fügen Sie ein oder zwei Wörter hinzu. Ungewöhnliche Wörter sind besser.",
+        en: "Add another word or two. Uncommon words are better.",
+    }
+    SuggestCapitalization {
+        fr: "Les majuscules n'aident pas beaucoup.",
+        de: "Grossschreibung hilft nicht viel.",
+        en: "Capitalization doesn't help very much.",
+    }
+    SuggestAllUppercase {
+        fr: "Tout en majuscules est presque aussi facile à deviner que tout en minuscules.",
+        de: "Nur Grossbuchstaben sind fast so leicht zu erraten wie nur Kleinbuchstaben.",
+        en: "All-uppercase is almost as easy to guess as all-lowercase.",
+    }
+    SuggestReversedWords {
+        fr: "Les mots inversés ne sont pas beaucoup plus difficiles à deviner.",
+        de: "Rückwärts geschriebene Wörter sind kaum schwerer zu erraten.",
+        en: "Reversed words aren't much harder to guess.",
+    }
+    SuggestSubstitutions {
+        fr: "Les substitutions prévisibles comme '@' pour 'a' n'aident pas beaucoup.",
+        de: "Vorhersehbare Ersetzungen wie '@' statt 'a' helfen nicht viel.",
+        en: "Predictable substitutions like '@' instead of 'a' don't help very much.",
+    }
+    SuggestLongerKeyboardPattern {
+        fr: "Utilisez un motif de clavier plus long, avec plus de changements de direction.",
+        de: "Verwenden Sie ein längeres Tastaturmuster mit mehr Richtungswechseln.",
+        en: "Use a longer keyboard pattern with more turns.",
+    }
+    SuggestAvoidRepeats {
+        fr: "Évitez les mots et caractères répétés.",
+        de: "Vermeiden Sie wiederholte Wörter und Zeichen.",
+        en: "Avoid repeated words and characters.",
+    }
+    SuggestAvoidSequences {
+        fr: "Évitez les suites.",
+        de: "Vermeiden Sie Folgen.",
+        en: "Avoid sequences.",
+    }
+    SuggestAvoidRecentYears {
+        fr: "Évitez les années récentes.",
+        de: "Vermeiden Sie die letzten Jahre.",
+        en: "Avoid recent years.",
+    }
+    SuggestAvoidPersonalYears {
+        fr: "Évitez les années qui vous sont associées.",
+        de: "Vermeiden Sie Jahre, die mit Ihnen in Verbindung stehen.",
+        en: "Avoid years that are associated with you.",
+    }
+    SuggestAvoidPersonalDates {
+        fr: "Évitez les dates et années qui vous sont associées.",
+        de: "Vermeiden Sie Daten und Jahre, die mit Ihnen in Verbindung stehen.",
+        en: "Avoid dates and years that are associated with you.",
+    }
+
+    // Import CSV
+    ImportMalformedRow {
+        fr: "ligne illisible: {0}",
+        de: "unlesbare Zeile: {0}",
+        en: "unreadable row: {0}",
+    }
+    ImportInvalidUsername {
+        fr: "nom d'utilisateur invalide «{0}»",
+        de: "ungültiger Benutzername «{0}»",
+        en: "invalid username “{0}”",
+    }
+    ImportInvalidRole {
+        fr: "rôle invalide «{0}» (Patient ou Doctor)",
+        de: "ungültige Rolle «{0}» (Patient oder Doctor)",
+        en: "invalid role “{0}” (Patient or Doctor)",
+    }
+    ImportInvalidAvsNumber {
+        fr: "numéro AVS invalide «{0}»",
+        de: "ungültige AHV-Nummer «{0}»",
+        en: "invalid AVS number “{0}”",
+    }
+    ImportInvalidBloodType {
+        fr: "groupe sanguin invalide «{0}» (A, AB, B ou O)",
+        de: "ungültige Blutgruppe «{0}» (A, AB, B oder O)",
+        en: "invalid blood type “{0}” (A, AB, B or O)",
+    }
+    ImportIncompleteFolder {
+        fr: "le numéro AVS et le groupe sanguin doivent être donnés ensemble",
+        de: "AHV-Nummer und Blutgruppe müssen zusammen angegeben werden",
+        en: "the AVS number and the blood type must be given together",
+    }
+    ImportDoctorWithoutFolder {
+        fr: "un médecin traitant ne peut être donné que pour un patient avec dossier",
+        de: "ein behandelnder Arzt kann nur für einen Patienten mit Dossier angegeben werden",
+        en: "a treating doctor can only be given for a patient with a folder",
+    }
+    ImportDuplicateUsername {
+        fr: "«{0}» apparaît plusieurs fois dans le fichier",
+        de: "«{0}» kommt mehrmals in der Datei vor",
+        en: "“{0}” appears several times in the file",
+    }
+    ImportUserExists {
+        fr: "l'utilisateur «{0}» existe déjà",
+        de: "der Benutzer «{0}» existiert bereits",
+        en: "the user “{0}” already exists",
+    }
+    ImportUnknownDoctor {
+        fr: "«{0}» n'est pas un médecin connu",
+        de: "«{0}» ist kein bekannter Arzt",
+        en: "“{0}” is not a known doctor",
+    }
+    ImportRowError {
+        fr: "Ligne {0}: {1}",
+        de: "Zeile {0}: {1}",
+        en: "Line {0}: {1}",
+    }
+    ImportAdminLogin {
+        fr: "L'import nécessite une connexion administrateur.",
+        de: "Der Import erfordert eine Anmeldung als Administrator.",
+        en: "Importing requires an administrator login.",
+    }
+    ImportSummary {
+        fr: "[*] {0} ligne(s) lue(s), {1} erreur(s)",
+        de: "[*] {0} Zeile(n) gelesen, {1} Fehler",
+        en: "[*] {0} row(s) read, {1} error(s)",
+    }
+    ImportDryRunOk {
+        fr: "[*] Essai à blanc réussi: aucun compte n'a été créé",
+        de: "[*] Probelauf erfolgreich: es wurde kein Konto erstellt",
+        en: "[*] Dry run succeeded: no account was created",
+    }
+    ImportAborted {
+        fr: "[!] Import annulé, aucun compte n'a été créé: corrigez les erreurs ci-dessus",
+        de: "[!] Import abgebrochen, kein Konto wurde erstellt: beheben Sie die obigen Fehler",
+        en: "[!] Import cancelled, no account was created: fix the errors above",
+    }
+    ImportDone {
+        fr: "[*] {0} compte(s) créé(s), mots de passe initiaux écrits dans {1}",
+        de: "[*] {0} Konto/Konten erstellt, Initialpasswörter in {1} geschrieben",
+        en: "[*] {0} account(s) created, initial passwords written to {1}",
+    }
+    ImportRolesPending {
+        fr: "[*] {0} rôle(s) privilégié(s) en attente de l'approbation d'un autre administrateur",
+        de: "[*] {0} privilegierte Rolle(n) warten auf die Genehmigung eines anderen Administrators",
+        en: "[*] {0} privileged role(s) awaiting another administrator's approval",
+    }
+    PasswordChangeRequired {
+        fr: "[!] Votre mot de passe initial doit être remplacé.",
+        de: "[!] Ihr Initialpasswort muss ersetzt werden.",
+        en: "[!] Your initial password must be replaced.",
+    }
+
+    // Export pour la recherche
+    ResearchAdminLogin {
+        fr: "L'export pour la recherche nécessite une connexion administrateur.",
+        de: "Der Forschungsexport erfordert eine Anmeldung als Administrator.",
+        en: "The research export requires an administrator login.",
+    }
+    ResearchInvalidKey {
+        fr: "Clé de pseudonymisation invalide dans {0}",
+        de: "Ungültiger Pseudonymisierungsschlüssel in {0}",
+        en: "Invalid pseudonymization key in {0}",
+    }
+    ResearchKeyCreated {
+        fr: "[*] Nouvelle clé de pseudonymisation créée dans {0}. Conservez-la pour garder les mêmes pseudonymes d'un export à l'autre.",
+        de: "[*] Neuer Pseudonymisierungsschlüssel in {0} erstellt. Bewahren Sie ihn auf, damit die Pseudonyme von Export zu Export gleich bleiben.",
+        en: "[*] New pseudonymization key created in {0}. Keep it to get the same pseudonyms across exports.",
+    }
+    ResearchSummary {
+        fr: "[*] {0} patient(s), {1} exporté(s), {2} retiré(s) dans {3} groupe(s) de moins de {4}",
+        de: "[*] {0} Patient(en), {1} exportiert, {2} entfernt in {3} Gruppe(n) mit weniger als {4}",
+        en: "[*] {0} patient(s), {1} exported, {2} suppressed in {3} group(s) smaller than {4}",
+    }
+    ResearchDone {
+        fr: "[*] Données écrites dans {0}, résumé dans {1}",
+        de: "[*] Daten in {0} geschrieben, Zusammenfassung in {1}",
+        en: "[*] Data written to {0}, summary to {1}",
+    }
+
+    // Chargement de la base
+    LoadInvalidRecord {
+        fr: "[!] Enregistrement invalide {0}/{1}: {2}",
+        de: "[!] Ungültiger Datensatz {0}/{1}: {2}",
+        en: "[!] Invalid record {0}/{1}: {2}",
+    }
+    LoadQuarantined {
+        fr: "[!] {0} enregistrement(s) invalide(s) déplacé(s) dans {1}",
+        de: "[!] {0} ungültige(r) Datensatz/Datensätze nach {1} verschoben",
+        en: "[!] {0} invalid record(s) moved to {1}",
+    }
+
+    // Configuration
+    WarnBuiltinPolicy {
+        fr: "[!] Règles d'accès intégrées utilisées, aucun fichier de règles configuré",
+        de: "[!] Eingebaute Zugriffsregeln in Verwendung, keine Regeldatei konfiguriert",
+        en: "[!] Using the built-in access policy, no policy file is configured",
+    }
+    WarnPolicyKeyOverridden {
+        fr: "[!] La clé publique des règles intégrée est remplacée par celle de la configuration",
+        de: "[!] Der eingebaute öffentliche Schlüssel der Regeln wird durch den der Konfiguration ersetzt",
+        en: "[!] The built-in policy public key is overridden by the configured one",
+    }
+    WarnLegacyDatabase {
+        fr: "[!] Base de données {0} du dossier courant utilisée: déplacez-la dans $XDG_DATA_HOME/karak/",
+        de: "[!] Datenbank {0} aus dem aktuellen Verzeichnis verwendet: verschieben Sie sie nach $XDG_DATA_HOME/karak/",
+        en: "[!] Using the database {0} from the current directory: move it to $XDG_DATA_HOME/karak/",
+    }
+    WarnPolicyLocked {
+        fr: "[!] Règles d'accès non authentiques: mode verrouillé, tout accès est refusé",
+        de: "[!] Zugriffsregeln nicht authentisch: gesperrter Modus, jeder Zugriff wird verweigert",
+        en: "[!] Access policy not authentic: locked down, every access is denied",
+    }
+    PolicyKeyCreated {
+        fr: "[*] Clé secrète écrite dans {0}, à garder hors ligne. Clé publique à intégrer à la compilation (KARAK_POLICY_KEY) ou à configurer dans un fichier de root (policy_key):\n{1}",
+        de: "[*] Geheimer Schlüssel in {0} geschrieben, offline aufzubewahren. Öffentlicher Schlüssel zum Einbauen beim Kompilieren (KARAK_POLICY_KEY) oder zum Konfigurieren in einer Datei von root (policy_key):\n{1}",
+        en: "[*] Secret key written to {0}, keep it offline. Public key to build in (KARAK_POLICY_KEY) or to configure in a root-owned file (policy_key):\n{1}",
+    }
+    PolicySigned {
+        fr: "[*] Signature écrite dans {0}",
+        de: "[*] Signatur in {0} geschrieben",
+        en: "[*] Signature written to {0}",
+    }
+    PolicyVerified {
+        fr: "[*] Signature des règles valide",
+        de: "[*] Signatur der Regeln gültig",
+        en: "[*] Policy signature is valid",
+    }
+
+    // Vérification de la base
+    FsckAdminLogin {
+        fr: "Connexion d'un administrateur pour vérifier la base",
+        de: "Anmeldung eines Administrators zur Prüfung der Datenbank",
+        en: "Administrator login to check the database",
+    }
+    FsckOrphanReports {
+        fr: "{0} rapport(s) du patient inexistant {1}",
+        de: "{0} Bericht(e) des nicht existierenden Patienten {1}",
+        en: "{0} report(s) of non-existent patient {1}",
+    }
+    FsckUnknownAuthor {
+        fr: "Le rapport {0} a un auteur inexistant ({1})",
+        de: "Der Bericht {0} hat einen nicht existierenden Autor ({1})",
+        en: "Report {0} has a non-existent author ({1})",
+    }
+    FsckOrphanPrescriptions {
+        fr: "{0} ordonnance(s) du patient inexistant {1}",
+        de: "{0} Rezept(e) des nicht existierenden Patienten {1}",
+        en: "{0} prescription(s) of non-existent patient {1}",
+    }
+    FsckUnknownPrescriber {
+        fr: "L'ordonnance {0} a un prescripteur inexistant ({1})",
+        de: "Das Rezept {0} hat einen nicht existierenden Verschreiber ({1})",
+        en: "Prescription {0} has a non-existent prescriber ({1})",
+    }
+    FsckUnknownPharmacist {
+        fr: "L'ordonnance {0} a été délivrée par un pharmacien inexistant ({1})",
+        de: "Das Rezept {0} wurde von einem nicht existierenden Apotheker abgegeben ({1})",
+        en: "Prescription {0} was dispensed by a non-existent pharmacist ({1})",
+    }
+    FsckOrphanLabResults {
+        fr: "{0} résultat(s) d'analyse du patient inexistant {1}",
+        de: "{0} Laborwert(e) des nicht existierenden Patienten {1}",
+        en: "{0} lab result(s) of non-existent patient {1}",
+    }
+    FsckUnknownLabAuthor {
+        fr: "Le résultat d'analyse {0} a un auteur inexistant ({1})",
+        de: "Der Laborwert {0} hat einen nicht existierenden Autor ({1})",
+        en: "Lab result {0} has a non-existent author ({1})",
+    }
+    FsckNotADoctor {
+        fr: "Le dossier de {0} donne accès à {1}, qui n'est pas médecin",
+        de: "Das Dossier von {0} gewährt {1} Zugriff, der/die kein Arzt ist",
+        en: "The folder of {0} grants access to {1}, who is not a doctor",
+    }
+    FsckDuplicateUsername {
+        fr: "Le nom d'utilisateur {0} est porté par {1} comptes",
+        de: "Der Benutzername {0} wird von {1} Konten verwendet",
+        en: "The username {0} is used by {1} accounts",
+    }
+    FsckRepairDeleteReports {
+        fr: "Supprimer ces rapports",
+        de: "Diese Berichte löschen",
+        en: "Delete these reports",
+    }
+    FsckRepairDeletedAuthor {
+        fr: "Rattacher le document à un compte verrouillé d'utilisateur supprimé",
+        de: "Das Dokument einem gesperrten Konto eines gelöschten Benutzers zuordnen",
+        en: "Attach the document to a locked deleted-user account",
+    }
+    FsckRepairDeletePrescriptions {
+        fr: "Supprimer ces ordonnances",
+        de: "Diese Rezepte löschen",
+        en: "Delete these prescriptions",
+    }
+    FsckRepairDeleteLabResults {
+        fr: "Supprimer ces résultats d'analyse",
+        de: "Diese Laborwerte löschen",
+        en: "Delete these lab results",
+    }
+    FsckRepairRevoke {
+        fr: "Retirer cet accès",
+        de: "Diesen Zugriff entziehen",
+        en: "Revoke this access",
+    }
+    FsckRepairRename {
+        fr: "Renommer les comptes en double",
+        de: "Die doppelten Konten umbenennen",
+        en: "Rename the duplicate accounts",
+    }
+    FsckConfirmRepair {
+        fr: "{0}: {1}?",
+        de: "{0}: {1}?",
+        en: "{0}: {1}?",
+    }
+    FsckClean {
+        fr: "[*] Aucune incohérence trouvée",
+        de: "[*] Keine Unstimmigkeiten gefunden",
+        en: "[*] No inconsistency found",
+    }
+    FsckSummary {
+        fr: "[*] {0} incohérence(s) trouvée(s); relancez avec --fix ou --interactive pour réparer",
+        de: "[*] {0} Unstimmigkeit(en) gefunden; mit --fix oder --interactive neu starten, um sie zu beheben",
+        en: "[*] {0} inconsistency(ies) found; run again with --fix or --interactive to repair",
+    }
+    FsckBackup {
+        fr: "[*] Copie de sauvegarde de la base: {0}",
+        de: "[*] Sicherungskopie der Datenbank: {0}",
+        en: "[*] Database backup: {0}",
+    }
+    FsckRenamed {
+        fr: "[*] Compte {0} renommé en {1}",
+        de: "[*] Konto {0} umbenannt in {1}",
+        en: "[*] Account {0} renamed to {1}",
+    }
+    FsckRepaired {
+        fr: "[*] {0} incohérence(s) réparée(s) sur {1}",
+        de: "[*] {0} von {1} Unstimmigkeit(en) behoben",
+        en: "[*] {0} of {1} inconsistency(ies) repaired",
+    }
+
+    // Erreurs
+    ErrAccessDenied {
+        fr: "Accès refusé.",
+        de: "Zugriff verweigert.",
+        en: "Access denied.",
+    }
+    ErrUserAlreadyExists {
+        fr: "Utilisateur déja inscrit",
+        de: "Benutzer bereits registriert",
+        en: "User already registered",
+    }
+    ErrNotAPatient {
+        fr: "Pas de dossier pour ce patient",
+        de: "Kein Dossier für diesen Patienten",
+        en: "No folder for this patient",
+    }
+    ErrNotADoctor {
+        fr: "Cet utilisateur n'est plus médecin",
+        de: "Dieser Benutzer ist kein Arzt mehr",
+        en: "This user is no longer a doctor",
+    }
+    ErrNoSuchReport {
+        fr: "Rapport inexistant",
+        de: "Bericht existiert nicht",
+        en: "No such report",
+    }
+    ErrAlreadyBootstrapped {
+        fr: "L'administrateur initial a déjà été créé",
+        de: "Der erste Administrator wurde bereits erstellt",
+        en: "The initial administrator has already been created",
+    }
+    ErrNoSuchRequest {
+        fr: "Demande d'accès inexistante",
+        de: "Zugriffsanfrage existiert nicht",
+        en: "No such access request",
+    }
+    ErrRequestAlreadyPending {
+        fr: "Une demande d'accès est déjà en attente pour ce patient",
+        de: "Für diesen Patienten ist bereits eine Zugriffsanfrage offen",
+        en: "An access request is already pending for this patient",
+    }
+    ErrNoSuchAccess {
+        fr: "Accès inexistant dans l'historique",
+        de: "Zugriff nicht im Verlauf vorhanden",
+        en: "No such access in the history",
+    }
+    ErrInvalidCredentials {
+        fr: "Mauvais mot de passe ou utilisateur inconnu",
+        de: "Falsches Passwort oder unbekannter Benutzer",
+        en: "Wrong password or unknown user",
+    }
+    ErrInvalidUserID {
+        fr: "Identifiant utilisateur invalide: {0}",
+        de: "Ungültige Benutzer-ID: {0}",
+        en: "Invalid user ID: {0}",
+    }
+    ErrUserExists {
+        fr: "Utilisateur déjà existant: {0}",
+        de: "Benutzer existiert bereits: {0}",
+        en: "User already exists: {0}",
+    }
+    ErrWeakPassword {
+        fr: "Mot de passe trop faible",
+        de: "Passwort zu schwach",
+        en: "Password too weak",
+    }
+    ErrInvalidInput {
+        fr: "Saisie invalide",
+        de: "Ungültige Eingabe",
+        en: "Invalid input",
+    }
+    ErrInvalidSession {
+        fr: "Session invalide ou expirée",
+        de: "Ungültige oder abgelaufene Sitzung",
+        en: "Invalid or expired session",
+    }
+    ErrPasswordChangeRequired {
+        fr: "Le mot de passe initial doit être remplacé",
+        de: "Das Initialpasswort muss ersetzt werden",
+        en: "The initial password must be replaced",
+    }
+    ErrPolicyRead {
+        fr: "Impossible de lire les règles d'accès {0}: {1}",
+        de: "Zugriffsregeln {0} können nicht gelesen werden: {1}",
+        en: "Cannot read the access policy {0}: {1}",
+    }
+    ErrPolicyNoKey {
+        fr: "Aucune clé publique intégrée (KARAK_POLICY_KEY) ni configurée (policy_key) pour vérifier les règles d'accès",
+        de: "Kein eingebauter (KARAK_POLICY_KEY) oder konfigurierter (policy_key) öffentlicher Schlüssel, um die Zugriffsregeln zu prüfen",
+        en: "No built-in (KARAK_POLICY_KEY) or configured (policy_key) public key to verify the access policy",
+    }
+    ErrPolicyUnsigned {
+        fr: "Règles d'accès non signées: {0} introuvable",
+        de: "Zugriffsregeln nicht signiert: {0} nicht gefunden",
+        en: "Access policy not signed: {0} not found",
+    }
+    ErrPolicyBuiltin {
+        fr: "Aucun fichier de règles à signer (--model, --policy): les règles intégrées n'ont pas de signature",
+        de: "Keine Regeldatei zu signieren (--model, --policy): die eingebauten Regeln haben keine Signatur",
+        en: "No policy file to sign (--model, --policy): the built-in policy has no signature",
+    }
+    ErrPolicyNotEditable {
+        fr: "Les règles d'accès de ce moteur ne peuvent pas être modifiées à l'exécution",
+        de: "Die Zugriffsregeln dieser Engine können zur Laufzeit nicht geändert werden",
+        en: "The access policy of this backend cannot be changed at runtime",
+    }
+    ErrInvalidPolicyRule {
+        fr: "Règle invalide: l'action doit être une action de l'application, et la condition non vide",
+        de: "Ungültige Regel: die Aktion muss eine Aktion der Anwendung sein, und die Bedingung darf nicht leer sein",
+        en: "Invalid rule: the action must be one of the application, and the condition must not be empty",
+    }
+    ErrPolicyOtherBase {
+        fr: "Ces règles d'accès dérivent d'autres règles signées que celles en vigueur",
+        de: "Diese Zugriffsregeln stammen von anderen signierten Regeln als den geltenden ab",
+        en: "This access policy derives from another signed policy than the one in force",
+    }
+    ErrPolicyUnchanged {
+        fr: "Cette modification ne change pas les règles d'accès",
+        de: "Diese Änderung ändert die Zugriffsregeln nicht",
+        en: "This change leaves the access policy unchanged",
+    }
+    ErrPolicyScenarios {
+        fr: "Modification refusée, les règles obtenues échouent aux scénarios: {0}",
+        de: "Änderung abgelehnt, die neuen Regeln bestehen diese Szenarien nicht: {0}",
+        en: "Change refused, the resulting policy fails these scenarios: {0}",
+    }
+    ErrPolicyLockout {
+        fr: "Modification refusée: plus aucun administrateur ne pourrait gérer les règles d'accès",
+        de: "Änderung abgelehnt: kein Administrator könnte die Zugriffsregeln mehr verwalten",
+        en: "Change refused: no administrator could manage the access policy any more",
+    }
+    ErrNoSuchRoleChange {
+        fr: "Demande de changement de rôle introuvable",
+        de: "Antrag auf Rollenänderung nicht gefunden",
+        en: "Role change request not found",
+    }
+    ErrRoleChangeAlreadyPending {
+        fr: "Un changement de rôle est déjà en attente pour cet utilisateur",
+        de: "Für diesen Benutzer ist bereits eine Rollenänderung offen",
+        en: "A role change is already pending for this user",
+    }
+    ErrNoSuchPolicyChange {
+        fr: "Demande de modification des règles introuvable",
+        de: "Antrag auf Regeländerung nicht gefunden",
+        en: "Policy change request not found",
+    }
+    ErrPolicyChangeExpired {
+        fr: "Cette demande de modification des règles a expiré",
+        de: "Dieser Antrag auf Regeländerung ist abgelaufen",
+        en: "This policy change request has expired",
+    }
+    ErrRoleChangeExpired {
+        fr: "Cette demande de changement de rôle a expiré",
+        de: "Dieser Antrag auf Rollenänderung ist abgelaufen",
+        en: "This role change request has expired",
+    }
+    ErrRoleChangeStale {
+        fr: "Cette demande de changement de rôle est dépassée: son auteur ne pourrait plus la faire, ou le rôle a changé depuis",
+        de: "Dieser Antrag auf Rollenänderung ist überholt: sein Urheber könnte ihn nicht mehr stellen, oder die Rolle hat sich seither geändert",
+        en: "This role change request is stale: its requester could no longer make it, or the role has changed since",
+    }
+    ErrNoReviewer {
+        fr: "Aucun autre administrateur ne pourrait approuver cette demande",
+        de: "Kein anderer Administrator könnte diesen Antrag genehmigen",
+        en: "No other administrator could approve this request",
+    }
+    ErrTooFewAdmins {
+        fr: "Il faut au moins deux administrateurs initiaux",
+        de: "Es braucht mindestens zwei erste Administratoren",
+        en: "At least two initial administrators are needed",
+    }
+    ErrLastAdmin {
+        fr: "Le dernier administrateur ne peut pas perdre son rôle",
+        de: "Der letzte Administrator kann seine Rolle nicht verlieren",
+        en: "The last administrator cannot lose their role",
+    }
+    ErrAdminClosure {
+        fr: "Le compte d'un administrateur ne se ferme qu'une fois son rôle retiré par un changement de rôle approuvé",
+        de: "Das Konto eines Administrators wird erst geschlossen, wenn ihm die Rolle durch eine genehmigte Rollenänderung entzogen wurde",
+        en: "An administrator's account can only be closed once an approved role change has removed their role",
+    }
+    ErrPolicyKey {
+        fr: "Clé de signature des règles invalide",
+        de: "Ungültiger Signaturschlüssel für die Regeln",
+        en: "Invalid policy signing key",
+    }
+    ErrPolicySignature {
+        fr: "Signature des règles d'accès invalide",
+        de: "Ungültige Signatur der Zugriffsregeln",
+        en: "Invalid access policy signature",
+    }
+    ErrConfigRead {
+        fr: "Impossible de lire la configuration {0}: {1}",
+        de: "Konfiguration {0} kann nicht gelesen werden: {1}",
+        en: "Cannot read the configuration {0}: {1}",
+    }
+    ErrConfigSyntax {
+        fr: "Configuration {0} invalide: {1}",
+        de: "Ungültige Konfiguration {0}: {1}",
+        en: "Invalid configuration {0}: {1}",
+    }
+    ErrUntrustedPolicyKey {
+        fr: "La clé publique des règles (policy_key) n'est acceptée que d'un fichier de configuration de root, que personne d'autre ne peut modifier: {0}",
+        de: "Der öffentliche Schlüssel der Regeln (policy_key) wird nur aus einer Konfigurationsdatei von root akzeptiert, die niemand sonst ändern kann: {0}",
+        en: "The policy public key (policy_key) is only accepted from a root-owned configuration file that nobody else can modify: {0}",
+    }
+    ErrConfigValue {
+        fr: "Valeur invalide pour «{0}» dans la configuration: {1}",
+        de: "Ungültiger Wert für «{0}» in der Konfiguration: {1}",
+        en: "Invalid value for \"{0}\" in the configuration: {1}",
+    }
+    ErrInvalidRecords {
+        fr: "La base contient {0} enregistrement(s) invalide(s); relancez avec --quarantine pour les mettre de côté",
+        de: "Die Datenbank enthält {0} ungültige(n) Datensatz/Datensätze; mit --quarantine neu starten, um sie auszusondern",
+        en: "The database contains {0} invalid record(s); run again with --quarantine to set them aside",
+    }
+    ErrCannotReassign {
+        fr: "Les documents ne peuvent être confiés qu'à un autre médecin ayant déjà accès à chacun d'eux",
+        de: "Die Dokumente können nur einem anderen Arzt übertragen werden, der bereits Zugriff auf jedes von ihnen hat",
+        en: "The documents can only be reassigned to another doctor who can already read each of them",
+    }
+    ErrInvalidProxy {
+        fr: "Représentation invalide: le représentant doit être une autre personne, la fin suivre le début, et un représentant légal avoir une date de fin",
+        de: "Ungültige Vertretung: die Vertretung muss eine andere Person sein, das Ende nach dem Beginn liegen und ein gesetzlicher Vertreter ein Enddatum haben",
+        en: "Invalid proxy: the proxy must be someone else, the end must follow the start, and a guardian needs an end date",
+    }
+    ErrNoSuchProxy {
+        fr: "Cette représentation n'existe pas",
+        de: "Diese Vertretung existiert nicht",
+        en: "No such proxy relationship",
+    }
+    ErrInvalidLabResult {
+        fr: "Résultat invalide: il faut un code d'analyse (lettres, chiffres, tirets), une valeur, une unité, des bornes dans l'ordre et une date passée",
+        de: "Ungültiges Ergebnis: nötig sind ein Analysecode (Buchstaben, Ziffern, Bindestriche), ein Wert, eine Einheit, geordnete Grenzen und ein vergangenes Datum",
+        en: "Invalid result: an analyte code (letters, digits, dashes), a value, a unit, ordered bounds and a past date are required",
+    }
+    ErrInvalidPrescription {
+        fr: "Ordonnance invalide: il faut un médicament, une posologie et une durée, et une délivrance restante pour un nouveau code",
+        de: "Ungültiges Rezept: Medikament, Dosierung und Dauer sind nötig, und für einen neuen Code eine verbleibende Abgabe",
+        en: "Invalid prescription: a drug, a dose and a duration are required, and a remaining dispensing for a new code",
+    }
+    ErrNoSuchPrescription {
+        fr: "Ordonnance introuvable, ou code déjà utilisé",
+        de: "Rezept nicht gefunden oder Code bereits verwendet",
+        en: "No such prescription, or the code was already used",
+    }
+    ErrStorage {
+        fr: "Erreur d'enregistrement des données",
+        de: "Fehler beim Speichern der Daten",
+        en: "Failed to save data",
+    }
+
+    // Valeurs affichées
+    RoleDoctor {
+        fr: "Médecin",
+        de: "Arzt",
+        en: "Doctor",
+    }
+    RolePatient {
+        fr: "Patient",
+        de: "Patient",
+        en: "Patient",
+    }
+    RoleAdmin {
+        fr: "Administrateur",
+        de: "Administrator",
+        en: "Administrator",
+    }
+    RolePharmacist {
+        fr: "Pharmacien",
+        de: "Apotheker",
+        en: "Pharmacist",
+    }
+    ScopeFolder {
+        fr: "Dossier complet",
+        de: "Vollständiges Dossier",
+        en: "Full folder",
+    }
+    ScopeReports {
+        fr: "Rapports uniquement",
+        de: "Nur Berichte",
+        en: "Reports only",
+    }
+    ProxyGuardian {
+        fr: "Représentant légal",
+        de: "Gesetzlicher Vertreter",
+        en: "Guardian",
+    }
+    ProxyCarer {
+        fr: "Proche aidant",
+        de: "Betreuende Person",
+        en: "Carer",
+    }
+    ProxyScopeRead {
+        fr: "Consultation",
+        de: "Einsicht",
+        en: "Read only",
+    }
+    ProxyScopeManage {
+        fr: "Gestion",
+        de: "Verwaltung",
+        en: "Manage",
+    }
+    StatusPending {
+        fr: "en attente",
+        de: "offen",
+        en: "pending",
+    }
+    StatusApproved {
+        fr: "approuvée",
+        de: "genehmigt",
+        en: "approved",
+    }
+    StatusDenied {
+        fr: "refusée",
+        de: "abgelehnt",
+        en: "denied",
+    }
+    StatusExpired {
+        fr: "expirée",
+        de: "abgelaufen",
+        en: "expired",
+    }
+    StatusCancelled {
+        fr: "annulée",
+        de: "zurückgezogen",
+        en: "cancelled",
+    }
+    KindReportAdded {
+        fr: "Nouveau rapport",
+        de: "Neuer Bericht",
+        en: "New report",
+    }
+    KindReportUpdated {
+        fr: "Rapport modifié",
+        de: "Bericht geändert",
+        en: "Report updated",
+    }
+    KindDoctorAdded {
+        fr: "Accès accordé à un médecin",
+        de: "Zugriff für einen Arzt erteilt",
+        en: "Access granted to a doctor",
+    }
+    KindRoleChanged {
+        fr: "Changement de rôle",
+        de: "Rollenänderung",
+        en: "Role change",
+    }
+    KindDataRead {
+        fr: "Consultation de mon dossier",
+        de: "Einsicht in mein Dossier",
+        en: "My folder was read",
+    }
+    KindDataDeleted {
+        fr: "Suppression de données",
+        de: "Löschung von Daten",
+        en: "Data deletion",
+    }
+    KindProxyChanged {
+        fr: "Représentation",
+        de: "Vertretung",
+        en: "Proxy",
+    }
+    KindPrescription {
+        fr: "Ordonnances",
+        de: "Rezepte",
+        en: "Prescriptions",
+    }
+    ActionReadData {
+        fr: "Lecture du dossier",
+        de: "Lesen des Dossiers",
+        en: "Folder read",
+    }
+    ActionReadReport {
+        fr: "Lecture du rapport {0}",
+        de: "Lesen des Berichts {0}",
+        en: "Report {0} read",
+    }
+    ActionRevealAvs {
+        fr: "Affichage du numéro AVS",
+        de: "Anzeige der AHV-Nummer",
+        en: "AVS number revealed",
+    }
+    ActionUpdateData {
+        fr: "Modification des données personnelles",
+        de: "Änderung der Personalien",
+        en: "Personal data updated",
+    }
+    ActionAddDoctor {
+        fr: "Ajout du médecin {0}",
+        de: "Hinzufügen des Arztes {0}",
+        en: "Doctor {0} added",
+    }
+    ActionRemoveDoctor {
+        fr: "Retrait du médecin {0}",
+        de: "Entfernen des Arztes {0}",
+        en: "Doctor {0} removed",
+    }
+    ActionReviewAccess {
+        fr: "Réponse à la demande d'accès {0}",
+        de: "Antwort auf die Zugriffsanfrage {0}",
+        en: "Access request {0} reviewed",
+    }
+    ActionReadLabResult {
+        fr: "Lecture du résultat de laboratoire {0}",
+        de: "Lesen des Laborergebnisses {0}",
+        en: "Lab result {0} read",
+    }
+    ActionDispense {
+        fr: "Délivrance de l'ordonnance {0}",
+        de: "Abgabe des Rezepts {0}",
+        en: "Prescription {0} dispensed",
+    }
+    DisputeDisputed {
+        fr: "contesté",
+        de: "beanstandet",
+        en: "disputed",
+    }
+    DisputeReviewed {
+        fr: "examiné",
+        de: "geprüft",
+        en: "reviewed",
+    }
+    SortNewestFirst {
+        fr: "Plus récents d'abord",
+        de: "Neueste zuerst",
+        en: "Newest first",
+    }
+    SortOldestFirst {
+        fr: "Plus anciens d'abord",
+        de: "Älteste zuerst",
+        en: "Oldest first",
+    }
+    SortRecentlyUpdated {
+        fr: "Derniers modifiés d'abord",
+        de: "Zuletzt geänderte zuerst",
+        en: "Recently updated first",
+    }
+    SortTitle {
+        fr: "Par titre",
+        de: "Nach Titel",
+        en: "By title",
+    }
+
+    // Notifications, rédigées dans la langue du destinataire
+    NotifyRoleChanged {
+        fr: "Votre rôle est maintenant: {0}",
+        de: "Ihre Rolle ist jetzt: {0}",
+        en: "Your role is now: {0}",
+    }
+    NotifyFolderRead {
+        fr: "{0} a consulté votre dossier",
+        de: "{0} hat Ihr Dossier eingesehen",
+        en: "{0} read your folder",
+    }
+    NotifyFolderDeleted {
+        fr: "{0} a effacé votre dossier médical",
+        de: "{0} hat Ihr Patientendossier gelöscht",
+        en: "{0} deleted your medical folder",
+    }
+    NotifyLabResultAdded {
+        fr: "{0} a ajouté un résultat d'analyse {1} à votre dossier",
+        de: "{0} hat ein Laborergebnis {1} zu Ihrem Dossier hinzugefügt",
+        en: "{0} added a {1} lab result to your folder",
+    }
+    NotifyPrescriptionIssued {
+        fr: "{0} vous a prescrit {1}",
+        de: "{0} hat Ihnen {1} verschrieben",
+        en: "{0} prescribed you {1}",
+    }
+    NotifyPrescriptionDispensed {
+        fr: "{0} vous a délivré {1}",
+        de: "{0} hat Ihnen {1} abgegeben",
+        en: "{0} dispensed {1} to you",
+    }
+    NotifyProxyAdded {
+        fr: "{0} a désigné {1} pour vous représenter ({2})",
+        de: "{0} hat {1} als Ihre Vertretung bestimmt ({2})",
+        en: "{0} named {1} as your proxy ({2})",
+    }
+    NotifyProxyEnded {
+        fr: "{0} a mis fin à une représentation",
+        de: "{0} hat eine Vertretung beendet",
+        en: "{0} ended a proxy relationship",
+    }
+    NotifyReportAdded {
+        fr: "{0} a ajouté le rapport «{1}» à votre dossier",
+        de: "{0} hat den Bericht «{1}» zu Ihrem Dossier hinzugefügt",
+        en: "{0} added the report \"{1}\" to your folder",
+    }
+    NotifyReportRead {
+        fr: "{0} a consulté le rapport «{1}»",
+        de: "{0} hat den Bericht «{1}» eingesehen",
+        en: "{0} read the report \"{1}\"",
+    }
+    NotifyReportUpdated {
+        fr: "{0} a modifié le rapport «{1}»",
+        de: "{0} hat den Bericht «{1}» geändert",
+        en: "{0} updated the report \"{1}\"",
+    }
+    NotifyReportsExported {
+        fr: "{0} a exporté {1} rapport(s) de votre dossier",
+        de: "{0} hat {1} Bericht(e) aus Ihrem Dossier exportiert",
+        en: "{0} exported {1} report(s) from your folder",
+    }
+    NotifyAccessGranted {
+        fr: "Vous avez maintenant accès au dossier de {0}",
+        de: "Sie haben nun Zugriff auf das Dossier von {0}",
+        en: "You now have access to the folder of {0}",
+    }
+    NotifyDoctorAdded {
+        fr: "{0} a donné accès à votre dossier à {1}",
+        de: "{0} hat {1} Zugriff auf Ihr Dossier gegeben",
+        en: "{0} gave {1} access to your folder",
+    }
+    NotifyRoleChangeRequested {
+        fr: "{0} demande le rôle {2} pour {1}, à approuver par un autre administrateur",
+        de: "{0} beantragt die Rolle {2} für {1}, zu genehmigen durch einen anderen Administrator",
+        en: "{0} requests the role {2} for {1}, to be approved by another administrator",
+    }
+    NotifyPolicyChangeRequested {
+        fr: "{0} demande la modification des règles d'accès {1}, à approuver par un autre administrateur",
+        de: "{0} beantragt die Zugriffsregeländerung {1}, zu genehmigen durch einen anderen Administrator",
+        en: "{0} requests the access policy change {1}, to be approved by another administrator",
+    }
+    NotifyPolicyChangeReviewed {
+        fr: "{0} a {2} votre modification des règles d'accès {1}",
+        de: "{0} hat Ihre Zugriffsregeländerung {1} {2}",
+        en: "{0} {2} your access policy change {1}",
+    }
+    NotifyRoleChangeReviewed {
+        fr: "{0} a {3} votre demande du rôle {2} pour {1}",
+        de: "{0} hat Ihren Antrag auf die Rolle {2} für {1} {3}",
+        en: "{0} {3} your request for the role {2} for {1}",
+    }
+    NotifyRequestReviewed {
+        fr: "{0} a {1} votre demande d'accès",
+        de: "{0} hat Ihre Zugriffsanfrage {1}",
+        en: "{0} {1} your access request",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        text.match_indices('{')
+            .filter_map(|(start, _)| {
+                let end = start + text[start..].find('}')?;
+                Some(&text[start..=end])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_translations_use_the_same_placeholders() {
+        for msg in Msg::iter() {
+            let french = placeholders(msg.text_in(Lang::Fr));
+            for lang in Lang::iter() {
+                assert!(!msg.text_in(lang).is_empty(), "{msg:?} is empty in {lang}");
+                assert_eq!(
+                    placeholders(msg.text_in(lang)),
+                    french,
+                    "{msg:?} has different parameters in {lang}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_arguments() {
+        with_lang(Lang::En, || {
+            assert_eq!(
+                t!(PromptChooseReport, 3, 1, 2),
+                "Choose a report (3 report(s), page 1/2):"
+            );
+            assert_eq!(t!(MenuLogin), "Log in");
+        });
+        with_lang(Lang::De, || {
+            assert_eq!(
+                t!(NotifyRequestReviewed, "alice", Msg::StatusApproved),
+                "alice hat Ihre Zugriffsanfrage genehmigt"
+            );
+        });
+    }
+
+    #[test]
+    fn test_with_lang_restores_session() {
+        set_session_lang(Some(Lang::De));
+        assert_eq!(with_lang(Lang::En, lang), Lang::En);
+        assert_eq!(lang(), Lang::De);
+        set_session_lang(None);
+        assert_eq!(lang(), default_lang());
+    }
+
+    #[test]
+    fn test_parse_lang() {
+        assert_eq!("fr".parse::<Lang>().unwrap(), Lang::Fr);
+        assert_eq!("de_CH.UTF-8".parse::<Lang>().unwrap(), Lang::De);
+        assert_eq!("EN-gb".parse::<Lang>().unwrap(), Lang::En);
+        assert!("it_CH".parse::<Lang>().is_err());
+        assert!("C".parse::<Lang>().is_err());
+    }
+}
@@ -0,0 +1,260 @@
+//! Import en masse d'utilisateurs et de patients depuis un fichier CSV.
+//!
+//! Le fichier contient une ligne d'en-tête et les colonnes `username`,
+//! `role`, `avs_number`, `blood_type` et `doctor`. Les trois dernières
+//! sont facultatives: un patient sans numéro AVS ni groupe sanguin est
+//! créé sans dossier médical. Ce module ne fait que lire et valider les
+//! lignes; la création des comptes et le contrôle d'accès sont faits par
+//! `Service::import_users`, qui crée les médecins comme patients en attendant
+//! qu'un autre administrateur approuve leur rôle.
+
+use std::io::Read;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::models::{BloodType, PersonalData, Role};
+use crate::t;
+use crate::utils::input_validation::{AVSNumber, Username};
+
+/// Une ligne du fichier, telle que lue
+#[derive(Debug, Deserialize)]
+struct RawRow {
+    username: String,
+    role: String,
+    #[serde(default)]
+    avs_number: String,
+    #[serde(default)]
+    blood_type: String,
+    #[serde(default)]
+    doctor: String,
+}
+
+/// Une ligne du fichier dont tous les champs ont été validés
+#[derive(Debug)]
+pub struct ImportRow {
+    /// Numéro de la ligne dans le fichier, en-tête compris
+    pub line: u64,
+    pub username: Username,
+    pub role: Role,
+    pub personal_data: Option<PersonalData>,
+    /// Médecin traitant à qui donner accès au dossier
+    pub doctor: Option<Username>,
+}
+
+/// Une erreur empêchant l'import d'une ligne
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("{}", t!(ImportMalformedRow, .0))]
+    MalformedRow(String),
+    #[error("{}", t!(ImportInvalidUsername, .0))]
+    InvalidUsername(String),
+    #[error("{}", t!(ImportInvalidRole, .0))]
+    InvalidRole(String),
+    #[error("{}", t!(ImportInvalidAvsNumber, .0))]
+    InvalidAvsNumber(String),
+    #[error("{}", t!(ImportInvalidBloodType, .0))]
+    InvalidBloodType(String),
+    #[error("{}", t!(ImportIncompleteFolder))]
+    IncompleteFolder,
+    #[error("{}", t!(ImportDoctorWithoutFolder))]
+    DoctorWithoutFolder,
+    #[error("{}", t!(ImportDuplicateUsername, .0))]
+    DuplicateUsername(Username),
+    #[error("{}", t!(ImportUserExists, .0))]
+    UserExists(Username),
+    #[error("{}", t!(ImportUnknownDoctor, .0))]
+    UnknownDoctor(Username),
+}
+
+/// Une erreur rattachée à sa ligne dans le fichier
+#[derive(Debug, Error)]
+#[error("{}", t!(ImportRowError, .line, .error))]
+pub struct RowError {
+    pub line: u64,
+    pub error: ImportError,
+}
+
+/// Un compte créé par l'import, avec son mot de passe initial
+#[derive(Debug)]
+pub struct ImportedAccount {
+    pub username: Username,
+    pub role: Role,
+    /// Le rôle privilégié demandé, en attente de l'approbation d'un autre
+    /// administrateur
+    pub pending_role: Option<Role>,
+    pub initial_password: String,
+}
+
+/// Le résultat d'un import. Les comptes ne sont créés que si aucune
+/// ligne n'est en erreur et que l'import n'est pas un essai à blanc.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub rows: usize,
+    pub errors: Vec<RowError>,
+    pub accounts: Vec<ImportedAccount>,
+}
+
+impl ImportReport {
+    /// Le fichier des identifiants initiaux, à transmettre séparément
+    /// aux utilisateurs
+    pub fn credentials_csv(&self) -> Result<Vec<u8>, csv::Error> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(["username", "role", "initial_password"])?;
+        for account in &self.accounts {
+            writer.write_record([
+                account.username.as_ref(),
+                &format!("{:?}", account.role),
+                &account.initial_password,
+            ])?;
+        }
+        writer
+            .into_inner()
+            .map_err(|e| csv::Error::from(e.into_error()))
+    }
+}
+
+/// Lit et valide individuellement chaque ligne du fichier. Les vérifications
+/// qui dépendent de la base ou des autres lignes sont faites par le service.
+pub fn parse(input: impl Read) -> Vec<Result<ImportRow, RowError>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(input);
+
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(e) => {
+            return vec![Err(RowError {
+                line: 1,
+                error: ImportError::MalformedRow(e.to_string()),
+            })]
+        }
+    };
+
+    reader
+        .records()
+        .enumerate()
+        .map(|(i, record)| {
+            // La ligne 1 est l'en-tête
+            let fallback_line = i as u64 + 2;
+            let malformed = |line, e: csv::Error| RowError {
+                line,
+                error: ImportError::MalformedRow(e.to_string()),
+            };
+
+            let mut record = record
+                .map_err(|e| malformed(e.position().map_or(fallback_line, |pos| pos.line()), e))?;
+            let line = record.position().map_or(fallback_line, |pos| pos.line());
+
+            // Les colonnes facultatives peuvent être omises en fin de ligne
+            while record.len() < headers.len() {
+                record.push_field("");
+            }
+            let raw: RawRow = record
+                .deserialize(Some(&headers))
+                .map_err(|e| malformed(line, e))?;
+            parse_row(line, raw).map_err(|error| RowError { line, error })
+        })
+        .collect()
+}
+
+fn parse_row(line: u64, raw: RawRow) -> Result<ImportRow, ImportError> {
+    let username = Username::try_from(raw.username.as_str())
+        .map_err(|_| ImportError::InvalidUsername(raw.username.clone()))?;
+
+    // Les administrateurs ne peuvent pas être créés en masse
+    let role = match raw.role.parse() {
+        Ok(role @ (Role::Patient | Role::Doctor | Role::Pharmacist)) => role,
+        _ => return Err(ImportError::InvalidRole(raw.role)),
+    };
+
+    let personal_data = match (raw.avs_number.as_str(), raw.blood_type.as_str()) {
+        ("", "") => None,
+        ("", _) | (_, "") => return Err(ImportError::IncompleteFolder),
+        (avs_number, blood_type) => Some(PersonalData {
+            avs_number: AVSNumber::try_from(avs_number)
+                .map_err(|_| ImportError::InvalidAvsNumber(avs_number.to_owned()))?,
+            blood_type: blood_type
+                .parse::<BloodType>()
+                .map_err(|_| ImportError::InvalidBloodType(blood_type.to_owned()))?,
+        }),
+    };
+
+    let doctor = match raw.doctor.as_str() {
+        "" => None,
+        _ if personal_data.is_none() => return Err(ImportError::DoctorWithoutFolder),
+        doctor => Some(
+            Username::try_from(doctor)
+                .map_err(|_| ImportError::InvalidUsername(doctor.to_owned()))?,
+        ),
+    };
+
+    Ok(ImportRow {
+        line,
+        username,
+        role,
+        personal_data,
+        doctor,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid_rows() {
+        let csv = "username,role,avs_number,blood_type,doctor\n\
+                   house,Doctor,,,\n\
+                   alice,patient,756.1234.5678.97,ab,house\n\
+                   bob,Patient\n";
+        let rows: Vec<ImportRow> = parse(csv.as_bytes())
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .expect("All rows should be valid");
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].role, Role::Doctor);
+        assert_eq!(rows[1].line, 3);
+        assert!(matches!(
+            rows[1].personal_data,
+            Some(PersonalData {
+                blood_type: BloodType::AB,
+                ..
+            })
+        ));
+        assert_eq!(rows[1].doctor.as_ref().map(AsRef::as_ref), Some("house"));
+        assert!(rows[2].personal_data.is_none(), "Missing columns are empty");
+    }
+
+    #[test]
+    fn test_parse_reports_row_errors() {
+        let csv = "username,role,avs_number,blood_type,doctor\n\
+                   1nvalid,Patient,,,\n\
+                   root,Admin,,,\n\
+                   carol,Patient,756.0000.0000.00,A,\n\
+                   dave,Patient,756.1234.5678.97,,\n\
+                   erin,Patient,756.1234.5678.97,Z,\n\
+                   frank,Patient,,,house\n";
+        let errors: Vec<(u64, String)> = parse(csv.as_bytes())
+            .into_iter()
+            .map(|row| row.expect_err("Every row is invalid"))
+            .map(|e| (e.line, format!("{:?}", e.error)))
+            .collect();
+
+        let expected = [
+            (2, "InvalidUsername"),
+            (3, "InvalidRole"),
+            (4, "InvalidAvsNumber"),
+            (5, "IncompleteFolder"),
+            (6, "InvalidBloodType"),
+            (7, "DoctorWithoutFolder"),
+        ];
+        assert_eq!(errors.len(), expected.len());
+        for ((line, error), (expected_line, expected_error)) in errors.iter().zip(expected) {
+            assert_eq!(*line, expected_line);
+            assert!(error.starts_with(expected_error), "{error}");
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Résultats de laboratoire: codes d'analyse et évolution dans le terminal.
+//!
+//! Le contrôle d'accès est fait en amont par `Service::read_lab_results`:
+//! ce module ne reçoit que des résultats dont la lecture a été autorisée.
+
+use crate::models::LabResult;
+
+/// Largeur du graphique d'évolution, en caractères
+const TREND_WIDTH: usize = 40;
+
+/// Longueur maximale d'un code d'analyse
+const ANALYTE_MAX_LENGTH: usize = 20;
+
+/// Normalise un code d'analyse (`HGB`, `CRP`, `718-7`...): lettres ASCII,
+/// chiffres et tirets, en majuscules
+pub fn analyte_code(raw: &str) -> Option<String> {
+    let code = raw.trim().to_ascii_uppercase();
+    let valid = !code.is_empty()
+        && code.len() <= ANALYTE_MAX_LENGTH
+        && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    valid.then_some(code)
+}
+
+/// Dessine l'évolution d'une analyse, une ligne par résultat dans l'ordre
+/// chronologique. Sur chaque ligne, `*` marque la valeur et `[---]`
+/// l'intervalle de référence, sur une échelle commune à toutes les lignes.
+pub fn trend(results: &[&LabResult]) -> String {
+    let mut results = results.to_vec();
+    results.sort_by_key(|result| (result.taken_at, result.id));
+
+    let points = results.iter().flat_map(|result| {
+        [Some(result.value), result.range.low, result.range.high]
+            .into_iter()
+            .flatten()
+    });
+    let (min, max) = points.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+        (min.min(v), max.max(v))
+    });
+    let position = |value: f64| {
+        if max > min {
+            ((value - min) / (max - min) * (TREND_WIDTH - 1) as f64).round() as usize
+        } else {
+            TREND_WIDTH / 2
+        }
+    };
+
+    let value_width = results
+        .iter()
+        .map(|result| result.value.to_string().len())
+        .max()
+        .unwrap_or_default();
+    let unit_width = results
+        .iter()
+        .map(|result| result.unit.chars().count())
+        .max()
+        .unwrap_or_default();
+
+    results
+        .iter()
+        .map(|result| {
+            let mut bar = [' '; TREND_WIDTH];
+            let (low, high) = (
+                result.range.low.map(position),
+                result.range.high.map(position),
+            );
+            if let (Some(low), Some(high)) = (low, high) {
+                bar[low..=high].fill('-');
+            }
+            if let Some(low) = low {
+                bar[low] = '[';
+            }
+            if let Some(high) = high {
+                bar[high] = ']';
+            }
+            bar[position(result.value)] = '*';
+
+            format!(
+                "{}  {:>value_width$} {:<unit_width$} {} |{}|",
+                result.taken_at.format("%d.%m.%Y"),
+                result.value,
+                result.unit,
+                result.flag(),
+                bar.iter().collect::<String>(),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::{LabFlag, LabResultID, ReferenceRange, UserID};
+    use chrono::{TimeZone, Utc};
+
+    fn result(day: u32, value: f64) -> LabResult {
+        LabResult {
+            id: LabResultID::new(),
+            patient: UserID::new(),
+            author: UserID::new(),
+            analyte: "HGB".into(),
+            value,
+            unit: "g/L".into(),
+            range: ReferenceRange {
+                low: Some(120.0),
+                high: Some(160.0),
+            },
+            taken_at: Utc.with_ymd_and_hms(2026, 3, day, 9, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_analyte_codes_are_normalized() {
+        assert_eq!(analyte_code(" hgb ").as_deref(), Some("HGB"));
+        assert_eq!(analyte_code("718-7").as_deref(), Some("718-7"));
+        assert_eq!(analyte_code(""), None);
+        assert_eq!(analyte_code("HGB; DROP"), None);
+    }
+
+    #[test]
+    fn test_out_of_range_values_are_flagged() {
+        assert_eq!(result(1, 110.0).flag(), LabFlag::Low);
+        assert_eq!(result(1, 120.0).flag(), LabFlag::Normal);
+        assert_eq!(result(1, 170.0).flag(), LabFlag::High);
+
+        let open = ReferenceRange {
+            low: None,
+            high: Some(5.0),
+        };
+        assert_eq!(open.flag(-100.0), LabFlag::Normal);
+    }
+
+    #[test]
+    fn test_trend_is_chronological_on_a_common_scale() {
+        let (low, normal, high) = (result(1, 100.0), result(2, 140.0), result(3, 180.0));
+        let trend = trend(&[&high, &low, &normal]);
+        let lines: Vec<&str> = trend.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("01.03.2026  100 g/L L |*"), "{trend}");
+        assert!(lines[2].starts_with("03.03.2026  180 g/L H |"), "{trend}");
+        assert!(lines[2].ends_with("*|"), "{trend}");
+
+        // The value sits in the middle of the reference range
+        let bar = lines[1].split('|').nth(1).unwrap();
+        let star = bar.find('*').unwrap();
+        assert!(bar.find('[').unwrap() < star && star < bar.find(']').unwrap());
+    }
+}
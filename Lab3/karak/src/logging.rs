@@ -0,0 +1,384 @@
+//! Journalisation structurée en JSON, avec rotation des fichiers.
+//!
+//! Chaque enregistrement est un objet JSON sur une ligne, avec la date, le
+//! niveau, la cible, le message et les champs passés au macro `log` (par
+//! exemple `info!(action, granted; "...")`). Les messages et les champs
+//! passent par une couche de rédaction qui masque les hachés de mots de
+//! passe et les numéros AVS, au cas où l'un d'eux y aurait été glissé.
+
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use chrono::{Local, NaiveDate, Utc};
+use clap::Args;
+use log::kv::{Key, Value, VisitSource};
+use log::{LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Map};
+
+use crate::utils::files::append_private;
+use crate::utils::input_validation::InvalidInput;
+
+/// Hachés Argon2 au format PHC
+static PASSWORD_HASH: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\$argon2(?:id|i|d)\$[^\s\x22]+").expect("Valid regex"));
+
+/// Numéros AVS, avec ou sans séparateurs
+static AVS_NUMBER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b756[.\s]?\d{4}[.\s]?\d{4}[.\s]?\d{2}\b").expect("Valid regex"));
+
+/// Masque les données sensibles d'un texte à journaliser
+pub fn redact(text: &str) -> Cow<'_, str> {
+    match PASSWORD_HASH.replace_all(text, "[REDACTED]") {
+        Cow::Borrowed(text) => AVS_NUMBER.replace_all(text, "756.****.****.**"),
+        Cow::Owned(text) => Cow::Owned(
+            AVS_NUMBER
+                .replace_all(&text, "756.****.****.**")
+                .into_owned(),
+        ),
+    }
+}
+
+/// Où écrire le journal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogDestination {
+    File(PathBuf),
+    Stderr,
+}
+
+impl FromStr for LogDestination {
+    type Err = InvalidInput;
+
+    /// `stderr`, ou le chemin d'un fichier
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "" => Err(InvalidInput),
+            "stderr" | "-" => Ok(LogDestination::Stderr),
+            path => Ok(LogDestination::File(path.into())),
+        }
+    }
+}
+
+/// Quand changer de fichier de journal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Never,
+    Daily,
+    /// Dès que le fichier dépasserait cette taille, en octets
+    Size(u64),
+}
+
+impl FromStr for Rotation {
+    type Err = InvalidInput;
+
+    /// `never`, `daily` ou `size:<taille>`, la taille acceptant les
+    /// suffixes K, M et G
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "never" => Ok(Rotation::Never),
+            "daily" => Ok(Rotation::Daily),
+            other => {
+                let size = other.strip_prefix("size:").ok_or(InvalidInput)?;
+                let (digits, unit) = match size.char_indices().last() {
+                    Some((i, 'k')) => (&size[..i], 1 << 10),
+                    Some((i, 'm')) => (&size[..i], 1 << 20),
+                    Some((i, 'g')) => (&size[..i], 1 << 30),
+                    _ => (size, 1),
+                };
+                match digits.parse::<u64>() {
+                    Ok(size) if size > 0 => Ok(Rotation::Size(size * unit)),
+                    _ => Err(InvalidInput),
+                }
+            }
+        }
+    }
+}
+
+/// La configuration du journal
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    pub destination: LogDestination,
+    pub rotation: Rotation,
+    /// Nombre d'anciens fichiers conservés (`karak.log.1` étant le plus récent)
+    pub keep: usize,
+    pub level: LevelFilter,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            destination: LogDestination::File("karak.log".into()),
+            rotation: Rotation::Size(10 << 20),
+            keep: 5,
+            level: LevelFilter::Info,
+        }
+    }
+}
+
+/// Les options de journalisation des exécutables, qui priment sur le
+/// fichier de configuration (voir `config`)
+#[derive(Debug, Clone, Default, Args)]
+pub struct LogArgs {
+    /// Fichier du journal, ou `stderr` [défaut: $XDG_STATE_HOME/karak/karak.log]
+    #[arg(long = "log", global = true)]
+    pub destination: Option<LogDestination>,
+
+    /// Rotation du journal: never, daily ou size:<taille> (par ex. size:10M)
+    /// [défaut: size:10M]
+    #[arg(long = "log-rotation", global = true)]
+    pub rotation: Option<Rotation>,
+
+    /// Nombre d'anciens fichiers de journal conservés [défaut: 5]
+    #[arg(long = "log-keep", global = true)]
+    pub keep: Option<usize>,
+}
+
+/// Installe le journal global
+pub fn init(config: LogConfig) -> io::Result<()> {
+    let sink = match &config.destination {
+        LogDestination::Stderr => Sink::Stderr,
+        LogDestination::File(path) => Sink::File(RotatingFile::open(
+            path.clone(),
+            config.rotation,
+            config.keep,
+        )?),
+    };
+
+    log::set_boxed_logger(Box::new(JsonLogger {
+        level: config.level,
+        sink: Mutex::new(sink),
+    }))
+    .map_err(io::Error::other)?;
+    log::set_max_level(config.level);
+    Ok(())
+}
+
+struct JsonLogger {
+    level: LevelFilter,
+    sink: Mutex<Sink>,
+}
+
+enum Sink {
+    Stderr,
+    File(RotatingFile),
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut line = format_record(record);
+        line.push('\n');
+
+        let mut sink = self.sink.lock().unwrap_or_else(|e| e.into_inner());
+        // Un échec d'écriture du journal ne doit pas interrompre l'application
+        let _ = match &mut *sink {
+            Sink::Stderr => io::stderr().write_all(line.as_bytes()),
+            Sink::File(file) => file.write(line.as_bytes()),
+        };
+    }
+
+    fn flush(&self) {
+        if let Sink::File(file) = &mut *self.sink.lock().unwrap_or_else(|e| e.into_inner()) {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+/// Un enregistrement en JSON, sur une ligne
+fn format_record(record: &Record) -> String {
+    let mut fields = Fields(Map::new());
+    let _ = record.key_values().visit(&mut fields);
+
+    let mut entry = Map::new();
+    entry.insert("ts".into(), json!(Utc::now().to_rfc3339()));
+    entry.insert("level".into(), json!(record.level().as_str()));
+    entry.insert("target".into(), json!(record.target()));
+    entry.insert("message".into(), json!(redact(&record.args().to_string())));
+    if !fields.0.is_empty() {
+        entry.insert("fields".into(), fields.0.into());
+    }
+    serde_json::Value::Object(entry).to_string()
+}
+
+/// Les champs structurés d'un enregistrement
+struct Fields(Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        let value = if let Some(flag) = value.to_bool() {
+            json!(flag)
+        } else if let Some(number) = value.to_i64() {
+            json!(number)
+        } else if let Some(number) = value.to_u64() {
+            json!(number)
+        } else {
+            json!(redact(&value.to_string()))
+        };
+        self.0.insert(key.as_str().to_owned(), value);
+        Ok(())
+    }
+}
+
+/// Un fichier de journal qui change de fichier selon sa `Rotation`
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    opened_on: NaiveDate,
+    rotation: Rotation,
+    keep: usize,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, rotation: Rotation, keep: usize) -> io::Result<Self> {
+        let file = append_private(&path)?;
+        let metadata = file.metadata()?;
+        let opened_on = metadata
+            .modified()
+            .map(|modified| chrono::DateTime::<Local>::from(modified).date_naive())
+            .unwrap_or_else(|_| Local::now().date_naive());
+
+        Ok(Self {
+            path,
+            file,
+            size: metadata.len(),
+            opened_on,
+            rotation,
+            keep,
+        })
+    }
+
+    fn write(&mut self, line: &[u8]) -> io::Result<()> {
+        let today = Local::now().date_naive();
+        let rotate = match self.rotation {
+            Rotation::Never => false,
+            Rotation::Daily => self.size > 0 && today != self.opened_on,
+            Rotation::Size(max) => self.size > 0 && self.size + line.len() as u64 > max,
+        };
+        if rotate {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        self.opened_on = today;
+        Ok(())
+    }
+
+    /// Décale les anciens fichiers (`.1` devient `.2`, ...) et recommence
+    /// un fichier vide
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(numbered(&self.path, self.keep));
+            for i in (1..self.keep).rev() {
+                let from = numbered(&self.path, i);
+                if from.exists() {
+                    fs::rename(from, numbered(&self.path, i + 1))?;
+                }
+            }
+            fs::rename(&self.path, numbered(&self.path, 1))?;
+        }
+
+        self.file = append_private(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn numbered(path: &Path, i: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{i}"));
+    name.into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_redact_hashes_and_avs_numbers() {
+        let text = "hash $argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA, avs 756.1234.5678.97 \
+                    and 7561234567897, id 42";
+        let redacted = redact(text);
+
+        assert!(!redacted.contains("argon2"), "{redacted}");
+        assert!(!redacted.contains("1234"), "{redacted}");
+        assert!(redacted.ends_with("id 42"));
+        assert!(matches!(redact("nothing to hide"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_records_are_json_with_fields() {
+        let line = format_record(
+            &Record::builder()
+                .args(format_args!("Decision for 756.1234.5678.97"))
+                .level(log::Level::Info)
+                .target("karak::authorization")
+                .key_values(&[("action", "read-data"), ("granted", "true")])
+                .build(),
+        );
+        let entry: serde_json::Value = serde_json::from_str(&line).unwrap();
+
+        assert_eq!(entry["level"], "INFO");
+        assert_eq!(entry["fields"]["action"], "read-data");
+        assert_eq!(entry["message"], "Decision for 756.****.****.**");
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(
+            "size:10M".parse::<Rotation>().ok(),
+            Some(Rotation::Size(10 << 20))
+        );
+        assert_eq!(
+            "size:512".parse::<Rotation>().ok(),
+            Some(Rotation::Size(512))
+        );
+        assert_eq!("Daily".parse::<Rotation>().ok(), Some(Rotation::Daily));
+        assert!("size:0".parse::<Rotation>().is_err());
+        assert!("weekly".parse::<Rotation>().is_err());
+        assert_eq!(
+            "stderr".parse::<LogDestination>().ok(),
+            Some(LogDestination::Stderr)
+        );
+    }
+
+    #[test]
+    fn test_size_rotation_keeps_recent_files() {
+        let dir = std::env::temp_dir().join(format!("karak-log-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("karak.log");
+
+        let mut file = RotatingFile::open(path.clone(), Rotation::Size(10), 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write(line.as_bytes()).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(numbered(&path, 1)).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(numbered(&path, 2)).unwrap(), "second\n");
+        assert!(!numbered(&path, 3).exists(), "Only two old files are kept");
+        #[cfg(unix)]
+        for path in [path.clone(), numbered(&path, 1)] {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600, "Logs are readable by their owner only");
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,15 +1,36 @@
 use anyhow::{anyhow, Result};
+use clap::{Args, Parser, Subcommand};
 use derive_more::Display;
-use inquire::{Confirm, Password, Select, Text};
-use karak::authorization::Enforcer;
-use karak::db::Database;
+use inquire::{Confirm, CustomType, DateSelect, MultiSelect, Password, Select, Text};
+use karak::authorization::{
+    BackendKind, CasbinBackend, Enforcer, PolicyKey, PolicySource, Request,
+};
+use karak::config::{Config, ConfigArgs};
+use karak::db::{Database, InvalidRecords, LoadReport, OpenError};
+use karak::export::ExportFormat;
+use karak::i18n::{self, Lang, Msg};
+use karak::lab;
+use karak::logging;
 use karak::models::*;
-use karak::services::Service;
-use karak::utils::input_validation::{username_input_validation, AVSNumber};
+use karak::research::{PseudonymKey, ResearchFormat};
+use karak::services::{
+    AuthoredReports, LabMeasurement, PolicyUpdate, PrescriptionTerms, ProxyTerms, ReportQuery,
+    ReportSort, RoleUpdate, Service,
+};
+use karak::t;
+use karak::utils::files::create_private;
+use karak::utils::input_validation::{
+    password_input_validation, username_input_validation, AVSNumber,
+};
+use karak::utils::keyed_hash::HashKey;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-const DB_FILE: &str = "database.json";
+/// Nombre de rapports affichés par page
+const REPORTS_PER_PAGE: usize = 15;
 
 // ---------------------------------- NE PAS MODIFIER -------------------------------------------
 
@@ -45,8 +66,11 @@ impl App {
     }
 
     pub fn start(&mut self) -> Result<()> {
-        println!("Bienvenue sur KARAK, le dossier électronique du patient super sécurisé.");
-        self.enter_loop();
+        println!("{}", Msg::Welcome);
+        if self.service.needs_bootstrap() {
+            println!("{}", Msg::NoAdminYet);
+        }
+        self.run();
         self.service.save()?;
         Ok(())
     }
@@ -56,37 +80,52 @@ impl Menu for App {
     fn enter(&mut self) -> Result<MenuExit> {
         #[derive(EnumIter, Display)]
         enum Choice {
-            #[display("Créer un compte")]
+            #[display("{}", Msg::MenuRegister)]
             Register,
-            #[display("Se connecter")]
+            #[display("{}", Msg::MenuLogin)]
             Login,
-            #[display("Quitter")]
+            #[display("{}", Msg::MenuExit)]
             Exit,
         }
 
-        let choice = Select::new("Que voulez-vous faire ?", Choice::iter().collect()).prompt()?;
+        let choice = Select::new(t!(WhatToDo), Choice::iter().collect()).prompt()?;
 
         match choice {
             Choice::Register => {
-                let username = username_input_validation("Username à enregistrer: ")?;
+                let username = username_input_validation(t!(PromptUsernameToRegister))?;
                 self.service.register(username)?;
                 Ok(MENU_LOOP) // Retourne au menu principal après l'enregistrement
             }
             Choice::Login => {
-                let username = username_input_validation("Username: ")?;
-                let password = Password::new("Entrez votre mot de passe : ")
+                let username = username_input_validation(t!(PromptUsername))?;
+                let password = Password::new(t!(PromptPassword))
                     .without_confirmation()
                     .with_display_mode(inquire::PasswordDisplayMode::Masked)
                     .prompt()?;
 
                 let user_id = self.service.login(&username, &password)?;
+                i18n::set_session_lang(self.service.language());
+
+                while self.service.password_change_required() {
+                    println!("{}", Msg::PasswordChangeRequired);
+                    let password = password_input_validation(username.as_ref());
+                    if let Err(error) = self.service.change_password(&password) {
+                        eprintln!("{}", t!(ErrorPrefix, error));
+                    }
+                }
 
-                eprintln!("[*] Bienvenue, {}.", username);
+                eprintln!("{}", t!(WelcomeUser, username));
+                match self.service.unread_notifications() {
+                    0 => {}
+                    unread => eprintln!("{}", t!(UnreadNotifications, unread)),
+                }
                 UserMenu {
                     service: &mut self.service,
                     user_id,
                 }
-                .enter_loop();
+                .run();
+                self.service.logout();
+                i18n::set_session_lang(None);
                 Ok(MENU_LOOP)
             }
             Choice::Exit => Ok(MENU_EXIT),
@@ -94,6 +133,28 @@ impl Menu for App {
     }
 }
 
+/// Un menu dont les erreurs sont affichées dans la langue de l'utilisateur,
+/// avant d'arriver à `Menu::enter_loop`
+struct Localized<'m, M>(&'m mut M);
+
+impl<M: Menu> Menu for Localized<'_, M> {
+    fn enter(&mut self) -> Result<MenuExit> {
+        self.0.enter().or_else(|error| {
+            eprintln!("{}", t!(ErrorPrefix, error));
+            Ok(MENU_LOOP)
+        })
+    }
+}
+
+trait LocalizedMenu: Menu + Sized {
+    /// Lance le menu en boucle avec `Menu::enter_loop`
+    fn run(&mut self) {
+        Localized(self).enter_loop();
+    }
+}
+
+impl<M: Menu> LocalizedMenu for M {}
+
 struct UserMenu<'srv> {
     service: &'srv mut Service,
     user_id: UserID,
@@ -103,47 +164,121 @@ impl Menu for UserMenu<'_> {
     fn enter(&mut self) -> Result<Option<()>> {
         #[derive(EnumIter, Display)]
         enum Choice {
-            #[display("Créer mon dossier médical")]
+            #[display("{}", Msg::MenuSetPersonalData)]
             SetPersonalData,
 
-            #[display("Lire mon dossier médical")]
+            #[display("{}", Msg::MenuReadFolder)]
             ReadFolder,
 
-            #[display("Donner accès à mon dossier à un médecin")]
+            #[display("{}", Msg::MenuRevealAvs)]
+            RevealAvs,
+
+            #[display("{}", Msg::MenuPrescriptions)]
+            Prescriptions,
+
+            #[display("{}", Msg::MenuAddDoctor)]
             AddDoctor,
 
-            #[display("Lire le dossier d'un patient")]
+            #[display("{}", Msg::MenuReviewAccessRequests)]
+            ReviewAccessRequests,
+
+            #[display("{}", Msg::MenuCheckPatient)]
             CheckPatient,
 
-            #[display("Écrire un rapport")]
+            #[display("{}", Msg::MenuRequestAccess)]
+            RequestAccess,
+
+            #[display("{}", Msg::MenuAddReport)]
             AddReport,
 
-            #[display("Administrer les Rôles")]
+            #[display("{}", Msg::MenuAddLabResult)]
+            AddLabResult,
+
+            #[display("{}", Msg::MenuPrescribe)]
+            Prescribe,
+
+            #[display("{}", Msg::MenuDispense)]
+            Dispense,
+
+            #[display("{}", Msg::MenuUpdateRole)]
             UpdateRole,
 
-            #[display("Supprimer toutes mes données")]
+            #[display("{}", Msg::MenuReviewRoleChanges)]
+            ReviewRoleChanges,
+
+            #[display("{}", Msg::MenuPolicy)]
+            Policy,
+
+            #[display("{}", Msg::MenuSearchAvs)]
+            SearchAvs,
+
+            #[display("{}", Msg::MenuReviewDisputes)]
+            ReviewDisputes,
+
+            #[display("{}", Msg::MenuAccessHistory)]
+            AccessHistory,
+
+            #[display("{}", Msg::MenuNotifications)]
+            Notifications,
+
+            #[display("{}", Msg::MenuNotificationPreferences)]
+            NotificationPreferences,
+
+            #[display("{}", Msg::MenuLanguage)]
+            Language,
+
+            #[display("{}", Msg::MenuWipeAccount)]
             WipeAccount,
 
-            #[display("Se déconnecter")]
+            #[display("{}", Msg::MenuCloseAccount)]
+            CloseAccount,
+
+            #[display("{}", Msg::MenuProxies)]
+            Proxies,
+
+            #[display("{}", Msg::MenuActForDependant)]
+            ActForDependant,
+
+            #[display("{}", Msg::MenuLogout)]
             Logout,
         }
 
-        let choice = Select::new("Que voulez-vous faire ?", Choice::iter().collect()).prompt()?;
+        let choice = Select::new(t!(WhatToDo), Choice::iter().collect()).prompt()?;
         match choice {
             Choice::ReadFolder => {
-                ReportsMenu {
-                    service: self.service,
-                    patient_id: self.user_id,
+                ReportsMenu::new(self.service, self.user_id).show()?;
+            }
+
+            Choice::RevealAvs => {
+                let username = username_input_validation(t!(PromptPatientUsername))?;
+                let patient = self
+                    .service
+                    .lookup_user(&username)
+                    .ok_or(anyhow!(t!(UnknownPatient)))?;
+
+                let avs_number = self.service.reveal_avs(patient)?;
+                println!("{}", t!(AvsRevealed, username, avs_number));
+            }
+
+            Choice::SearchAvs => {
+                let avs_number: AVSNumber =
+                    Text::new(t!(PromptSearchAvsNumber)).prompt()?.try_into()?;
+
+                let patients = self.service.find_by_avs(&avs_number)?;
+                if patients.is_empty() {
+                    println!("{}", Msg::AvsNoMatch);
+                }
+                for patient in patients {
+                    if let Some(username) = self.service.username(patient) {
+                        println!("{}", t!(AvsMatch, username));
+                    }
                 }
-                .show()?;
             }
 
             Choice::SetPersonalData => {
-                let avs_number: AVSNumber =
-                    Text::new("Entrez votre numéro AVS:").prompt()?.try_into()?;
+                let avs_number: AVSNumber = Text::new(t!(PromptAvsNumber)).prompt()?.try_into()?;
                 let blood_type =
-                    Select::new("Entrez votre groupe sanguin:", BloodType::iter().collect())
-                        .prompt()?;
+                    Select::new(t!(PromptBloodType), BloodType::iter().collect()).prompt()?;
 
                 self.service.update_data(
                     self.user_id,
@@ -155,59 +290,656 @@ impl Menu for UserMenu<'_> {
             }
 
             Choice::AddDoctor => {
-                let username = username_input_validation("Username du médecin: ")?;
+                let username = username_input_validation(t!(PromptDoctorUsername))?;
 
                 if let Some(doctor) = self.service.lookup_user(&username) {
                     self.service.add_doctor(self.user_id, doctor)?;
-                    println!("Ce médecin a maintenant accès a votre dossier");
+                    println!("{}", Msg::DoctorHasAccess);
+                }
+            }
+
+            Choice::ReviewAccessRequests => {
+                #[derive(EnumIter, Display)]
+                enum Decision {
+                    #[display("{}", Msg::DecisionApprove)]
+                    Approve,
+                    #[display("{}", Msg::DecisionDeny)]
+                    Deny,
+                    #[display("{}", Msg::DecisionLater)]
+                    Later,
+                }
+
+                let requests: Vec<AccessRequestItem> = self
+                    .service
+                    .list_access_requests()
+                    .map(|request| AccessRequestItem {
+                        id: request.id,
+                        label: format!(
+                            "{} ({request})",
+                            self.service
+                                .username(request.doctor)
+                                .map(ToString::to_string)
+                                .unwrap_or_else(|| request.doctor.to_string())
+                        ),
+                    })
+                    .collect();
+
+                if requests.is_empty() {
+                    println!("{}", Msg::NoPendingRequests);
+                    return Ok(MENU_LOOP);
+                }
+
+                let Some(request) =
+                    Select::new(t!(PromptChooseRequest), requests).prompt_skippable()?
+                else {
+                    return Ok(MENU_LOOP);
+                };
+
+                match Select::new(t!(PromptDecision), Decision::iter().collect()).prompt()? {
+                    Decision::Approve => {
+                        self.service.review_access_request(request.id, true)?;
+                        println!("{}", Msg::DoctorHasAccess);
+                    }
+                    Decision::Deny => self.service.review_access_request(request.id, false)?,
+                    Decision::Later => {}
                 }
             }
 
+            Choice::RequestAccess => {
+                let patient = self
+                    .service
+                    .lookup_user(&username_input_validation(t!(PromptPatientUsername))?)
+                    .ok_or(anyhow!(t!(UnknownPatient)))?;
+
+                let reason = Text::new(t!(PromptRequestReason)).prompt()?;
+                let scope =
+                    Select::new(t!(PromptRequestScope), AccessScope::iter().collect()).prompt()?;
+
+                self.service.request_access(patient, reason, scope)?;
+                println!("{}", Msg::RequestSent);
+            }
+
             Choice::CheckPatient => {
                 let patients: Vec<&UserData> = self.service.list_patients().collect();
 
-                let patient_id = Select::new("Choisissez un patient:", patients).prompt()?.id;
+                let patient_id = Select::new(t!(PromptChoosePatient), patients).prompt()?.id;
 
-                ReportsMenu {
-                    service: self.service,
-                    patient_id,
-                }
-                .enter_loop()
+                ReportsMenu::new(self.service, patient_id).run()
             }
 
             Choice::AddReport => {
                 let patient = self
                     .service
-                    .lookup_user(&username_input_validation("Username du patient:")?)
-                    .ok_or(anyhow!("Patient inexistant"))?;
+                    .lookup_user(&username_input_validation(t!(PromptPatientUsername))?)
+                    .ok_or(anyhow!(t!(UnknownPatient)))?;
 
-                let title = Text::new("Entrez le titre du rapport:").prompt()?;
+                let title = Text::new(t!(PromptReportTitle)).prompt()?;
 
-                let content = inquire::Editor::new("Enter the report:").prompt()?;
+                let content = inquire::Editor::new(t!(PromptReportContent)).prompt()?;
 
                 self.service
                     .add_report(self.user_id, patient, title, content)?;
             }
 
+            Choice::AddLabResult => {
+                let patient = self
+                    .service
+                    .lookup_user(&username_input_validation(t!(PromptPatientUsername))?)
+                    .ok_or(anyhow!(t!(UnknownPatient)))?;
+
+                let analyte = Text::new(t!(PromptAnalyte)).prompt()?;
+                let value = CustomType::new(t!(PromptLabValue))
+                    .with_error_message(t!(InvalidDecimal))
+                    .prompt()?;
+                let unit = Text::new(t!(PromptLabUnit)).prompt()?;
+                let low = CustomType::new(t!(PromptRangeLow))
+                    .with_error_message(t!(InvalidDecimal))
+                    .prompt_skippable()?;
+                let high = CustomType::new(t!(PromptRangeHigh))
+                    .with_error_message(t!(InvalidDecimal))
+                    .prompt_skippable()?;
+
+                // Les heures sont en UTC, comme partout dans l'application
+                let now = chrono::Utc::now();
+                let day = DateSelect::new(t!(PromptTakenOn))
+                    .with_week_start(chrono::Weekday::Mon)
+                    .prompt()?;
+                let time = Text::new(t!(PromptTakenAt))
+                    .with_default(&now.format("%H:%M").to_string())
+                    .prompt()?;
+                let time = chrono::NaiveTime::parse_from_str(&time, "%H:%M")
+                    .map_err(|_| anyhow!(t!(InvalidTime)))?;
+
+                self.service.add_lab_result(
+                    patient,
+                    LabMeasurement {
+                        analyte,
+                        value,
+                        unit,
+                        range: ReferenceRange { low, high },
+                        taken_at: day.and_time(time).and_utc(),
+                    },
+                )?;
+            }
+
+            Choice::Prescribe => {
+                let patient = self
+                    .service
+                    .lookup_user(&username_input_validation(t!(PromptPatientUsername))?)
+                    .ok_or(anyhow!(t!(UnknownPatient)))?;
+
+                let terms = PrescriptionTerms {
+                    drug: Text::new(t!(PromptDrug)).prompt()?,
+                    dose: Text::new(t!(PromptDose)).prompt()?,
+                    duration_days: CustomType::new(t!(PromptDurationDays))
+                        .with_error_message(t!(InvalidNumber))
+                        .prompt()?,
+                    repeats: CustomType::new(t!(PromptRepeats))
+                        .with_error_message(t!(InvalidNumber))
+                        .with_default(0)
+                        .prompt()?,
+                };
+
+                let (_, code) = self.service.prescribe(patient, terms)?;
+                println!("{}", t!(PrescriptionCode, code));
+            }
+
+            Choice::Prescriptions => {
+                let prescriptions: Vec<PrescriptionItem> = self
+                    .service
+                    .list_prescriptions(self.user_id)
+                    .map(PrescriptionItem::new)
+                    .collect();
+
+                if prescriptions.is_empty() {
+                    println!("{}", Msg::NoPrescriptions);
+                    return Ok(MENU_LOOP);
+                }
+                for prescription in &prescriptions {
+                    println!("{prescription}");
+                }
+
+                // Chaque délivrance demande un nouveau code
+                if let Some(prescription) =
+                    Select::new(t!(PromptNewCode), prescriptions).prompt_skippable()?
+                {
+                    let code = self.service.issue_prescription_code(prescription.id)?;
+                    println!("{}", t!(PrescriptionCode, code));
+                }
+            }
+
+            Choice::Dispense => {
+                let code = Text::new(t!(PromptPrescriptionCode)).prompt()?;
+
+                let prescription = self.service.lookup_prescription(&code)?;
+                println!("{}", PrescriptionItem::new(prescription));
+
+                if Confirm::new(t!(ConfirmDispense)).prompt()? {
+                    self.service.dispense(&code)?;
+                    println!("{}", Msg::Dispensed);
+                }
+            }
+
+            Choice::AccessHistory => {
+                let entries: Vec<AccessLogItem> = self
+                    .service
+                    .access_history(self.user_id)
+                    .map(|entry| AccessLogItem::new(self.service, entry))
+                    .collect();
+
+                if entries.is_empty() {
+                    println!("{}", Msg::NoOtherAccess);
+                    return Ok(MENU_LOOP);
+                }
+                for entry in &entries {
+                    println!("{entry}");
+                }
+
+                if let Some(entry) =
+                    Select::new(t!(PromptDisputeAccess), entries).prompt_skippable()?
+                {
+                    self.service.dispute_access(entry.id)?;
+                    println!("{}", Msg::AccessDisputed);
+                }
+            }
+
+            Choice::ReviewDisputes => {
+                let entries: Vec<AccessLogItem> = self
+                    .service
+                    .list_disputed_accesses()
+                    .map(|entry| AccessLogItem::new(self.service, entry))
+                    .collect();
+
+                if entries.is_empty() {
+                    println!("{}", Msg::NoDisputes);
+                    return Ok(MENU_LOOP);
+                }
+
+                if let Some(entry) =
+                    Select::new(t!(PromptChooseDispute), entries).prompt_skippable()?
+                {
+                    if Confirm::new(t!(ConfirmResolveDispute)).prompt()? {
+                        self.service.resolve_dispute(entry.id)?;
+                    }
+                }
+            }
+
+            Choice::Notifications => {
+                let notifications: Vec<String> = self
+                    .service
+                    .notifications()
+                    .map(|n| format!("{} {n}", if n.read { " " } else { "*" }))
+                    .collect();
+
+                if notifications.is_empty() {
+                    println!("{}", Msg::NoNotifications);
+                }
+                for notification in notifications {
+                    println!("{notification}");
+                }
+                self.service.mark_notifications_read();
+            }
+
+            Choice::NotificationPreferences => {
+                let kinds: Vec<NotificationKind> = NotificationKind::iter().collect();
+                let enabled: Vec<usize> = kinds
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &kind)| self.service.notification_enabled(kind))
+                    .map(|(i, _)| i)
+                    .collect();
+
+                let selected = MultiSelect::new(t!(PromptNotificationKinds), kinds)
+                    .with_default(&enabled)
+                    .prompt()?;
+
+                for kind in NotificationKind::iter() {
+                    self.service
+                        .set_notification_enabled(kind, selected.contains(&kind))?;
+                }
+            }
+
+            Choice::Language => {
+                let languages: Vec<Lang> = Lang::iter().collect();
+                let current = languages.iter().position(|&l| l == i18n::lang());
+                let lang = Select::new(t!(PromptLanguage), languages)
+                    .with_starting_cursor(current.unwrap_or_default())
+                    .prompt()?;
+
+                self.service.set_language(lang)?;
+                i18n::set_session_lang(Some(lang));
+            }
+
             Choice::WipeAccount => {
-                if Confirm::new("VOULEZ-VOUS VRAIMENT EFFACER VOTRE COMPTE ?")
-                    .with_help_message("Si vous effacez votre compte, toutes vos données médicales seront effacées.")
+                if Confirm::new(t!(ConfirmWipe))
+                    .with_help_message(t!(WipeHelp))
                     .prompt()? {
                         self.service.delete_data(self.user_id)?;
                     }
             }
 
+            Choice::CloseAccount => {
+                #[derive(EnumIter, Display)]
+                enum Policy {
+                    #[display("{}", Msg::AuthoredReportsPseudonymize)]
+                    Pseudonymize,
+                    #[display("{}", Msg::AuthoredReportsReassign)]
+                    Reassign,
+                }
+
+                if !Confirm::new(t!(ConfirmCloseAccount))
+                    .with_help_message(t!(CloseAccountHelp))
+                    .prompt()?
+                {
+                    return Ok(MENU_LOOP);
+                }
+
+                let authored =
+                    match Select::new(t!(PromptAuthoredReports), Policy::iter().collect())
+                        .prompt()?
+                    {
+                        Policy::Pseudonymize => AuthoredReports::Pseudonymize,
+                        Policy::Reassign => {
+                            let username = username_input_validation(t!(PromptDoctorUsername))?;
+                            AuthoredReports::Reassign(
+                                self.service
+                                    .lookup_user(&username)
+                                    .ok_or(anyhow!(t!(UnknownUser)))?,
+                            )
+                        }
+                    };
+
+                self.service.close_account(self.user_id, authored)?;
+                println!("{}", Msg::AccountClosed);
+                return Ok(MENU_EXIT);
+            }
+
+            Choice::Proxies => {
+                #[derive(EnumIter, Display)]
+                enum Action {
+                    #[display("{}", Msg::ProxyActionAdd)]
+                    Add,
+                    #[display("{}", Msg::ProxyActionEnd)]
+                    End,
+                }
+
+                let proxies: Vec<ProxyItem> = self
+                    .service
+                    .list_proxies()
+                    .map(|proxy| ProxyItem::new(self.service, proxy))
+                    .collect();
+
+                if proxies.is_empty() {
+                    println!("{}", Msg::NoProxies);
+                }
+                for proxy in &proxies {
+                    println!("{proxy}");
+                }
+
+                let Some(action) =
+                    Select::new(t!(WhatToDo), Action::iter().collect()).prompt_skippable()?
+                else {
+                    return Ok(MENU_LOOP);
+                };
+
+                match action {
+                    Action::Add => {
+                        let kind = Select::new(t!(PromptProxyKind), ProxyKind::iter().collect())
+                            .prompt()?;
+
+                        // Un proche aidant est désigné par la personne elle-même
+                        let dependant = match kind {
+                            ProxyKind::Guardian => self
+                                .service
+                                .lookup_user(&username_input_validation(t!(
+                                    PromptDependantUsername
+                                ))?)
+                                .ok_or(anyhow!(t!(UnknownUser)))?,
+                            ProxyKind::Carer => self.user_id,
+                        };
+                        let proxy = self
+                            .service
+                            .lookup_user(&username_input_validation(t!(PromptProxyUsername))?)
+                            .ok_or(anyhow!(t!(UnknownUser)))?;
+                        let scope = Select::new(t!(PromptProxyScope), ProxyScope::iter().collect())
+                            .prompt()?;
+
+                        // La représentation légale prend fin à la majorité
+                        let (end, birth_date) = match kind {
+                            ProxyKind::Guardian => (
+                                None,
+                                Some(
+                                    DateSelect::new(t!(PromptBirthDate))
+                                        .with_week_start(chrono::Weekday::Mon)
+                                        .prompt()?,
+                                ),
+                            ),
+                            ProxyKind::Carer => (
+                                DateSelect::new(t!(PromptProxyEnd))
+                                    .with_week_start(chrono::Weekday::Mon)
+                                    .prompt_skippable()?,
+                                None,
+                            ),
+                        };
+
+                        self.service.add_proxy(
+                            dependant,
+                            proxy,
+                            ProxyTerms {
+                                kind,
+                                scope,
+                                start: chrono::Utc::now().date_naive(),
+                                end,
+                                birth_date,
+                            },
+                        )?;
+                        println!("{}", Msg::ProxyAdded);
+                    }
+                    Action::End => {
+                        if let Some(proxy) =
+                            Select::new(t!(PromptChooseProxy), proxies).prompt_skippable()?
+                        {
+                            self.service.end_proxy(proxy.id)?;
+                        }
+                    }
+                }
+            }
+
+            Choice::ActForDependant => {
+                let dependants: Vec<UserItem> = self
+                    .service
+                    .dependants()
+                    .into_iter()
+                    .filter_map(|id| {
+                        let username = self.service.username(id)?.to_string();
+                        Some(UserItem { id, username })
+                    })
+                    .collect();
+
+                if dependants.is_empty() {
+                    println!("{}", Msg::NoDependants);
+                    return Ok(MENU_LOOP);
+                }
+
+                let Some(dependant) =
+                    Select::new(t!(PromptChooseDependant), dependants).prompt_skippable()?
+                else {
+                    return Ok(MENU_LOOP);
+                };
+
+                // Chaque action reste autorisée au nom de l'utilisateur
+                // connecté, selon l'étendue de sa représentation
+                println!("{}", t!(ActingFor, dependant.username));
+                UserMenu {
+                    service: self.service,
+                    user_id: dependant.id,
+                }
+                .run();
+            }
+
             Choice::UpdateRole => {
-                let username = username_input_validation("Username à administrer: ")?;
+                let username = username_input_validation(t!(PromptUsernameToManage))?;
 
                 let user_id = self
                     .service
                     .lookup_user(&username)
-                    .ok_or(anyhow!("Utilisateur inconnu"))?;
+                    .ok_or(anyhow!(t!(UnknownUser)))?;
 
-                let role = Select::new("Nouveau rôle", Role::iter().collect()).prompt()?;
+                let role = Select::new(t!(PromptNewRole), Role::iter().collect()).prompt()?;
 
-                self.service.update_role(user_id, role)?;
+                if let RoleUpdate::Pending(_) = self.service.update_role(user_id, role)? {
+                    println!("{}", Msg::RoleChangePending);
+                }
+            }
+
+            Choice::ReviewRoleChanges => {
+                #[derive(EnumIter, Display)]
+                enum Decision {
+                    #[display("{}", Msg::DecisionApprove)]
+                    Approve,
+                    #[display("{}", Msg::DecisionDeny)]
+                    Deny,
+                    #[display("{}", Msg::DecisionLater)]
+                    Later,
+                }
+
+                let name = |user: UserID| {
+                    self.service
+                        .username(user)
+                        .map(ToString::to_string)
+                        .unwrap_or_else(|| user.to_string())
+                };
+                let changes: Vec<RoleChangeItem> = self
+                    .service
+                    .list_role_changes()
+                    .chain(self.service.list_own_role_changes())
+                    .map(|change| RoleChangeItem {
+                        id: change.id,
+                        own: change.requester == self.user_id,
+                        label: t!(
+                            RoleChangeLine,
+                            name(change.requester),
+                            name(change.target),
+                            change.role,
+                            change.expires_at.format("%d.%m.%Y %H:%M")
+                        ),
+                    })
+                    .collect();
+
+                if changes.is_empty() {
+                    println!("{}", Msg::NoPendingRoleChanges);
+                    return Ok(MENU_LOOP);
+                }
+
+                let Some(change) =
+                    Select::new(t!(PromptChooseRoleChange), changes).prompt_skippable()?
+                else {
+                    return Ok(MENU_LOOP);
+                };
+                if change.own {
+                    if Confirm::new(t!(ConfirmCancelChange)).prompt()? {
+                        self.service.cancel_role_change(change.id)?;
+                    }
+                    return Ok(MENU_LOOP);
+                }
+
+                match Select::new(t!(PromptDecision), Decision::iter().collect()).prompt()? {
+                    Decision::Approve => self.service.review_role_change(change.id, true)?,
+                    Decision::Deny => self.service.review_role_change(change.id, false)?,
+                    Decision::Later => {}
+                }
+            }
+
+            Choice::Policy => {
+                #[derive(EnumIter, Display)]
+                enum Action {
+                    #[display("{}", Msg::PolicyActionAdd)]
+                    Add,
+                    #[display("{}", Msg::PolicyActionRemove)]
+                    Remove,
+                    #[display("{}", Msg::PolicyActionReview)]
+                    Review,
+                    #[display("{}", Msg::PolicyActionHistory)]
+                    History,
+                }
+
+                let rules = self.service.policy_rules()?;
+                for rule in &rules {
+                    println!("{rule}");
+                }
+
+                let Some(action) =
+                    Select::new(t!(WhatToDo), Action::iter().collect()).prompt_skippable()?
+                else {
+                    return Ok(MENU_LOOP);
+                };
+
+                // Chaque modification est essayée sur une copie des règles
+                // avant d'être appliquée et enregistrée
+                let change = match action {
+                    Action::Add => {
+                        let action = Select::new(t!(PromptPolicyAction), Request::ACTIONS.to_vec())
+                            .prompt()?;
+                        let rule = Text::new(t!(PromptPolicyRule)).prompt()?;
+                        PolicyChange::Add(PolicyRule {
+                            action: action.to_owned(),
+                            rule,
+                        })
+                    }
+                    Action::Remove => {
+                        match Select::new(t!(PromptChoosePolicyRule), rules).prompt_skippable()? {
+                            Some(rule) => PolicyChange::Remove(rule),
+                            None => return Ok(MENU_LOOP),
+                        }
+                    }
+                    Action::Review => {
+                        #[derive(EnumIter, Display)]
+                        enum Decision {
+                            #[display("{}", Msg::DecisionApprove)]
+                            Approve,
+                            #[display("{}", Msg::DecisionDeny)]
+                            Deny,
+                            #[display("{}", Msg::DecisionLater)]
+                            Later,
+                        }
+
+                        let changes: Vec<PolicyChangeItem> = self
+                            .service
+                            .list_policy_changes()
+                            .chain(self.service.list_own_policy_changes())
+                            .map(|change| PolicyChangeItem {
+                                id: change.id,
+                                own: change.requester == self.user_id,
+                                label: t!(
+                                    PolicyChangeLine,
+                                    self.service
+                                        .username(change.requester)
+                                        .map(ToString::to_string)
+                                        .unwrap_or_else(|| change.requester.to_string()),
+                                    change.change,
+                                    change.expires_at.format("%d.%m.%Y %H:%M")
+                                ),
+                            })
+                            .collect();
+                        if changes.is_empty() {
+                            println!("{}", Msg::NoPendingPolicyChanges);
+                            return Ok(MENU_LOOP);
+                        }
+
+                        let Some(change) = Select::new(t!(PromptChoosePolicyChange), changes)
+                            .prompt_skippable()?
+                        else {
+                            return Ok(MENU_LOOP);
+                        };
+                        if change.own {
+                            if Confirm::new(t!(ConfirmCancelChange)).prompt()? {
+                                self.service.cancel_policy_change(change.id)?;
+                            }
+                            return Ok(MENU_LOOP);
+                        }
+                        match Select::new(t!(PromptDecision), Decision::iter().collect())
+                            .prompt()?
+                        {
+                            Decision::Approve => {
+                                self.service.review_policy_change(change.id, true)?
+                            }
+                            Decision::Deny => {
+                                self.service.review_policy_change(change.id, false)?
+                            }
+                            Decision::Later => {}
+                        }
+                        return Ok(MENU_LOOP);
+                    }
+                    Action::History => {
+                        let mut revisions = self.service.policy_history()?.peekable();
+                        if revisions.peek().is_none() {
+                            println!("{}", Msg::NoPolicyHistory);
+                        }
+                        for revision in revisions {
+                            let author = self
+                                .service
+                                .username(revision.author)
+                                .map(ToString::to_string)
+                                .unwrap_or_else(|| revision.author.to_string());
+                            println!(
+                                "{}",
+                                t!(
+                                    PolicyRevisionLine,
+                                    revision.version,
+                                    revision.at.format("%d.%m.%Y %H:%M"),
+                                    author,
+                                    revision.change
+                                )
+                            );
+                        }
+                        return Ok(MENU_LOOP);
+                    }
+                };
+
+                match self.service.change_policy(change)? {
+                    PolicyUpdate::Applied(version) => println!("{}", t!(PolicyChanged, version)),
+                    PolicyUpdate::Pending(_) => println!("{}", Msg::PolicyChangePending),
+                }
             }
 
             Choice::Logout => return Ok(MENU_EXIT),
@@ -216,12 +948,268 @@ impl Menu for UserMenu<'_> {
     }
 }
 
+/// Une demande d'accès telle qu'affichée au patient
+#[derive(Display)]
+#[display("{label}")]
+struct AccessRequestItem {
+    id: AccessRequestID,
+    label: String,
+}
+
+/// Un changement de rôle tel qu'affiché à l'administrateur qui l'approuve,
+/// ou à son auteur qui peut l'annuler
+#[derive(Display)]
+#[display("{label}")]
+struct RoleChangeItem {
+    id: RoleChangeID,
+    own: bool,
+    label: String,
+}
+
+/// Une modification des règles telle qu'affichée à l'administrateur qui
+/// l'approuve, ou à son auteur qui peut l'annuler
+#[derive(Display)]
+#[display("{label}")]
+struct PolicyChangeItem {
+    id: PolicyChangeID,
+    own: bool,
+    label: String,
+}
+
+/// Une ordonnance telle qu'affichée
+#[derive(Display)]
+#[display("{label}")]
+struct PrescriptionItem {
+    id: PrescriptionID,
+    label: String,
+}
+
+impl PrescriptionItem {
+    fn new(prescription: &Prescription) -> Self {
+        Self {
+            id: prescription.id,
+            label: t!(
+                PrescriptionLine,
+                prescription,
+                prescription.duration_days,
+                prescription.remaining()
+            ),
+        }
+    }
+}
+
+/// Une relation de représentation telle qu'affichée
+#[derive(Display)]
+#[display("{label}")]
+struct ProxyItem {
+    id: ProxyID,
+    label: String,
+}
+
+impl ProxyItem {
+    fn new(service: &Service, proxy: &ProxyRelationship) -> Self {
+        let name = |user: UserID| {
+            service
+                .username(user)
+                .map(ToString::to_string)
+                .unwrap_or_else(|| user.to_string())
+        };
+        let until = proxy
+            .end
+            .map(|end| t!(ProxyUntil, end.format("%d.%m.%Y")))
+            .unwrap_or_default();
+        Self {
+            id: proxy.id,
+            label: t!(
+                ProxyLine,
+                name(proxy.proxy),
+                name(proxy.dependant),
+                proxy.kind,
+                proxy.scope,
+                proxy.start.format("%d.%m.%Y"),
+                until
+            ),
+        }
+    }
+}
+
+/// Un utilisateur tel qu'affiché dans une liste de choix
+#[derive(Display)]
+#[display("{username}")]
+struct UserItem {
+    id: UserID,
+    username: String,
+}
+
+/// Une entrée de l'historique des accès telle qu'affichée
+#[derive(Display)]
+#[display("{label}")]
+struct AccessLogItem {
+    id: AccessLogID,
+    label: String,
+}
+
+impl AccessLogItem {
+    fn new(service: &Service, entry: &AccessLogEntry) -> Self {
+        let accessor = service
+            .username(entry.accessor)
+            .map(ToString::to_string)
+            .unwrap_or_else(|| entry.accessor.to_string());
+        let as_proxy = entry
+            .as_proxy
+            .map(|kind| t!(AccessAsProxy, kind))
+            .unwrap_or_default();
+        Self {
+            id: entry.id,
+            label: format!(
+                "{} {accessor} ({}){as_proxy} - {} {}",
+                entry.at.format("%d.%m.%Y %H:%M"),
+                entry.accessor_role,
+                entry.action,
+                entry.dispute
+            ),
+        }
+    }
+}
+
 struct ReportsMenu<'srv> {
     service: &'srv mut Service,
     patient_id: UserID,
+    query: ReportQuery,
+    page: usize,
+}
+
+/// Une entrée de la liste paginée des rapports
+enum ReportEntry {
+    Report(ReportID, String),
+    PreviousPage,
+    NextPage,
+    EditQuery,
+    ResetQuery,
+    Export,
+    LabResults,
+}
+
+impl std::fmt::Display for ReportEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportEntry::Report(_, label) => write!(f, "{label}"),
+            ReportEntry::PreviousPage => write!(f, "{}", Msg::PreviousPage),
+            ReportEntry::NextPage => write!(f, "{}", Msg::NextPage),
+            ReportEntry::EditQuery => write!(f, "{}", Msg::EditQuery),
+            ReportEntry::ResetQuery => write!(f, "{}", Msg::ResetQuery),
+            ReportEntry::Export => write!(f, "{}", Msg::Export),
+            ReportEntry::LabResults => write!(f, "{}", Msg::LabResults),
+        }
+    }
+}
+
+/// Ce qu'un export contient: tout le dossier ou un seul rapport
+#[derive(Display)]
+enum ExportTarget {
+    #[display("{}", Msg::WholeFolder)]
+    WholeFolder,
+    #[display("{_1}")]
+    Report(ReportID, String),
+}
+
+impl<'srv> ReportsMenu<'srv> {
+    fn new(service: &'srv mut Service, patient_id: UserID) -> Self {
+        Self {
+            service,
+            patient_id,
+            query: ReportQuery::default(),
+            page: 0,
+        }
+    }
+
+    fn is_filtered(&self) -> bool {
+        let ReportQuery {
+            author,
+            from,
+            to,
+            keywords,
+            ..
+        } = &self.query;
+        author.is_some() || from.is_some() || to.is_some() || !keywords.is_empty()
+    }
+
+    /// Demande les critères de tri et de filtrage des rapports
+    fn edit_query(&mut self) -> Result<()> {
+        self.query.sort = Select::new(t!(PromptSortBy), ReportSort::iter().collect()).prompt()?;
+
+        let author = Text::new(t!(PromptAuthorFilter)).prompt()?;
+        self.query.author = match author.trim() {
+            "" => None,
+            name => Some(
+                self.service
+                    .lookup_user(&name.try_into()?)
+                    .ok_or(anyhow!(t!(UnknownUser)))?,
+            ),
+        };
+
+        self.query.from = DateSelect::new(t!(PromptFromDate))
+            .with_week_start(chrono::Weekday::Mon)
+            .prompt_skippable()?;
+        self.query.to = DateSelect::new(t!(PromptToDate))
+            .with_week_start(chrono::Weekday::Mon)
+            .prompt_skippable()?;
+
+        self.query.keywords = Text::new(t!(PromptKeywords))
+            .prompt()?
+            .split_whitespace()
+            .map(str::to_owned)
+            .collect();
+
+        self.page = 0;
+        Ok(())
+    }
+
+    /// Exporte un rapport ou l'ensemble du dossier dans un fichier
+    fn export(&mut self, reports: Vec<ExportTarget>) -> Result<()> {
+        let mut targets = vec![ExportTarget::WholeFolder];
+        targets.extend(reports);
+        let target = Select::new(t!(PromptExportTarget), targets).prompt()?;
+        let report = match target {
+            ExportTarget::Report(id, _) => Some(id),
+            ExportTarget::WholeFolder => None,
+        };
+
+        let format =
+            Select::new(t!(PromptExportFormat), ExportFormat::iter().collect()).prompt()?;
+        let document = self.service.export_reports(self.patient_id, report)?;
+
+        let default_path = format!(
+            "{}_{}.{}",
+            document.patient_name,
+            document.generated_at.format("%Y%m%d_%H%M%S"),
+            format.extension()
+        );
+        let path = Text::new(t!(PromptExportPath))
+            .with_default(&default_path)
+            .prompt()?;
+
+        std::fs::write(&path, document.render(format))?;
+        println!("{}", t!(ExportDone, document.reports.len(), path));
+        Ok(())
+    }
 }
 
 impl ReportsMenu<'_> {
+    /// Affiche l'évolution d'une analyse choisie parmi celles du dossier
+    fn show_lab_trend(&mut self) -> Result<()> {
+        let analytes = self.service.lab_analytes(self.patient_id);
+        let Some(analyte) = Select::new(t!(PromptAnalyteTrend), analytes).prompt_skippable()?
+        else {
+            return Ok(());
+        };
+
+        let results = self.service.read_lab_results(self.patient_id, &analyte)?;
+        println!("{}", t!(LabTrendHeader, analyte, results.len()));
+        println!("{}", lab::trend(&results));
+        Ok(())
+    }
+
     fn show(&mut self) -> Result<()> {
         if let Ok(user) = self.service.get_data(self.patient_id) {
             let UserData {
@@ -231,54 +1219,482 @@ impl ReportsMenu<'_> {
                 ..
             } = user;
             let has_data = if medical_folder.is_some() {
-                "oui"
+                Msg::Yes
             } else {
-                "non"
+                Msg::No
             };
-            println!("User: {username}\nRole: {role}\nDossier électronique: {has_data}");
+            println!("{}", t!(FolderSummary, username, role, has_data));
 
             if let Some(folder) = medical_folder {
                 let PersonalData {
                     avs_number,
                     blood_type,
                 } = &folder.personal_data;
-                println!("Numéro AVS: {avs_number}\nGroupe sanguin: {blood_type}");
+                println!("{}", t!(PersonalDataSummary, avs_number, blood_type));
             }
         } else {
-            println!("[!] L'accès à ce dossier est restreint")
+            println!("{}", Msg::FolderRestricted)
         }
 
-        Ok(self.enter_loop())
+        self.run();
+        Ok(())
     }
 }
 
 impl Menu for ReportsMenu<'_> {
     fn enter(&mut self) -> Result<Option<()>> {
-        let reports: Vec<&MedicalReport> = self.service.list_reports(self.patient_id).collect();
+        let reports: Vec<&MedicalReport> = self.service.query_reports(self.patient_id, &self.query);
+        let has_lab_results = !self.service.lab_analytes(self.patient_id).is_empty();
 
-        if reports.is_empty() {
-            println!("[*] Il n'y a pas de rapports dans ce dossier");
+        if reports.is_empty() && !self.is_filtered() && !has_lab_results {
+            println!("{}", Msg::NoReports);
             return Ok(MENU_EXIT);
         }
 
-        let Some(report) = Select::new("Choisissez un rapport:", reports).prompt_skippable()?
+        let total = reports.len();
+        let pages = total.div_ceil(REPORTS_PER_PAGE).max(1);
+        self.page = self.page.min(pages - 1);
+
+        let mut entries: Vec<ReportEntry> = reports
+            .iter()
+            .skip(self.page * REPORTS_PER_PAGE)
+            .take(REPORTS_PER_PAGE)
+            .map(|report| ReportEntry::Report(report.id, report.to_string()))
+            .collect();
+        if self.page + 1 < pages {
+            entries.push(ReportEntry::NextPage);
+        }
+        if self.page > 0 {
+            entries.push(ReportEntry::PreviousPage);
+        }
+        entries.push(ReportEntry::EditQuery);
+        if self.is_filtered() {
+            entries.push(ReportEntry::ResetQuery);
+        }
+        entries.push(ReportEntry::Export);
+        if has_lab_results {
+            entries.push(ReportEntry::LabResults);
+        }
+
+        let prompt = t!(PromptChooseReport, total, self.page + 1, pages);
+        let page_size = entries.len();
+        let Some(entry) = Select::new(&prompt, entries)
+            .with_page_size(page_size)
+            .prompt_skippable()?
         else {
             return Ok(MENU_EXIT);
         };
 
+        let report_id = match entry {
+            ReportEntry::Report(id, _) => id,
+            ReportEntry::NextPage => {
+                self.page += 1;
+                return Ok(MENU_LOOP);
+            }
+            ReportEntry::PreviousPage => {
+                self.page -= 1;
+                return Ok(MENU_LOOP);
+            }
+            ReportEntry::EditQuery => {
+                self.edit_query()?;
+                return Ok(MENU_LOOP);
+            }
+            ReportEntry::ResetQuery => {
+                self.query = ReportQuery {
+                    sort: self.query.sort,
+                    ..Default::default()
+                };
+                self.page = 0;
+                return Ok(MENU_LOOP);
+            }
+            ReportEntry::LabResults => {
+                self.show_lab_trend()?;
+                return Ok(MENU_LOOP);
+            }
+            ReportEntry::Export => {
+                let reports = self
+                    .service
+                    .query_reports(self.patient_id, &self.query)
+                    .iter()
+                    .map(|report| ExportTarget::Report(report.id, report.to_string()))
+                    .collect();
+                self.export(reports)?;
+                return Ok(MENU_LOOP);
+            }
+        };
+        let report = self.service.read_report(report_id)?;
+
         println!(
-            "\n[{}]\nTitre: {}\nAuteur: {}\n\n{}\n===============",
-            report.id, report.title, report.author, report.content
+            "{}",
+            t!(
+                ReportView,
+                report.id,
+                report.title,
+                report.author,
+                report.created_at.format("%d.%m.%Y %H:%M"),
+                report.updated_at.format("%d.%m.%Y %H:%M"),
+                report.content
+            )
         );
 
         Ok(MENU_LOOP)
     }
 }
 
+/// Crée les administrateurs initiaux d'une installation vierge
+fn init(mut service: Service) -> Result<()> {
+    if !service.needs_bootstrap() {
+        return Err(anyhow!(t!(InitAdminExists)));
+    }
+
+    println!("{}", Msg::InitTitle);
+    let mut admins = Vec::new();
+    for _ in 0..2 {
+        let username = username_input_validation(t!(PromptAdminUsername))?;
+        let password = password_input_validation(username.as_ref());
+        admins.push((username, password));
+    }
+
+    let admins: Vec<_> = admins
+        .iter()
+        .map(|(username, password)| (username.clone(), password.as_str()))
+        .collect();
+    service.bootstrap_admins(&admins)?;
+    service.save()?;
+    println!("{}", Msg::InitDone);
+    Ok(())
+}
+
+/// Connexion préalable aux commandes réservées aux administrateurs. Le rôle
+/// est vérifié ensuite par le service.
+fn admin_login(service: &mut Service, notice: Msg) -> Result<()> {
+    println!("{notice}");
+    let username = username_input_validation(t!(PromptUsername))?;
+    let password = Password::new(t!(PromptPassword))
+        .without_confirmation()
+        .with_display_mode(inquire::PasswordDisplayMode::Masked)
+        .prompt()?;
+    service.login(&username, &password)?;
+    Ok(())
+}
+
+/// Importe des comptes depuis un fichier CSV, après connexion d'un administrateur
+fn import(mut service: Service, file: PathBuf, dry_run: bool, credentials: PathBuf) -> Result<()> {
+    admin_login(&mut service, Msg::ImportAdminLogin)?;
+
+    let report = service.import_users(File::open(&file)?, dry_run)?;
+    for error in &report.errors {
+        eprintln!("{error}");
+    }
+    println!("{}", t!(ImportSummary, report.rows, report.errors.len()));
+
+    if !report.errors.is_empty() {
+        return Err(anyhow!(t!(ImportAborted)));
+    }
+    if dry_run {
+        println!("{}", Msg::ImportDryRunOk);
+        return Ok(());
+    }
+
+    // Les mots de passe sont écrits avant la sauvegarde: si l'écriture échoue,
+    // les comptes ne sont pas créés.
+    create_private(&credentials)?.write_all(&report.credentials_csv()?)?;
+
+    service.save()?;
+    println!(
+        "{}",
+        t!(ImportDone, report.accounts.len(), credentials.display())
+    );
+    let pending = report
+        .accounts
+        .iter()
+        .filter(|account| account.pending_role.is_some())
+        .count();
+    if pending > 0 {
+        println!("{}", t!(ImportRolesPending, pending));
+    }
+    Ok(())
+}
+
+/// Vérifie la cohérence de la base et, si demandé, la répare après en avoir
+/// fait une copie
+fn fsck(mut service: Service, fix: bool, interactive: bool) -> Result<()> {
+    admin_login(&mut service, Msg::FsckAdminLogin)?;
+
+    let issues = service.check_integrity()?;
+    if issues.is_empty() {
+        println!("{}", Msg::FsckClean);
+        return Ok(());
+    }
+    for issue in &issues {
+        println!("[!] {issue}");
+    }
+    if !fix && !interactive {
+        println!("{}", t!(FsckSummary, issues.len()));
+        return Ok(());
+    }
+
+    let backup = service.backup_database()?;
+    println!("{}", t!(FsckBackup, backup.display()));
+
+    let mut repaired = 0;
+    for issue in &issues {
+        if interactive
+            && !Confirm::new(&t!(FsckConfirmRepair, issue, issue.repair_description())).prompt()?
+        {
+            continue;
+        }
+        for (user, new_name) in service.repair(issue)? {
+            println!("{}", t!(FsckRenamed, user, new_name));
+        }
+        repaired += 1;
+    }
+    service.save()?;
+
+    println!("{}", t!(FsckRepaired, repaired, issues.len()));
+    Ok(())
+}
+
+/// Crée une clé de signature des règles, les signe ou vérifie leur signature
+fn policy(command: PolicyCommand, source: &PolicySource) -> Result<()> {
+    match command {
+        PolicyCommand::Keygen { key: path } => {
+            let key = PolicyKey::generate();
+            create_private(&path)?.write_all(key.to_hex().as_bytes())?;
+            println!("{}", t!(PolicyKeyCreated, path.display(), key.public_key()));
+        }
+        PolicyCommand::Sign { key } => {
+            let key = PolicyKey::from_hex(&fs::read_to_string(key)?)?;
+            let path = source
+                .signature()
+                .ok_or_else(|| anyhow!(t!(ErrPolicyBuiltin)))?;
+            let (model, policy) = source.read()?;
+            fs::write(&path, key.sign(&model, &policy))?;
+            println!("{}", t!(PolicySigned, path.display()));
+        }
+        PolicyCommand::Verify => {
+            if source.uses_builtin() {
+                eprintln!("{}", Msg::WarnBuiltinPolicy);
+            }
+            CasbinBackend::from_source(source)?;
+            println!("{}", Msg::PolicyVerified);
+        }
+    }
+    Ok(())
+}
+
+/// Exporte un jeu de données désidentifié pour la recherche, avec le résumé
+/// de ce qui en a été retiré
+fn research(mut service: Service, args: ResearchArgs) -> Result<()> {
+    admin_login(&mut service, Msg::ResearchAdminLogin)?;
+
+    let (key, new_key) = match fs::read_to_string(&args.key) {
+        Ok(hex) => (
+            PseudonymKey::from_hex(&hex)
+                .map_err(|_| anyhow!(t!(ResearchInvalidKey, args.key.display())))?,
+            false,
+        ),
+        Err(e) if e.kind() == ErrorKind::NotFound => (PseudonymKey::generate(), true),
+        Err(e) => return Err(e.into()),
+    };
+
+    let dataset = service.research_export(&key, args.k as usize)?;
+
+    // La clé n'est enregistrée qu'une fois l'export autorisé
+    if new_key {
+        create_private(&args.key)?.write_all(key.to_hex().as_bytes())?;
+        println!("{}", t!(ResearchKeyCreated, args.key.display()));
+    }
+    fs::write(&args.output, dataset.render(args.format)?)?;
+    fs::write(&args.summary, dataset.summary_json())?;
+
+    let summary = &dataset.summary;
+    println!(
+        "{}",
+        t!(
+            ResearchSummary,
+            summary.patients,
+            summary.exported,
+            summary.suppressed,
+            summary.suppressed_groups.len(),
+            summary.k
+        )
+    );
+    println!(
+        "{}",
+        t!(ResearchDone, args.output.display(), args.summary.display())
+    );
+    Ok(())
+}
+
+/// Ouvre la base, en affichant ses enregistrements invalides
+fn open_database(path: PathBuf, avs_key: &Path, quarantine: bool) -> Result<Database> {
+    let avs_key = HashKey::load_or_create(avs_key)?;
+    let invalid_records = if quarantine {
+        InvalidRecords::Quarantine
+    } else {
+        InvalidRecords::Reject
+    };
+
+    let print_invalid = |report: &LoadReport| {
+        for record in &report.invalid {
+            eprintln!(
+                "{}",
+                t!(
+                    LoadInvalidRecord,
+                    record.collection,
+                    record.key,
+                    record.error
+                )
+            );
+        }
+    };
+
+    match Database::open_with(path, &avs_key, invalid_records) {
+        Ok((db, report)) => {
+            if let Some(path) = &report.quarantine {
+                print_invalid(&report);
+                eprintln!(
+                    "{}",
+                    t!(LoadQuarantined, report.invalid.len(), path.display())
+                );
+            }
+            Ok(db)
+        }
+        Err(OpenError::InvalidRecords(report)) => {
+            print_invalid(&report);
+            Err(OpenError::InvalidRecords(report).into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(Parser)]
+#[command(version, about = "KARAK, le dossier électronique du patient")]
+struct Cli {
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// Met de côté les enregistrements invalides de la base au lieu de
+    /// refuser de l'ouvrir
+    #[arg(long, global = true)]
+    quarantine: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Crée le premier administrateur (une seule fois)
+    Init,
+
+    /// Importe des médecins et des patients depuis un fichier CSV
+    /// (colonnes: username, role, avs_number, blood_type, doctor)
+    Import {
+        file: PathBuf,
+
+        /// Vérifie le fichier sans créer de compte
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Fichier où écrire les mots de passe initiaux
+        #[arg(long, default_value = "credentials.csv")]
+        credentials: PathBuf,
+    },
+
+    /// Exporte des données désidentifiées pour la recherche
+    Research(ResearchArgs),
+
+    /// Vérifie la cohérence de la base
+    Fsck {
+        /// Répare toutes les incohérences, après une copie de la base
+        #[arg(long)]
+        fix: bool,
+
+        /// Demande avant chaque réparation, après une copie de la base
+        #[arg(long, conflicts_with = "fix")]
+        interactive: bool,
+    },
+
+    /// Signe les règles d'accès configurées (--model, --policy), hors ligne
+    #[command(subcommand)]
+    Policy(PolicyCommand),
+}
+
+#[derive(Subcommand)]
+enum PolicyCommand {
+    /// Crée une clé de signature et affiche sa clé publique
+    Keygen {
+        /// Fichier où écrire la clé secrète
+        #[arg(default_value = "policy.key")]
+        key: PathBuf,
+    },
+
+    /// Signe le modèle et les règles
+    Sign {
+        /// Fichier de la clé secrète
+        #[arg(default_value = "policy.key")]
+        key: PathBuf,
+    },
+
+    /// Vérifie la signature du modèle et des règles avec la clé configurée
+    Verify,
+}
+
+#[derive(Args)]
+struct ResearchArgs {
+    /// Fichier du jeu de données
+    #[arg(long, default_value = "research.csv")]
+    output: PathBuf,
+
+    /// Format du jeu de données (csv ou json)
+    #[arg(long, default_value = "csv")]
+    format: ResearchFormat,
+
+    /// Taille minimale de chaque groupe de quasi-identifiants exporté
+    #[arg(short, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    k: u32,
+
+    /// Clé de pseudonymisation, créée au premier export
+    #[arg(long, default_value = "research.key")]
+    key: PathBuf,
+
+    /// Fichier du résumé des données retirées
+    #[arg(long, default_value = "research-summary.json")]
+    summary: PathBuf,
+}
+
 fn main() -> anyhow::Result<()> {
-    simple_logging::log_to_file("./karak.log", log::LevelFilter::Info)?;
+    let cli = Cli::parse();
+    let config = Config::load(cli.config)?;
+    i18n::set_default_lang(config.lang);
+    config.create_dirs()?;
+    logging::init(config.log.clone())?;
+    config.warn_on_start();
 
-    let db = Database::open(DB_FILE.into())?;
-    let enforcer = Enforcer::load()?;
-    App::new(Service::new(db, enforcer)).start()
+    let service = || -> Result<Service> {
+        let db = open_database(config.database.clone(), &config.avs_key, cli.quarantine)?;
+        if config.authorization == BackendKind::Casbin && config.policy.uses_builtin() {
+            eprintln!("{}", Msg::WarnBuiltinPolicy);
+        }
+        let enforcer = Enforcer::with_backend(config.authorization, &config.policy)?;
+        if enforcer.is_locked() {
+            eprintln!("{}", Msg::WarnPolicyLocked);
+        }
+        Ok(Service::new(db, enforcer))
+    };
+
+    match cli.command {
+        Some(Command::Init) => init(service()?),
+        Some(Command::Import {
+            file,
+            dry_run,
+            credentials,
+        }) => import(service()?, file, dry_run, credentials),
+        Some(Command::Research(args)) => research(service()?, args),
+        Some(Command::Fsck { fix, interactive }) => fsck(service()?, fix, interactive),
+        Some(Command::Policy(command)) => policy(command, &config.policy),
+        None => App::new(service()?).start(),
+    }
 }
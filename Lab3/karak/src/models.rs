@@ -1,25 +1,79 @@
 //! Modèle de données
 
 use std::collections::BTreeSet;
+use std::str::FromStr;
 
+use chrono::{DateTime, Months, NaiveDate, Utc};
 use derive_more::Display;
 use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use uuid::Uuid;
 
-use crate::utils::input_validation::{AVSNumber, Username};
+use crate::i18n::Msg;
+use crate::t;
+use crate::utils::input_validation::{AVSNumber, InvalidInput, Username};
 use crate::utils::password_utils::PWHash;
 
-/// Role d'un utilisateur: Médecin, Patient ou Admin
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, Hash, EnumIter, Display)]
+/// Role d'un utilisateur: Médecin, Patient, Pharmacien ou Admin
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumIter, Display)]
 pub enum Role {
+    #[display("{}", Msg::RoleDoctor)]
     Doctor,
+    #[display("{}", Msg::RolePatient)]
     Patient,
+    #[display("{}", Msg::RoleAdmin)]
     Admin,
+    #[display("{}", Msg::RolePharmacist)]
+    Pharmacist,
+}
+
+impl Role {
+    /// Vrai pour les rôles qui donnent accès aux dossiers d'autres personnes
+    /// ou à l'administration: les donner demande l'approbation d'un
+    /// deuxième administrateur
+    pub fn is_privileged(self) -> bool {
+        matches!(self, Role::Admin | Role::Doctor)
+    }
+
+    /// Vrai si passer de ce rôle au nouveau demande l'approbation d'un
+    /// deuxième administrateur: donner un rôle privilégié, ou retirer le
+    /// sien à un administrateur. Un compte créé part du rôle `Patient`.
+    pub fn change_needs_approval(self, new_role: Role) -> bool {
+        new_role.is_privileged() || self == Role::Admin
+    }
+}
+
+impl FromStr for Role {
+    type Err = InvalidInput;
+
+    /// Accepte le nom anglais du rôle, sans tenir compte de la casse
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "doctor" => Ok(Role::Doctor),
+            "patient" => Ok(Role::Patient),
+            "admin" => Ok(Role::Admin),
+            "pharmacist" => Ok(Role::Pharmacist),
+            _ => Err(InvalidInput),
+        }
+    }
 }
 
 /// Un groupe sanguin dans le système ABO
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, Hash, EnumIter, Display)]
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    EnumIter,
+    Display,
+)]
 pub enum BloodType {
     A,
     AB,
@@ -27,6 +81,16 @@ pub enum BloodType {
     O,
 }
 
+impl FromStr for BloodType {
+    type Err = InvalidInput;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        BloodType::iter()
+            .find(|blood_type| blood_type.to_string().eq_ignore_ascii_case(value))
+            .ok_or(InvalidInput)
+    }
+}
+
 /// Un identifiant unique d'utilisateur.
 #[derive(
     Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Display,
@@ -39,6 +103,12 @@ impl UserID {
     }
 }
 
+impl Default for UserID {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Un identifiant unique de rapport médical
 #[derive(
     Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Display,
@@ -51,6 +121,30 @@ impl ReportID {
     }
 }
 
+impl Default for ReportID {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Un identifiant unique de demande d'accès
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Display,
+)]
+pub struct AccessRequestID(Uuid);
+
+impl AccessRequestID {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for AccessRequestID {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Les données associées à un utilisateur.
 ///
 /// Un utilisateur peut être un médecin ou un simple patient.
@@ -75,17 +169,34 @@ impl UserData {
             .map(|folder| folder.doctors.contains(&doctor))
             .unwrap_or(false)
     }
+
+    /// Vrai si le médecin a accès aux rapports du patient, que ce soit
+    /// en tant que médecin traitant ou avec un accès restreint aux rapports
+    pub fn has_reader(&self, doctor: UserID) -> bool {
+        self.medical_folder
+            .as_ref()
+            .map(|folder| {
+                folder.doctors.contains(&doctor) || folder.report_readers.contains(&doctor)
+            })
+            .unwrap_or(false)
+    }
 }
 
 /// Le contenu d'un rapport médical
 #[derive(Debug, Serialize, Deserialize, Hash, Display)]
-#[display("{title}")]
+#[display("{title} ({})", created_at.format("%d.%m.%Y"))]
 pub struct MedicalReport {
     pub id: ReportID,
     pub title: String,
     pub author: UserID,
     pub patient: UserID,
     pub content: String,
+    /// Les rapports antérieurs à l'horodatage sont migrés à l'ouverture
+    /// de la base (voir `Database::open`)
+    #[serde(default)]
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub updated_at: DateTime<Utc>,
 }
 
 /// Les données personnelles d'un patient
@@ -97,12 +208,15 @@ pub struct PersonalData {
 
 /// Un dossier médical pour un patient donné.
 /// Contient des données personnelles génériques,
-/// une liste de rapports, et une liste
-/// de médecins traitants autorisés.
+/// une liste de rapports, une liste
+/// de médecins traitants autorisés, et une liste de médecins
+/// n'ayant accès qu'aux rapports.
 #[derive(Debug, Serialize, Deserialize, Hash)]
 pub struct MedicalFolder {
     pub personal_data: PersonalData,
     pub doctors: BTreeSet<UserID>,
+    #[serde(default)]
+    pub report_readers: BTreeSet<UserID>,
 }
 
 impl MedicalFolder {
@@ -110,6 +224,538 @@ impl MedicalFolder {
         Self {
             personal_data,
             doctors: BTreeSet::default(),
+            report_readers: BTreeSet::default(),
+        }
+    }
+
+    /// Accorde à un médecin l'accès correspondant à l'étendue donnée
+    pub fn grant(&mut self, doctor: UserID, scope: AccessScope) {
+        match scope {
+            AccessScope::Folder => {
+                self.report_readers.remove(&doctor);
+                self.doctors.insert(doctor);
+            }
+            AccessScope::Reports => {
+                if !self.doctors.contains(&doctor) {
+                    self.report_readers.insert(doctor);
+                }
+            }
         }
     }
+
+    /// Retire tout accès au dossier à un médecin
+    pub fn revoke(&mut self, doctor: UserID) {
+        self.doctors.remove(&doctor);
+        self.report_readers.remove(&doctor);
+    }
+}
+
+/// L'étendue d'un accès demandé par un médecin
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumIter, Display)]
+pub enum AccessScope {
+    /// Données personnelles et rapports, comme un médecin traitant
+    #[display("{}", Msg::ScopeFolder)]
+    Folder,
+    /// Rapports médicaux uniquement
+    #[display("{}", Msg::ScopeReports)]
+    Reports,
+}
+
+/// L'état d'une demande d'accès
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum RequestStatus {
+    #[display("{}", Msg::StatusPending)]
+    Pending,
+    #[display("{}", Msg::StatusApproved)]
+    Approved,
+    #[display("{}", Msg::StatusDenied)]
+    Denied,
+}
+
+/// Une demande d'accès au dossier d'un patient, déposée par un médecin
+/// et soumise à l'approbation du patient.
+#[derive(Debug, Serialize, Deserialize, Hash, Display)]
+#[display("{scope}: {reason}")]
+pub struct AccessRequest {
+    pub id: AccessRequestID,
+    pub doctor: UserID,
+    pub patient: UserID,
+    pub reason: String,
+    pub scope: AccessScope,
+    pub status: RequestStatus,
+}
+
+/// Un identifiant unique de notification
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Display,
+)]
+pub struct NotificationID(Uuid);
+
+impl NotificationID {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for NotificationID {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Les évènements pouvant donner lieu à une notification
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    EnumIter,
+    Display,
+)]
+pub enum NotificationKind {
+    #[display("{}", Msg::KindReportAdded)]
+    ReportAdded,
+    #[display("{}", Msg::KindReportUpdated)]
+    ReportUpdated,
+    #[display("{}", Msg::KindDoctorAdded)]
+    DoctorAdded,
+    #[display("{}", Msg::KindRoleChanged)]
+    RoleChanged,
+    #[display("{}", Msg::KindDataRead)]
+    DataRead,
+    #[display("{}", Msg::KindDataDeleted)]
+    DataDeleted,
+    #[display("{}", Msg::KindProxyChanged)]
+    ProxyChanged,
+    #[display("{}", Msg::KindPrescription)]
+    Prescription,
+}
+
+/// Une notification adressée à un utilisateur
+#[derive(Debug, Serialize, Deserialize, Hash, Display)]
+#[display("{} [{kind}] {message}", created_at.format("%d.%m.%Y %H:%M"))]
+pub struct Notification {
+    pub id: NotificationID,
+    pub recipient: UserID,
+    pub kind: NotificationKind,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+    pub read: bool,
+}
+
+/// Un identifiant unique d'entrée de l'historique des accès
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Display,
+)]
+pub struct AccessLogID(Uuid);
+
+impl AccessLogID {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for AccessLogID {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Une lecture de données médicales enregistrée dans l'historique
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum AccessAction {
+    #[display("{}", Msg::ActionReadData)]
+    ReadData,
+    #[display("{}", t!(ActionReadReport, _0))]
+    ReadReport(ReportID),
+    #[display("{}", Msg::ActionRevealAvs)]
+    RevealAvs,
+    #[display("{}", Msg::ActionUpdateData)]
+    UpdateData,
+    #[display("{}", t!(ActionAddDoctor, _0))]
+    AddDoctor(UserID),
+    #[display("{}", t!(ActionRemoveDoctor, _0))]
+    RemoveDoctor(UserID),
+    #[display("{}", t!(ActionReviewAccess, _0))]
+    ReviewAccess(AccessRequestID),
+    #[display("{}", t!(ActionDispense, _0))]
+    Dispense(PrescriptionID),
+    #[display("{}", t!(ActionReadLabResult, _0))]
+    ReadLabResult(LabResultID),
+}
+
+/// L'état de contestation d'un accès par le patient
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum DisputeStatus {
+    #[display("")]
+    Undisputed,
+    #[display("{}", Msg::DisputeDisputed)]
+    Disputed,
+    #[display("{}", Msg::DisputeReviewed)]
+    Reviewed,
+}
+
+/// Un accès d'un tiers aux données médicales d'un patient
+#[derive(Debug, Serialize, Deserialize, Hash)]
+pub struct AccessLogEntry {
+    pub id: AccessLogID,
+    pub patient: UserID,
+    pub accessor: UserID,
+    /// Rôle de l'accédant au moment de l'accès
+    pub accessor_role: Role,
+    pub action: AccessAction,
+    pub at: DateTime<Utc>,
+    pub dispute: DisputeStatus,
+    /// Présent si l'accédant agissait en tant que représentant du patient
+    #[serde(default)]
+    pub as_proxy: Option<ProxyKind>,
+}
+
+/// Un identifiant unique de relation de représentation
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Display,
+)]
+pub struct ProxyID(Uuid);
+
+impl ProxyID {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for ProxyID {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Le type d'une relation de représentation
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumIter, Display)]
+pub enum ProxyKind {
+    /// Le représentant légal d'un mineur, jusqu'à sa majorité
+    #[display("{}", Msg::ProxyGuardian)]
+    Guardian,
+    /// Un proche aidant, désigné par la personne ou par un administrateur
+    #[display("{}", Msg::ProxyCarer)]
+    Carer,
+}
+
+/// Ce qu'un représentant peut faire au nom de la personne représentée
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumIter, Display)]
+pub enum ProxyScope {
+    /// Consulter le dossier, les rapports et l'historique des accès
+    #[display("{}", Msg::ProxyScopeRead)]
+    Read,
+    /// Consulter, et aussi modifier le dossier, choisir les médecins et
+    /// répondre aux demandes d'accès
+    #[display("{}", Msg::ProxyScopeManage)]
+    Manage,
+}
+
+/// Une relation de représentation: `proxy` agit au nom de `dependant` du
+/// jour `start` inclus au jour `end` exclu
+#[derive(Debug, Serialize, Deserialize, Clone, Hash)]
+pub struct ProxyRelationship {
+    pub id: ProxyID,
+    pub proxy: UserID,
+    pub dependant: UserID,
+    pub kind: ProxyKind,
+    pub scope: ProxyScope,
+    pub start: NaiveDate,
+    pub end: Option<NaiveDate>,
+}
+
+impl ProxyRelationship {
+    /// Vrai si la relation est en vigueur ce jour-là: elle prend fin
+    /// d'elle-même à sa date de fin
+    pub fn is_active(&self, day: NaiveDate) -> bool {
+        self.start <= day && self.end.is_none_or(|end| day < end)
+    }
+}
+
+/// Le jour des 18 ans d'une personne, où prend fin la représentation légale
+pub fn majority_date(birth_date: NaiveDate) -> NaiveDate {
+    birth_date
+        .checked_add_months(Months::new(18 * 12))
+        .unwrap_or(NaiveDate::MAX)
+}
+
+/// Un identifiant unique d'ordonnance
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Display,
+)]
+pub struct PrescriptionID(Uuid);
+
+impl PrescriptionID {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for PrescriptionID {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Une ordonnance électronique. Le code à usage unique que le patient
+/// présente en pharmacie n'en fait pas partie: la base n'en garde que
+/// l'empreinte (voir `Database::set_prescription_code`).
+#[derive(Debug, Serialize, Deserialize, Clone, Hash, Display)]
+#[display("{drug}, {dose} ({})", issued_at.format("%d.%m.%Y"))]
+pub struct Prescription {
+    pub id: PrescriptionID,
+    pub patient: UserID,
+    pub prescriber: UserID,
+    pub drug: String,
+    pub dose: String,
+    /// Durée du traitement, en jours
+    pub duration_days: u32,
+    /// Nombre de renouvellements après la première délivrance
+    pub repeats: u32,
+    pub issued_at: DateTime<Utc>,
+    #[serde(default)]
+    pub dispensings: Vec<Dispensing>,
+}
+
+impl Prescription {
+    /// Le nombre de délivrances encore possibles
+    pub fn remaining(&self) -> u32 {
+        (self.repeats + 1).saturating_sub(self.dispensings.len() as u32)
+    }
+}
+
+/// Une délivrance d'ordonnance en pharmacie
+#[derive(Debug, Serialize, Deserialize, Clone, Hash)]
+pub struct Dispensing {
+    pub pharmacist: UserID,
+    pub at: DateTime<Utc>,
+}
+
+/// Un identifiant unique de résultat de laboratoire
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Display,
+)]
+pub struct LabResultID(Uuid);
+
+impl LabResultID {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for LabResultID {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Un intervalle de référence, bornes comprises. Une borne absente n'est
+/// pas vérifiée.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct ReferenceRange {
+    pub low: Option<f64>,
+    pub high: Option<f64>,
+}
+
+impl ReferenceRange {
+    pub fn flag(&self, value: f64) -> LabFlag {
+        match (self.low, self.high) {
+            (Some(low), _) if value < low => LabFlag::Low,
+            (_, Some(high)) if value > high => LabFlag::High,
+            _ => LabFlag::Normal,
+        }
+    }
+}
+
+impl std::fmt::Display for ReferenceRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.low, self.high) {
+            (Some(low), Some(high)) => write!(f, "{low}-{high}"),
+            (Some(low), None) => write!(f, ">= {low}"),
+            (None, Some(high)) => write!(f, "<= {high}"),
+            (None, None) => write!(f, "-"),
+        }
+    }
+}
+
+/// La position d'une valeur par rapport à l'intervalle de référence, avec
+/// les abréviations habituelles des laboratoires
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Display)]
+pub enum LabFlag {
+    #[display(" ")]
+    Normal,
+    #[display("L")]
+    Low,
+    #[display("H")]
+    High,
+}
+
+/// Un résultat d'analyse de laboratoire, rattaché au dossier d'un patient
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LabResult {
+    pub id: LabResultID,
+    pub patient: UserID,
+    pub author: UserID,
+    /// Le code de l'analyse (voir `lab::analyte_code`)
+    pub analyte: String,
+    pub value: f64,
+    pub unit: String,
+    #[serde(default)]
+    pub range: ReferenceRange,
+    pub taken_at: DateTime<Utc>,
+}
+
+impl LabResult {
+    pub fn flag(&self) -> LabFlag {
+        self.range.flag(self.value)
+    }
+}
+
+/// Une règle d'accès: l'action et l'expression qui l'autorise
+/// (`p, <action>, <règle>`)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Display)]
+#[display("p, {action}, {rule}")]
+pub struct PolicyRule {
+    pub action: String,
+    pub rule: String,
+}
+
+/// Une modification des règles d'accès
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Display)]
+pub enum PolicyChange {
+    #[display("+ {_0}")]
+    Add(PolicyRule),
+    #[display("- {_0}")]
+    Remove(PolicyRule),
+}
+
+impl PolicyChange {
+    /// La règle ajoutée ou retirée
+    pub fn rule(&self) -> &PolicyRule {
+        match self {
+            PolicyChange::Add(rule) | PolicyChange::Remove(rule) => rule,
+        }
+    }
+
+    /// Vrai si la modification porte sur les changements de rôle ou sur la
+    /// gestion des règles: comme un changement de rôle privilégié, elle
+    /// doit être approuvée par un autre administrateur
+    pub fn needs_approval(&self) -> bool {
+        matches!(
+            self.rule().action.as_str(),
+            "update-role" | "review-role-change" | "manage-policy"
+        )
+    }
+}
+
+/// Une version des règles d'accès, enregistrée à chaque modification faite
+/// depuis l'application. La dernière remplace les règles du fichier, si
+/// elle en dérive.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PolicyRevision {
+    /// Le numéro de la version, à partir de 1
+    pub version: usize,
+    /// L'empreinte des règles signées dont la version dérive
+    #[serde(default)]
+    pub base: String,
+    pub author: UserID,
+    /// L'administrateur qui a approuvé la modification, si elle devait
+    /// l'être
+    #[serde(default)]
+    pub reviewer: Option<UserID>,
+    pub at: DateTime<Utc>,
+    pub change: PolicyChange,
+    pub rules: Vec<PolicyRule>,
+}
+
+/// Un identifiant unique de demande de modification des règles
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Display,
+)]
+pub struct PolicyChangeID(Uuid);
+
+impl PolicyChangeID {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for PolicyChangeID {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Une modification des règles qui attend l'approbation d'un autre
+/// administrateur (`PolicyChange::needs_approval`). Elle suit le même
+/// cycle qu'un changement de rôle.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PolicyChangeRequest {
+    pub id: PolicyChangeID,
+    pub change: PolicyChange,
+    pub requester: UserID,
+    pub requested_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub status: RoleChangeStatus,
+    /// L'administrateur qui a approuvé ou refusé la demande
+    pub reviewer: Option<UserID>,
+}
+
+/// Un identifiant unique de demande de changement de rôle
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Display,
+)]
+pub struct RoleChangeID(Uuid);
+
+impl RoleChangeID {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for RoleChangeID {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// L'état d'une demande de changement de rôle
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum RoleChangeStatus {
+    #[display("{}", Msg::StatusPending)]
+    Pending,
+    #[display("{}", Msg::StatusApproved)]
+    Approved,
+    #[display("{}", Msg::StatusDenied)]
+    Denied,
+    #[display("{}", Msg::StatusExpired)]
+    Expired,
+    #[display("{}", Msg::StatusCancelled)]
+    Cancelled,
+}
+
+/// Un changement de rôle privilégié, qui ne prend effet qu'une fois
+/// approuvé par un autre administrateur que son auteur, avant son expiration
+#[derive(Debug, Serialize, Deserialize, Clone, Hash)]
+pub struct RoleChangeRequest {
+    pub id: RoleChangeID,
+    pub target: UserID,
+    pub role: Role,
+    /// Le rôle de l'utilisateur visé au moment de la demande, qui ne peut
+    /// plus être approuvée s'il a changé depuis
+    #[serde(default)]
+    pub previous: Option<Role>,
+    pub requester: UserID,
+    pub requested_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub status: RoleChangeStatus,
+    /// L'administrateur qui a approuvé ou refusé la demande
+    pub reviewer: Option<UserID>,
 }
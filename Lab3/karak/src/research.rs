@@ -0,0 +1,286 @@
+//! Export de données désidentifiées pour la recherche.
+//!
+//! Chaque patient ayant un dossier devient une ligne contenant un pseudonyme,
+//! son groupe sanguin et son nombre de rapports, regroupé par tranches. Le
+//! nom d'utilisateur et le numéro AVS ne sont jamais exportés. Le pseudonyme
+//! est un HMAC-SHA256 de l'identifiant: il est stable d'un export à l'autre
+//! tant que la même clé est utilisée, mais ne peut pas être relié à un
+//! patient sans elle.
+//!
+//! Le groupe sanguin et la tranche de rapports sont des quasi-identifiants:
+//! toute combinaison partagée par moins de `k` patients est retirée de
+//! l'export (k-anonymat), et décrite dans le résumé.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use serde::Serialize;
+use strum_macros::EnumIter;
+
+use crate::models::{BloodType, UserID};
+use crate::utils::input_validation::InvalidInput;
+use crate::utils::keyed_hash::{to_hex, HashKey};
+
+/// Un format de sortie du jeu de données
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+pub enum ResearchFormat {
+    #[display("csv")]
+    Csv,
+    #[display("json")]
+    Json,
+}
+
+impl FromStr for ResearchFormat {
+    type Err = InvalidInput;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ResearchFormat::Csv),
+            "json" => Ok(ResearchFormat::Json),
+            _ => Err(InvalidInput),
+        }
+    }
+}
+
+/// La clé secrète des pseudonymes, à conserver hors de la base et à ne
+/// jamais transmettre aux partenaires
+pub struct PseudonymKey(HashKey);
+
+impl PseudonymKey {
+    pub fn generate() -> Self {
+        Self(HashKey::generate())
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, InvalidInput> {
+        HashKey::from_hex(hex).map(Self)
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.0.to_hex()
+    }
+
+    /// Le pseudonyme d'un utilisateur: les 128 premiers bits du HMAC
+    pub fn pseudonym(&self, user: UserID) -> String {
+        to_hex(&self.0.digest(user.to_string().as_bytes())[..16])
+    }
+}
+
+/// Tranche du nombre de rapports d'un patient, un nombre exact étant
+/// trop identifiant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Display)]
+pub enum ReportBand {
+    #[display("0")]
+    #[serde(rename = "0")]
+    None,
+    #[display("1-2")]
+    #[serde(rename = "1-2")]
+    Few,
+    #[display("3-5")]
+    #[serde(rename = "3-5")]
+    Several,
+    #[display("6-10")]
+    #[serde(rename = "6-10")]
+    Many,
+    #[display("11+")]
+    #[serde(rename = "11+")]
+    Lots,
+}
+
+impl From<usize> for ReportBand {
+    fn from(count: usize) -> Self {
+        match count {
+            0 => ReportBand::None,
+            1..=2 => ReportBand::Few,
+            3..=5 => ReportBand::Several,
+            6..=10 => ReportBand::Many,
+            _ => ReportBand::Lots,
+        }
+    }
+}
+
+/// Les données d'un patient avant désidentification
+pub struct PatientFacts {
+    pub id: UserID,
+    pub blood_type: BloodType,
+    pub report_count: usize,
+}
+
+/// Une ligne du jeu de données exporté
+#[derive(Debug, Serialize)]
+pub struct ResearchRecord {
+    pub pseudonym: String,
+    pub blood_type: BloodType,
+    pub reports: ReportBand,
+}
+
+/// Une combinaison de quasi-identifiants retirée de l'export
+#[derive(Debug, Serialize)]
+pub struct SuppressedGroup {
+    pub blood_type: BloodType,
+    pub reports: ReportBand,
+    pub size: usize,
+}
+
+/// Ce qui a été exporté, et ce qui a été retiré
+#[derive(Debug, Serialize)]
+pub struct ResearchSummary {
+    pub generated_at: DateTime<Utc>,
+    pub k: usize,
+    pub patients: usize,
+    pub exported: usize,
+    pub suppressed: usize,
+    pub dropped_fields: [&'static str; 2],
+    pub quasi_identifiers: [&'static str; 2],
+    pub suppressed_groups: Vec<SuppressedGroup>,
+}
+
+/// Un jeu de données prêt à être transmis, avec son résumé
+#[derive(Debug)]
+pub struct ResearchDataset {
+    pub records: Vec<ResearchRecord>,
+    pub summary: ResearchSummary,
+}
+
+impl ResearchDataset {
+    /// Pseudonymise les patients et retire les groupes de moins de `k`
+    pub fn build(
+        patients: impl IntoIterator<Item = PatientFacts>,
+        key: &PseudonymKey,
+        k: usize,
+    ) -> Self {
+        let mut groups: BTreeMap<(BloodType, ReportBand), Vec<ResearchRecord>> = BTreeMap::new();
+        for patient in patients {
+            let reports = ReportBand::from(patient.report_count);
+            groups
+                .entry((patient.blood_type, reports))
+                .or_default()
+                .push(ResearchRecord {
+                    pseudonym: key.pseudonym(patient.id),
+                    blood_type: patient.blood_type,
+                    reports,
+                });
+        }
+
+        let mut records = Vec::new();
+        let mut suppressed_groups = Vec::new();
+        for ((blood_type, reports), group) in groups {
+            if group.len() >= k {
+                records.extend(group);
+            } else {
+                suppressed_groups.push(SuppressedGroup {
+                    blood_type,
+                    reports,
+                    size: group.len(),
+                });
+            }
+        }
+        // L'ordre des lignes ne doit rien révéler de l'ordre de la base
+        records.sort_by(|a, b| a.pseudonym.cmp(&b.pseudonym));
+
+        let suppressed: usize = suppressed_groups.iter().map(|group| group.size).sum();
+        let summary = ResearchSummary {
+            generated_at: Utc::now(),
+            k,
+            patients: records.len() + suppressed,
+            exported: records.len(),
+            suppressed,
+            dropped_fields: ["username", "avs_number"],
+            quasi_identifiers: ["blood_type", "reports"],
+            suppressed_groups,
+        };
+        Self { records, summary }
+    }
+
+    /// Génère le jeu de données dans le format demandé
+    pub fn render(&self, format: ResearchFormat) -> Result<Vec<u8>, io::Error> {
+        match format {
+            ResearchFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                for record in &self.records {
+                    writer.serialize(record)?;
+                }
+                writer.into_inner().map_err(|e| e.into_error())
+            }
+            ResearchFormat::Json => Ok(serde_json::to_vec_pretty(&self.records)?),
+        }
+    }
+
+    /// Le résumé, en JSON
+    pub fn summary_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(&self.summary).expect("The summary is always serializable")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn facts(blood_type: BloodType, report_count: usize) -> PatientFacts {
+        PatientFacts {
+            id: UserID::new(),
+            blood_type,
+            report_count,
+        }
+    }
+
+    #[test]
+    fn test_pseudonyms_are_keyed_and_stable() {
+        let user = UserID::new();
+        let key = PseudonymKey::generate();
+        let same_key = PseudonymKey::from_hex(&key.to_hex()).unwrap();
+
+        assert_eq!(key.pseudonym(user), same_key.pseudonym(user));
+        assert_ne!(
+            key.pseudonym(user),
+            PseudonymKey::generate().pseudonym(user)
+        );
+        assert_eq!(key.pseudonym(user).len(), 32);
+        assert!(!key.pseudonym(user).contains(&user.to_string()));
+
+        assert!(PseudonymKey::from_hex("abcd").is_err());
+        assert!(PseudonymKey::from_hex(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_small_groups_are_suppressed() {
+        let mut patients: Vec<PatientFacts> = (0..3).map(|_| facts(BloodType::A, 1)).collect();
+        patients.push(facts(BloodType::A, 2));
+        patients.push(facts(BloodType::AB, 7));
+        patients.push(facts(BloodType::O, 0));
+        patients.push(facts(BloodType::O, 0));
+
+        let dataset = ResearchDataset::build(patients, &PseudonymKey::generate(), 2);
+
+        assert_eq!(dataset.summary.patients, 7);
+        assert_eq!(dataset.summary.exported, 6);
+        assert_eq!(dataset.summary.suppressed, 1);
+        assert_eq!(dataset.summary.suppressed_groups.len(), 1);
+        assert_eq!(
+            dataset.summary.suppressed_groups[0].reports,
+            ReportBand::Many
+        );
+        assert!(dataset
+            .records
+            .windows(2)
+            .all(|pair| pair[0].pseudonym <= pair[1].pseudonym));
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let patients = (0..2).map(|_| facts(BloodType::B, 4));
+        let dataset = ResearchDataset::build(patients, &PseudonymKey::generate(), 2);
+
+        let csv = String::from_utf8(dataset.render(ResearchFormat::Csv).unwrap()).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("pseudonym,blood_type,reports"));
+        assert!(lines.all(|line| line.ends_with(",B,3-5")));
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&dataset.render(ResearchFormat::Json).unwrap()).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[0]["reports"], "3-5");
+    }
+}
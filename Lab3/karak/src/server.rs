@@ -0,0 +1,1253 @@
+//! API HTTP en JSON devant le `Service`, pour les outils du secrétariat et
+//! les applications mobiles.
+//!
+//! Un client s'authentifie avec `POST /sessions` et joint ensuite le jeton
+//! obtenu à chaque requête (`Authorization: Bearer <jeton>`). Le service est
+//! partagé derrière un mutex: les requêtes sont traitées une à une, avec les
+//! mêmes contrôles Casbin que l'interface en ligne de commande, et la base
+//! est sauvegardée après chaque modification.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{Json, Router};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+use tokio::net::TcpListener;
+
+use crate::authorization::PolicyError;
+use crate::db::DBError;
+use crate::i18n::Msg;
+use crate::models::{
+    BloodType, LabFlag, LabResult, LabResultID, MedicalReport, PersonalData, Prescription,
+    PrescriptionID, ProxyID, ProxyKind, ProxyScope, ReferenceRange, ReportID, Role, RoleChangeID,
+    UserData, UserID,
+};
+use crate::services::{
+    AuthoredReports, LabMeasurement, LoginError, PrescriptionTerms, ProxyTerms, RoleUpdate,
+    Service, ServiceError,
+};
+use crate::utils::input_validation::{AVSNumber, InvalidInput, Username};
+use crate::utils::password_utils::{generate_token, verify};
+
+/// Durée de validité d'une session
+const SESSION_DURATION: TimeDelta = TimeDelta::hours(8);
+
+/// L'état partagé entre toutes les requêtes
+#[derive(Clone)]
+struct AppState(Arc<Mutex<Shared>>);
+
+struct Shared {
+    service: Service,
+    sessions: HashMap<String, Session>,
+}
+
+struct Session {
+    user: UserID,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error(transparent)]
+    Service(#[from] ServiceError),
+
+    #[error(transparent)]
+    Login(#[from] LoginError),
+
+    #[error(transparent)]
+    InvalidInput(#[from] InvalidInput),
+
+    #[error("{}", Msg::ErrInvalidSession)]
+    InvalidSession,
+
+    #[error("{}", Msg::ErrPasswordChangeRequired)]
+    PasswordChangeRequired,
+
+    #[error("{}", Msg::ErrStorage)]
+    Storage(#[from] io::Error),
+}
+
+impl ApiError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Service(e) => match e {
+                ServiceError::AccessDenied(_) => StatusCode::FORBIDDEN,
+                ServiceError::DBError(DBError::InvalidUserID(_))
+                | ServiceError::NoSuchProxy
+                | ServiceError::NoSuchPrescription
+                | ServiceError::NoSuchReport
+                | ServiceError::NoSuchRequest
+                | ServiceError::NoSuchRoleChange
+                | ServiceError::NoSuchPolicyChange
+                | ServiceError::NoSuchAccess => StatusCode::NOT_FOUND,
+                ServiceError::UserAlreadyExists
+                | ServiceError::DBError(DBError::UserAlreadyExists { .. })
+                | ServiceError::AlreadyBootstrapped
+                | ServiceError::RequestAlreadyPending
+                | ServiceError::RoleChangeAlreadyPending
+                | ServiceError::RoleChangeExpired
+                | ServiceError::PolicyChangeExpired
+                | ServiceError::NoReviewer
+                | ServiceError::RoleChangeStale
+                | ServiceError::LastAdmin
+                | ServiceError::AdminClosure
+                | ServiceError::Policy(PolicyError::NotEditable | PolicyError::Unchanged) => {
+                    StatusCode::CONFLICT
+                }
+                ServiceError::NotAPatient
+                | ServiceError::NotADoctor
+                | ServiceError::WeakPassword
+                | ServiceError::CannotReassign
+                | ServiceError::InvalidProxy
+                | ServiceError::InvalidPrescription
+                | ServiceError::InvalidLabResult
+                | ServiceError::TooFewAdmins
+                | ServiceError::Policy(PolicyError::InvalidRule)
+                | ServiceError::PolicyScenarios(_)
+                | ServiceError::PolicyLockout => StatusCode::UNPROCESSABLE_ENTITY,
+                ServiceError::Storage(_) | ServiceError::Policy(_) => {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            },
+            ApiError::Login(_) | ApiError::InvalidSession => StatusCode::UNAUTHORIZED,
+            ApiError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            ApiError::PasswordChangeRequired => StatusCode::FORBIDDEN,
+            ApiError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if let ApiError::Storage(e) = &self {
+            error!("Sauvegarde de la base impossible: {e}");
+        }
+
+        let body = Json(json!({ "error": self.to_string() }));
+        if status == StatusCode::UNAUTHORIZED {
+            (status, [(header::WWW_AUTHENTICATE, "Bearer")], body).into_response()
+        } else {
+            (status, body).into_response()
+        }
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+impl AppState {
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        // La session du service est réinitialisée à chaque requête, l'état
+        // reste donc utilisable si une requête précédente a paniqué
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Verrouille le service et identifie l'utilisateur de la session
+    fn session(&self, headers: &HeaderMap) -> ApiResult<(MutexGuard<'_, Shared>, UserID)> {
+        let token = bearer_token(headers).ok_or(ApiError::InvalidSession)?;
+        let mut shared = self.lock();
+
+        let now = Utc::now();
+        shared
+            .sessions
+            .retain(|_, session| session.expires_at > now);
+        let user = shared
+            .sessions
+            .get(token)
+            .ok_or(ApiError::InvalidSession)?
+            .user;
+        Ok((shared, user))
+    }
+
+    /// Exécute une opération du service au nom de l'utilisateur de la session
+    fn as_user<T>(
+        &self,
+        headers: &HeaderMap,
+        operation: impl FnOnce(&mut Service, UserID) -> ApiResult<T>,
+    ) -> ApiResult<T> {
+        let (mut shared, user) = self.session(headers)?;
+        let service = &mut shared.service;
+
+        service.resume_session(user);
+        let result = if service.password_change_required() {
+            Err(ApiError::PasswordChangeRequired)
+        } else {
+            operation(service, user)
+        };
+        service.logout();
+        result
+    }
+
+    /// Comme `as_user`, en sauvegardant la base si l'opération réussit
+    fn update_as_user<T>(
+        &self,
+        headers: &HeaderMap,
+        operation: impl FnOnce(&mut Service, UserID) -> ApiResult<T>,
+    ) -> ApiResult<T> {
+        self.as_user(headers, |service, user| {
+            let result = operation(service, user)?;
+            service.save()?;
+            Ok(result)
+        })
+    }
+}
+
+/// Exécute un calcul coûteux, comme le hachage d'un mot de passe, hors des
+/// threads asynchrones et sans tenir le verrou du service
+async fn blocking<T: Send + 'static>(task: impl FnOnce() -> T + Send + 'static) -> T {
+    match tokio::task::spawn_blocking(task).await {
+        Ok(value) => value,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+#[derive(Deserialize)]
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct NewPassword {
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct PersonalDataBody {
+    avs_number: String,
+    blood_type: String,
+}
+
+#[derive(Deserialize)]
+struct RoleBody {
+    role: String,
+}
+
+#[derive(Deserialize)]
+struct ReviewBody {
+    approve: bool,
+}
+
+#[derive(Deserialize)]
+struct NewReport {
+    title: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ReportContent {
+    content: String,
+}
+
+#[derive(Serialize)]
+struct Created<T> {
+    id: T,
+}
+
+#[derive(Serialize)]
+struct SessionView {
+    token: String,
+    user: UserID,
+    expires_at: DateTime<Utc>,
+    password_change_required: bool,
+}
+
+/// Le médecin à qui confier les rapports écrits par un compte fermé; sans
+/// médecin, ils sont pseudonymisés
+#[derive(Deserialize)]
+struct ClosureQuery {
+    reassign_to: Option<String>,
+}
+
+/// Une nouvelle relation de représentation. Pour un représentant légal,
+/// la date de naissance suffit: la relation prend fin à la majorité.
+#[derive(Deserialize)]
+struct ProxyBody {
+    proxy: UserID,
+    kind: ProxyKind,
+    scope: ProxyScope,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+    birth_date: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+struct LabResultBody {
+    analyte: String,
+    value: f64,
+    unit: String,
+    #[serde(default)]
+    range: ReferenceRange,
+    taken_at: Option<DateTime<Utc>>,
+}
+
+/// Un résultat de laboratoire, avec sa position par rapport à l'intervalle
+/// de référence
+#[derive(Serialize)]
+struct LabResultView<'a> {
+    #[serde(flatten)]
+    result: &'a LabResult,
+    flag: LabFlag,
+}
+
+#[derive(Deserialize)]
+struct PrescriptionBody {
+    drug: String,
+    dose: String,
+    duration_days: u32,
+    #[serde(default)]
+    repeats: u32,
+}
+
+/// Le code est envoyé dans le corps de la requête, jamais dans l'URL
+#[derive(Deserialize)]
+struct PrescriptionCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct AvsSearch {
+    avs_number: String,
+}
+
+/// Un utilisateur, sans son mot de passe haché
+#[derive(Serialize)]
+struct UserView<'a> {
+    id: UserID,
+    username: &'a Username,
+    role: Role,
+    medical_folder: Option<FolderView<'a>>,
+}
+
+/// Un dossier médical, dont le numéro AVS est masqué
+#[derive(Serialize)]
+struct FolderView<'a> {
+    avs_number: String,
+    blood_type: BloodType,
+    doctors: &'a BTreeSet<UserID>,
+    report_readers: &'a BTreeSet<UserID>,
+}
+
+impl<'a> From<&'a UserData> for UserView<'a> {
+    fn from(user: &'a UserData) -> Self {
+        Self {
+            id: user.id,
+            username: &user.username,
+            role: user.role,
+            medical_folder: user.medical_folder.as_ref().map(|folder| FolderView {
+                avs_number: folder.personal_data.avs_number.to_string(),
+                blood_type: folder.personal_data.blood_type,
+                doctors: &folder.doctors,
+                report_readers: &folder.report_readers,
+            }),
+        }
+    }
+}
+
+/// Un rapport, avec ou sans son contenu
+#[derive(Serialize)]
+struct ReportView<'a> {
+    id: ReportID,
+    title: &'a str,
+    author: UserID,
+    patient: UserID,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<&'a str>,
+}
+
+impl<'a> ReportView<'a> {
+    fn summary(report: &'a MedicalReport) -> Self {
+        Self {
+            id: report.id,
+            title: &report.title,
+            author: report.author,
+            patient: report.patient,
+            created_at: report.created_at,
+            updated_at: report.updated_at,
+            content: None,
+        }
+    }
+
+    fn full(report: &'a MedicalReport) -> Self {
+        Self {
+            content: Some(&report.content),
+            ..Self::summary(report)
+        }
+    }
+}
+
+/// Les vues empruntent le service verrouillé: elles sont sérialisées avant
+/// de relâcher le verrou.
+fn to_json(value: impl Serialize) -> ApiResult<Json<serde_json::Value>> {
+    serde_json::to_value(value)
+        .map(Json)
+        .map_err(|e| ApiError::Storage(e.into()))
+}
+
+async fn register(
+    State(state): State<AppState>,
+    Json(body): Json<Credentials>,
+) -> ApiResult<(StatusCode, Json<Created<UserID>>)> {
+    let username = Username::try_from(body.username)?;
+    let hash = {
+        let username = username.clone();
+        blocking(move || Service::hash_new_password(&username, &body.password)).await?
+    };
+
+    let mut shared = state.lock();
+    let id = shared.service.register_with_hash(username, hash)?;
+    shared.service.save()?;
+    Ok((StatusCode::CREATED, Json(Created { id })))
+}
+
+async fn login(
+    State(state): State<AppState>,
+    Json(body): Json<Credentials>,
+) -> ApiResult<Json<SessionView>> {
+    let username = Username::try_from(body.username).map_err(|_| LoginError::InvalidCredentials)?;
+    let stored = state.lock().service.password_hash(&username);
+    let verified = blocking(move || {
+        let valid = verify(&body.password, stored.as_ref().map(|(_, hash)| hash));
+        stored.filter(|_| valid)
+    })
+    .await;
+
+    let mut shared = state.lock();
+    let service = &mut shared.service;
+    let login = verified
+        .ok_or(LoginError::InvalidCredentials)
+        .and_then(|(user, hash)| service.login_verified(user, &hash));
+    let session = login.map(|user| SessionView {
+        token: generate_token(),
+        user,
+        expires_at: Utc::now() + SESSION_DURATION,
+        password_change_required: service.password_change_required(),
+    });
+    service.logout();
+
+    let Ok(session) = session else {
+        warn!("API: échec de connexion pour {username}");
+        return Err(LoginError::InvalidCredentials.into());
+    };
+    info!("API: session ouverte pour {username}");
+    shared.sessions.insert(
+        session.token.clone(),
+        Session {
+            user: session.user,
+            expires_at: session.expires_at,
+        },
+    );
+    Ok(Json(session))
+}
+
+async fn logout(State(state): State<AppState>, headers: HeaderMap) -> ApiResult<StatusCode> {
+    let (mut shared, user) = state.session(&headers)?;
+    if let Some(token) = bearer_token(&headers) {
+        shared.sessions.remove(token);
+    }
+    info!("API: session fermée pour {user}");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Le seul point d'accès autorisé tant que le mot de passe initial n'a pas
+/// été remplacé
+async fn change_password(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<NewPassword>,
+) -> ApiResult<StatusCode> {
+    let username = {
+        let (shared, user) = state.session(&headers)?;
+        let username = shared.service.username(user).cloned();
+        username.ok_or(ApiError::InvalidSession)?
+    };
+    let hash = blocking(move || Service::hash_new_password(&username, &body.password)).await?;
+
+    let (mut shared, user) = state.session(&headers)?;
+    let service = &mut shared.service;
+    service.resume_session(user);
+    let result = service.set_password(hash);
+    service.logout();
+    result?;
+
+    service.save()?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_user(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<UserID>,
+) -> ApiResult<Json<serde_json::Value>> {
+    // La lecture est journalisée et notifiée au patient, d'où la sauvegarde
+    state.update_as_user(&headers, |service, _| {
+        to_json(UserView::from(service.get_data(user_id)?))
+    })
+}
+
+async fn reveal_avs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<UserID>,
+) -> ApiResult<Json<serde_json::Value>> {
+    state.update_as_user(&headers, |service, _| {
+        Ok(Json(json!({ "avs_number": service.reveal_avs(user_id)? })))
+    })
+}
+
+async fn find_by_avs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<AvsSearch>,
+) -> ApiResult<Json<Vec<UserID>>> {
+    let avs_number = AVSNumber::try_from(body.avs_number)?;
+    state.as_user(&headers, |service, _| {
+        Ok(Json(service.find_by_avs(&avs_number)?))
+    })
+}
+
+async fn update_folder(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<UserID>,
+    Json(body): Json<PersonalDataBody>,
+) -> ApiResult<StatusCode> {
+    let personal_data = PersonalData {
+        avs_number: AVSNumber::try_from(body.avs_number)?,
+        blood_type: body.blood_type.parse()?,
+    };
+    state.update_as_user(&headers, |service, _| {
+        service.update_data(user_id, personal_data)?;
+        Ok(StatusCode::NO_CONTENT)
+    })
+}
+
+async fn delete_folder(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<UserID>,
+) -> ApiResult<StatusCode> {
+    state.update_as_user(&headers, |service, _| {
+        service.delete_data(user_id)?;
+        Ok(StatusCode::NO_CONTENT)
+    })
+}
+
+async fn close_account(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<UserID>,
+    Query(query): Query<ClosureQuery>,
+) -> ApiResult<StatusCode> {
+    let reassign_to = query.reassign_to.map(Username::try_from).transpose()?;
+    state.update_as_user(&headers, |service, _| {
+        let authored = match reassign_to {
+            Some(doctor) => {
+                AuthoredReports::Reassign(service.lookup_user(&doctor).ok_or(InvalidInput)?)
+            }
+            None => AuthoredReports::Pseudonymize,
+        };
+        service.close_account(user_id, authored)?;
+        Ok(())
+    })?;
+
+    // Les sessions du compte fermé ne doivent plus être utilisables
+    state
+        .lock()
+        .sessions
+        .retain(|_, session| session.user != user_id);
+    info!("API: sessions de {user_id} fermées avec son compte");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn update_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<UserID>,
+    Json(body): Json<RoleBody>,
+) -> ApiResult<Response> {
+    let role: Role = body.role.parse()?;
+    state.update_as_user(&headers, |service, _| {
+        match service.update_role(user_id, role)? {
+            RoleUpdate::Applied => Ok(StatusCode::NO_CONTENT.into_response()),
+            RoleUpdate::Pending(id) => {
+                Ok((StatusCode::ACCEPTED, Json(Created { id })).into_response())
+            }
+        }
+    })
+}
+
+/// Les changements de rôle que l'utilisateur connecté peut approuver
+async fn list_role_changes(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Json<serde_json::Value>> {
+    state.as_user(&headers, |service, _| {
+        to_json(service.list_role_changes().collect::<Vec<_>>())
+    })
+}
+
+async fn review_role_change(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(change): Path<RoleChangeID>,
+    Json(body): Json<ReviewBody>,
+) -> ApiResult<StatusCode> {
+    state.update_as_user(&headers, |service, _| {
+        service.review_role_change(change, body.approve)?;
+        Ok(StatusCode::NO_CONTENT)
+    })
+}
+
+/// Retire une demande de changement de rôle de l'utilisateur connecté
+async fn cancel_role_change(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(change): Path<RoleChangeID>,
+) -> ApiResult<StatusCode> {
+    state.update_as_user(&headers, |service, _| {
+        service.cancel_role_change(change)?;
+        Ok(StatusCode::NO_CONTENT)
+    })
+}
+
+async fn list_reports(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<UserID>,
+) -> ApiResult<Json<serde_json::Value>> {
+    state.as_user(&headers, |service, _| {
+        let reports: Vec<ReportView> = service
+            .list_reports(user_id)
+            .map(ReportView::summary)
+            .collect();
+        to_json(reports)
+    })
+}
+
+async fn add_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(patient): Path<UserID>,
+    Json(body): Json<NewReport>,
+) -> ApiResult<(StatusCode, Json<Created<ReportID>>)> {
+    state.update_as_user(&headers, |service, author| {
+        let id = service.add_report(author, patient, body.title, body.content)?;
+        Ok((StatusCode::CREATED, Json(Created { id })))
+    })
+}
+
+async fn read_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(report_id): Path<ReportID>,
+) -> ApiResult<Json<serde_json::Value>> {
+    state.update_as_user(&headers, |service, _| {
+        to_json(ReportView::full(service.read_report(report_id)?))
+    })
+}
+
+async fn update_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(report_id): Path<ReportID>,
+    Json(body): Json<ReportContent>,
+) -> ApiResult<StatusCode> {
+    state.update_as_user(&headers, |service, _| {
+        service.update_report(report_id, body.content)?;
+        Ok(StatusCode::NO_CONTENT)
+    })
+}
+
+async fn list_patients(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Json<serde_json::Value>> {
+    state.as_user(&headers, |service, _| {
+        let patients: Vec<serde_json::Value> = service
+            .list_patients()
+            .map(|patient| json!({ "id": patient.id, "username": patient.username }))
+            .collect();
+        to_json(patients)
+    })
+}
+
+async fn metrics(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Json<serde_json::Value>> {
+    state.as_user(&headers, |service, _| {
+        let stats = service.authorization_metrics()?;
+        Ok(Json(json!({
+            "decision_cache": {
+                "hits": stats.hits,
+                "misses": stats.misses,
+                "invalidations": stats.invalidations,
+                "entries": stats.entries,
+                "hit_rate": stats.hit_rate(),
+            }
+        })))
+    })
+}
+
+async fn add_doctor(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((patient, doctor)): Path<(UserID, UserID)>,
+) -> ApiResult<StatusCode> {
+    state.update_as_user(&headers, |service, _| {
+        service.add_doctor(patient, doctor)?;
+        Ok(StatusCode::NO_CONTENT)
+    })
+}
+
+async fn list_lab_analytes(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(patient): Path<UserID>,
+) -> ApiResult<Json<serde_json::Value>> {
+    state.as_user(&headers, |service, _| {
+        to_json(service.lab_analytes(patient))
+    })
+}
+
+async fn add_lab_result(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(patient): Path<UserID>,
+    Json(body): Json<LabResultBody>,
+) -> ApiResult<(StatusCode, Json<Created<LabResultID>>)> {
+    let measurement = LabMeasurement {
+        analyte: body.analyte,
+        value: body.value,
+        unit: body.unit,
+        range: body.range,
+        taken_at: body.taken_at.unwrap_or_else(Utc::now),
+    };
+    state.update_as_user(&headers, |service, _| {
+        let id = service.add_lab_result(patient, measurement)?;
+        Ok((StatusCode::CREATED, Json(Created { id })))
+    })
+}
+
+async fn read_lab_results(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((patient, analyte)): Path<(UserID, String)>,
+) -> ApiResult<Json<serde_json::Value>> {
+    state.update_as_user(&headers, |service, _| {
+        let results: Vec<LabResultView> = service
+            .read_lab_results(patient, &analyte)?
+            .into_iter()
+            .map(|result| LabResultView {
+                result,
+                flag: result.flag(),
+            })
+            .collect();
+        to_json(results)
+    })
+}
+
+async fn list_prescriptions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(patient): Path<UserID>,
+) -> ApiResult<Json<serde_json::Value>> {
+    state.as_user(&headers, |service, _| {
+        let prescriptions: Vec<&Prescription> = service.list_prescriptions(patient).collect();
+        to_json(prescriptions)
+    })
+}
+
+async fn prescribe(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(patient): Path<UserID>,
+    Json(body): Json<PrescriptionBody>,
+) -> ApiResult<(StatusCode, Json<serde_json::Value>)> {
+    let terms = PrescriptionTerms {
+        drug: body.drug,
+        dose: body.dose,
+        duration_days: body.duration_days,
+        repeats: body.repeats,
+    };
+    state.update_as_user(&headers, |service, _| {
+        let (id, code) = service.prescribe(patient, terms)?;
+        Ok((StatusCode::CREATED, Json(json!({ "id": id, "code": code }))))
+    })
+}
+
+async fn issue_prescription_code(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(prescription): Path<PrescriptionID>,
+) -> ApiResult<Json<serde_json::Value>> {
+    state.update_as_user(&headers, |service, _| {
+        let code = service.issue_prescription_code(prescription)?;
+        Ok(Json(json!({ "code": code })))
+    })
+}
+
+async fn lookup_prescription(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<PrescriptionCode>,
+) -> ApiResult<Json<serde_json::Value>> {
+    state.as_user(&headers, |service, _| {
+        to_json(service.lookup_prescription(&body.code)?)
+    })
+}
+
+async fn dispense(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<PrescriptionCode>,
+) -> ApiResult<StatusCode> {
+    state.update_as_user(&headers, |service, _| {
+        service.dispense(&body.code)?;
+        Ok(StatusCode::NO_CONTENT)
+    })
+}
+
+async fn add_proxy(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(dependant): Path<UserID>,
+    Json(body): Json<ProxyBody>,
+) -> ApiResult<(StatusCode, Json<serde_json::Value>)> {
+    let terms = ProxyTerms {
+        kind: body.kind,
+        scope: body.scope,
+        start: body.start.unwrap_or_else(|| Utc::now().date_naive()),
+        end: body.end,
+        birth_date: body.birth_date,
+    };
+    state.update_as_user(&headers, |service, _| {
+        let id = service.add_proxy(dependant, body.proxy, terms)?;
+        Ok((StatusCode::CREATED, Json(json!({ "id": id }))))
+    })
+}
+
+async fn end_proxy(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(proxy): Path<ProxyID>,
+) -> ApiResult<StatusCode> {
+    state.update_as_user(&headers, |service, _| {
+        service.end_proxy(proxy)?;
+        Ok(StatusCode::NO_CONTENT)
+    })
+}
+
+async fn remove_doctor(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((patient, doctor)): Path<(UserID, UserID)>,
+) -> ApiResult<StatusCode> {
+    state.update_as_user(&headers, |service, _| {
+        service.remove_doctor(patient, doctor)?;
+        Ok(StatusCode::NO_CONTENT)
+    })
+}
+
+/// Les routes de l'API, servies par-dessus le service donné
+pub fn router(service: Service) -> Router {
+    let state = AppState(Arc::new(Mutex::new(Shared {
+        service,
+        sessions: HashMap::new(),
+    })));
+
+    Router::new()
+        .route("/users", post(register))
+        .route("/sessions", post(login).delete(logout))
+        .route("/password", put(change_password))
+        .route("/users/by-avs", post(find_by_avs))
+        .route("/users/{user}", get(get_user).delete(close_account))
+        .route("/users/{user}/avs", get(reveal_avs))
+        .route(
+            "/users/{user}/folder",
+            put(update_folder).delete(delete_folder),
+        )
+        .route("/users/{user}/role", put(update_role))
+        .route("/role-changes", get(list_role_changes))
+        .route(
+            "/role-changes/{change}",
+            put(review_role_change).delete(cancel_role_change),
+        )
+        .route("/users/{user}/reports", get(list_reports).post(add_report))
+        .route(
+            "/users/{user}/doctors/{doctor}",
+            put(add_doctor).delete(remove_doctor),
+        )
+        .route(
+            "/users/{user}/lab-results",
+            get(list_lab_analytes).post(add_lab_result),
+        )
+        .route("/users/{user}/lab-results/{analyte}", get(read_lab_results))
+        .route(
+            "/users/{user}/prescriptions",
+            get(list_prescriptions).post(prescribe),
+        )
+        .route(
+            "/prescriptions/{prescription}/code",
+            post(issue_prescription_code),
+        )
+        .route("/dispensings/lookup", post(lookup_prescription))
+        .route("/dispensings", post(dispense))
+        .route("/users/{user}/proxies", post(add_proxy))
+        .route("/proxies/{proxy}", delete(end_proxy))
+        .route("/reports/{report}", get(read_report).put(update_report))
+        .route("/patients", get(list_patients))
+        .route("/metrics", get(metrics))
+        .with_state(state)
+}
+
+/// Sert l'API sur une connexion déjà ouverte, jusqu'à l'arrêt du processus
+pub async fn serve(listener: TcpListener, service: Service) -> io::Result<()> {
+    info!("API: écoute sur {}", listener.local_addr()?);
+    axum::serve(listener, router(service)).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::authorization::Enforcer;
+    use crate::db::Database;
+    use reqwest::{Client, StatusCode};
+    use serde_json::Value;
+
+    const PASSWORD: &str = "Correct-Horse-Battery-42";
+
+    /// Starts a server on a random local port, with two initial admins
+    async fn spawn() -> String {
+        let mut service = Service::new(Database::default(), Enforcer::load().unwrap());
+        service
+            .bootstrap_admins(&[
+                (Username::try_from("admin").unwrap(), PASSWORD),
+                (Username::try_from("admin2").unwrap(), PASSWORD),
+            ])
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, service));
+        url
+    }
+
+    async fn register(client: &Client, url: &str, username: &str) -> String {
+        let response = client
+            .post(format!("{url}/users"))
+            .json(&json!({ "username": username, "password": PASSWORD }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body: Value = response.json().await.unwrap();
+        body["id"].as_str().unwrap().to_owned()
+    }
+
+    async fn login(client: &Client, url: &str, username: &str) -> String {
+        let response = client
+            .post(format!("{url}/sessions"))
+            .json(&json!({ "username": username, "password": PASSWORD }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        body["token"].as_str().unwrap().to_owned()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_register_and_sessions() {
+        let url = spawn().await;
+        let client = Client::new();
+        register(&client, &url, "alice").await;
+
+        let duplicate = client
+            .post(format!("{url}/users"))
+            .json(&json!({ "username": "alice", "password": PASSWORD }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+
+        let weak = client
+            .post(format!("{url}/users"))
+            .json(&json!({ "username": "bob", "password": "bob" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(weak.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let wrong_password = client
+            .post(format!("{url}/sessions"))
+            .json(&json!({ "username": "alice", "password": "nope" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(wrong_password.status(), StatusCode::UNAUTHORIZED);
+
+        let no_token = client.get(format!("{url}/patients")).send().await.unwrap();
+        assert_eq!(no_token.status(), StatusCode::UNAUTHORIZED);
+
+        let token = login(&client, &url, "alice").await;
+        let patients = client
+            .get(format!("{url}/patients"))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(patients.status(), StatusCode::OK);
+
+        let logout = client
+            .delete(format!("{url}/sessions"))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(logout.status(), StatusCode::NO_CONTENT);
+
+        let expired = client
+            .get(format!("{url}/patients"))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(expired.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_folder_and_report_workflow() {
+        let url = spawn().await;
+        let client = Client::new();
+        let doctor = register(&client, &url, "house").await;
+        let patient = register(&client, &url, "alice").await;
+        let admin_token = login(&client, &url, "admin").await;
+        let doctor_token = login(&client, &url, "house").await;
+        let patient_token = login(&client, &url, "alice").await;
+
+        // Only an admin can change a role, and a second admin must approve
+        // a promotion to Doctor
+        let denied = client
+            .put(format!("{url}/users/{doctor}/role"))
+            .bearer_auth(&patient_token)
+            .json(&json!({ "role": "Doctor" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+        let requested = client
+            .put(format!("{url}/users/{doctor}/role"))
+            .bearer_auth(&admin_token)
+            .json(&json!({ "role": "Doctor" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(requested.status(), StatusCode::ACCEPTED);
+        let change: Value = requested.json().await.unwrap();
+        let change = change["id"].as_str().unwrap();
+        let self_approved = client
+            .put(format!("{url}/role-changes/{change}"))
+            .bearer_auth(&admin_token)
+            .json(&json!({ "approve": true }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(self_approved.status(), StatusCode::FORBIDDEN);
+        let second_admin_token = login(&client, &url, "admin2").await;
+        let pending: Value = client
+            .get(format!("{url}/role-changes"))
+            .bearer_auth(&second_admin_token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(pending[0]["id"], change);
+        let approved = client
+            .put(format!("{url}/role-changes/{change}"))
+            .bearer_auth(&second_admin_token)
+            .json(&json!({ "approve": true }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(approved.status(), StatusCode::NO_CONTENT);
+
+        let folder = client
+            .put(format!("{url}/users/{patient}/folder"))
+            .bearer_auth(&patient_token)
+            .json(&json!({ "avs_number": "756.1234.5678.97", "blood_type": "AB" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(folder.status(), StatusCode::NO_CONTENT);
+        let added = client
+            .put(format!("{url}/users/{patient}/doctors/{doctor}"))
+            .bearer_auth(&patient_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(added.status(), StatusCode::NO_CONTENT);
+
+        let patients: Value = client
+            .get(format!("{url}/patients"))
+            .bearer_auth(&doctor_token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(patients[0]["username"], "alice");
+
+        let data: Value = client
+            .get(format!("{url}/users/{patient}"))
+            .bearer_auth(&doctor_token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(data["medical_folder"]["blood_type"], "AB");
+        assert_eq!(data["medical_folder"]["avs_number"], "756.****.****.97");
+        assert!(data.get("password").is_none(), "Hashes are never exposed");
+
+        let revealed: Value = client
+            .get(format!("{url}/users/{patient}/avs"))
+            .bearer_auth(&doctor_token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(revealed["avs_number"], "756.1234.5678.97");
+
+        let found: Value = client
+            .post(format!("{url}/users/by-avs"))
+            .bearer_auth(&admin_token)
+            .json(&json!({ "avs_number": "7561234567897" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(found[0], patient.as_str());
+
+        let created = client
+            .post(format!("{url}/users/{patient}/reports"))
+            .bearer_auth(&doctor_token)
+            .json(&json!({ "title": "Check-up", "content": "All good" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(created.status(), StatusCode::CREATED);
+        let report = created.json::<Value>().await.unwrap()["id"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+
+        let updated = client
+            .put(format!("{url}/reports/{report}"))
+            .bearer_auth(&doctor_token)
+            .json(&json!({ "content": "Still good" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(updated.status(), StatusCode::NO_CONTENT);
+
+        let read: Value = client
+            .get(format!("{url}/reports/{report}"))
+            .bearer_auth(&doctor_token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(read["content"], "Still good");
+
+        let listed: Value = client
+            .get(format!("{url}/users/{patient}/reports"))
+            .bearer_auth(&doctor_token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(listed[0]["title"], "Check-up");
+        assert!(listed[0].get("content").is_none());
+
+        let removed = client
+            .delete(format!("{url}/users/{patient}/doctors/{doctor}"))
+            .bearer_auth(&patient_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(removed.status(), StatusCode::NO_CONTENT);
+        let revoked = client
+            .get(format!("{url}/users/{patient}"))
+            .bearer_auth(&doctor_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(revoked.status(), StatusCode::FORBIDDEN);
+
+        let missing = client
+            .get(format!("{url}/users/{}", UserID::new()))
+            .bearer_auth(&admin_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_requests() {
+        let url = spawn().await;
+        let client = Client::new();
+
+        let tasks: Vec<_> = (0..16)
+            .map(|i| {
+                let (client, url) = (client.clone(), url.clone());
+                tokio::spawn(async move {
+                    let username = format!("user{i}");
+                    register(&client, &url, &username).await;
+                    login(&client, &url, &username).await
+                })
+            })
+            .collect();
+
+        let mut tokens = Vec::new();
+        for task in tasks {
+            tokens.push(task.await.unwrap());
+        }
+        tokens.sort();
+        tokens.dedup();
+        assert_eq!(tokens.len(), 16, "Every session gets its own token");
+    }
+}
@@ -1,13 +1,45 @@
 //! API d'accès au dossier, et point d'entrée unique pour le contrôle d'accès.
 //!
-use crate::authorization::{AccessDenied, Context, Enforcer};
+use crate::authorization::{
+    failed_scenarios, AccessDenied, Backend, CacheStats, Context, Enforcer, PolicyError, Request,
+    Subject,
+};
 use crate::db::{DBError, Database};
-use crate::models::{MedicalFolder, MedicalReport, PersonalData, ReportID, Role, UserData, UserID};
-use crate::utils::input_validation::{password_input_validation, Username};
-use crate::utils::password_utils::{hash, verify};
-use log::info;
+use crate::export::{ExportDocument, ExportedReport};
+use crate::fsck::{self, Issue};
+use crate::i18n::{self, Lang, Msg};
+use crate::import::{self, ImportError, ImportReport, ImportRow, ImportedAccount, RowError};
+use crate::lab;
+use crate::models::{
+    majority_date, AccessAction, AccessLogEntry, AccessLogID, AccessRequest, AccessRequestID,
+    AccessScope, Dispensing, DisputeStatus, LabResult, LabResultID, MedicalFolder, MedicalReport,
+    Notification, NotificationID, NotificationKind, PersonalData, PolicyChange, PolicyChangeID,
+    PolicyChangeRequest, PolicyRevision, PolicyRule, Prescription, PrescriptionID, ProxyID,
+    ProxyKind, ProxyRelationship, ProxyScope, ReferenceRange, ReportID, RequestStatus, Role,
+    RoleChangeID, RoleChangeRequest, RoleChangeStatus, UserData, UserID,
+};
+use crate::research::{PatientFacts, PseudonymKey, ResearchDataset};
+use crate::t;
+use crate::utils::input_validation::{
+    password_input_validation, password_validation, AVSNumber, Username,
+};
+use crate::utils::password_utils::{
+    code_digest, generate_code, generate_password, generate_token, hash, verify, PWHash,
+};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use derive_more::Display;
+use log::{error, info, warn};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::PathBuf;
+use strum_macros::EnumIter;
 use thiserror::Error;
 
+/// Le délai laissé à un deuxième administrateur pour approuver un
+/// changement de rôle privilégié
+const ROLE_CHANGE_VALIDITY: TimeDelta = TimeDelta::hours(48);
+
 pub struct Service {
     user: Option<UserID>,
     db: Database,
@@ -19,27 +51,231 @@ pub enum ServiceError {
     #[error(transparent)]
     AccessDenied(#[from] AccessDenied),
 
-    #[error("Utilisateur déja inscrit")]
+    #[error("{}", Msg::ErrUserAlreadyExists)]
     UserAlreadyExists,
 
     #[error(transparent)]
     DBError(#[from] DBError),
 
-    #[error("Pas de dossier pour ce patient")]
+    #[error("{}", Msg::ErrNotAPatient)]
     NotAPatient,
 
-    #[error("Rapport inexistant")]
+    #[error("{}", Msg::ErrNotADoctor)]
+    NotADoctor,
+
+    #[error("{}", Msg::ErrNoSuchReport)]
     NoSuchReport,
+
+    #[error("{}", Msg::ErrAlreadyBootstrapped)]
+    AlreadyBootstrapped,
+
+    #[error("{}", Msg::ErrNoSuchRequest)]
+    NoSuchRequest,
+
+    #[error("{}", Msg::ErrRequestAlreadyPending)]
+    RequestAlreadyPending,
+
+    #[error("{}", Msg::ErrNoSuchAccess)]
+    NoSuchAccess,
+
+    #[error("{}", Msg::ErrWeakPassword)]
+    WeakPassword,
+
+    #[error("{}", Msg::ErrStorage)]
+    Storage(#[from] std::io::Error),
+
+    #[error("{}", Msg::ErrCannotReassign)]
+    CannotReassign,
+
+    #[error("{}", Msg::ErrInvalidProxy)]
+    InvalidProxy,
+
+    #[error("{}", Msg::ErrNoSuchProxy)]
+    NoSuchProxy,
+
+    #[error("{}", Msg::ErrInvalidPrescription)]
+    InvalidPrescription,
+
+    #[error("{}", Msg::ErrInvalidLabResult)]
+    InvalidLabResult,
+
+    /// Aussi retournée pour un code inconnu, déjà utilisé ou remplacé
+    #[error("{}", Msg::ErrNoSuchPrescription)]
+    NoSuchPrescription,
+
+    #[error(transparent)]
+    Policy(#[from] PolicyError),
+
+    /// Les noms des scénarios d'accès qui échouent
+    #[error("{}", t!(ErrPolicyScenarios, .0.join(", ")))]
+    PolicyScenarios(Vec<&'static str>),
+
+    #[error("{}", Msg::ErrPolicyLockout)]
+    PolicyLockout,
+
+    #[error("{}", Msg::ErrNoSuchRoleChange)]
+    NoSuchRoleChange,
+
+    #[error("{}", Msg::ErrNoSuchPolicyChange)]
+    NoSuchPolicyChange,
+
+    #[error("{}", Msg::ErrPolicyChangeExpired)]
+    PolicyChangeExpired,
+
+    #[error("{}", Msg::ErrRoleChangeAlreadyPending)]
+    RoleChangeAlreadyPending,
+
+    #[error("{}", Msg::ErrRoleChangeExpired)]
+    RoleChangeExpired,
+
+    #[error("{}", Msg::ErrRoleChangeStale)]
+    RoleChangeStale,
+
+    #[error("{}", Msg::ErrNoReviewer)]
+    NoReviewer,
+
+    #[error("{}", Msg::ErrTooFewAdmins)]
+    TooFewAdmins,
+
+    #[error("{}", Msg::ErrLastAdmin)]
+    LastAdmin,
+
+    #[error("{}", Msg::ErrAdminClosure)]
+    AdminClosure,
+}
+
+/// Le résultat d'un changement de rôle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoleUpdate {
+    /// Le nouveau rôle est déjà en vigueur
+    Applied,
+    /// Le changement attend l'approbation d'un autre administrateur
+    Pending(RoleChangeID),
+}
+
+/// Le résultat d'une modification des règles d'accès
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyUpdate {
+    /// La modification est en vigueur, avec ce numéro de version
+    Applied(usize),
+    /// La modification attend l'approbation d'un autre administrateur
+    Pending(PolicyChangeID),
+}
+
+/// Ce que deviennent, à la fermeture d'un compte, les rapports, ordonnances
+/// et résultats d'analyse que son titulaire a écrits pour d'autres patients.
+/// Les délivrances d'un pharmacien ne se réassignent pas: son compte est
+/// toujours pseudonymisé.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthoredReports {
+    /// Attribués à un médecin qui a déjà accès à chacun de ces documents
+    Reassign(UserID),
+    /// Gardés sous un auteur pseudonyme: le compte est anonymisé et
+    /// verrouillé au lieu d'être supprimé
+    Pseudonymize,
+}
+
+/// Les termes d'une nouvelle relation de représentation
+#[derive(Debug, Clone, Copy)]
+pub struct ProxyTerms {
+    pub kind: ProxyKind,
+    pub scope: ProxyScope,
+    pub start: NaiveDate,
+    /// Pour un représentant légal, au plus tard la majorité du représenté
+    pub end: Option<NaiveDate>,
+    /// Date de naissance du représenté, obligatoire pour un représentant
+    /// légal si elle n'est pas déjà enregistrée
+    pub birth_date: Option<NaiveDate>,
+}
+
+/// Un résultat d'analyse à enregistrer
+#[derive(Debug, Clone)]
+pub struct LabMeasurement {
+    pub analyte: String,
+    pub value: f64,
+    pub unit: String,
+    pub range: ReferenceRange,
+    pub taken_at: DateTime<Utc>,
+}
+
+/// Ce que prescrit le médecin
+#[derive(Debug, Clone)]
+pub struct PrescriptionTerms {
+    pub drug: String,
+    pub dose: String,
+    pub duration_days: u32,
+    pub repeats: u32,
+}
+
+/// L'ordre d'affichage des rapports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, EnumIter, Display)]
+pub enum ReportSort {
+    #[default]
+    #[display("{}", Msg::SortNewestFirst)]
+    NewestFirst,
+    #[display("{}", Msg::SortOldestFirst)]
+    OldestFirst,
+    #[display("{}", Msg::SortRecentlyUpdated)]
+    RecentlyUpdated,
+    #[display("{}", Msg::SortTitle)]
+    Title,
+}
+
+/// Filtres et tri appliqués à la liste des rapports d'un patient
+#[derive(Debug, Clone, Default)]
+pub struct ReportQuery {
+    pub sort: ReportSort,
+    pub author: Option<UserID>,
+    /// Date de création minimale (incluse)
+    pub from: Option<NaiveDate>,
+    /// Date de création maximale (incluse)
+    pub to: Option<NaiveDate>,
+    /// Mots devant tous apparaître dans le titre, sans tenir compte de la casse
+    pub keywords: Vec<String>,
+}
+
+impl ReportQuery {
+    pub fn matches(&self, report: &MedicalReport) -> bool {
+        let created = report.created_at.date_naive();
+        let title = report.title.to_lowercase();
+
+        self.author.is_none_or(|author| report.author == author)
+            && self.from.is_none_or(|from| created >= from)
+            && self.to.is_none_or(|to| created <= to)
+            && self
+                .keywords
+                .iter()
+                .all(|keyword| title.contains(&keyword.to_lowercase()))
+    }
+
+    fn sort(&self, reports: &mut [&MedicalReport]) {
+        match self.sort {
+            ReportSort::NewestFirst => reports.sort_by_key(|r| Reverse(r.created_at)),
+            ReportSort::OldestFirst => reports.sort_by_key(|r| r.created_at),
+            ReportSort::RecentlyUpdated => reports.sort_by_key(|r| Reverse(r.updated_at)),
+            ReportSort::Title => reports.sort_by_key(|r| r.title.to_lowercase()),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
 pub enum LoginError {
-    #[error("Mauvais mot de passe ou utilisateur inconnu")]
+    #[error("{}", Msg::ErrInvalidCredentials)]
     InvalidCredentials,
 }
 
 impl Service {
-    pub fn new(db: Database, enforcer: Enforcer) -> Self {
+    pub fn new(db: Database, mut enforcer: Enforcer) -> Self {
+        // Les règles modifiées depuis l'application remplacent celles du
+        // fichier, si elles en dérivent et passent encore les vérifications
+        if let Some(revision) = db.current_policy() {
+            if let Err(e) = apply_revision(&db, &mut enforcer, revision) {
+                error!(
+                    "Règles d'accès de la base (version {}) rejetées, règles signées utilisées: {e}",
+                    revision.version
+                );
+            }
+        }
         Self {
             db,
             user: None,
@@ -54,8 +290,36 @@ impl Service {
     /// Enregistre un nouvel utilisateur (Patient ou Docteur) dans la base de données.
     pub fn register(&mut self, username: Username) -> Result<UserID, ServiceError> {
         let password = password_input_validation(username.as_ref());
-        let password = hash(&password);
+        self.register_with_password(username, &password)
+    }
+
+    /// Enregistre un nouvel utilisateur avec un mot de passe déjà saisi,
+    /// qui doit être suffisamment robuste.
+    pub fn register_with_password(
+        &mut self,
+        username: Username,
+        password: &str,
+    ) -> Result<UserID, ServiceError> {
+        let password = Self::hash_new_password(&username, password)?;
+        self.register_with_hash(username, password)
+    }
 
+    /// Vérifie la robustesse d'un nouveau mot de passe et le hache. Le
+    /// hachage est coûteux: l'API HTTP le fait hors du verrou du service.
+    pub fn hash_new_password(username: &Username, password: &str) -> Result<PWHash, ServiceError> {
+        if !password_validation(password, username.as_ref()) {
+            return Err(ServiceError::WeakPassword);
+        }
+        Ok(hash(password))
+    }
+
+    /// Enregistre un nouvel utilisateur avec un mot de passe déjà haché par
+    /// `hash_new_password`
+    pub fn register_with_hash(
+        &mut self,
+        username: Username,
+        password: PWHash,
+    ) -> Result<UserID, ServiceError> {
         if self.db.lookup_username(&username).is_some() {
             return Err(ServiceError::UserAlreadyExists);
         }
@@ -77,6 +341,56 @@ impl Service {
         Ok(new_uid)
     }
 
+    /// Indique si la base ne contient encore aucun administrateur
+    /// et qu'un amorçage est nécessaire.
+    pub fn needs_bootstrap(&self) -> bool {
+        !self.db.is_bootstrapped()
+    }
+
+    /// Crée ensemble les administrateurs initiaux d'une installation vierge.
+    /// Il en faut au moins deux pour que les changements de rôle privilégiés
+    /// puissent être approuvés.
+    ///
+    /// Cette opération n'est possible qu'une seule fois: elle est refusée
+    /// dès qu'un administrateur existe ou que l'amorçage a déjà eu lieu.
+    pub fn bootstrap_admins(
+        &mut self,
+        admins: &[(Username, &str)],
+    ) -> Result<Vec<UserID>, ServiceError> {
+        if self.db.is_bootstrapped() {
+            for (username, _) in admins {
+                warn!("Tentative d'amorçage refusée pour {username}: déjà effectué");
+            }
+            return Err(ServiceError::AlreadyBootstrapped);
+        }
+        if admins.len() < 2 {
+            return Err(ServiceError::TooFewAdmins);
+        }
+
+        for (i, (username, _)) in admins.iter().enumerate() {
+            let repeated = admins[..i].iter().any(|(other, _)| other == username);
+            if repeated || self.db.lookup_username(username).is_some() {
+                return Err(ServiceError::UserAlreadyExists);
+            }
+        }
+
+        let mut ids = Vec::with_capacity(admins.len());
+        for (username, password) in admins {
+            let new_uid = UserID::new();
+            warn!("Amorçage: administrateur initial {username} ({new_uid}) créé");
+            self.db.store_user(UserData {
+                id: new_uid,
+                role: Role::Admin,
+                username: username.clone(),
+                password: hash(password),
+                medical_folder: None,
+            });
+            ids.push(new_uid);
+        }
+        self.db.mark_bootstrapped();
+        Ok(ids)
+    }
+
     /// Obtient les données courantes de l'utilisateur connecté
     fn get_subject(&self) -> Option<&UserData> {
         self.db.get_user(self.user?).ok()
@@ -84,11 +398,109 @@ impl Service {
 
     /// Crée un contexte d'autorisation ayant l'utilisateur connecté comme sujet
     fn enforce(&self) -> Result<Context<'_>, ServiceError> {
+        self.enforce_as(self.user.ok_or(AccessDenied)?)
+    }
+
+    /// Crée un contexte d'autorisation ayant l'utilisateur donné comme sujet
+    fn enforce_as(&self, user: UserID) -> Result<Context<'_>, ServiceError> {
         let subject = self
-            .get_subject()
-            .ok_or(ServiceError::AccessDenied(AccessDenied))?;
+            .db
+            .get_user(user)
+            .map_err(|_| ServiceError::AccessDenied(AccessDenied))?;
+
+        let today = Utc::now().date_naive();
+        Ok(self
+            .enforcer
+            .with_subject(subject)
+            .with_proxies(self.db.list_proxies(subject.id), today))
+    }
+
+    /// Nom de l'utilisateur connecté, pour les messages de notification
+    fn actor_name(&self) -> String {
+        self.get_subject()
+            .map(|user| user.username.to_string())
+            .unwrap_or_default()
+    }
+
+    /// Envoie une notification à un utilisateur, sauf s'il est lui-même
+    /// l'auteur de l'action ou s'il a désactivé ce type de notification.
+    /// Le message est rédigé dans la langue du destinataire.
+    fn notify(
+        &mut self,
+        recipient: UserID,
+        kind: NotificationKind,
+        message: Msg,
+        args: &[&dyn Display],
+    ) {
+        if self.user == Some(recipient) || self.db.is_muted(recipient, kind) {
+            return;
+        }
+
+        let lang = self
+            .db
+            .get_language(recipient)
+            .unwrap_or_else(i18n::default_lang);
+        let message = i18n::with_lang(lang, || message.format(args));
+
+        self.db.store_notification(Notification {
+            id: NotificationID::new(),
+            recipient,
+            kind,
+            message,
+            created_at: Utc::now(),
+            read: false,
+        });
+    }
+
+    /// Enregistre dans l'historique du patient une lecture réussie de ses
+    /// données par un tiers.
+    fn record_access(&mut self, patient: UserID, action: AccessAction) {
+        let Some(accessor) = self.get_subject() else {
+            return;
+        };
+        if accessor.id == patient {
+            return;
+        }
+
+        let entry = AccessLogEntry {
+            id: AccessLogID::new(),
+            patient,
+            accessor: accessor.id,
+            accessor_role: accessor.role,
+            action,
+            at: Utc::now(),
+            dispute: DisputeStatus::Undisputed,
+            as_proxy: self.proxy_kind(patient),
+        };
+        if let Some(kind) = entry.as_proxy {
+            info!(
+                "{} agit pour {patient} en tant que {kind:?}: {action:?}",
+                accessor.id
+            );
+        }
+        self.db.store_access_log(entry);
+    }
+
+    /// Enregistre dans l'historique du patient une modification faite par
+    /// l'un de ses représentants. Les modifications faites par le patient
+    /// lui-même, un médecin ou un administrateur ne sont pas enregistrées.
+    fn record_proxy_action(&mut self, patient: UserID, action: AccessAction) {
+        if self.proxy_kind(patient).is_some() {
+            self.record_access(patient, action);
+        }
+    }
 
-        Ok(self.enforcer.with_subject(subject))
+    /// Le type de la relation par laquelle l'utilisateur connecté représente
+    /// ce patient aujourd'hui, s'il y en a une
+    fn proxy_kind(&self, patient: UserID) -> Option<ProxyKind> {
+        let user = self.user.filter(|&user| user != patient)?;
+        let today = Utc::now().date_naive();
+        self.db
+            .list_proxies(user)
+            .find(|proxy| {
+                proxy.proxy == user && proxy.dependant == patient && proxy.is_active(today)
+            })
+            .map(|proxy| proxy.kind)
     }
 
     /// Vérifie si le mot de passe est correct, et si oui, enregistre
@@ -104,205 +516,3487 @@ impl Service {
         Ok(user.id)
     }
 
-    /// Ferme la session
-    pub fn logout(&mut self) {
-        self.user = None
+    /// Le mot de passe haché d'un utilisateur, pour vérifier ses identifiants
+    /// hors du service avant `login_verified`
+    pub fn password_hash(&self, username: &Username) -> Option<(UserID, PWHash)> {
+        let user = self.db.lookup_username(username)?;
+        Some((user.id, user.password.clone()))
     }
 
-    /// Cherche un ID utilisateur par nom d'utilisateur
-    pub fn lookup_user(&self, username: &Username) -> Option<UserID> {
-        Some(self.db.lookup_username(username)?.id)
+    /// Ouvre la session d'un utilisateur dont le mot de passe a été vérifié
+    /// contre `verified`, s'il n'a pas changé depuis
+    pub fn login_verified(
+        &mut self,
+        user_id: UserID,
+        verified: &PWHash,
+    ) -> Result<UserID, LoginError> {
+        match self.db.get_user(user_id) {
+            Ok(user) if user.password == *verified => {
+                self.user = Some(user_id);
+                Ok(user_id)
+            }
+            _ => Err(LoginError::InvalidCredentials),
+        }
     }
 
-    /// Change le role d'un utilisateur
-    pub fn update_role(&mut self, user_id: UserID, new_role: Role) -> Result<(), ServiceError> {
-        // Only an admin can do that, authorization check
-        let user = self
-            .db
-            .get_user(user_id)
-            .map_err(ServiceError::from)?;
-
-        // Perform authorization check
-        self.enforce()?.update_role(user, new_role)?;
+    /// Vrai si l'utilisateur connecté doit remplacer son mot de passe initial
+    pub fn password_change_required(&self) -> bool {
+        self.user
+            .is_some_and(|user| self.db.password_change_required(user))
+    }
 
-        // Actual update
-        let user = self.db.get_user_mut(user_id)?;
-        user.role = new_role;
+    /// Remplace le mot de passe de l'utilisateur connecté
+    pub fn change_password(&mut self, password: &str) -> Result<(), ServiceError> {
+        let user_id = self.user.ok_or(ServiceError::AccessDenied(AccessDenied))?;
+        let username = &self.db.get_user(user_id)?.username;
+        let password = Self::hash_new_password(username, password)?;
+        self.set_password(password)
+    }
 
+    /// Remplace le mot de passe de l'utilisateur connecté par un mot de
+    /// passe déjà haché par `hash_new_password`
+    pub fn set_password(&mut self, password: PWHash) -> Result<(), ServiceError> {
+        let user_id = self.user.ok_or(ServiceError::AccessDenied(AccessDenied))?;
+        self.db.get_user_mut(user_id)?.password = password;
+        self.db.clear_password_change(user_id);
+        info!("Mot de passe de {user_id} changé");
         Ok(())
     }
 
-    /// Récupère les données d'un utilisateur
-    pub fn get_data(&self, user_id: UserID) -> Result<&UserData, ServiceError> {
+    /// Importe en masse des médecins et des patients depuis un fichier CSV
+    /// (voir le module `import` pour le format).
+    ///
+    /// L'import est atomique: en essai à blanc, ou si une ligne est en
+    /// erreur, aucun compte n'est créé et le rapport liste les erreurs.
+    /// Sinon, chaque compte reçoit un mot de passe initial à usage unique,
+    /// à changer à la première connexion.
+    pub fn import_users(
+        &mut self,
+        input: impl Read,
+        dry_run: bool,
+    ) -> Result<ImportReport, ServiceError> {
         // Authorization check
-        let user = self
-            .db
-            .get_user(user_id)
-            .map_err(ServiceError::from)?;
+        self.enforce()?.import_users()?;
 
-        self.enforce()?.read_data(user)?;
+        let parsed = import::parse(input);
+        let mut report = ImportReport {
+            rows: parsed.len(),
+            ..Default::default()
+        };
+        let mut rows: Vec<ImportRow> = Vec::new();
+        for row in parsed {
+            match row {
+                Ok(row) => rows.push(row),
+                Err(error) => report.errors.push(error),
+            }
+        }
 
-        // Retrieval of the info
-        let user = self.db.get_user(user_id)?;
+        // Les médecins du fichier n'ont pas encore leur rôle: seuls ceux de
+        // la base peuvent être médecins traitants
+        let mut seen = HashSet::new();
+        for row in &rows {
+            let doctor_known = |doctor: &Username| {
+                self.db
+                    .lookup_username(doctor)
+                    .is_some_and(|user| user.role == Role::Doctor)
+            };
 
-        Ok(user)
+            let error = if !seen.insert(&row.username) {
+                Some(ImportError::DuplicateUsername(row.username.clone()))
+            } else if self.db.lookup_username(&row.username).is_some() {
+                Some(ImportError::UserExists(row.username.clone()))
+            } else {
+                row.doctor
+                    .as_ref()
+                    .filter(|doctor| !doctor_known(doctor))
+                    .map(|doctor| ImportError::UnknownDoctor(doctor.clone()))
+            };
+            if let Some(error) = error {
+                report.errors.push(RowError {
+                    line: row.line,
+                    error,
+                });
+            }
+        }
+        report.errors.sort_by_key(|error| error.line);
+
+        // Les rôles privilégiés sont demandés comme par `update_role`, pour
+        // des comptes créés comme patients
+        let needs_approval = |row: &ImportRow| Role::Patient.change_needs_approval(row.role);
+        if report.errors.is_empty() && rows.iter().any(needs_approval) {
+            self.reviewers(&[self.user.ok_or(AccessDenied)?])?;
+        }
+
+        let actor = self.actor_name();
+        if dry_run || !report.errors.is_empty() {
+            info!(
+                "Import CSV par {actor}: {} ligne(s), {} erreur(s), aucun compte créé",
+                report.rows,
+                report.errors.len()
+            );
+            return Ok(report);
+        }
+
+        let mut treating_doctors = Vec::new();
+        let mut pending_roles = Vec::new();
+        for row in rows {
+            let initial_password = generate_password();
+            let id = UserID::new();
+            let (role, pending_role) = if needs_approval(&row) {
+                pending_roles.push((id, row.role));
+                (Role::Patient, Some(row.role))
+            } else {
+                (row.role, None)
+            };
+            self.db.store_user(UserData {
+                id,
+                role,
+                username: row.username.clone(),
+                password: hash(&initial_password),
+                medical_folder: row.personal_data.map(MedicalFolder::new),
+            });
+            self.db.require_password_change(id);
+            if let Some(doctor) = row.doctor {
+                treating_doctors.push((id, row.username.clone(), doctor));
+            }
+            report.accounts.push(ImportedAccount {
+                username: row.username,
+                role,
+                pending_role,
+                initial_password,
+            });
+        }
+        for (id, role) in pending_roles {
+            self.request_role_change(id, role)?;
+        }
+
+        for (patient_id, patient_name, doctor) in treating_doctors {
+            let Some(doctor_id) = self.lookup_user(&doctor) else {
+                continue;
+            };
+            if let Some(folder) = self.db.get_user_mut(patient_id)?.medical_folder.as_mut() {
+                folder.grant(doctor_id, AccessScope::Folder);
+            }
+            self.notify(
+                doctor_id,
+                NotificationKind::DoctorAdded,
+                Msg::NotifyAccessGranted,
+                &[&patient_name],
+            );
+        }
+
+        warn!(
+            "Import CSV par {actor}: {} compte(s) créé(s)",
+            report.accounts.len()
+        );
+        Ok(report)
     }
 
-    /// Change les données personnelles d'un utilisateur. Si le dossier médical
-    /// n'existait pas, il est créé pour l'occasion.
-    pub fn update_data(
-        &mut self,
-        user_id: UserID,
-        personal_data: PersonalData,
-    ) -> Result<(), ServiceError> {
+    /// Prépare un jeu de données désidentifié pour la recherche (voir le
+    /// module `research`). Seuls les patients ayant un dossier y figurent.
+    pub fn research_export(
+        &self,
+        key: &PseudonymKey,
+        k: usize,
+    ) -> Result<ResearchDataset, ServiceError> {
         // Authorization check
-        let user = self
-            .db
-            .get_user(user_id)
-            .map_err(ServiceError::from)?;
+        self.enforce()?.research_export()?;
 
-        self.enforce()?.update_data(user)?;
+        let patients = self.db.list_users().filter_map(|user| {
+            let folder = user.medical_folder.as_ref()?;
+            Some(PatientFacts {
+                id: user.id,
+                blood_type: folder.personal_data.blood_type,
+                report_count: self.db.list_patient_reports(user.id).count(),
+            })
+        });
+        let dataset = ResearchDataset::build(patients, key, k);
 
-        let folder = &mut self.db.get_user_mut(user_id)?.medical_folder;
+        warn!(
+            "Export pour la recherche par {}: {} patient(s) exporté(s), {} retiré(s) (k = {k})",
+            self.actor_name(),
+            dataset.summary.exported,
+            dataset.summary.suppressed
+        );
+        Ok(dataset)
+    }
 
-        if let Some(folder) = folder {
-            folder.personal_data = personal_data;
-        } else {
-            *folder = Some(MedicalFolder::new(personal_data));
-        }
-        Ok(())
+    /// Cherche les incohérences de la base
+    pub fn check_integrity(&self) -> Result<Vec<Issue>, ServiceError> {
+        // Authorization check
+        self.enforce()?.fsck()?;
+
+        Ok(fsck::check(&self.db))
     }
 
-    /// Efface toutes les données médicales relatives à un patient
-    /// (S'il est également médecin, son rôle de médecin n'est pas
-    /// affecté)
-    pub fn delete_data(&mut self, patient: UserID) -> Result<(), ServiceError> {
+    /// Copie le fichier de la base avant de la réparer
+    pub fn backup_database(&self) -> Result<PathBuf, ServiceError> {
         // Authorization check
-        let user = self
-            .db
-            .get_user(patient)
-            .map_err(ServiceError::from)?;
+        self.enforce()?.fsck()?;
 
-        self.enforce()?.update_data(user)?;
+        Ok(self.db.backup()?)
+    }
 
-        self.db.get_user_mut(patient)?.medical_folder = None;
-        self.db.remove_reports(patient);
-        Ok(())
+    /// Les mesures du cache des décisions d'accès
+    pub fn authorization_metrics(&self) -> Result<CacheStats, ServiceError> {
+        // Authorization check
+        self.enforce()?.read_metrics()?;
+
+        Ok(self.enforcer.cache_stats())
     }
 
-    /// Ecrire un nouveau rapport médical
-    pub fn add_report(
+    /// Les règles d'accès en vigueur
+    pub fn policy_rules(&self) -> Result<Vec<PolicyRule>, ServiceError> {
+        // Authorization check
+        self.enforce()?.manage_policy()?;
+
+        Ok(self.enforcer.rules().ok_or(PolicyError::NotEditable)?)
+    }
+
+    /// Les versions des règles d'accès, de la plus récente à la plus ancienne
+    pub fn policy_history(
+        &self,
+    ) -> Result<impl Iterator<Item = &PolicyRevision> + '_, ServiceError> {
+        // Authorization check
+        self.enforce()?.manage_policy()?;
+
+        Ok(self.db.list_policy_revisions())
+    }
+
+    /// Modifie les règles d'accès. La modification est refusée si les
+    /// règles obtenues échouent à un scénario d'accès, ou si plus aucun
+    /// administrateur existant ne pourrait gérer les règles pour revenir en
+    /// arrière. Une modification des règles des changements de rôle ou de
+    /// la gestion des règles ne fait que déposer une demande, appliquée une
+    /// fois approuvée par un autre administrateur (`review_policy_change`).
+    pub fn change_policy(&mut self, change: PolicyChange) -> Result<PolicyUpdate, ServiceError> {
+        // Authorization check
+        self.enforce()?.manage_policy()?;
+        let author = self.user.ok_or(AccessDenied)?;
+
+        if change.needs_approval() {
+            // Vérifiée dès la demande, puis de nouveau à l'approbation
+            let admins = admins(&self.db);
+            self.enforcer
+                .check_change(&change, |candidate| check_policy(candidate, &admins))?;
+            let id = self.request_policy_change(change)?;
+            return Ok(PolicyUpdate::Pending(id));
+        }
+        let version = self.apply_policy_change(change, author, None)?;
+        Ok(PolicyUpdate::Applied(version))
+    }
+
+    /// Applique une modification des règles déjà autorisée, et retourne le
+    /// numéro de la nouvelle version
+    fn apply_policy_change(
         &mut self,
+        change: PolicyChange,
         author: UserID,
-        patient: UserID,
-        title: String,
-        content: String,
-    ) -> Result<(), ServiceError> {
-        let report = MedicalReport {
-            id: ReportID::new(),
-            title,
-            author,
-            patient,
-            content,
-        };
+        reviewer: Option<UserID>,
+    ) -> Result<usize, ServiceError> {
+        let admins = admins(&self.db);
+        let rules = self
+            .enforcer
+            .change_rules(&change, |candidate| check_policy(candidate, &admins))?;
 
-        let user = self
+        let version = self
             .db
-            .get_user(patient)
-            .map_err(ServiceError::from)?;
+            .current_policy()
+            .map_or(0, |revision| revision.version)
+            + 1;
+        warn!(
+            "Règles d'accès modifiées par {} (version {version}): {change}",
+            self.actor_name()
+        );
+        let base = self.enforcer.base_digest().unwrap_or_default().to_owned();
+        self.db.store_policy_revision(PolicyRevision {
+            version,
+            base,
+            author,
+            reviewer,
+            at: Utc::now(),
+            change,
+            rules,
+        });
+        Ok(version)
+    }
 
-        self.enforce()?.add_report(user, &report)?;
+    /// Dépose une demande de modification des règles, et prévient les
+    /// autres administrateurs, qui seuls peuvent l'approuver
+    fn request_policy_change(
+        &mut self,
+        change: PolicyChange,
+    ) -> Result<PolicyChangeID, ServiceError> {
+        let requester = self.user.ok_or(AccessDenied)?;
+        self.expire_pending_changes();
 
-        self.db.store_report(report);
-        Ok(())
+        let reviewers = self.reviewers(&[requester])?;
+
+        let now = Utc::now();
+        let request = PolicyChangeRequest {
+            id: PolicyChangeID::new(),
+            change,
+            requester,
+            requested_at: now,
+            expires_at: now + ROLE_CHANGE_VALIDITY,
+            status: RoleChangeStatus::Pending,
+            reviewer: None,
+        };
+        let actor = self.actor_name();
+        warn!(
+            "Modification des règles {} demandée par {actor}: {}, à approuver avant le {}",
+            request.id, request.change, request.expires_at
+        );
+        let (id, change) = (request.id, request.change.clone());
+        self.db.store_policy_change(request);
+
+        for reviewer in reviewers {
+            self.notify(
+                reviewer,
+                NotificationKind::RoleChanged,
+                Msg::NotifyPolicyChangeRequested,
+                &[&actor, &change],
+            );
+        }
+        Ok(id)
     }
 
-    pub fn list_reports(&self, user_id: UserID) -> impl Iterator<Item = &MedicalReport> + '_ {
-        self.enforce().ok().into_iter().flat_map(move |ctx| {
-            self.db
-                .list_reports()
-                .filter(move |report| report.patient == user_id)
-                .filter(move |report| {
-                    let Ok(patient) = self.db.get_user(report.patient) else {
-                        return false;
-                    };
-
-                    ctx.read_report(report, patient).is_ok()
-                })
+    /// Liste les modifications des règles en attente que l'utilisateur
+    /// connecté peut approuver
+    pub fn list_policy_changes(&self) -> impl Iterator<Item = &PolicyChangeRequest> + '_ {
+        let now = Utc::now();
+        let reviewer = self
+            .enforce()
+            .ok()
+            .filter(|ctx| ctx.manage_policy().is_ok())
+            .and(self.user);
+        reviewer.into_iter().flat_map(move |reviewer| {
+            self.db.list_policy_changes().filter(move |change| {
+                change.status == RoleChangeStatus::Pending
+                    && change.expires_at > now
+                    && change.requester != reviewer
+            })
         })
     }
 
-    pub fn list_patients(&self) -> impl Iterator<Item = &UserData> + '_ {
-        self.user
-            .iter()
-            .flat_map(|&u| self.db.get_patients(u))
-            .filter_map(|id| self.db.get_user(id).ok())
+    /// Liste les demandes de modification des règles de l'utilisateur
+    /// connecté encore en attente, qu'il peut annuler
+    pub fn list_own_policy_changes(&self) -> impl Iterator<Item = &PolicyChangeRequest> + '_ {
+        let now = Utc::now();
+        self.user.into_iter().flat_map(move |user| {
+            self.db.list_policy_changes().filter(move |change| {
+                change.status == RoleChangeStatus::Pending
+                    && change.expires_at > now
+                    && change.requester == user
+            })
+        })
     }
 
-    pub fn add_doctor(
-        &mut self,
-        patient_id: UserID,
-        doctor_id: UserID,
-    ) -> Result<(), ServiceError> {
+    /// Annule une demande de modification des règles encore en attente.
+    /// Seul son auteur peut le faire.
+    pub fn cancel_policy_change(&mut self, change_id: PolicyChangeID) -> Result<(), ServiceError> {
+        self.expire_pending_changes();
+
         // Authorization check
-        let _patient = self
+        let request = self
             .db
-            .get_user(patient_id)
-            .map_err(ServiceError::from)?;
+            .get_policy_change(change_id)
+            .ok_or(ServiceError::NoSuchPolicyChange)?;
+        if request.status == RoleChangeStatus::Expired {
+            return Err(ServiceError::PolicyChangeExpired);
+        }
+        self.enforce()?.manage_policy()?;
+        if request.status != RoleChangeStatus::Pending || Some(request.requester) != self.user {
+            return Err(AccessDenied.into());
+        }
 
-        let doctor = self
+        warn!(
+            "Modification des règles {change_id} ({}) annulée par {}",
+            request.change,
+            self.actor_name()
+        );
+        let request = self
             .db
-            .get_user(doctor_id)
-            .map_err(ServiceError::from)?;
-
-        self.enforce()?.add_doctor(_patient, doctor)?;
-
-        let patient = self.db.get_user_mut(patient_id)?;
-        patient
-            .medical_folder
-            .as_mut()
-            .map(|f| f.doctors.insert(doctor_id));
+            .get_policy_change_mut(change_id)
+            .ok_or(ServiceError::NoSuchPolicyChange)?;
+        request.status = RoleChangeStatus::Cancelled;
         Ok(())
     }
 
-    pub fn remove_doctor(
+    /// Approuve ou refuse une modification des règles demandée par un autre
+    /// administrateur. Une approbation l'applique, si les règles obtenues
+    /// passent encore les vérifications.
+    pub fn review_policy_change(
         &mut self,
-        patient_id: UserID,
-        doctor_id: UserID,
+        change_id: PolicyChangeID,
+        approve: bool,
     ) -> Result<(), ServiceError> {
+        self.expire_pending_changes();
+
         // Authorization check
-        let patient = self
+        let request = self
             .db
-            .get_user(patient_id)
-            .map_err(ServiceError::from)?;
+            .get_policy_change(change_id)
+            .ok_or(ServiceError::NoSuchPolicyChange)?;
+        if request.status == RoleChangeStatus::Expired {
+            return Err(ServiceError::PolicyChangeExpired);
+        }
+        self.enforce()?.manage_policy()?;
+        let reviewer = self.user.ok_or(AccessDenied)?;
+        if request.status != RoleChangeStatus::Pending || request.requester == reviewer {
+            return Err(AccessDenied.into());
+        }
 
-        let doctor = self
-            .db
-            .get_user(doctor_id)
-            .map_err(ServiceError::from)?;
+        let (change, requester) = (request.change.clone(), request.requester);
+        if approve {
+            self.apply_policy_change(change.clone(), requester, Some(reviewer))?;
+        }
 
-        self.enforce()?.remove_doctor(patient, doctor)?;
+        let status = if approve {
+            RoleChangeStatus::Approved
+        } else {
+            RoleChangeStatus::Denied
+        };
+        let actor = self.actor_name();
+        warn!(
+            "Modification des règles {change_id} ({change}), demandée par {requester}: \
+             {status:?} par {actor}"
+        );
+        let request = self
+            .db
+            .get_policy_change_mut(change_id)
+            .ok_or(ServiceError::NoSuchPolicyChange)?;
+        request.status = status;
+        request.reviewer = Some(reviewer);
+
+        self.notify(
+            requester,
+            NotificationKind::RoleChanged,
+            Msg::NotifyPolicyChangeReviewed,
+            &[&actor, &change, &status],
+        );
+        Ok(())
+    }
+
+    /// Répare une incohérence trouvée par `check_integrity`, et retourne
+    /// les comptes renommés
+    pub fn repair(&mut self, issue: &Issue) -> Result<Vec<(UserID, Username)>, ServiceError> {
+        // Authorization check
+        self.enforce()?.fsck()?;
+
+        warn!("Réparation de la base par {}: {issue:?}", self.actor_name());
+        let repaired = fsck::repair(&mut self.db, issue);
+        self.enforcer.invalidate();
+        Ok(repaired)
+    }
+
+    /// Reprend la session d'un utilisateur déjà authentifié par `login`,
+    /// par exemple à partir d'un jeton de l'API HTTP
+    pub fn resume_session(&mut self, user: UserID) {
+        self.user = Some(user);
+    }
+
+    /// Ferme la session
+    pub fn logout(&mut self) {
+        self.user = None
+    }
+
+    /// Cherche un ID utilisateur par nom d'utilisateur
+    pub fn lookup_user(&self, username: &Username) -> Option<UserID> {
+        Some(self.db.lookup_username(username)?.id)
+    }
+
+    /// Donne le nom d'utilisateur associé à un ID, pour l'affichage
+    pub fn username(&self, user_id: UserID) -> Option<&Username> {
+        Some(&self.db.get_user(user_id).ok()?.username)
+    }
+
+    /// Change le role d'un utilisateur. Donner un rôle privilégié, ou
+    /// retirer le sien à un administrateur, ne fait que déposer une demande,
+    /// appliquée une fois approuvée par un autre administrateur
+    /// (`review_role_change`).
+    pub fn update_role(
+        &mut self,
+        user_id: UserID,
+        new_role: Role,
+    ) -> Result<RoleUpdate, ServiceError> {
+        // Only an admin can do that, authorization check
+        let user = self
+            .db
+            .get_user(user_id)
+            .map_err(ServiceError::from)?;
+
+        // Perform authorization check
+        self.enforce()?.update_role(user, new_role)?;
+        self.check_admin_remains(user, new_role)?;
+
+        if user.role.change_needs_approval(new_role) {
+            let id = self.request_role_change(user_id, new_role)?;
+            return Ok(RoleUpdate::Pending(id));
+        }
+        self.apply_role(user_id, new_role)?;
+        Ok(RoleUpdate::Applied)
+    }
+
+    /// Dépose une demande de changement de rôle, et prévient les
+    /// administrateurs qui peuvent l'approuver: ni son auteur, ni celui dont
+    /// le rôle change
+    fn request_role_change(
+        &mut self,
+        target: UserID,
+        role: Role,
+    ) -> Result<RoleChangeID, ServiceError> {
+        let requester = self.user.ok_or(AccessDenied)?;
+        self.expire_pending_changes();
+
+        let already_pending = self
+            .db
+            .list_role_changes()
+            .any(|change| change.target == target && change.status == RoleChangeStatus::Pending);
+        if already_pending {
+            return Err(ServiceError::RoleChangeAlreadyPending);
+        }
+        let reviewers = self.reviewers(&[requester, target])?;
+        let previous = self.db.get_user(target)?.role;
+
+        let now = Utc::now();
+        let change = RoleChangeRequest {
+            id: RoleChangeID::new(),
+            target,
+            role,
+            previous: Some(previous),
+            requester,
+            requested_at: now,
+            expires_at: now + ROLE_CHANGE_VALIDITY,
+            status: RoleChangeStatus::Pending,
+            reviewer: None,
+        };
+        let actor = self.actor_name();
+        warn!(
+            "Changement de rôle {} demandé par {actor}: {target} en {role:?}, à approuver avant le {}",
+            change.id, change.expires_at
+        );
+        let id = change.id;
+        self.db.store_role_change(change);
+
+        let target_name = self.db.get_user(target)?.username.to_string();
+        for reviewer in reviewers {
+            self.notify(
+                reviewer,
+                NotificationKind::RoleChanged,
+                Msg::NotifyRoleChangeRequested,
+                &[&actor, &target_name, &role],
+            );
+        }
+        Ok(id)
+    }
+
+    /// Liste les changements de rôle en attente que l'utilisateur connecté
+    /// peut approuver
+    pub fn list_role_changes(&self) -> impl Iterator<Item = &RoleChangeRequest> + '_ {
+        let now = Utc::now();
+        let user = self.user;
+        self.enforce().ok().into_iter().flat_map(move |ctx| {
+            self.db
+                .list_role_changes()
+                .filter(move |change| change.expires_at > now)
+                .filter(move |change| Some(change.requester) != user && Some(change.target) != user)
+                .filter(move |change| ctx.review_role_change(change).is_ok())
+        })
+    }
+
+    /// Les administrateurs autres que ceux donnés, qui peuvent approuver une
+    /// demande. Refuse une demande que personne ne pourrait approuver.
+    fn reviewers(&self, excluded: &[UserID]) -> Result<Vec<UserID>, ServiceError> {
+        let reviewers: Vec<UserID> = admins(&self.db)
+            .into_iter()
+            .map(|admin| admin.id)
+            .filter(|admin| !excluded.contains(admin))
+            .collect();
+        if reviewers.is_empty() {
+            return Err(ServiceError::NoReviewer);
+        }
+        Ok(reviewers)
+    }
+
+    /// Liste les demandes de changement de rôle de l'utilisateur connecté
+    /// encore en attente, qu'il peut annuler
+    pub fn list_own_role_changes(&self) -> impl Iterator<Item = &RoleChangeRequest> + '_ {
+        let now = Utc::now();
+        self.enforce().ok().into_iter().flat_map(move |ctx| {
+            self.db
+                .list_role_changes()
+                .filter(move |change| change.expires_at > now)
+                .filter(move |change| ctx.cancel_role_change(change).is_ok())
+        })
+    }
+
+    /// Annule une demande de changement de rôle encore en attente. Seul son
+    /// auteur peut le faire.
+    pub fn cancel_role_change(&mut self, change_id: RoleChangeID) -> Result<(), ServiceError> {
+        self.expire_pending_changes();
+
+        // Authorization check
+        let change = self
+            .db
+            .get_role_change(change_id)
+            .ok_or(ServiceError::NoSuchRoleChange)?;
+        if change.status == RoleChangeStatus::Expired {
+            return Err(ServiceError::RoleChangeExpired);
+        }
+        self.enforce()?.cancel_role_change(change)?;
+        // Quelles que soient les règles, qui peuvent être modifiées
+        if Some(change.requester) != self.user {
+            return Err(AccessDenied.into());
+        }
+
+        warn!(
+            "Changement de rôle {change_id} de {} en {:?} annulé par {}",
+            change.target,
+            change.role,
+            self.actor_name()
+        );
+        let change = self
+            .db
+            .get_role_change_mut(change_id)
+            .ok_or(ServiceError::NoSuchRoleChange)?;
+        change.status = RoleChangeStatus::Cancelled;
+        Ok(())
+    }
+
+    /// Approuve ou refuse un changement de rôle demandé par un autre
+    /// administrateur. Une approbation applique le nouveau rôle, si la
+    /// demande vaut encore: son auteur pourrait toujours la faire, et le
+    /// rôle n'a pas changé entre-temps.
+    pub fn review_role_change(
+        &mut self,
+        change_id: RoleChangeID,
+        approve: bool,
+    ) -> Result<(), ServiceError> {
+        self.expire_pending_changes();
+
+        // Authorization check
+        let change = self
+            .db
+            .get_role_change(change_id)
+            .ok_or(ServiceError::NoSuchRoleChange)?;
+        if change.status == RoleChangeStatus::Expired {
+            return Err(ServiceError::RoleChangeExpired);
+        }
+        self.enforce()?.review_role_change(change)?;
+        let reviewer = self.user.ok_or(AccessDenied)?;
+        // Quelles que soient les règles, qui peuvent être modifiées
+        if reviewer == change.requester || reviewer == change.target {
+            return Err(AccessDenied.into());
+        }
+
+        let (target, role, requester) = (change.target, change.role, change.requester);
+        if approve {
+            self.check_role_change_current(change)?;
+            self.apply_role(target, role)?;
+        }
+
+        let status = if approve {
+            RoleChangeStatus::Approved
+        } else {
+            RoleChangeStatus::Denied
+        };
+        let actor = self.actor_name();
+        warn!(
+            "Changement de rôle {change_id} de {target} en {role:?}, demandé par {requester}: \
+             {status:?} par {actor}"
+        );
+        let change = self
+            .db
+            .get_role_change_mut(change_id)
+            .ok_or(ServiceError::NoSuchRoleChange)?;
+        change.status = status;
+        change.reviewer = Some(reviewer);
+
+        let target_name = self.db.get_user(target)?.username.to_string();
+        self.notify(
+            requester,
+            NotificationKind::RoleChanged,
+            Msg::NotifyRoleChangeReviewed,
+            &[&actor, &target_name, &role, &status],
+        );
+        Ok(())
+    }
+
+    /// Refuse d'appliquer un changement de rôle dépassé: son auteur n'est
+    /// plus administrateur ou ne pourrait plus le demander, ou le rôle de
+    /// l'utilisateur visé a changé depuis la demande
+    fn check_role_change_current(&self, change: &RoleChangeRequest) -> Result<(), ServiceError> {
+        let target = self.db.get_user(change.target)?;
+        let allowed = self.db.get_user(change.requester).is_ok_and(|requester| {
+            requester.role == Role::Admin
+                && self
+                    .enforce_as(requester.id)
+                    .is_ok_and(|ctx| ctx.update_role(target, change.role).is_ok())
+        });
+        if !allowed || change.previous != Some(target.role) {
+            warn!(
+                "Changement de rôle {} de {} en {:?}, demandé par {}: dépassé, non appliqué",
+                change.id, change.target, change.role, change.requester
+            );
+            return Err(ServiceError::RoleChangeStale);
+        }
+        Ok(())
+    }
+
+    /// Marque comme expirées les demandes de changement de rôle ou de
+    /// modification des règles restées sans réponse trop longtemps
+    fn expire_pending_changes(&mut self) {
+        let now = Utc::now();
+        let expired: Vec<RoleChangeID> = self
+            .db
+            .list_role_changes()
+            .filter(|change| change.status == RoleChangeStatus::Pending && change.expires_at <= now)
+            .map(|change| change.id)
+            .collect();
+        for id in expired {
+            if let Some(change) = self.db.get_role_change_mut(id) {
+                warn!(
+                    "Changement de rôle {id} de {} en {:?}, demandé par {}: expiré sans approbation",
+                    change.target, change.role, change.requester
+                );
+                change.status = RoleChangeStatus::Expired;
+            }
+        }
+
+        let expired: Vec<PolicyChangeID> = self
+            .db
+            .list_policy_changes()
+            .filter(|change| change.status == RoleChangeStatus::Pending && change.expires_at <= now)
+            .map(|change| change.id)
+            .collect();
+        for id in expired {
+            if let Some(change) = self.db.get_policy_change_mut(id) {
+                warn!(
+                    "Modification des règles {id} ({}), demandée par {}: expirée sans approbation",
+                    change.change, change.requester
+                );
+                change.status = RoleChangeStatus::Expired;
+            }
+        }
+    }
+
+    /// Refuse de retirer son rôle au dernier administrateur
+    fn check_admin_remains(&self, user: &UserData, new_role: Role) -> Result<(), ServiceError> {
+        let admins = self
+            .db
+            .list_users()
+            .filter(|user| user.role == Role::Admin)
+            .count();
+        if user.role == Role::Admin && new_role != Role::Admin && admins <= 1 {
+            return Err(ServiceError::LastAdmin);
+        }
+        Ok(())
+    }
+
+    /// Applique un nouveau rôle, déjà autorisé
+    fn apply_role(&mut self, user_id: UserID, new_role: Role) -> Result<(), ServiceError> {
+        self.check_admin_remains(self.db.get_user(user_id)?, new_role)?;
+        self.db.get_user_mut(user_id)?.role = new_role;
+        // Les décisions en cache ont pu dépendre de l'ancien rôle
+        self.enforcer.invalidate();
+
+        self.notify(
+            user_id,
+            NotificationKind::RoleChanged,
+            Msg::NotifyRoleChanged,
+            &[&new_role],
+        );
+        Ok(())
+    }
+
+    /// Récupère les données d'un utilisateur
+    pub fn get_data(&mut self, user_id: UserID) -> Result<&UserData, ServiceError> {
+        // Authorization check
+        let user = self
+            .db
+            .get_user(user_id)
+            .map_err(ServiceError::from)?;
+
+        self.enforce()?.read_data(user)?;
+
+        self.record_access(user_id, AccessAction::ReadData);
+        let actor = self.actor_name();
+        self.notify(
+            user_id,
+            NotificationKind::DataRead,
+            Msg::NotifyFolderRead,
+            &[&actor],
+        );
 
-        let patient = self.db.get_user_mut(patient_id)?;
-        patient
+        // Retrieval of the info
+        let user = self.db.get_user(user_id)?;
+
+        Ok(user)
+    }
+
+    /// Donne le numéro AVS complet d'un patient, masqué partout ailleurs.
+    /// L'affichage est enregistré dans l'historique des accès du patient.
+    pub fn reveal_avs(&mut self, user_id: UserID) -> Result<String, ServiceError> {
+        let user = self.db.get_user(user_id)?;
+
+        self.enforce()?.reveal_avs(user)?;
+
+        let avs_number = user
             .medical_folder
-            .as_mut()
-            .map(|f| f.doctors.remove(&doctor_id));
+            .as_ref()
+            .ok_or(ServiceError::NotAPatient)?
+            .personal_data
+            .avs_number
+            .reveal()
+            .to_owned();
+        info!("Numéro AVS de {user_id} affiché pour {}", self.actor_name());
+        self.record_access(user_id, AccessAction::RevealAvs);
+        Ok(avs_number)
+    }
+
+    /// Cherche les patients ayant ce numéro AVS, grâce à l'index aveugle
+    pub fn find_by_avs(&self, avs_number: &AVSNumber) -> Result<Vec<UserID>, ServiceError> {
+        self.enforce()?.search_avs()?;
+
+        let patients: Vec<UserID> = self.db.find_by_avs(avs_number).collect();
+        warn!(
+            "Recherche par numéro AVS par {}: {} résultat(s)",
+            self.actor_name(),
+            patients.len()
+        );
+        Ok(patients)
+    }
+
+    /// Change les données personnelles d'un utilisateur. Si le dossier médical
+    /// n'existait pas, il est créé pour l'occasion.
+    pub fn update_data(
+        &mut self,
+        user_id: UserID,
+        personal_data: PersonalData,
+    ) -> Result<(), ServiceError> {
+        // Authorization check
+        let user = self
+            .db
+            .get_user(user_id)
+            .map_err(ServiceError::from)?;
+
+        self.enforce()?.update_data(user)?;
+
+        {
+            let folder = &mut self.db.get_user_mut(user_id)?.medical_folder;
+
+            if let Some(folder) = folder {
+                folder.personal_data = personal_data;
+            } else {
+                *folder = Some(MedicalFolder::new(personal_data));
+            }
+        }
+        self.enforcer.invalidate();
+        self.record_proxy_action(user_id, AccessAction::UpdateData);
         Ok(())
     }
 
-    pub fn update_report(
+    /// Efface toutes les données médicales relatives à un patient
+    /// (S'il est également médecin, son rôle de médecin n'est pas
+    /// affecté)
+    pub fn delete_data(&mut self, patient: UserID) -> Result<(), ServiceError> {
+        // Authorization check
+        let user = self
+            .db
+            .get_user(patient)
+            .map_err(ServiceError::from)?;
+
+        self.enforce()?.delete_data(user)?;
+
+        self.db.get_user_mut(patient)?.medical_folder = None;
+        self.enforcer.invalidate();
+        self.db.remove_reports(patient);
+        self.db.remove_prescriptions(patient);
+        self.db.remove_lab_results(patient);
+
+        let actor = self.actor_name();
+        self.notify(
+            patient,
+            NotificationKind::DataDeleted,
+            Msg::NotifyFolderDeleted,
+            &[&actor],
+        );
+        Ok(())
+    }
+
+    /// Ferme un compte: retire tous les accès donnés par l'utilisateur et
+    /// à l'utilisateur, efface son dossier et ses rapports, puis supprime
+    /// son compte. S'il a écrit des rapports, des ordonnances ou des
+    /// résultats d'analyse pour d'autres patients, ils sont réassignés ou
+    /// pseudonymisés selon `authored`.
+    pub fn close_account(
         &mut self,
-        report_id: ReportID,
-        content: String,
+        user_id: UserID,
+        authored: AuthoredReports,
     ) -> Result<(), ServiceError> {
+        // Authorization check
+        let user = self.db.get_user(user_id)?;
+        self.enforce()?.close_account(user)?;
+
+        // Un administrateur perd d'abord son rôle, par un changement de rôle
+        // approuvé par un second administrateur
+        if user.role == Role::Admin {
+            return Err(ServiceError::AdminClosure);
+        }
+
+        let authored_reports: Vec<ReportID> = self
+            .db
+            .list_reports()
+            .filter(|report| report.author == user_id && report.patient != user_id)
+            .map(|report| report.id)
+            .collect();
+        let authored_prescriptions: Vec<PrescriptionID> = self
+            .db
+            .list_all_prescriptions()
+            .filter(|prescription| {
+                prescription.prescriber == user_id && prescription.patient != user_id
+            })
+            .map(|prescription| prescription.id)
+            .collect();
+        let authored_results: Vec<LabResultID> = self
+            .db
+            .list_all_lab_results()
+            .filter(|result| result.author == user_id && result.patient != user_id)
+            .map(|result| result.id)
+            .collect();
+        let dispensed = self.db.list_all_prescriptions().any(|prescription| {
+            prescription.patient != user_id
+                && prescription
+                    .dispensings
+                    .iter()
+                    .any(|dispensing| dispensing.pharmacist == user_id)
+        });
+        let authored_count =
+            authored_reports.len() + authored_prescriptions.len() + authored_results.len();
+
+        // Le nouvel auteur doit déjà pouvoir lire chaque document: la
+        // réassignation ne donne accès à aucune nouvelle donnée
+        if let AuthoredReports::Reassign(doctor_id) = authored {
+            let doctor = self.db.get_user(doctor_id)?;
+            if doctor_id == user_id || doctor.role != Role::Doctor {
+                return Err(ServiceError::CannotReassign);
+            }
+            let context = self.enforcer.with_subject(doctor);
+            for &report_id in &authored_reports {
+                let report = self
+                    .db
+                    .get_report(report_id)
+                    .ok_or(ServiceError::NoSuchReport)?;
+                let patient = self.db.get_user(report.patient)?;
+                context
+                    .read_report(report, patient)
+                    .map_err(|_| ServiceError::CannotReassign)?;
+            }
+            for &prescription_id in &authored_prescriptions {
+                let prescription = self
+                    .db
+                    .get_prescription(prescription_id)
+                    .ok_or(ServiceError::NoSuchPrescription)?;
+                let patient = self.db.get_user(prescription.patient)?;
+                context
+                    .read_prescription(prescription, patient)
+                    .map_err(|_| ServiceError::CannotReassign)?;
+            }
+            for result in self
+                .db
+                .list_all_lab_results()
+                .filter(|result| authored_results.contains(&result.id))
+            {
+                let patient = self.db.get_user(result.patient)?;
+                context
+                    .read_lab_result(result, patient)
+                    .map_err(|_| ServiceError::CannotReassign)?;
+            }
+        }
+
+        let patients: Vec<UserID> = self.db.get_patients(user_id).collect();
+        for &patient in &patients {
+            if let Some(folder) = self.db.get_user_mut(patient)?.medical_folder.as_mut() {
+                folder.revoke(user_id);
+            }
+        }
+        self.db.remove_reports(user_id);
+        self.db.remove_prescriptions(user_id);
+        self.db.remove_lab_results(user_id);
+
+        if let AuthoredReports::Reassign(doctor_id) = authored {
+            for &report_id in &authored_reports {
+                if let Some(mut report) = self.db.get_report_mut(report_id) {
+                    report.author = doctor_id;
+                }
+            }
+            for &prescription_id in &authored_prescriptions {
+                if let Some(prescription) = self.db.get_prescription_mut(prescription_id) {
+                    prescription.prescriber = doctor_id;
+                }
+            }
+            for &result_id in &authored_results {
+                if let Some(result) = self.db.get_lab_result_mut(result_id) {
+                    result.author = doctor_id;
+                }
+            }
+        }
+
+        let pseudonymize =
+            (authored == AuthoredReports::Pseudonymize && authored_count > 0) || dispensed;
+        if pseudonymize {
+            let pseudonym = loop {
+                let name = Username::try_from(format!("closed_{}", &generate_token()[..12]))
+                    .expect("Pseudonyms are valid usernames");
+                if self.db.lookup_username(&name).is_none() {
+                    break name;
+                }
+            };
+            let mut user = self.db.get_user_mut(user_id)?;
+            user.username = pseudonym;
+            user.role = Role::Patient;
+            user.medical_folder = None;
+            // Personne ne connaît ce mot de passe: le compte est verrouillé
+            user.password = hash(&generate_password());
+            drop(user);
+            self.db.forget_user(user_id);
+        } else {
+            self.db.remove_user(user_id);
+        }
+        self.enforcer.invalidate();
+
+        warn!(
+            "Compte {user_id} fermé par {}: {} accès retiré(s), {} document(s) écrit(s) {}",
+            self.actor_name(),
+            patients.len(),
+            authored_count,
+            match authored {
+                AuthoredReports::Reassign(doctor) => format!("réassigné(s) à {doctor}"),
+                AuthoredReports::Pseudonymize => "pseudonymisé(s)".to_owned(),
+            }
+        );
+
+        if self.user == Some(user_id) {
+            self.logout();
+        }
+        Ok(())
+    }
+
+    /// Ecrire un nouveau rapport médical
+    pub fn add_report(
+        &mut self,
+        author: UserID,
+        patient: UserID,
+        title: String,
+        content: String,
+    ) -> Result<ReportID, ServiceError> {
+        let now = Utc::now();
+        let report_id = ReportID::new();
+        let report = MedicalReport {
+            id: report_id,
+            title,
+            author,
+            patient,
+            content,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let user = self
+            .db
+            .get_user(patient)
+            .map_err(ServiceError::from)?;
+
+        self.enforce()?.add_report(user, &report)?;
+
+        let (actor, title) = (self.actor_name(), report.title.clone());
+        self.db.store_report(report);
+        self.notify(
+            patient,
+            NotificationKind::ReportAdded,
+            Msg::NotifyReportAdded,
+            &[&actor, &title],
+        );
+        Ok(report_id)
+    }
+
+    /// Lit un rapport médical
+    pub fn read_report(&mut self, report_id: ReportID) -> Result<&MedicalReport, ServiceError> {
+        // Authorization check
         let report = self
             .db
             .get_report(report_id)
             .ok_or(ServiceError::NoSuchReport)?;
+        let patient = self.db.get_user(report.patient)?;
 
-        self.enforce()?.update_report(report)?;
-        *self.db.get_report_data_mut(report_id).unwrap() = content;
-        Ok(())
+        self.enforce()?.read_report(report, patient)?;
+
+        let (actor, title) = (self.actor_name(), report.title.clone());
+        let patient_id = patient.id;
+        self.record_access(patient_id, AccessAction::ReadReport(report_id));
+        self.notify(
+            patient_id,
+            NotificationKind::DataRead,
+            Msg::NotifyReportRead,
+            &[&actor, &title],
+        );
+
+        self.db
+            .get_report(report_id)
+            .ok_or(ServiceError::NoSuchReport)
+    }
+
+    pub fn list_reports(&self, user_id: UserID) -> impl Iterator<Item = &MedicalReport> + '_ {
+        self.enforce().ok().into_iter().flat_map(move |ctx| {
+            self.db.list_patient_reports(user_id).filter(move |report| {
+                let Ok(patient) = self.db.get_user(report.patient) else {
+                    return false;
+                };
+
+                ctx.read_report(report, patient).is_ok()
+            })
+        })
+    }
+
+    /// Enregistre un résultat de laboratoire dans le dossier d'un patient
+    pub fn add_lab_result(
+        &mut self,
+        patient: UserID,
+        measurement: LabMeasurement,
+    ) -> Result<LabResultID, ServiceError> {
+        let author = self.user.ok_or(ServiceError::AccessDenied(AccessDenied))?;
+        let result = LabResult {
+            id: LabResultID::new(),
+            patient,
+            author,
+            analyte: lab::analyte_code(&measurement.analyte).unwrap_or_default(),
+            value: measurement.value,
+            unit: measurement.unit.trim().to_owned(),
+            range: measurement.range,
+            taken_at: measurement.taken_at,
+        };
+
+        // Authorization check
+        let user = self.db.get_user(patient)?;
+        self.enforce()?.add_lab_result(user, &result)?;
+
+        let ReferenceRange { low, high } = result.range;
+        let valid_range = low.is_none_or(f64::is_finite)
+            && high.is_none_or(f64::is_finite)
+            && low.zip(high).is_none_or(|(low, high)| low <= high);
+        if result.analyte.is_empty()
+            || result.unit.is_empty()
+            || !result.value.is_finite()
+            || !valid_range
+            || result.taken_at > Utc::now()
+        {
+            return Err(ServiceError::InvalidLabResult);
+        }
+
+        let (id, analyte) = (result.id, result.analyte.clone());
+        info!("Résultat de laboratoire {id} pour {patient} par {author}");
+        self.db.store_lab_result(result);
+
+        let actor = self.actor_name();
+        self.notify(
+            patient,
+            NotificationKind::ReportAdded,
+            Msg::NotifyLabResultAdded,
+            &[&actor, &analyte],
+        );
+        Ok(id)
+    }
+
+    /// Les analyses dont l'utilisateur connecté peut lire des résultats
+    /// pour ce patient, sans les valeurs
+    pub fn lab_analytes(&self, patient: UserID) -> Vec<String> {
+        let Ok(ctx) = self.enforce() else {
+            return Vec::new();
+        };
+        let Ok(user) = self.db.get_user(patient) else {
+            return Vec::new();
+        };
+        let mut analytes: Vec<String> = self
+            .db
+            .list_lab_results(patient)
+            .filter(|result| ctx.read_lab_result(result, user).is_ok())
+            .map(|result| result.analyte.clone())
+            .collect();
+        analytes.sort();
+        analytes.dedup();
+        analytes
+    }
+
+    /// Lit les résultats d'une analyse pour un patient, par ordre
+    /// chronologique. Chaque résultat lu est enregistré dans l'historique.
+    pub fn read_lab_results(
+        &mut self,
+        patient: UserID,
+        analyte: &str,
+    ) -> Result<Vec<&LabResult>, ServiceError> {
+        let analyte = lab::analyte_code(analyte).ok_or(ServiceError::InvalidLabResult)?;
+        let user = self.db.get_user(patient)?;
+        let ctx = self.enforce()?;
+        let ids: Vec<LabResultID> = self
+            .db
+            .list_lab_results(patient)
+            .filter(|result| result.analyte == analyte)
+            .filter(|result| ctx.read_lab_result(result, user).is_ok())
+            .map(|result| result.id)
+            .collect();
+
+        for &id in &ids {
+            self.record_access(patient, AccessAction::ReadLabResult(id));
+        }
+        Ok(self
+            .db
+            .list_lab_results(patient)
+            .filter(|result| ids.contains(&result.id))
+            .collect())
+    }
+
+    /// Prépare l'export d'un rapport, ou de tous les rapports lisibles d'un
+    /// patient si aucun rapport n'est donné. Chaque rapport exporté passe
+    /// le contrôle `read-report` et est enregistré comme une lecture.
+    pub fn export_reports(
+        &mut self,
+        patient_id: UserID,
+        report: Option<ReportID>,
+    ) -> Result<ExportDocument, ServiceError> {
+        let ctx = self.enforce()?;
+        let patient = self.db.get_user(patient_id)?;
+
+        let report_ids: Vec<ReportID> = match report {
+            Some(report_id) => {
+                let report = self
+                    .db
+                    .get_report(report_id)
+                    .filter(|report| report.patient == patient_id)
+                    .ok_or(ServiceError::NoSuchReport)?;
+                ctx.read_report(report, patient)?;
+                vec![report_id]
+            }
+            None => {
+                let ids: Vec<ReportID> = self
+                    .list_reports(patient_id)
+                    .map(|report| report.id)
+                    .collect();
+                // Sans rapport lisible, l'export ne contient que le dossier
+                if ids.is_empty() {
+                    ctx.read_data(patient)?;
+                }
+                ids
+            }
+        };
+
+        let avs_number = ctx
+            .read_data(patient)
+            .ok()
+            .and(patient.medical_folder.as_ref())
+            .map(|folder| folder.personal_data.avs_number.to_string());
+
+        let reports = report_ids
+            .iter()
+            .filter_map(|&id| self.db.get_report(id))
+            .map(|report| ExportedReport {
+                id: report.id,
+                title: report.title.clone(),
+                author: self
+                    .username(report.author)
+                    .map(ToString::to_string)
+                    .unwrap_or_else(|| report.author.to_string()),
+                created_at: report.created_at,
+                updated_at: report.updated_at,
+                content: report.content.clone(),
+            })
+            .collect();
+
+        let document = ExportDocument {
+            patient_id,
+            patient_name: patient.username.to_string(),
+            avs_number,
+            generated_at: Utc::now(),
+            reports,
+        };
+
+        if document.avs_number.is_some() {
+            self.record_access(patient_id, AccessAction::ReadData);
+        }
+        for &id in &report_ids {
+            self.record_access(patient_id, AccessAction::ReadReport(id));
+        }
+        info!(
+            "{} rapport(s) de {patient_id} exporté(s) par {}",
+            report_ids.len(),
+            self.actor_name()
+        );
+        let actor = self.actor_name();
+        self.notify(
+            patient_id,
+            NotificationKind::DataRead,
+            Msg::NotifyReportsExported,
+            &[&actor, &report_ids.len()],
+        );
+        Ok(document)
+    }
+
+    /// Liste les rapports lisibles d'un patient, filtrés et triés selon la requête
+    pub fn query_reports(&self, user_id: UserID, query: &ReportQuery) -> Vec<&MedicalReport> {
+        let mut reports: Vec<&MedicalReport> = self
+            .list_reports(user_id)
+            .filter(|report| query.matches(report))
+            .collect();
+        query.sort(&mut reports);
+        reports
+    }
+
+    pub fn list_patients(&self) -> impl Iterator<Item = &UserData> + '_ {
+        self.user
+            .iter()
+            .flat_map(|&u| self.db.get_patients(u))
+            .filter_map(|id| self.db.get_user(id).ok())
+    }
+
+    pub fn add_doctor(
+        &mut self,
+        patient_id: UserID,
+        doctor_id: UserID,
+    ) -> Result<(), ServiceError> {
+        // Authorization check
+        let _patient = self
+            .db
+            .get_user(patient_id)
+            .map_err(ServiceError::from)?;
+
+        let doctor = self
+            .db
+            .get_user(doctor_id)
+            .map_err(ServiceError::from)?;
+
+        self.enforce()?.add_doctor(_patient, doctor)?;
+
+        if let Some(folder) = self.db.get_user_mut(patient_id)?.medical_folder.as_mut() {
+            folder.doctors.insert(doctor_id);
+        }
+        self.enforcer.invalidate();
+        self.record_proxy_action(patient_id, AccessAction::AddDoctor(doctor_id));
+
+        let patient_name = self.db.get_user(patient_id)?.username.to_string();
+        let doctor_name = self
+            .username(doctor_id)
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        let actor = self.actor_name();
+        self.notify(
+            doctor_id,
+            NotificationKind::DoctorAdded,
+            Msg::NotifyAccessGranted,
+            &[&patient_name],
+        );
+        self.notify(
+            patient_id,
+            NotificationKind::DoctorAdded,
+            Msg::NotifyDoctorAdded,
+            &[&actor, &doctor_name],
+        );
+        Ok(())
+    }
+
+    pub fn remove_doctor(
+        &mut self,
+        patient_id: UserID,
+        doctor_id: UserID,
+    ) -> Result<(), ServiceError> {
+        // Authorization check
+        let patient = self
+            .db
+            .get_user(patient_id)
+            .map_err(ServiceError::from)?;
+
+        let doctor = self
+            .db
+            .get_user(doctor_id)
+            .map_err(ServiceError::from)?;
+
+        self.enforce()?.remove_doctor(patient, doctor)?;
+
+        if let Some(folder) = self.db.get_user_mut(patient_id)?.medical_folder.as_mut() {
+            folder.revoke(doctor_id);
+        }
+        self.enforcer.invalidate();
+        self.record_proxy_action(patient_id, AccessAction::RemoveDoctor(doctor_id));
+        Ok(())
+    }
+
+    /// Dépose, au nom du médecin connecté, une demande d'accès au dossier
+    /// d'un patient. L'accès n'est accordé qu'après approbation du patient.
+    pub fn request_access(
+        &mut self,
+        patient_id: UserID,
+        reason: String,
+        scope: AccessScope,
+    ) -> Result<AccessRequestID, ServiceError> {
+        let doctor_id = self.user.ok_or(ServiceError::AccessDenied(AccessDenied))?;
+        let request = AccessRequest {
+            id: AccessRequestID::new(),
+            doctor: doctor_id,
+            patient: patient_id,
+            reason,
+            scope,
+            status: RequestStatus::Pending,
+        };
+
+        // Authorization check
+        let patient = self.db.get_user(patient_id)?;
+        self.enforce()?.request_access(patient, &request)?;
+
+        let already_pending = self.db.list_access_requests().any(|r| {
+            r.doctor == doctor_id && r.patient == patient_id && r.status == RequestStatus::Pending
+        });
+        if already_pending {
+            return Err(ServiceError::RequestAlreadyPending);
+        }
+
+        info!(
+            "Demande d'accès {} déposée par {doctor_id} pour {patient_id} ({scope:?})",
+            request.id
+        );
+        let id = request.id;
+        self.db.store_access_request(request);
+        Ok(id)
+    }
+
+    /// Liste les demandes d'accès en attente que l'utilisateur connecté peut traiter
+    pub fn list_access_requests(&self) -> impl Iterator<Item = &AccessRequest> + '_ {
+        self.enforce().ok().into_iter().flat_map(move |ctx| {
+            self.db
+                .list_access_requests()
+                .filter(move |request| ctx.review_access(request).is_ok())
+        })
+    }
+
+    /// Désigne `proxy` comme représentant de `dependant`
+    pub fn add_proxy(
+        &mut self,
+        dependant: UserID,
+        proxy: UserID,
+        terms: ProxyTerms,
+    ) -> Result<ProxyID, ServiceError> {
+        self.db.get_user(dependant)?;
+        let proxy_name = self.db.get_user(proxy)?.username.to_string();
+
+        // Une représentation légale prend toujours fin, au plus tard à la
+        // majorité. La date de naissance déjà enregistrée fait foi.
+        let known_birth_date = self.db.get_birth_date(dependant);
+        let birth_date = known_birth_date.or(terms.birth_date);
+        let end = match terms.kind {
+            ProxyKind::Guardian => birth_date.map(|birth_date| {
+                let majority = majority_date(birth_date);
+                terms.end.map_or(majority, |end| end.min(majority))
+            }),
+            ProxyKind::Carer => terms.end,
+        };
+        let relationship = ProxyRelationship {
+            id: ProxyID::new(),
+            proxy,
+            dependant,
+            kind: terms.kind,
+            scope: terms.scope,
+            start: terms.start,
+            end,
+        };
+
+        // Authorization check
+        self.enforce()?.manage_proxy(&relationship)?;
+
+        let ends_after_start = end.is_none_or(|end| terms.start < end);
+        let guardian_without_end = terms.kind == ProxyKind::Guardian && end.is_none();
+        let other_birth_date = known_birth_date
+            .zip(terms.birth_date)
+            .is_some_and(|(known, given)| known != given);
+        if proxy == dependant || !ends_after_start || guardian_without_end || other_birth_date {
+            return Err(ServiceError::InvalidProxy);
+        }
+
+        let id = relationship.id;
+        info!("Représentation {id}: {proxy} pour {dependant} ({terms:?}), jusqu'au {end:?}");
+        if let (ProxyKind::Guardian, Some(birth_date)) = (terms.kind, birth_date) {
+            self.db.set_birth_date(dependant, birth_date);
+        }
+        self.db.store_proxy(relationship);
+        self.enforcer.invalidate();
+
+        let actor = self.actor_name();
+        self.notify(
+            dependant,
+            NotificationKind::ProxyChanged,
+            Msg::NotifyProxyAdded,
+            &[&actor, &proxy_name, &terms.kind],
+        );
+        Ok(id)
+    }
+
+    /// Met fin dès aujourd'hui à une relation de représentation, qui reste
+    /// dans l'historique
+    pub fn end_proxy(&mut self, id: ProxyID) -> Result<(), ServiceError> {
+        // Authorization check
+        let relationship = self.db.get_proxy(id).ok_or(ServiceError::NoSuchProxy)?;
+        self.enforce()?.manage_proxy(relationship)?;
+
+        let today = Utc::now().date_naive();
+        let dependant = relationship.dependant;
+        let relationship = self.db.get_proxy_mut(id).ok_or(ServiceError::NoSuchProxy)?;
+        if relationship.end.is_none_or(|end| end > today) {
+            relationship.end = Some(today.max(relationship.start));
+        }
+        info!("Représentation {id} terminée");
+        self.enforcer.invalidate();
+
+        let actor = self.actor_name();
+        self.notify(
+            dependant,
+            NotificationKind::ProxyChanged,
+            Msg::NotifyProxyEnded,
+            &[&actor],
+        );
+        Ok(())
+    }
+
+    /// Les relations de représentation de l'utilisateur connecté, comme
+    /// représentant ou comme représenté
+    pub fn list_proxies(&self) -> impl Iterator<Item = &ProxyRelationship> + '_ {
+        self.user
+            .into_iter()
+            .flat_map(|user| self.db.list_proxies(user))
+    }
+
+    /// Les personnes que l'utilisateur connecté représente aujourd'hui
+    pub fn dependants(&self) -> Vec<UserID> {
+        let today = Utc::now().date_naive();
+        let mut dependants: Vec<UserID> = self
+            .list_proxies()
+            .filter(|proxy| Some(proxy.proxy) == self.user && proxy.is_active(today))
+            .map(|proxy| proxy.dependant)
+            .collect();
+        dependants.sort();
+        dependants.dedup();
+        dependants
+    }
+
+    /// Prescrit un traitement à un patient. Retourne le premier code à
+    /// usage unique de l'ordonnance, à remettre au patient.
+    pub fn prescribe(
+        &mut self,
+        patient: UserID,
+        terms: PrescriptionTerms,
+    ) -> Result<(PrescriptionID, String), ServiceError> {
+        let prescriber = self.user.ok_or(ServiceError::AccessDenied(AccessDenied))?;
+        let prescription = Prescription {
+            id: PrescriptionID::new(),
+            patient,
+            prescriber,
+            drug: terms.drug.trim().to_owned(),
+            dose: terms.dose.trim().to_owned(),
+            duration_days: terms.duration_days,
+            repeats: terms.repeats,
+            issued_at: Utc::now(),
+            dispensings: Vec::new(),
+        };
+
+        // Authorization check
+        let user = self.db.get_user(patient)?;
+        self.enforce()?.prescribe(user, &prescription)?;
+
+        if prescription.drug.is_empty() || prescription.dose.is_empty() || terms.duration_days == 0
+        {
+            return Err(ServiceError::InvalidPrescription);
+        }
+
+        let id = prescription.id;
+        let drug = prescription.drug.clone();
+        info!("Ordonnance {id} pour {patient} par {prescriber}");
+        self.db.store_prescription(prescription);
+        let code = generate_code();
+        self.db.set_prescription_code(id, code_digest(&code));
+
+        let actor = self.actor_name();
+        self.notify(
+            patient,
+            NotificationKind::Prescription,
+            Msg::NotifyPrescriptionIssued,
+            &[&actor, &drug],
+        );
+        Ok((id, code))
+    }
+
+    /// Les ordonnances d'un patient que l'utilisateur connecté peut voir
+    pub fn list_prescriptions(&self, patient: UserID) -> impl Iterator<Item = &Prescription> + '_ {
+        self.enforce().ok().into_iter().flat_map(move |ctx| {
+            self.db
+                .list_prescriptions(patient)
+                .filter(move |prescription| {
+                    self.db
+                        .get_user(prescription.patient)
+                        .is_ok_and(|user| ctx.read_prescription(prescription, user).is_ok())
+                })
+        })
+    }
+
+    /// Remplace le code d'une ordonnance par un nouveau code, pour la
+    /// prochaine délivrance. L'ancien code n'est plus valable.
+    pub fn issue_prescription_code(&mut self, id: PrescriptionID) -> Result<String, ServiceError> {
+        // Authorization check
+        let prescription = self
+            .db
+            .get_prescription(id)
+            .ok_or(ServiceError::NoSuchPrescription)?;
+        self.enforce()?.issue_prescription_code(prescription)?;
+
+        if prescription.remaining() == 0 {
+            return Err(ServiceError::InvalidPrescription);
+        }
+
+        let code = generate_code();
+        self.db.set_prescription_code(id, code_digest(&code));
+        info!("Nouveau code pour l'ordonnance {id}");
+        Ok(code)
+    }
+
+    /// Retrouve une ordonnance par son code, sans le consommer. Le
+    /// pharmacien ne voit que l'ordonnance, pas le dossier du patient.
+    pub fn lookup_prescription(&self, code: &str) -> Result<&Prescription, ServiceError> {
+        let prescription = self
+            .db
+            .find_prescription_by_code(&code_digest(code))
+            .ok_or(ServiceError::NoSuchPrescription)?;
+        self.enforce()?.lookup_prescription(prescription)?;
+        Ok(prescription)
+    }
+
+    /// Enregistre la délivrance d'une ordonnance. Le code est consommé: une
+    /// délivrance suivante demande un nouveau code.
+    pub fn dispense(&mut self, code: &str) -> Result<PrescriptionID, ServiceError> {
+        let digest = code_digest(code);
+        let pharmacist = self.user.ok_or(ServiceError::AccessDenied(AccessDenied))?;
+
+        // Authorization check
+        let prescription = self
+            .db
+            .find_prescription_by_code(&digest)
+            .ok_or(ServiceError::NoSuchPrescription)?;
+        self.enforce()?.dispense(prescription)?;
+
+        let (id, patient, drug) = (
+            prescription.id,
+            prescription.patient,
+            prescription.drug.clone(),
+        );
+        self.db.take_prescription_code(&digest);
+        self.db
+            .get_prescription_mut(id)
+            .ok_or(ServiceError::NoSuchPrescription)?
+            .dispensings
+            .push(Dispensing {
+                pharmacist,
+                at: Utc::now(),
+            });
+        info!("Ordonnance {id} délivrée par {pharmacist}");
+
+        self.record_access(patient, AccessAction::Dispense(id));
+        let actor = self.actor_name();
+        self.notify(
+            patient,
+            NotificationKind::Prescription,
+            Msg::NotifyPrescriptionDispensed,
+            &[&actor, &drug],
+        );
+        Ok(id)
+    }
+
+    /// Approuve ou refuse une demande d'accès. Une approbation accorde
+    /// au médecin l'accès demandé sur le dossier du patient.
+    pub fn review_access_request(
+        &mut self,
+        request_id: AccessRequestID,
+        approve: bool,
+    ) -> Result<(), ServiceError> {
+        // Authorization check
+        let request = self
+            .db
+            .get_access_request(request_id)
+            .ok_or(ServiceError::NoSuchRequest)?;
+        self.enforce()?.review_access(request)?;
+
+        let (doctor_id, patient_id, scope) = (request.doctor, request.patient, request.scope);
+
+        // Le médecin a pu perdre son rôle depuis sa demande
+        if approve && self.db.get_user(doctor_id)?.role != Role::Doctor {
+            return Err(ServiceError::NotADoctor);
+        }
+
+        if approve {
+            self.db
+                .get_user_mut(patient_id)?
+                .medical_folder
+                .as_mut()
+                .ok_or(ServiceError::NotAPatient)?
+                .grant(doctor_id, scope);
+            self.enforcer.invalidate();
+        }
+
+        let status = if approve {
+            RequestStatus::Approved
+        } else {
+            RequestStatus::Denied
+        };
+        info!("Demande d'accès {request_id} de {doctor_id} pour {patient_id}: {status:?}");
+        self.record_proxy_action(patient_id, AccessAction::ReviewAccess(request_id));
+        self.db
+            .get_access_request_mut(request_id)
+            .ok_or(ServiceError::NoSuchRequest)?
+            .status = status;
+
+        let actor = self.actor_name();
+        self.notify(
+            doctor_id,
+            NotificationKind::DoctorAdded,
+            Msg::NotifyRequestReviewed,
+            &[&actor, &status],
+        );
+        Ok(())
+    }
+
+    pub fn update_report(
+        &mut self,
+        report_id: ReportID,
+        content: String,
+    ) -> Result<(), ServiceError> {
+        let report = self
+            .db
+            .get_report(report_id)
+            .ok_or(ServiceError::NoSuchReport)?;
+
+        self.enforce()?.update_report(report)?;
+
+        let (actor, title) = (self.actor_name(), report.title.clone());
+        let patient = report.patient;
+        let mut report = self.db.get_report_mut(report_id).unwrap();
+        report.content = content;
+        report.updated_at = Utc::now();
+        drop(report);
+        self.notify(
+            patient,
+            NotificationKind::ReportUpdated,
+            Msg::NotifyReportUpdated,
+            &[&actor, &title],
+        );
+        Ok(())
+    }
+
+    /// L'historique des accès de tiers au dossier d'un patient, du plus récent au plus ancien
+    pub fn access_history(&self, patient: UserID) -> impl Iterator<Item = &AccessLogEntry> + '_ {
+        self.enforce().ok().into_iter().flat_map(move |ctx| {
+            self.db
+                .list_access_log(patient)
+                .filter(move |entry| ctx.read_access_log(entry).is_ok())
+        })
+    }
+
+    /// Conteste un accès au dossier, qui sera signalé aux administrateurs
+    pub fn dispute_access(&mut self, entry_id: AccessLogID) -> Result<(), ServiceError> {
+        // Authorization check
+        let entry = self
+            .db
+            .get_access_log(entry_id)
+            .ok_or(ServiceError::NoSuchAccess)?;
+        self.enforce()?.dispute_access(entry)?;
+
+        warn!(
+            "Accès {entry_id} de {} au dossier de {} contesté",
+            entry.accessor, entry.patient
+        );
+        self.db
+            .get_access_log_mut(entry_id)
+            .ok_or(ServiceError::NoSuchAccess)?
+            .dispute = DisputeStatus::Disputed;
+        Ok(())
+    }
+
+    /// Liste les accès contestés en attente d'examen
+    pub fn list_disputed_accesses(&self) -> impl Iterator<Item = &AccessLogEntry> + '_ {
+        self.enforce().ok().into_iter().flat_map(move |ctx| {
+            self.db
+                .list_disputed_accesses()
+                .filter(move |entry| ctx.review_dispute(entry).is_ok())
+        })
+    }
+
+    /// Marque un accès contesté comme examiné par un administrateur
+    pub fn resolve_dispute(&mut self, entry_id: AccessLogID) -> Result<(), ServiceError> {
+        // Authorization check
+        let entry = self
+            .db
+            .get_access_log(entry_id)
+            .ok_or(ServiceError::NoSuchAccess)?;
+        self.enforce()?.review_dispute(entry)?;
+
+        info!(
+            "Contestation de l'accès {entry_id} examinée par {}",
+            self.actor_name()
+        );
+        self.db
+            .get_access_log_mut(entry_id)
+            .ok_or(ServiceError::NoSuchAccess)?
+            .dispute = DisputeStatus::Reviewed;
+        Ok(())
+    }
+
+    /// Les notifications de l'utilisateur connecté, des plus récentes aux plus anciennes
+    pub fn notifications(&self) -> impl Iterator<Item = &Notification> + '_ {
+        self.user
+            .into_iter()
+            .flat_map(|user| self.db.list_notifications(user))
+    }
+
+    /// Nombre de notifications non lues de l'utilisateur connecté
+    pub fn unread_notifications(&self) -> usize {
+        self.notifications()
+            .filter(|notification| !notification.read)
+            .count()
+    }
+
+    /// Marque toutes les notifications de l'utilisateur connecté comme lues
+    pub fn mark_notifications_read(&mut self) {
+        if let Some(user) = self.user {
+            self.db.mark_notifications_read(user);
+        }
+    }
+
+    /// Indique si l'utilisateur connecté reçoit ce type de notification
+    pub fn notification_enabled(&self, kind: NotificationKind) -> bool {
+        self.user.is_some_and(|user| !self.db.is_muted(user, kind))
+    }
+
+    /// Active ou désactive un type de notification pour l'utilisateur connecté
+    pub fn set_notification_enabled(
+        &mut self,
+        kind: NotificationKind,
+        enabled: bool,
+    ) -> Result<(), ServiceError> {
+        let user = self.user.ok_or(ServiceError::AccessDenied(AccessDenied))?;
+        self.db.set_muted(user, kind, !enabled);
+        Ok(())
+    }
+
+    /// La langue d'interface choisie par l'utilisateur connecté, s'il en a choisi une
+    pub fn language(&self) -> Option<Lang> {
+        self.db.get_language(self.user?)
+    }
+
+    /// Enregistre la langue d'interface de l'utilisateur connecté
+    pub fn set_language(&mut self, lang: Lang) -> Result<(), ServiceError> {
+        let user = self.user.ok_or(ServiceError::AccessDenied(AccessDenied))?;
+        self.db.set_language(user, lang);
+        Ok(())
+    }
+}
+
+/// Les administrateurs existants
+fn admins(db: &Database) -> Vec<&UserData> {
+    db.list_users()
+        .filter(|user| user.role == Role::Admin)
+        .collect()
+}
+
+/// Vérifie des règles avant de les appliquer: elles doivent passer les
+/// scénarios d'accès, et un administrateur existant doit pouvoir encore
+/// gérer les règles pour revenir en arrière
+fn check_policy(candidate: &dyn Backend, admins: &[&UserData]) -> Result<(), ServiceError> {
+    let failed = failed_scenarios(candidate);
+    if !failed.is_empty() {
+        return Err(ServiceError::PolicyScenarios(failed));
+    }
+    let manages =
+        |admin: &&UserData| candidate.decide(&Subject::new(admin), &Request::ManagePolicy);
+    match admins.iter().any(manages) {
+        true => Ok(()),
+        false => Err(ServiceError::PolicyLockout),
+    }
+}
+
+/// Applique la version des règles gardée dans la base. La base n'est pas
+/// signée: la version doit dériver des règles signées chargées, et passer
+/// les mêmes vérifications qu'une modification.
+fn apply_revision(
+    db: &Database,
+    enforcer: &mut Enforcer,
+    revision: &PolicyRevision,
+) -> Result<(), ServiceError> {
+    let base = enforcer.base_digest().ok_or(PolicyError::NotEditable)?;
+    if base != revision.base {
+        return Err(PolicyError::OtherBase.into());
+    }
+    let admins = admins(db);
+    enforcer.set_rules(&revision.rules, |candidate| {
+        check_policy(candidate, &admins)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::research::ResearchFormat;
+
+    fn set_service() -> Service {
+        Service::new(
+            Database::default(),
+            Enforcer::load().expect("Error in loading Enforcer"),
+        )
+    }
+
+    fn username(name: &str) -> Username {
+        Username::try_from(name).unwrap()
+    }
+
+    /// Stores a user with the given role directly in the database
+    fn add_user(service: &mut Service, name: &str, role: Role) -> UserID {
+        let id = UserID::new();
+        service.db.store_user(UserData {
+            id,
+            role,
+            username: username(name),
+            password: hash("password123"),
+            medical_folder: None,
+        });
+        id
+    }
+
+    /// Stores a patient with an empty medical folder
+    fn add_patient(service: &mut Service, name: &str) -> UserID {
+        let id = add_user(service, name, Role::Patient);
+        service.db.get_user_mut(id).unwrap().medical_folder =
+            Some(MedicalFolder::new(PersonalData {
+                avs_number: "756.1234.5678.97".try_into().unwrap(),
+                blood_type: crate::models::BloodType::O,
+            }));
+        id
+    }
+
+    const IMPORT_CSV: &str = "username,role,avs_number,blood_type,doctor\n\
+                              wilson,Doctor,,,\n\
+                              alice,Patient,756.1234.5678.97,A,house\n\
+                              bob,Patient,,,\n";
+
+    #[test]
+    fn test_import_users_creates_accounts() {
+        let mut service = set_service();
+        let admin = add_user(&mut service, "admin", Role::Admin);
+        let other_admin = add_user(&mut service, "other_admin", Role::Admin);
+        let house = add_user(&mut service, "house", Role::Doctor);
+        service.user = Some(admin);
+
+        let dry_run = service.import_users(IMPORT_CSV.as_bytes(), true).unwrap();
+        assert_eq!(dry_run.rows, 3);
+        assert!(dry_run.errors.is_empty());
+        assert!(dry_run.accounts.is_empty());
+        assert!(
+            service.lookup_user(&username("alice")).is_none(),
+            "Dry run must not create accounts"
+        );
+
+        let report = service.import_users(IMPORT_CSV.as_bytes(), false).unwrap();
+        assert_eq!(report.accounts.len(), 3);
+
+        let alice = service.lookup_user(&username("alice")).unwrap();
+        assert!(service.db.get_user(alice).unwrap().has_doctor(house));
+        assert_eq!(
+            service.db.get_patients(house).collect::<Vec<_>>(),
+            vec![alice]
+        );
+
+        // Initial passwords are one-time: they must be replaced at first login
+        let account = &report.accounts[1];
+        service.logout();
+        service
+            .login(&account.username, &account.initial_password)
+            .unwrap();
+        assert!(service.password_change_required());
+        assert!(matches!(
+            service.change_password("alice"),
+            Err(ServiceError::WeakPassword)
+        ));
+        service
+            .change_password("correct-horse-battery-staple")
+            .unwrap();
+        assert!(!service.password_change_required());
+
+        // An imported doctor is only a patient until another admin approves
+        let wilson = service.lookup_user(&username("wilson")).unwrap();
+        assert_eq!(service.db.get_user(wilson).unwrap().role, Role::Patient);
+        assert_eq!(
+            (report.accounts[0].role, report.accounts[0].pending_role),
+            (Role::Patient, Some(Role::Doctor))
+        );
+        service.user = Some(other_admin);
+        let change = service.list_role_changes().next().unwrap().id;
+        service.review_role_change(change, true).unwrap();
+        assert_eq!(service.db.get_user(wilson).unwrap().role, Role::Doctor);
+    }
+
+    #[test]
+    fn test_import_users_is_atomic_and_admin_only() {
+        let mut service = set_service();
+        let admin = add_user(&mut service, "admin", Role::Admin);
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        add_patient(&mut service, "bob");
+
+        service.user = Some(doctor);
+        assert!(matches!(
+            service.import_users(IMPORT_CSV.as_bytes(), true),
+            Err(ServiceError::AccessDenied(_))
+        ));
+
+        service.user = Some(admin);
+        let csv = "username,role,avs_number,blood_type,doctor\n\
+                   carol,Patient,756.1234.5678.97,A,doctor\n\
+                   dave,Patient,756.1234.5678.97,A,bob\n\
+                   bob,Patient,,,\n\
+                   carol,Doctor,,,\n";
+        let report = service.import_users(csv.as_bytes(), false).unwrap();
+        let errors: Vec<String> = report
+            .errors
+            .iter()
+            .map(|e| format!("{} {:?}", e.line, e.error))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "3 UnknownDoctor(Username(\"bob\"))",
+                "4 UserExists(Username(\"bob\"))",
+                "5 DuplicateUsername(Username(\"carol\"))",
+            ]
+        );
+        assert!(report.accounts.is_empty());
+        assert!(
+            service.lookup_user(&username("carol")).is_none(),
+            "No account should be created when a row is invalid"
+        );
+    }
+
+    #[test]
+    fn test_imported_privileged_roles_need_another_admin() {
+        let mut service = set_service();
+        let alice = add_user(&mut service, "alice", Role::Admin);
+        let csv = "username,role,avs_number,blood_type,doctor\n\
+                   wilson,Doctor,,,\n\
+                   cuddy,Pharmacist,,,\n";
+
+        // Nobody could approve the doctor
+        service.user = Some(alice);
+        assert!(matches!(
+            service.import_users(csv.as_bytes(), false),
+            Err(ServiceError::NoReviewer)
+        ));
+        assert!(service.lookup_user(&username("wilson")).is_none());
+
+        // Even with rules letting any admin review any change, the importer
+        // cannot approve the roles they asked for
+        let bob = add_user(&mut service, "bob", Role::Admin);
+        let mut rules: Vec<PolicyRule> = service
+            .enforcer
+            .rules()
+            .unwrap()
+            .into_iter()
+            .filter(|rule| rule.action != "review-role-change")
+            .collect();
+        rules.push(PolicyRule {
+            action: "review-role-change".into(),
+            rule: "r.sub.role == \"Admin\"".into(),
+        });
+        service
+            .enforcer
+            .set_rules(&rules, |_| Ok::<_, PolicyError>(()))
+            .unwrap();
+        service.import_users(csv.as_bytes(), false).unwrap();
+        let wilson = service.lookup_user(&username("wilson")).unwrap();
+        let cuddy = service.lookup_user(&username("cuddy")).unwrap();
+        assert_eq!(service.db.get_user(cuddy).unwrap().role, Role::Pharmacist);
+        assert_eq!(service.db.get_user(wilson).unwrap().role, Role::Patient);
+        let change = service.list_own_role_changes().next().unwrap().id;
+        assert!(matches!(
+            service.review_role_change(change, true),
+            Err(ServiceError::AccessDenied(_))
+        ));
+
+        service.user = Some(bob);
+        service.review_role_change(change, true).unwrap();
+        assert_eq!(service.db.get_user(wilson).unwrap().role, Role::Doctor);
+    }
+
+    #[test]
+    fn test_research_export_is_admin_only_and_deidentified() {
+        let mut service = set_service();
+        let admin = add_user(&mut service, "admin", Role::Admin);
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let patients: Vec<UserID> = ["alice", "bob", "carol"]
+            .into_iter()
+            .map(|name| add_patient(&mut service, name))
+            .collect();
+        service.user = Some(doctor);
+        service
+            .add_report(doctor, patients[0], "Check-up".into(), "Fine".into())
+            .unwrap();
+        let key = PseudonymKey::generate();
+
+        assert!(matches!(
+            service.research_export(&key, 2),
+            Err(ServiceError::AccessDenied(_))
+        ));
+
+        service.user = Some(admin);
+        let dataset = service.research_export(&key, 2).unwrap();
+        assert_eq!(
+            dataset.summary.patients, 3,
+            "Users without folder are left out"
+        );
+        assert_eq!(dataset.summary.exported, 2);
+        assert_eq!(dataset.summary.suppressed, 1);
+
+        let csv = String::from_utf8(dataset.render(ResearchFormat::Csv).unwrap()).unwrap();
+        for patient in &patients[1..] {
+            assert!(csv.contains(&key.pseudonym(*patient)));
+        }
+        assert!(!csv.contains(&key.pseudonym(patients[0])));
+        assert!(!csv.contains("alice") && !csv.contains("756."));
+    }
+
+    #[test]
+    fn test_avs_numbers_are_revealed_on_authorized_request() {
+        let mut service = set_service();
+        let admin = add_user(&mut service, "admin", Role::Admin);
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let patient = add_patient(&mut service, "alice");
+        let avs_number = AVSNumber::try_from("7561234567897").unwrap();
+
+        service.user = Some(doctor);
+        assert!(matches!(
+            service.reveal_avs(patient),
+            Err(ServiceError::AccessDenied(_))
+        ));
+        assert!(service.find_by_avs(&avs_number).is_err());
+
+        service.user = Some(patient);
+        service.add_doctor(patient, doctor).unwrap();
+        assert_eq!(service.reveal_avs(patient).unwrap(), "756.1234.5678.97");
+
+        service.user = Some(doctor);
+        assert_eq!(service.reveal_avs(patient).unwrap(), "756.1234.5678.97");
+        let history: Vec<AccessAction> = service
+            .db
+            .list_access_log(patient)
+            .map(|e| e.action)
+            .collect();
+        assert_eq!(history, [AccessAction::RevealAvs]);
+
+        service.user = Some(admin);
+        assert_eq!(service.find_by_avs(&avs_number).unwrap(), [patient]);
+        assert!(matches!(
+            service.reveal_avs(doctor),
+            Err(ServiceError::NotAPatient)
+        ));
+    }
+
+    #[test]
+    fn test_account_closure_reassigns_or_pseudonymizes_reports() {
+        let mut service = set_service();
+        let house = add_user(&mut service, "house", Role::Doctor);
+        let wilson = add_user(&mut service, "wilson", Role::Doctor);
+        let stranger = add_user(&mut service, "stranger", Role::Doctor);
+        let alice = add_patient(&mut service, "alice");
+        let bob = add_patient(&mut service, "bob");
+
+        for patient in [alice, bob] {
+            service.user = Some(patient);
+            service.add_doctor(patient, house).unwrap();
+        }
+        service.add_doctor(bob, wilson).unwrap();
+        service.user = Some(house);
+        let alice_report = service
+            .add_report(house, alice, "Alice".into(), String::new())
+            .unwrap();
+        let bob_report = service
+            .add_report(house, bob, "Bob".into(), String::new())
+            .unwrap();
+
+        service.user = Some(stranger);
+        assert!(matches!(
+            service.close_account(house, AuthoredReports::Pseudonymize),
+            Err(ServiceError::AccessDenied(_))
+        ));
+
+        // Wilson cannot read Alice's reports, so they cannot be given to him
+        service.user = Some(house);
+        assert!(matches!(
+            service.close_account(house, AuthoredReports::Reassign(wilson)),
+            Err(ServiceError::CannotReassign)
+        ));
+        assert!(service.db.get_user(alice).unwrap().has_doctor(house));
+
+        service
+            .close_account(house, AuthoredReports::Pseudonymize)
+            .unwrap();
+        assert_eq!(service.user, None, "Closing one's account logs out");
+        assert!(service.lookup_user(&username("house")).is_none());
+        let pseudonym = service.db.get_user(house).unwrap();
+        assert!(pseudonym.username.as_ref().starts_with("closed_"));
+        assert_eq!(pseudonym.role, Role::Patient);
+        for patient in [alice, bob] {
+            assert!(!service.db.get_user(patient).unwrap().has_reader(house));
+        }
+        assert_eq!(service.db.get_report(alice_report).unwrap().author, house);
+
+        // A patient who wrote no report is removed entirely, as is a doctor
+        // whose reports are reassigned
+        service.user = Some(alice);
+        service
+            .close_account(alice, AuthoredReports::Pseudonymize)
+            .unwrap();
+        assert!(service.db.get_user(alice).is_err());
+        assert!(service.db.get_report(alice_report).is_none());
+
+        let admin = add_user(&mut service, "admin", Role::Admin);
+        service.user = Some(bob);
+        service.add_doctor(bob, stranger).unwrap();
+        service.user = Some(stranger);
+        let stranger_report = service
+            .add_report(stranger, bob, "Bob again".into(), String::new())
+            .unwrap();
+        service.user = Some(admin);
+        service
+            .close_account(stranger, AuthoredReports::Reassign(wilson))
+            .unwrap();
+        assert!(service.db.get_user(stranger).is_err());
+        assert_eq!(
+            service.db.get_report(stranger_report).unwrap().author,
+            wilson
+        );
+        assert_eq!(service.db.get_report(bob_report).unwrap().author, house);
+        assert_eq!(crate::fsck::check(&service.db), []);
+    }
+
+    #[test]
+    fn test_account_closure_keeps_prescriptions_and_lab_results_attributed() {
+        let mut service = set_service();
+        let house = add_user(&mut service, "house", Role::Doctor);
+        let wilson = add_user(&mut service, "wilson", Role::Doctor);
+        let stranger = add_user(&mut service, "stranger", Role::Doctor);
+        let phil = add_user(&mut service, "phil", Role::Pharmacist);
+        let bob = add_patient(&mut service, "bob");
+
+        service.user = Some(bob);
+        service.add_doctor(bob, house).unwrap();
+        service.add_doctor(bob, wilson).unwrap();
+        service.user = Some(house);
+        let terms = PrescriptionTerms {
+            drug: "Ibuprofen 400mg".into(),
+            dose: "1 tablet 3x a day".into(),
+            duration_days: 5,
+            repeats: 1,
+        };
+        let (prescription, code) = service.prescribe(bob, terms).unwrap();
+        let result = service
+            .add_lab_result(
+                bob,
+                LabMeasurement {
+                    analyte: "hgb".into(),
+                    value: 130.0,
+                    unit: "g/L".into(),
+                    range: ReferenceRange::default(),
+                    taken_at: Utc::now(),
+                },
+            )
+            .unwrap();
+        service.user = Some(phil);
+        service.dispense(&code).unwrap();
+
+        // The stranger cannot read Bob's prescription nor his results
+        service.user = Some(house);
+        assert!(matches!(
+            service.close_account(house, AuthoredReports::Reassign(stranger)),
+            Err(ServiceError::CannotReassign)
+        ));
+        service
+            .close_account(house, AuthoredReports::Reassign(wilson))
+            .unwrap();
+        assert!(service.db.get_user(house).is_err());
+        let prescription = service.db.get_prescription(prescription).unwrap();
+        assert_eq!(prescription.prescriber, wilson);
+        let result = service.db.list_lab_results(bob).find(|r| r.id == result);
+        assert_eq!(result.unwrap().author, wilson);
+
+        // A pharmacist's dispensings keep a pseudonymous pharmacist
+        service.user = Some(phil);
+        service
+            .close_account(phil, AuthoredReports::Reassign(wilson))
+            .unwrap();
+        let pseudonym = service.db.get_user(phil).unwrap();
+        assert!(pseudonym.username.as_ref().starts_with("closed_"));
+        assert_eq!(crate::fsck::check(&service.db), []);
+    }
+
+    #[test]
+    fn test_proxies_act_within_their_scope_and_term() {
+        let mut service = set_service();
+        let admin = add_user(&mut service, "admin", Role::Admin);
+        let parent = add_user(&mut service, "parent", Role::Patient);
+        let child = add_patient(&mut service, "child");
+        let house = add_user(&mut service, "house", Role::Doctor);
+        let today = Utc::now().date_naive();
+        let birth_date = today - chrono::Months::new(10 * 12);
+        let guardian = ProxyTerms {
+            kind: ProxyKind::Guardian,
+            scope: ProxyScope::Manage,
+            start: today,
+            end: None,
+            birth_date: Some(birth_date),
+        };
+
+        // Only an administrator may name a guardian, and a guardian's
+        // relationship ends at the latest on the dependant's majority
+        service.user = Some(parent);
+        assert!(matches!(
+            service.add_proxy(child, parent, guardian),
+            Err(ServiceError::AccessDenied(_))
+        ));
+        assert!(service.get_data(child).is_err());
+        service.user = Some(admin);
+        assert!(matches!(
+            service.add_proxy(
+                child,
+                parent,
+                ProxyTerms {
+                    birth_date: None,
+                    ..guardian
+                }
+            ),
+            Err(ServiceError::InvalidProxy)
+        ));
+        let proxy = service
+            .add_proxy(
+                child,
+                parent,
+                ProxyTerms {
+                    end: Some(today + chrono::Months::new(30 * 12)),
+                    ..guardian
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            service.db.get_proxy(proxy).unwrap().end,
+            Some(majority_date(birth_date))
+        );
+
+        // The recorded birth date cannot be changed by naming another guardian
+        let adult = ProxyTerms {
+            birth_date: Some(today - chrono::Months::new(30 * 12)),
+            ..guardian
+        };
+        assert!(matches!(
+            service.add_proxy(child, house, adult),
+            Err(ServiceError::InvalidProxy)
+        ));
+
+        service.user = Some(parent);
+        assert_eq!(service.dependants(), [child]);
+        service.get_data(child).unwrap();
+        service
+            .update_data(
+                child,
+                PersonalData {
+                    avs_number: "756.1234.5678.97".try_into().unwrap(),
+                    blood_type: crate::models::BloodType::A,
+                },
+            )
+            .unwrap();
+        service.add_doctor(child, house).unwrap();
+        assert!(matches!(
+            service.delete_data(child),
+            Err(ServiceError::AccessDenied(_))
+        ));
+
+        service.user = Some(child);
+        // Most recent first
+        let history: Vec<_> = service
+            .access_history(child)
+            .map(|entry| (entry.accessor, entry.action, entry.as_proxy))
+            .collect();
+        assert_eq!(
+            history,
+            [
+                (
+                    parent,
+                    AccessAction::AddDoctor(house),
+                    Some(ProxyKind::Guardian)
+                ),
+                (parent, AccessAction::UpdateData, Some(ProxyKind::Guardian)),
+                (parent, AccessAction::ReadData, Some(ProxyKind::Guardian)),
+            ]
+        );
+
+        // A carer with read access cannot change anything, and the child
+        // cannot name a guardian for themselves
+        let carer = ProxyTerms {
+            kind: ProxyKind::Carer,
+            scope: ProxyScope::Read,
+            start: today,
+            end: None,
+            birth_date: None,
+        };
+        service.add_proxy(child, house, carer).unwrap();
+        assert!(matches!(
+            service.add_proxy(child, house, guardian),
+            Err(ServiceError::AccessDenied(_))
+        ));
+        service.user = Some(house);
+        assert!(matches!(
+            service.remove_doctor(child, house),
+            Err(ServiceError::AccessDenied(_))
+        ));
+
+        // Relationships lapse on their end date
+        service.db.get_proxy_mut(proxy).unwrap().end = Some(today);
+        service.user = Some(parent);
+        assert!(service.dependants().is_empty());
+        assert!(service.get_data(child).is_err());
+    }
+
+    #[test]
+    fn test_lab_results_follow_report_rules() {
+        let mut service = set_service();
+        let house = add_user(&mut service, "house", Role::Doctor);
+        let stranger = add_user(&mut service, "stranger", Role::Doctor);
+        let alice = add_patient(&mut service, "alice");
+        service.user = Some(alice);
+        service.add_doctor(alice, house).unwrap();
+
+        let measurement = |value, days_ago| LabMeasurement {
+            analyte: "hgb".into(),
+            value,
+            unit: "g/L".into(),
+            range: ReferenceRange {
+                low: Some(120.0),
+                high: Some(160.0),
+            },
+            taken_at: Utc::now() - chrono::TimeDelta::days(days_ago),
+        };
+        assert!(matches!(
+            service.add_lab_result(alice, measurement(130.0, 1)),
+            Err(ServiceError::AccessDenied(_))
+        ));
+
+        service.user = Some(house);
+        let inverted = LabMeasurement {
+            range: ReferenceRange {
+                low: Some(160.0),
+                high: Some(120.0),
+            },
+            ..measurement(130.0, 1)
+        };
+        assert!(matches!(
+            service.add_lab_result(alice, inverted),
+            Err(ServiceError::InvalidLabResult)
+        ));
+        let recent = service
+            .add_lab_result(alice, measurement(110.0, 1))
+            .unwrap();
+        let older = service
+            .add_lab_result(alice, measurement(140.0, 30))
+            .unwrap();
+
+        service.user = Some(stranger);
+        assert!(service.lab_analytes(alice).is_empty());
+        assert!(service.read_lab_results(alice, "HGB").unwrap().is_empty());
+
+        service.user = Some(house);
+        assert_eq!(service.lab_analytes(alice), ["HGB"]);
+        let results = service.read_lab_results(alice, "hgb").unwrap();
+        let ids: Vec<LabResultID> = results.iter().map(|result| result.id).collect();
+        assert_eq!(ids, [older, recent], "Results should be chronological");
+        assert_eq!(results[1].flag(), crate::models::LabFlag::Low);
+
+        service.user = Some(alice);
+        assert_eq!(
+            service
+                .access_history(alice)
+                .filter(|entry| matches!(entry.action, AccessAction::ReadLabResult(_)))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_prescriptions_are_dispensed_with_one_time_codes() {
+        let mut service = set_service();
+        let house = add_user(&mut service, "house", Role::Doctor);
+        let stranger = add_user(&mut service, "stranger", Role::Doctor);
+        let pharmacist = add_user(&mut service, "pharmacist", Role::Pharmacist);
+        let alice = add_patient(&mut service, "alice");
+        service.user = Some(alice);
+        service.add_doctor(alice, house).unwrap();
+
+        let terms = PrescriptionTerms {
+            drug: "Ibuprofen 400mg".into(),
+            dose: "1 tablet 3x a day".into(),
+            duration_days: 5,
+            repeats: 1,
+        };
+        service.user = Some(stranger);
+        assert!(matches!(
+            service.prescribe(alice, terms.clone()),
+            Err(ServiceError::AccessDenied(_))
+        ));
+        service.user = Some(house);
+        assert!(matches!(
+            service.prescribe(
+                alice,
+                PrescriptionTerms {
+                    duration_days: 0,
+                    ..terms.clone()
+                }
+            ),
+            Err(ServiceError::InvalidPrescription)
+        ));
+        let (id, code) = service.prescribe(alice, terms).unwrap();
+
+        // Only pharmacists look prescriptions up, and they cannot list them
+        service.user = Some(alice);
+        assert_eq!(service.list_prescriptions(alice).count(), 1);
+        assert!(matches!(
+            service.lookup_prescription(&code),
+            Err(ServiceError::AccessDenied(_))
+        ));
+        service.user = Some(pharmacist);
+        assert_eq!(service.list_prescriptions(alice).count(), 0);
+        assert!(service.get_data(alice).is_err());
+        let typed = code.to_lowercase().replace('-', " ");
+        assert_eq!(service.lookup_prescription(&typed).unwrap().id, id);
+
+        // A code is used once
+        service.dispense(&code).unwrap();
+        assert!(matches!(
+            service.dispense(&code),
+            Err(ServiceError::NoSuchPrescription)
+        ));
+
+        service.user = Some(alice);
+        let code = service.issue_prescription_code(id).unwrap();
+        service.user = Some(pharmacist);
+        service.dispense(&code).unwrap();
+        assert_eq!(service.db.get_prescription(id).unwrap().remaining(), 0);
+
+        service.user = Some(alice);
+        assert!(matches!(
+            service.issue_prescription_code(id),
+            Err(ServiceError::InvalidPrescription)
+        ));
+        let dispensings: Vec<_> = service
+            .access_history(alice)
+            .filter(|entry| entry.action == AccessAction::Dispense(id))
+            .map(|entry| (entry.accessor, entry.accessor_role))
+            .collect();
+        assert_eq!(dispensings, [(pharmacist, Role::Pharmacist); 2]);
+    }
+
+    #[test]
+    fn test_verified_login_needs_the_current_password() {
+        let mut service = set_service();
+        let alice = service
+            .register_with_password(username("alice"), "StrongP@ssw0rd!")
+            .unwrap();
+
+        let (user, stored) = service.password_hash(&username("alice")).unwrap();
+        assert_eq!(user, alice);
+        assert!(verify("StrongP@ssw0rd!", Some(&stored)));
+
+        // Le mot de passe a changé pendant la vérification
+        service.user = Some(alice);
+        let new_hash = Service::hash_new_password(&username("alice"), "Other-P@ssw0rd!").unwrap();
+        service.set_password(new_hash).unwrap();
+        service.logout();
+        assert!(service.login_verified(alice, &stored).is_err());
+        assert_eq!(service.user, None);
+    }
+
+    #[test]
+    fn test_bootstrap_creates_admins_once() {
+        let mut service = set_service();
+        assert!(service.needs_bootstrap());
+
+        let admins = service
+            .bootstrap_admins(&[
+                (username("root"), "StrongP@ssw0rd!"),
+                (username("toor"), "StrongP@ssw0rd!"),
+            ])
+            .expect("First bootstrap should succeed");
+        assert!(!service.needs_bootstrap());
+        assert_eq!(service.db.get_user(admins[0]).unwrap().role, Role::Admin);
+
+        let second = service.bootstrap_admins(&[
+            (username("other"), "StrongP@ssw0rd!"),
+            (username("another"), "StrongP@ssw0rd!"),
+        ]);
+        assert!(
+            matches!(second, Err(ServiceError::AlreadyBootstrapped)),
+            "Bootstrap should be refused once an admin exists, but got: {:?}",
+            second
+        );
+    }
+
+    #[test]
+    fn test_bootstrap_refused_after_admin_demoted() {
+        let mut service = set_service();
+        let admins = service
+            .bootstrap_admins(&[
+                (username("root"), "StrongP@ssw0rd!"),
+                (username("toor"), "StrongP@ssw0rd!"),
+            ])
+            .unwrap();
+        for admin in admins {
+            service.db.get_user_mut(admin).unwrap().role = Role::Patient;
+        }
+
+        assert!(
+            service
+                .bootstrap_admins(&[
+                    (username("other"), "StrongP@ssw0rd!"),
+                    (username("another"), "StrongP@ssw0rd!"),
+                ])
+                .is_err(),
+            "Bootstrap should only ever run once"
+        );
+    }
+
+    #[test]
+    fn test_access_request_approval_grants_access() {
+        let mut service = set_service();
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let patient = add_patient(&mut service, "patient");
+
+        service.user = Some(doctor);
+        let request = service
+            .request_access(patient, "Suivi".to_string(), AccessScope::Reports)
+            .expect("Doctor should be able to request access");
+        assert!(matches!(
+            service.request_access(patient, "Encore".to_string(), AccessScope::Reports),
+            Err(ServiceError::RequestAlreadyPending)
+        ));
+        assert!(
+            service.review_access_request(request, true).is_err(),
+            "Doctor should not be able to approve their own request"
+        );
+
+        service.user = Some(patient);
+        assert_eq!(service.list_access_requests().count(), 1);
+        service.review_access_request(request, true).unwrap();
+        assert_eq!(service.list_access_requests().count(), 0);
+
+        let folder = service
+            .db
+            .get_user(patient)
+            .unwrap()
+            .medical_folder
+            .as_ref()
+            .unwrap();
+        assert!(folder.report_readers.contains(&doctor));
+        assert!(!folder.doctors.contains(&doctor));
+        assert!(
+            service.review_access_request(request, false).is_err(),
+            "A request should not be reviewed twice"
+        );
+    }
+
+    #[test]
+    fn test_access_request_approval_needs_a_doctor() {
+        let mut service = set_service();
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let patient = add_patient(&mut service, "patient");
+
+        service.user = Some(doctor);
+        let request = service
+            .request_access(patient, "Suivi".to_string(), AccessScope::Folder)
+            .unwrap();
+        service.db.get_user_mut(doctor).unwrap().role = Role::Patient;
+
+        service.user = Some(patient);
+        assert!(matches!(
+            service.review_access_request(request, true),
+            Err(ServiceError::NotADoctor)
+        ));
+        assert!(!service.db.get_user(patient).unwrap().has_reader(doctor));
+        service.review_access_request(request, false).unwrap();
+    }
+
+    #[test]
+    fn test_access_request_denial() {
+        let mut service = set_service();
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let patient = add_patient(&mut service, "patient");
+
+        service.user = Some(doctor);
+        let request = service
+            .request_access(patient, "Urgence".to_string(), AccessScope::Folder)
+            .unwrap();
+
+        service.user = Some(patient);
+        service.review_access_request(request, false).unwrap();
+
+        assert!(!service.db.get_user(patient).unwrap().has_reader(doctor));
+        assert_eq!(
+            service.db.get_access_request(request).unwrap().status,
+            RequestStatus::Denied
+        );
+    }
+
+    #[test]
+    fn test_notifications_follow_preferences() {
+        let mut service = set_service();
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let patient = add_patient(&mut service, "patient");
+
+        service.user = Some(patient);
+        service
+            .set_notification_enabled(NotificationKind::DataRead, false)
+            .unwrap();
+        service.add_doctor(patient, doctor).unwrap();
+
+        service.user = Some(doctor);
+        assert_eq!(
+            service.unread_notifications(),
+            1,
+            "Doctor should be told they were given access"
+        );
+        service.get_data(patient).unwrap();
+        service
+            .add_report(doctor, patient, "Bilan".to_string(), "RAS".to_string())
+            .unwrap();
+
+        service.user = Some(patient);
+        let kinds: Vec<NotificationKind> = service.notifications().map(|n| n.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![NotificationKind::ReportAdded],
+            "Own actions and muted events should not create notifications"
+        );
+
+        service.mark_notifications_read();
+        assert_eq!(service.unread_notifications(), 0);
+    }
+
+    #[test]
+    fn test_notifications_use_recipient_language() {
+        let mut service = set_service();
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let patient = add_patient(&mut service, "patient");
+
+        service.user = Some(doctor);
+        service.set_language(Lang::De).unwrap();
+        assert_eq!(service.language(), Some(Lang::De));
+
+        service.user = Some(patient);
+        assert_eq!(service.language(), None);
+        service.add_doctor(patient, doctor).unwrap();
+
+        service.user = Some(doctor);
+        let messages: Vec<&str> = service
+            .notifications()
+            .map(|n| n.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec!["Sie haben nun Zugriff auf das Dossier von patient"],
+            "Notification should be written in the doctor's language"
+        );
+    }
+
+    #[test]
+    fn test_reads_are_recorded_and_disputable() {
+        let mut service = set_service();
+        let admin = add_user(&mut service, "admin", Role::Admin);
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let patient = add_patient(&mut service, "patient");
+
+        service.user = Some(patient);
+        service.add_doctor(patient, doctor).unwrap();
+        service.get_data(patient).unwrap();
+
+        service.user = Some(doctor);
+        service.get_data(patient).unwrap();
+        service
+            .add_report(doctor, patient, "Bilan".to_string(), "RAS".to_string())
+            .unwrap();
+        let report = service.db.list_reports().next().unwrap().id;
+        service.read_report(report).unwrap();
+        assert_eq!(
+            service.access_history(patient).count(),
+            0,
+            "Doctor should not see the access history"
+        );
+
+        service.user = Some(patient);
+        let history: Vec<(UserID, AccessAction)> = service
+            .access_history(patient)
+            .map(|entry| (entry.accessor, entry.action))
+            .collect();
+        assert_eq!(
+            history,
+            vec![
+                (doctor, AccessAction::ReadReport(report)),
+                (doctor, AccessAction::ReadData)
+            ],
+            "Only reads by others should be recorded, most recent first"
+        );
+
+        let entry = service.access_history(patient).next().unwrap().id;
+        service.dispute_access(entry).unwrap();
+
+        service.user = Some(admin);
+        assert_eq!(service.list_disputed_accesses().count(), 1);
+        service.resolve_dispute(entry).unwrap();
+        assert_eq!(service.list_disputed_accesses().count(), 0);
+    }
+
+    #[test]
+    fn test_report_exports_are_authorized_and_recorded() {
+        let mut service = set_service();
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let other = add_user(&mut service, "other", Role::Doctor);
+        let patient = add_patient(&mut service, "patient");
+
+        service.user = Some(patient);
+        service.add_doctor(patient, doctor).unwrap();
+        service.user = Some(doctor);
+        let report = service
+            .add_report(doctor, patient, "Bilan".to_string(), "RAS".to_string())
+            .unwrap();
+
+        service.user = Some(other);
+        assert!(matches!(
+            service.export_reports(patient, Some(report)),
+            Err(ServiceError::AccessDenied(_))
+        ));
+        assert!(matches!(
+            service.export_reports(patient, None),
+            Err(ServiceError::AccessDenied(_))
+        ));
+
+        service.user = Some(doctor);
+        let document = service.export_reports(patient, None).unwrap();
+        assert!(document.avs_number.is_some());
+        assert_eq!(document.reports.len(), 1);
+
+        service.user = Some(patient);
+        let history: Vec<(UserID, AccessAction)> = service
+            .access_history(patient)
+            .map(|entry| (entry.accessor, entry.action))
+            .collect();
+        assert_eq!(
+            history,
+            [
+                (doctor, AccessAction::ReadReport(report)),
+                (doctor, AccessAction::ReadData)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report_query_filters_and_sorts() {
+        let mut service = set_service();
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let other = add_user(&mut service, "other", Role::Doctor);
+        let admin = add_user(&mut service, "admin", Role::Admin);
+        let patient = add_patient(&mut service, "patient");
+
+        let date = |d: u32| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        for (author, title, day) in [
+            (doctor, "Bilan sanguin", 3),
+            (other, "Radiographie", 1),
+            (doctor, "Bilan annuel", 2),
+        ] {
+            let created_at = date(day).and_hms_opt(12, 0, 0).unwrap().and_utc();
+            service.db.store_report(MedicalReport {
+                id: ReportID::new(),
+                title: title.to_string(),
+                author,
+                patient,
+                content: String::new(),
+                created_at,
+                updated_at: created_at,
+            });
+        }
+        service.user = Some(admin);
+
+        let titles = |query: &ReportQuery| -> Vec<String> {
+            service
+                .query_reports(patient, query)
+                .iter()
+                .map(|r| r.title.clone())
+                .collect()
+        };
+
+        assert_eq!(
+            titles(&ReportQuery::default()),
+            ["Bilan sanguin", "Bilan annuel", "Radiographie"]
+        );
+        assert_eq!(
+            titles(&ReportQuery {
+                sort: ReportSort::Title,
+                ..Default::default()
+            }),
+            ["Bilan annuel", "Bilan sanguin", "Radiographie"]
+        );
+        assert_eq!(
+            titles(&ReportQuery {
+                author: Some(doctor),
+                sort: ReportSort::OldestFirst,
+                ..Default::default()
+            }),
+            ["Bilan annuel", "Bilan sanguin"]
+        );
+        assert_eq!(
+            titles(&ReportQuery {
+                from: Some(date(2)),
+                to: Some(date(2)),
+                ..Default::default()
+            }),
+            ["Bilan annuel"]
+        );
+        assert_eq!(
+            titles(&ReportQuery {
+                keywords: vec!["BILAN".to_string(), "sang".to_string()],
+                ..Default::default()
+            }),
+            ["Bilan sanguin"]
+        );
+    }
+
+    #[test]
+    fn test_decision_cache_is_invalidated_by_grants() {
+        let mut service = set_service();
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let admin = add_user(&mut service, "admin", Role::Admin);
+        let patient = add_patient(&mut service, "patient");
+        service.user = Some(doctor);
+        service
+            .add_report(doctor, patient, "Bilan".into(), String::new())
+            .unwrap();
+
+        assert_eq!(service.list_reports(patient).count(), 1);
+        assert_eq!(service.list_reports(patient).count(), 1);
+        assert!(matches!(
+            service.authorization_metrics(),
+            Err(ServiceError::AccessDenied(_))
+        ));
+
+        service.user = Some(patient);
+        service.add_doctor(patient, doctor).unwrap();
+        service.remove_doctor(patient, doctor).unwrap();
+        service.user = Some(admin);
+        let stats = service.authorization_metrics().unwrap();
+        assert_eq!(stats.invalidations, 2);
+        assert!(
+            stats.hits > 0,
+            "Listing the reports twice should hit the cache"
+        );
+
+        // The author keeps reading their report, without stale decisions
+        service.user = Some(doctor);
+        assert_eq!(service.list_reports(patient).count(), 1);
+        assert!(service.get_data(patient).is_err());
+    }
+
+    #[test]
+    fn test_policy_changes_are_checked_and_recorded() {
+        let mut service = set_service();
+        let admin = add_user(&mut service, "alice", Role::Admin);
+        let bob = add_user(&mut service, "bob", Role::Admin);
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let rule = |action: &str, rule: &str| PolicyRule {
+            action: action.to_owned(),
+            rule: rule.to_owned(),
+        };
+
+        service.user = Some(doctor);
+        assert!(matches!(
+            service.change_policy(PolicyChange::Add(rule("fsck", "true"))),
+            Err(ServiceError::AccessDenied(_))
+        ));
+        assert!(service.authorization_metrics().is_err());
+
+        service.user = Some(admin);
+        let before = service.policy_rules().unwrap();
+        let Err(ServiceError::PolicyScenarios(failed)) = service.change_policy(PolicyChange::Add(
+            rule("read-data", "r.sub.role == \"Doctor\""),
+        )) else {
+            panic!("Every doctor reading every folder should fail the scenarios");
+        };
+        assert_eq!(failed, ["other-doctor-cannot-read-data"]);
+        assert!(matches!(
+            service.change_policy(PolicyChange::Add(rule("drop-tables", "true"))),
+            Err(ServiceError::Policy(PolicyError::InvalidRule))
+        ));
+
+        let metrics = PolicyChange::Add(rule("read-metrics", "r.sub.role == \"Doctor\""));
+        assert_eq!(
+            service.change_policy(metrics.clone()).unwrap(),
+            PolicyUpdate::Applied(1)
+        );
+        assert!(matches!(
+            service.change_policy(metrics),
+            Err(ServiceError::Policy(PolicyError::Unchanged))
+        ));
+
+        // Changes to the policy management need a second admin, who
+        // reviews them once they are checked again
+        let by_name = rule("manage-policy", "r.sub.username == \"admin\"");
+        let PolicyUpdate::Pending(request) = service
+            .change_policy(PolicyChange::Add(by_name.clone()))
+            .unwrap()
+        else {
+            panic!("Managing the policy by name should need an approval");
+        };
+        assert_eq!(service.list_policy_changes().count(), 0);
+        assert!(matches!(
+            service.review_policy_change(request, true),
+            Err(ServiceError::AccessDenied(_))
+        ));
+        service.user = Some(bob);
+        assert_eq!(service.notifications().count(), 1);
+        assert_eq!(service.list_policy_changes().count(), 1);
+        service.review_policy_change(request, true).unwrap();
+        let revision = service.db.current_policy().unwrap();
+        assert_eq!(
+            (revision.version, revision.author, revision.reviewer),
+            (2, admin, Some(bob))
+        );
+        assert_eq!(service.list_policy_changes().count(), 0);
+
+        // The scenarios' admin is not alice nor bob: they are the last ones
+        // who could manage the policy, and cannot lock themselves out
+        service.user = Some(admin);
+        assert!(matches!(
+            service.change_policy(PolicyChange::Remove(rule(
+                "manage-policy",
+                "r.sub.role == \"Admin\""
+            ))),
+            Err(ServiceError::PolicyLockout)
+        ));
+        let PolicyUpdate::Pending(request) = service
+            .change_policy(PolicyChange::Remove(by_name))
+            .unwrap()
+        else {
+            panic!("Managing the policy by name should need an approval");
+        };
+        service.user = Some(bob);
+        service.review_policy_change(request, false).unwrap();
+        service.user = Some(admin);
+        let PolicyUpdate::Pending(request) = service
+            .change_policy(PolicyChange::Remove(rule(
+                "manage-policy",
+                "r.sub.username == \"admin\"",
+            )))
+            .unwrap()
+        else {
+            panic!("Managing the policy by name should need an approval");
+        };
+        service.user = Some(bob);
+        service.review_policy_change(request, true).unwrap();
+        service.user = Some(admin);
+        assert_eq!(service.policy_rules().unwrap().len(), before.len() + 1);
+
+        let history: Vec<usize> = service
+            .policy_history()
+            .unwrap()
+            .map(|revision| revision.version)
+            .collect();
+        assert_eq!(history, [3, 2, 1]);
+
+        // The rules stored in the database replace those of the file
+        let db = std::mem::take(&mut service.db);
+        let mut service = Service::new(db, Enforcer::load().unwrap());
+        service.user = Some(doctor);
+        assert!(service.authorization_metrics().is_ok());
+
+        // The native backend cannot be changed at runtime
+        let db = std::mem::take(&mut service.db);
+        let mut service = Service::new(db, Enforcer::native());
+        service.user = Some(admin);
+        assert!(matches!(
+            service.policy_rules(),
+            Err(ServiceError::Policy(PolicyError::NotEditable))
+        ));
+    }
+
+    #[test]
+    fn test_stored_policy_is_checked_at_load() {
+        let mut service = set_service();
+        let admin = add_user(&mut service, "alice", Role::Admin);
+        let doctor = add_user(&mut service, "doctor", Role::Doctor);
+        let rule = |action: &str, rule: &str| PolicyRule {
+            action: action.to_owned(),
+            rule: rule.to_owned(),
+        };
+        service.user = Some(admin);
+        let metrics = rule("read-metrics", "r.sub.role == \"Doctor\"");
+        service
+            .change_policy(PolicyChange::Add(metrics.clone()))
+            .unwrap();
+        let signed = Enforcer::load().unwrap().rules().unwrap();
+        let base = Enforcer::load().unwrap().base_digest().unwrap().to_owned();
+
+        // Revisions written straight into the unsigned database
+        let tampered = |db: &mut Database, base: &str, rules: Vec<PolicyRule>| {
+            let version = db.current_policy().unwrap().version + 1;
+            db.store_policy_revision(PolicyRevision {
+                version,
+                base: base.to_owned(),
+                author: admin,
+                reviewer: None,
+                at: Utc::now(),
+                change: PolicyChange::Add(metrics.clone()),
+                rules,
+            });
+        };
+        let reads_metrics = |db: Database| {
+            let mut service = Service::new(db, Enforcer::load().unwrap());
+            service.user = Some(doctor);
+            (service.authorization_metrics().is_ok(), service.db)
+        };
+
+        let (granted, mut db) = reads_metrics(std::mem::take(&mut service.db));
+        assert!(granted, "A checked revision of the signed rules is applied");
+
+        let mut everyone = signed.clone();
+        everyone.push(rule("read-data", "r.sub.role == \"Doctor\""));
+        everyone.push(metrics.clone());
+        tampered(&mut db, &base, everyone);
+        let (granted, mut db) = reads_metrics(db);
+        assert!(
+            !granted,
+            "Rules failing the scenarios fall back to the file"
+        );
+
+        let mut locked_out: Vec<PolicyRule> = signed
+            .iter()
+            .filter(|rule| rule.action != "manage-policy")
+            .cloned()
+            .collect();
+        locked_out.push(metrics.clone());
+        tampered(&mut db, &base, locked_out);
+        let (granted, mut db) = reads_metrics(db);
+        assert!(
+            !granted,
+            "Rules locking every admin out fall back to the file"
+        );
+
+        let mut derived = signed.clone();
+        derived.push(metrics.clone());
+        tampered(&mut db, &"0".repeat(64), derived.clone());
+        let (granted, mut db) = reads_metrics(db);
+        assert!(!granted, "Rules derived from other signed rules fall back");
+
+        tampered(&mut db, &base, derived);
+        assert!(reads_metrics(db).0);
+    }
+
+    #[test]
+    fn test_privileged_role_changes_need_a_second_admin() {
+        let mut service = set_service();
+        let alice = add_user(&mut service, "alice", Role::Admin);
+        let bob = add_user(&mut service, "bob", Role::Admin);
+        let carol = add_user(&mut service, "carol", Role::Admin);
+        let house = add_user(&mut service, "house", Role::Patient);
+        let mallory = add_user(&mut service, "mallory", Role::Doctor);
+
+        // Demoting a doctor takes effect at once
+        service.user = Some(alice);
+        assert_eq!(
+            service.update_role(mallory, Role::Patient).unwrap(),
+            RoleUpdate::Applied
+        );
+        assert_eq!(service.db.get_user(mallory).unwrap().role, Role::Patient);
+
+        let RoleUpdate::Pending(change) = service.update_role(house, Role::Doctor).unwrap() else {
+            panic!("Promoting to Doctor should need an approval");
+        };
+        assert_eq!(service.db.get_user(house).unwrap().role, Role::Patient);
+        assert!(matches!(
+            service.update_role(house, Role::Admin),
+            Err(ServiceError::RoleChangeAlreadyPending)
+        ));
+        // Only the admins who could approve it are told about it
+        assert_eq!(service.notifications().count(), 0);
+        assert_eq!(service.list_role_changes().count(), 0);
+        assert!(matches!(
+            service.review_role_change(change, true),
+            Err(ServiceError::AccessDenied(_))
+        ));
+
+        service.user = Some(bob);
+        assert_eq!(service.notifications().count(), 1);
+        let pending: Vec<RoleChangeID> = service.list_role_changes().map(|c| c.id).collect();
+        assert_eq!(pending, [change]);
+        service.review_role_change(change, true).unwrap();
+        assert_eq!(service.db.get_user(house).unwrap().role, Role::Doctor);
+        let change = service.db.get_role_change(change).unwrap();
+        assert_eq!(change.status, RoleChangeStatus::Approved);
+        assert_eq!(change.reviewer, Some(bob));
+
+        // Demoting an admin needs an approval too, which the demoted admin
+        // cannot give, and expires unanswered
+        let RoleUpdate::Pending(change) = service.update_role(alice, Role::Patient).unwrap() else {
+            panic!("Demoting an admin should need an approval");
+        };
+        service.user = Some(carol);
+        assert_eq!(service.list_role_changes().count(), 1);
+        service.user = Some(alice);
+        assert_eq!(service.list_role_changes().count(), 0);
+        assert!(matches!(
+            service.review_role_change(change, false),
+            Err(ServiceError::AccessDenied(_))
+        ));
+        service.db.get_role_change_mut(change).unwrap().expires_at = Utc::now();
+        assert!(matches!(
+            service.review_role_change(change, true),
+            Err(ServiceError::RoleChangeExpired)
+        ));
+        assert_eq!(
+            service.db.get_role_change(change).unwrap().status,
+            RoleChangeStatus::Expired
+        );
+        assert_eq!(service.db.get_user(alice).unwrap().role, Role::Admin);
+    }
+
+    #[test]
+    fn test_role_changes_need_another_reviewer_whatever_the_policy() {
+        let mut service = set_service();
+        let alice = add_user(&mut service, "alice", Role::Admin);
+        let bob = add_user(&mut service, "bob", Role::Admin);
+        let house = add_user(&mut service, "house", Role::Patient);
+
+        // Rules letting any admin review any change
+        let mut rules: Vec<PolicyRule> = service
+            .enforcer
+            .rules()
+            .unwrap()
+            .into_iter()
+            .filter(|rule| rule.action != "review-role-change")
+            .collect();
+        rules.push(PolicyRule {
+            action: "review-role-change".into(),
+            rule: "r.sub.role == \"Admin\"".into(),
+        });
+        service
+            .enforcer
+            .set_rules(&rules, |_| Ok::<_, PolicyError>(()))
+            .unwrap();
+
+        service.user = Some(alice);
+        let RoleUpdate::Pending(promotion) = service.update_role(house, Role::Doctor).unwrap()
+        else {
+            panic!("Promoting to Doctor should need an approval");
+        };
+        // Only bob could approve his own demotion
+        assert!(matches!(
+            service.update_role(bob, Role::Patient),
+            Err(ServiceError::NoReviewer)
+        ));
+        add_user(&mut service, "carol", Role::Admin);
+        let RoleUpdate::Pending(demotion) = service.update_role(bob, Role::Patient).unwrap() else {
+            panic!("Demoting an admin should need an approval");
+        };
+        assert_eq!(service.list_role_changes().count(), 0);
+        assert!(matches!(
+            service.review_role_change(promotion, true),
+            Err(ServiceError::AccessDenied(_))
+        ));
+
+        service.user = Some(bob);
+        let pending: Vec<RoleChangeID> = service.list_role_changes().map(|c| c.id).collect();
+        assert_eq!(pending, [promotion]);
+        assert!(matches!(
+            service.review_role_change(demotion, false),
+            Err(ServiceError::AccessDenied(_))
+        ));
+        assert_eq!(
+            service.db.get_role_change(demotion).unwrap().status,
+            RoleChangeStatus::Pending
+        );
+    }
+
+    #[test]
+    fn test_stale_role_changes_are_not_applied() {
+        let mut service = set_service();
+        let alice = add_user(&mut service, "alice", Role::Admin);
+        let bob = add_user(&mut service, "bob", Role::Admin);
+        let carol = add_user(&mut service, "carol", Role::Admin);
+        let house = add_user(&mut service, "house", Role::Patient);
+
+        // The role changed since the request
+        service.user = Some(alice);
+        let RoleUpdate::Pending(change) = service.update_role(house, Role::Doctor).unwrap() else {
+            panic!("Promoting to Doctor should need an approval");
+        };
+        service.db.get_user_mut(house).unwrap().role = Role::Pharmacist;
+        service.user = Some(bob);
+        assert!(matches!(
+            service.review_role_change(change, true),
+            Err(ServiceError::RoleChangeStale)
+        ));
+        assert_eq!(service.db.get_user(house).unwrap().role, Role::Pharmacist);
+        service.review_role_change(change, false).unwrap();
+
+        // The requester is no longer an admin
+        service.user = Some(alice);
+        let RoleUpdate::Pending(change) = service.update_role(house, Role::Doctor).unwrap() else {
+            panic!("Promoting to Doctor should need an approval");
+        };
+        service.user = Some(carol);
+        let RoleUpdate::Pending(demotion) = service.update_role(alice, Role::Patient).unwrap()
+        else {
+            panic!("Demoting an admin should need an approval");
+        };
+        service.user = Some(bob);
+        service.review_role_change(demotion, true).unwrap();
+        assert!(matches!(
+            service.review_role_change(change, true),
+            Err(ServiceError::RoleChangeStale)
+        ));
+        assert_eq!(service.db.get_user(house).unwrap().role, Role::Pharmacist);
+        assert_eq!(
+            service.db.get_role_change(change).unwrap().status,
+            RoleChangeStatus::Pending
+        );
+    }
+
+    #[test]
+    fn test_pending_changes_are_cancelled_by_their_requester() {
+        let mut service = set_service();
+        let alice = add_user(&mut service, "alice", Role::Admin);
+        let bob = add_user(&mut service, "bob", Role::Admin);
+        let house = add_user(&mut service, "house", Role::Patient);
+
+        service.user = Some(alice);
+        let RoleUpdate::Pending(change) = service.update_role(house, Role::Doctor).unwrap() else {
+            panic!("Promoting to Doctor should need an approval");
+        };
+        service.user = Some(bob);
+        assert_eq!(service.list_own_role_changes().count(), 0);
+        assert!(matches!(
+            service.cancel_role_change(change),
+            Err(ServiceError::AccessDenied(_))
+        ));
+
+        service.user = Some(alice);
+        let own: Vec<RoleChangeID> = service.list_own_role_changes().map(|c| c.id).collect();
+        assert_eq!(own, [change]);
+        service.cancel_role_change(change).unwrap();
+        assert_eq!(
+            service.db.get_role_change(change).unwrap().status,
+            RoleChangeStatus::Cancelled
+        );
+        assert!(service.cancel_role_change(change).is_err());
+        service.user = Some(bob);
+        assert_eq!(service.list_role_changes().count(), 0);
+        assert!(service.review_role_change(change, true).is_err());
+        assert_eq!(service.db.get_user(house).unwrap().role, Role::Patient);
+
+        // A cancelled change does not stand in the way of a new one
+        service.user = Some(alice);
+        assert!(matches!(
+            service.update_role(house, Role::Doctor),
+            Ok(RoleUpdate::Pending(_))
+        ));
+
+        let by_name = PolicyChange::Add(PolicyRule {
+            action: "manage-policy".to_owned(),
+            rule: "r.sub.username == \"admin\"".to_owned(),
+        });
+        let PolicyUpdate::Pending(change) = service.change_policy(by_name).unwrap() else {
+            panic!("Managing the policy by name should need an approval");
+        };
+        service.user = Some(bob);
+        assert!(matches!(
+            service.cancel_policy_change(change),
+            Err(ServiceError::AccessDenied(_))
+        ));
+        service.user = Some(alice);
+        assert_eq!(service.list_own_policy_changes().count(), 1);
+        service.cancel_policy_change(change).unwrap();
+        assert_eq!(service.list_own_policy_changes().count(), 0);
+        service.user = Some(bob);
+        assert_eq!(service.list_policy_changes().count(), 0);
+        assert!(service.review_policy_change(change, true).is_err());
+        assert_eq!(service.db.current_policy().map(|r| r.version), None);
+    }
+
+    #[test]
+    fn test_admins_are_closed_once_demoted() {
+        let mut service = set_service();
+        let alice = add_user(&mut service, "alice", Role::Admin);
+
+        service.user = Some(alice);
+        assert!(matches!(
+            service.update_role(alice, Role::Patient),
+            Err(ServiceError::LastAdmin)
+        ));
+        assert!(service
+            .close_account(alice, AuthoredReports::Pseudonymize)
+            .is_err());
+
+        let bob = add_user(&mut service, "bob", Role::Admin);
+        let carol = add_user(&mut service, "carol", Role::Admin);
+        assert!(service
+            .close_account(bob, AuthoredReports::Pseudonymize)
+            .is_err());
+        assert!(service.db.get_user(bob).is_ok());
+
+        let RoleUpdate::Pending(change) = service.update_role(bob, Role::Patient).unwrap() else {
+            panic!("Demoting an admin should need an approval");
+        };
+        service.user = Some(carol);
+        service.review_role_change(change, true).unwrap();
+        service.user = Some(alice);
+        service
+            .close_account(bob, AuthoredReports::Pseudonymize)
+            .unwrap();
+        assert!(service.db.get_user(bob).is_err());
+    }
+
+    #[test]
+    fn test_bootstrap_admins_creates_them_together() {
+        let mut service = set_service();
+        assert!(matches!(
+            service.bootstrap_admins(&[(username("root"), "StrongP@ssw0rd!")]),
+            Err(ServiceError::TooFewAdmins)
+        ));
+        let same = [
+            (username("root"), "StrongP@ssw0rd!"),
+            (username("root"), "StrongP@ssw0rd!"),
+        ];
+        assert!(matches!(
+            service.bootstrap_admins(&same),
+            Err(ServiceError::UserAlreadyExists)
+        ));
+        assert!(service.needs_bootstrap());
+
+        let admins = service
+            .bootstrap_admins(&[
+                (username("root"), "StrongP@ssw0rd!"),
+                (username("other"), "StrongP@ssw0rd!"),
+            ])
+            .unwrap();
+        assert_eq!(admins.len(), 2);
+        assert!(!service.needs_bootstrap());
     }
 }
@@ -0,0 +1,25 @@
+//! Fichiers contenant des données sensibles
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+/// Crée un fichier lisible par son seul propriétaire, sans écraser un
+/// fichier existant
+pub fn create_private(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
+
+/// Ouvre un fichier pour y ajouter des données, en le créant lisible par son
+/// seul propriétaire s'il n'existe pas
+pub fn append_private(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
@@ -5,7 +5,10 @@ use thiserror::Error;
 use regex::Regex;
 use gtin_validate::gtin13;
 use inquire::Text;
+use zxcvbn::feedback::{Suggestion, Warning};
 use zxcvbn::{zxcvbn, Score};
+use crate::i18n::Msg;
+use crate::t;
 
 // Regex for username
 static USERNAME_REGEX: Lazy<Regex> = Lazy::new(|| {
@@ -18,7 +21,7 @@ static MIN_SCORE: Score = Score::Three;
 
 /// This function checks if the given password is valid
 /// Returns true if the password is strong enough, false otherwise
-fn password_validation(password: &str, username: &str) -> bool {
+pub fn password_validation(password: &str, username: &str) -> bool {
     // First check: password should not be the same as username
     if password.eq_ignore_ascii_case(username) {
         return false;
@@ -38,10 +41,9 @@ fn password_validation(password: &str, username: &str) -> bool {
 /// Interactively prompts the user for a password
 pub fn password_input_validation(username: &str) -> String {
     loop {
-        print!("Enter your password: ");
         // let password = rpassword::read_password().unwrap();
 
-        let password = inquire::Password::new("Enter your password: ")
+        let password = inquire::Password::new(t!(PromptPassword))
             .prompt()
             .unwrap_or("".to_string());
 
@@ -49,9 +51,9 @@ pub fn password_input_validation(username: &str) -> String {
             return password;
         }
 
-        println!("Your password must be between 8 to 64 characters long [8-64]");
-        println!("Your password must be different from your username");
-        println!("Your password must be unguessable");
+        println!("{}", Msg::PasswordRuleLength);
+        println!("{}", Msg::PasswordRuleUsername);
+        println!("{}", Msg::PasswordRuleGuessable);
 
         let entropy = zxcvbn(password.as_str(), &[username]);
 
@@ -59,23 +61,65 @@ pub fn password_input_validation(username: &str) -> String {
         // function
         if let Some(feedback) = entropy.feedback() {
             if let Some(warning) = feedback.warning() {
-                println!("\nWarning: {}", warning);
+                println!("{}", t!(PasswordWarning, warning_message(warning)));
             }
             if !feedback.suggestions().is_empty() {
-                println!("Suggestions: ");
-                for suggestion in feedback.suggestions() {
-                    println!("- {}", suggestion);
+                println!("{}", Msg::PasswordSuggestions);
+                for &suggestion in feedback.suggestions() {
+                    println!("- {}", suggestion_message(suggestion));
                 }
             }
         }
     }
 }
 
+/// Traduction d'un avertissement de zxcvbn
+fn warning_message(warning: Warning) -> Msg {
+    match warning {
+        Warning::StraightRowsOfKeysAreEasyToGuess => Msg::WarnStraightRows,
+        Warning::ShortKeyboardPatternsAreEasyToGuess => Msg::WarnShortKeyboardPatterns,
+        Warning::RepeatsLikeAaaAreEasyToGuess => Msg::WarnRepeats,
+        Warning::RepeatsLikeAbcAbcAreOnlySlightlyHarderToGuess => Msg::WarnRepeatedPatterns,
+        Warning::ThisIsATop10Password => Msg::WarnTop10,
+        Warning::ThisIsATop100Password => Msg::WarnTop100,
+        Warning::ThisIsACommonPassword => Msg::WarnCommon,
+        Warning::ThisIsSimilarToACommonlyUsedPassword => Msg::WarnSimilarToCommon,
+        Warning::SequencesLikeAbcAreEasyToGuess => Msg::WarnSequences,
+        Warning::RecentYearsAreEasyToGuess => Msg::WarnRecentYears,
+        Warning::AWordByItselfIsEasyToGuess => Msg::WarnSingleWord,
+        Warning::DatesAreOftenEasyToGuess => Msg::WarnDates,
+        Warning::NamesAndSurnamesByThemselvesAreEasyToGuess => Msg::WarnNames,
+        Warning::CommonNamesAndSurnamesAreEasyToGuess => Msg::WarnCommonNames,
+    }
+}
+
+/// Traduction d'une suggestion de zxcvbn
+fn suggestion_message(suggestion: Suggestion) -> Msg {
+    match suggestion {
+        Suggestion::UseAFewWordsAvoidCommonPhrases => Msg::SuggestFewWords,
+        Suggestion::NoNeedForSymbolsDigitsOrUppercaseLetters => Msg::SuggestNoSymbolsNeeded,
+        Suggestion::AddAnotherWordOrTwo => Msg::SuggestAnotherWord,
+        Suggestion::CapitalizationDoesntHelpVeryMuch => Msg::SuggestCapitalization,
+        Suggestion::AllUppercaseIsAlmostAsEasyToGuessAsAllLowercase => Msg::SuggestAllUppercase,
+        Suggestion::ReversedWordsArentMuchHarderToGuess => Msg::SuggestReversedWords,
+        Suggestion::PredictableSubstitutionsDontHelpVeryMuch => Msg::SuggestSubstitutions,
+        Suggestion::UseALongerKeyboardPatternWithMoreTurns => Msg::SuggestLongerKeyboardPattern,
+        Suggestion::AvoidRepeatedWordsAndCharacters => Msg::SuggestAvoidRepeats,
+        Suggestion::AvoidSequences => Msg::SuggestAvoidSequences,
+        Suggestion::AvoidRecentYears => Msg::SuggestAvoidRecentYears,
+        Suggestion::AvoidYearsThatAreAssociatedWithYou => Msg::SuggestAvoidPersonalYears,
+        Suggestion::AvoidDatesAndYearsThatAreAssociatedWithYou => Msg::SuggestAvoidPersonalDates,
+    }
+}
+
 #[derive(Debug, Clone, Copy, Display, Error)]
+#[display("{}", Msg::ErrInvalidInput)]
 pub struct InvalidInput;
 
-/// Wrapper type for a username thas has been validated
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
+/// Wrapper type for a username thas has been validated, including when
+/// loaded from the database
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
+#[serde(try_from = "String")]
 pub struct Username(String);
 
 impl TryFrom<String> for Username {
@@ -117,19 +161,43 @@ pub fn username_input_validation(message: &str) -> Result<Username, InvalidInput
     Username::try_from(username)
 }
 
-/// Wrapper type for an AVS number that has been validated
-#[derive(Debug, Display, Serialize, Deserialize, Hash)]
+/// Wrapper type for an AVS number that has been validated, stored in its
+/// canonical form (`756.XXXX.XXXX.XX`).
+///
+/// The number is masked when displayed (`756.****.****.97`), including with
+/// `Debug`: only `reveal` gives the full number, after authorization.
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[serde(try_from = "String")]
 pub struct AVSNumber(String);
 
+impl AVSNumber {
+    /// The full number, to be shown only after a `reveal-avs` check
+    pub fn reveal(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for AVSNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let check = self
+            .0
+            .get(self.0.len().saturating_sub(2)..)
+            .unwrap_or_default();
+        write!(f, "756.****.****.{check}")
+    }
+}
+
+impl std::fmt::Debug for AVSNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AVSNumber({self})")
+    }
+}
+
 impl TryFrom<String> for AVSNumber {
     type Error = InvalidInput;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        if validate_avs_number(&value) {
-            Ok(AVSNumber(value))
-        } else {
-            Err(InvalidInput)
-        }
+        AVSNumber::try_from(value.as_str())
     }
 }
 
@@ -138,13 +206,25 @@ impl TryFrom<&str> for AVSNumber {
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         if validate_avs_number(value) {
-            Ok(AVSNumber(value.to_string()))
+            Ok(AVSNumber(canonical_avs_number(value)))
         } else {
             Err(InvalidInput)
         }
     }
 }
 
+/// Puts a valid number in the `756.XXXX.XXXX.XX` form
+fn canonical_avs_number(avs_number: &str) -> String {
+    let digits: String = avs_number.chars().filter(|c| c.is_ascii_digit()).collect();
+    format!(
+        "{}.{}.{}.{}",
+        &digits[..3],
+        &digits[3..7],
+        &digits[7..11],
+        &digits[11..]
+    )
+}
+
 fn validate_avs_number(avs_number: &str) -> bool {
 
     // Remove the dots
@@ -153,7 +233,7 @@ fn validate_avs_number(avs_number: &str) -> bool {
         .collect();
 
     // Check that it starts with the swiss number
-    if !clean_number.starts_with("756") {
+    if clean_number.len() != 13 || !clean_number.starts_with("756") {
         return false;
     }
 
@@ -259,6 +339,29 @@ mod tests {
             }
         }
 
+        #[test]
+        fn test_avs_number_is_canonical_and_masked() {
+            let number = AVSNumber::try_from("756 9217 076985").unwrap();
+
+            assert_eq!(number.reveal(), "756.9217.0769.85");
+            assert_eq!(number.to_string(), "756.****.****.85");
+            assert!(!format!("{number:?}").contains("9217"));
+            assert_eq!(
+                serde_json::to_string(&number).unwrap(),
+                "\"756.9217.0769.85\""
+            );
+
+            let stored: AVSNumber = serde_json::from_str("\"7569217076985\"").unwrap();
+            assert_eq!(stored.reveal(), "756.9217.0769.85");
+        }
+
+        #[test]
+        fn test_deserialization_is_validated() {
+            assert!(serde_json::from_str::<AVSNumber>("\"756.9217.0769.84\"").is_err());
+            assert!(serde_json::from_str::<Username>("\"test_user\"").is_ok());
+            assert!(serde_json::from_str::<Username>("\"1234\"").is_err());
+        }
+
         mod password_tests {
             use super::*;
 
@@ -0,0 +1,166 @@
+//! Hachage à clé (HMAC-SHA256), pour les pseudonymes et les index aveugles,
+//! et chiffrement authentifié de courtes données (XChaCha20-Poly1305)
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit as _, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, KeyInit, Mac};
+use log::info;
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+
+use crate::utils::files::create_private;
+
+use crate::utils::input_validation::InvalidInput;
+
+/// Une clé secrète de 256 bits
+pub struct HashKey([u8; 32]);
+
+impl HashKey {
+    pub fn generate() -> Self {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        Self(key)
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, InvalidInput> {
+        from_hex(hex).map(Self)
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.0)
+    }
+
+    /// Lit la clé d'un fichier, ou l'y crée s'il n'existe pas
+    pub fn load_or_create(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(hex) => Self::from_hex(&hex).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Clé invalide dans {}", path.display()),
+                )
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let key = Self::generate();
+                create_private(path)?.write_all(key.to_hex().as_bytes())?;
+                info!("Nouvelle clé créée dans {}", path.display());
+                Ok(key)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Une sous-clé indépendante pour chaque usage
+    pub fn derive(&self, label: &str) -> Self {
+        Self(self.digest(label.as_bytes()))
+    }
+
+    /// Le HMAC-SHA256 des données
+    pub fn digest(&self, data: &[u8]) -> [u8; 32] {
+        self.mac(&[data]).finalize().into_bytes().into()
+    }
+
+    fn mac(&self, parts: &[&[u8]]) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC accepts keys of any length");
+        parts.iter().for_each(|part| mac.update(part));
+        mac
+    }
+
+    /// Chiffre les données avec XChaCha20-Poly1305, liées à leur contexte
+    /// (données associées): un nonce aléatoire, suivi du chiffré authentifié
+    pub fn seal(&self, data: &[u8], context: &[u8]) -> Vec<u8> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: data,
+            aad: context,
+        };
+        let mut sealed = nonce.to_vec();
+        sealed.extend(
+            self.cipher()
+                .encrypt(&nonce, payload)
+                .expect("Short data can always be encrypted"),
+        );
+        sealed
+    }
+
+    /// Déchiffre des données scellées avec la même clé et le même contexte
+    pub fn open(&self, sealed: &[u8], context: &[u8]) -> Option<Vec<u8>> {
+        if sealed.len() < NONCE_LEN + TAG_LEN {
+            return None;
+        }
+        let (nonce, data) = sealed.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: data,
+            aad: context,
+        };
+        self.cipher()
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .ok()
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(Key::from_slice(&self.0))
+    }
+}
+
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+
+/// Une clé tirée au hasard
+impl Default for HashKey {
+    fn default() -> Self {
+        Self::generate()
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Lit exactement `N` octets en hexadécimal
+pub fn from_hex<const N: usize>(hex: &str) -> Result<[u8; N], InvalidInput> {
+    hex_bytes(hex)?.try_into().map_err(|_| InvalidInput)
+}
+
+/// Lit des octets en hexadécimal
+pub fn hex_bytes(hex: &str) -> Result<Vec<u8>, InvalidInput> {
+    let hex = hex.trim();
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(InvalidInput);
+    }
+
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).map_err(|_| InvalidInput)?;
+            u8::from_str_radix(pair, 16).map_err(|_| InvalidInput)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sealed_data_needs_the_key_and_context() {
+        let key = HashKey::generate();
+        let data = b"756.1234.5678.97 and more than one block of data";
+        let sealed = key.seal(data, b"user");
+
+        assert_ne!(&sealed[NONCE_LEN..NONCE_LEN + data.len()], data);
+        assert_ne!(key.seal(data, b"user"), sealed);
+        assert_eq!(key.open(&sealed, b"user").unwrap(), data);
+
+        assert_eq!(key.open(&sealed, b"other"), None);
+        assert_eq!(HashKey::generate().open(&sealed, b"user"), None);
+        let mut tampered = sealed.clone();
+        tampered[NONCE_LEN] ^= 1;
+        assert_eq!(key.open(&tampered, b"user"), None);
+        assert_eq!(key.open(&sealed[..TAG_LEN], b"user"), None);
+    }
+}
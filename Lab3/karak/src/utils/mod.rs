@@ -1,2 +1,4 @@
+pub mod files;
 pub mod input_validation;
+pub mod keyed_hash;
 pub mod password_utils;
@@ -1,23 +1,42 @@
 //! Hachage et vérification des mots de passe
 
 use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHashString, PasswordVerifier, SaltString},
+    password_hash::{
+        rand_core::{OsRng, RngCore},
+        PasswordHashString, PasswordVerifier, SaltString,
+    },
     Argon2, PasswordHasher,
 };
-use derive_more::derive::Display;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{str::FromStr, sync::LazyLock};
 
+use crate::utils::keyed_hash::to_hex;
+
 static DEFAULT_HASHER: LazyLock<Argon2<'static>> = LazyLock::new(|| Argon2::default());
 
 /// Le hash d'un mot de passe vide, à utiliser quand l'utilisateur n'existe pas
 /// pour éviter une attaque par canal auxiliaire
 static EMPTY_HASH: LazyLock<PWHash> = LazyLock::new(|| hash(""));
 
-/// Un mot de passe haché
-#[derive(Clone, Debug, Display)]
+/// Un mot de passe haché, jamais affiché
+#[derive(Clone)]
 pub struct PWHash(PasswordHashString);
 
+impl std::fmt::Debug for PWHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PWHash(***)")
+    }
+}
+
+impl PartialEq for PWHash {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for PWHash {}
+
 impl std::hash::Hash for PWHash {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.0.as_str().hash(state)
@@ -53,6 +72,61 @@ pub fn hash(password: &str) -> PWHash {
     PWHash(hash)
 }
 
+/// Alphabet des mots de passe générés, sans les chiffres 0 et 1 ni la lettre l:
+/// o et i restent lisibles, même en majuscules, puisque 0 et 1 sont exclus.
+/// Sa taille de 32 évite tout biais lors du tirage.
+const GENERATED_ALPHABET: &[u8; 32] = b"abcdefghijkmnopqrstuvwxyz2345678";
+
+/// Longueur des mots de passe générés, soit 100 bits d'entropie
+const GENERATED_LENGTH: usize = 20;
+
+/// Génère un mot de passe initial aléatoire, à changer à la première connexion
+pub fn generate_password() -> String {
+    let mut bytes = [0u8; GENERATED_LENGTH];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+        .iter()
+        .map(|byte| GENERATED_ALPHABET[usize::from(byte % 32)] as char)
+        .collect()
+}
+
+/// Longueur des codes d'ordonnance, soit 60 bits d'entropie
+const CODE_LENGTH: usize = 12;
+
+/// Génère un code à usage unique lisible à voix haute, en groupes de quatre
+/// caractères (`ABCD-EFGH-JKMN`)
+pub fn generate_code() -> String {
+    let mut bytes = [0u8; CODE_LENGTH];
+    OsRng.fill_bytes(&mut bytes);
+    let chars: Vec<char> = bytes
+        .iter()
+        .map(|byte| GENERATED_ALPHABET[usize::from(byte % 32)].to_ascii_uppercase() as char)
+        .collect();
+    chars
+        .chunks(4)
+        .map(|group| group.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// L'empreinte SHA-256 d'un code, sans tenir compte de la casse, des tirets
+/// ni des espaces. Seule l'empreinte est enregistrée.
+pub fn code_digest(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    to_hex(&Sha256::digest(normalized.as_bytes()))
+}
+
+/// Génère un jeton de session aléatoire de 256 bits, en hexadécimal
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
 /// Vérifie si le mot de passe correspond au hash stocké.
 /// 
 /// Si un hash n'est pas fourni, on doit quand même tester